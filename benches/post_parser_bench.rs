@@ -0,0 +1,90 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput, BenchmarkId};
+use new_post_parser_lib::{PostParserContext, PostParser, PostRaw, ThreadParser};
+use std::collections::HashSet;
+
+const THREAD_POSTS_COUNT: u64 = 500;
+const CATALOG_SNIPPET_LENGTH: usize = 200;
+
+const SMALL_POST: &str = "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>\
+  <span class=\"quote\">&gt;be me</span><br>\
+  Check this out https://github.com/K1rakishou/Kuroba-Experimental <s>it's good</s>";
+
+const COPYPASTA_PARAGRAPH: &str = "<span class=\"quote\">&gt;What the fuck did you just fucking say about me</span><br>\
+  I'll have you know I graduated top of my class in the <b>Navy Seals</b>, and I've been involved \
+  in numerous secret raids on <a href=\"//boards.4chan.org/g/thread/1#p2\" class=\"quotelink\">&gt;&gt;&gt;/g/2</a>, \
+  and I have over 300 confirmed kills. <s>I am trained in gorilla warfare</s> and I'm the top \
+  sniper in the entire US armed forces. Ünïcödé 😀👍🏽 https://example.com/path?q=1<br><br>";
+
+fn thread_posts() -> HashSet<u64> {
+  return (1..=THREAD_POSTS_COUNT).collect();
+}
+
+fn huge_copypasta_post() -> String {
+  return COPYPASTA_PARAGRAPH.repeat(200);
+}
+
+fn thread(thread_posts_count: u64) -> Vec<PostRaw> {
+  return (1..=thread_posts_count)
+    .map(|post_no| {
+      let comment = format!(
+        "<a href=\"#p{}\" class=\"quotelink\">&gt;&gt;{}</a><br>{}",
+        (post_no / 2).max(1),
+        (post_no / 2).max(1),
+        if post_no % 10 == 0 { COPYPASTA_PARAGRAPH } else { SMALL_POST }
+      );
+
+      PostRaw::new("4chan", "g", 1, post_no, 0, &comment)
+    })
+    .collect();
+}
+
+fn bench_single_post(criterion: &mut Criterion) {
+  let post_parser_context = PostParserContext::new(HashSet::new(), thread_posts());
+  let post_parser = PostParser::new(&post_parser_context);
+
+  let mut group = criterion.benchmark_group("parse_comment");
+
+  for (name, comment) in [("small_post", SMALL_POST.to_string()), ("huge_copypasta_post", huge_copypasta_post())].iter() {
+    let post_raw = PostRaw::new("4chan", "g", 1, 1234, 0, comment);
+
+    group.throughput(Throughput::Bytes(comment.len() as u64));
+    group.bench_with_input(BenchmarkId::from_parameter(name), &post_raw, |bencher, post_raw| {
+      bencher.iter(|| post_parser.parse_comment(post_raw));
+    });
+  }
+
+  group.finish();
+}
+
+fn bench_thread(criterion: &mut Criterion) {
+  let post_parser_context = PostParserContext::new(HashSet::new(), thread_posts());
+  let posts = thread(THREAD_POSTS_COUNT);
+
+  let mut group = criterion.benchmark_group("parse_thread");
+  group.throughput(Throughput::Elements(THREAD_POSTS_COUNT));
+  group.bench_function(BenchmarkId::from_parameter(THREAD_POSTS_COUNT), |bencher| {
+    bencher.iter(|| ThreadParser::parse_thread(&posts, &post_parser_context));
+  });
+
+  group.finish();
+}
+
+fn bench_catalog_snippet(criterion: &mut Criterion) {
+  let post_parser_context = PostParserContext::new(HashSet::new(), thread_posts());
+  let post_parser = PostParser::new(&post_parser_context);
+
+  let mut group = criterion.benchmark_group("parse_for_catalog");
+
+  for (name, comment) in [("small_post", SMALL_POST.to_string()), ("huge_copypasta_post", huge_copypasta_post())].iter() {
+    let post_raw = PostRaw::new("4chan", "g", 1, 1234, 0, comment);
+
+    group.bench_with_input(BenchmarkId::from_parameter(name), &post_raw, |bencher, post_raw| {
+      bencher.iter(|| post_parser.parse_for_catalog(post_raw, CATALOG_SNIPPET_LENGTH));
+    });
+  }
+
+  group.finish();
+}
+
+criterion_group!(benches, bench_single_post, bench_thread, bench_catalog_snippet);
+criterion_main!(benches);
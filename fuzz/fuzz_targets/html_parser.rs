@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use new_post_parser_lib::{HtmlParser, BbCodeParser, MarkdownParser, PlainTextParser};
+
+// cargo fuzz run html_parser -- -dict=fuzz/html.dict
+fuzz_target!(|data: &[u8]| {
+  let html = String::from_utf8_lossy(data);
+
+  let _ = HtmlParser::new().parse(&html);
+  let _ = HtmlParser::new().decode_entities(true).lowercase_names(false).parse(&html);
+  let _ = BbCodeParser::new().parse(&html);
+  let _ = MarkdownParser::new().parse(&html);
+  let _ = PlainTextParser::new().parse(&html);
+});
@@ -0,0 +1,36 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashSet;
+use new_post_parser_lib::{PostParserContext, PostParser, PostRaw, ParserProfile, ParserLimits};
+
+// cargo fuzz run parse_comment fuzz/corpus/parse_comment
+fuzz_target!(|data: &[u8]| {
+  // The first byte selects the profile so that the corpus covers all the front-ends
+  let (profile_byte, comment) = match data.split_first() {
+    Some((profile_byte, comment)) => (*profile_byte, String::from_utf8_lossy(comment)),
+    None => return
+  };
+
+  let parser_profile = match profile_byte % 5 {
+    0 => ParserProfile::four_chan(),
+    1 => ParserProfile::dvach(),
+    2 => ParserProfile::desuarchive(),
+    3 => ParserProfile::bbcode(),
+    _ => ParserProfile::markdown()
+  };
+
+  let thread_posts = (1..=10u64).collect::<HashSet<u64>>();
+  let post_parser_context = PostParserContext::new(HashSet::new(), thread_posts);
+  let post_parser = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default());
+
+  let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, &comment);
+  let parsed_comment = post_parser.parse_comment(&post_raw);
+  let text_length = parsed_comment.parsed_text.chars().count();
+
+  for spannable in parsed_comment.spannables.iter() {
+    assert!(spannable.start + spannable.len <= text_length, "{} is out of bounds of {:?}", spannable, parsed_comment.parsed_text);
+  }
+
+  let _ = post_parser.parse_for_catalog(&post_raw, 100);
+});
@@ -0,0 +1,153 @@
+#ifndef KUROBA_EX_POST_PARSER_H
+#define KUROBA_EX_POST_PARSER_H
+
+/* Generated with cbindgen, do not edit manually */
+
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define FFI_FLAG_IS_YOU 1
+
+#define FFI_FLAG_IS_OP (1 << 1)
+
+#define FFI_FLAG_ORDERED (1 << 2)
+
+#define FFI_FLAG_IS_LIGHT_THEME (1 << 3)
+
+#define FFI_FLAG_DISPLAY_MODE (1 << 4)
+
+typedef enum FfiSpannableKind {
+  FfiSpannableKind_Unknown = 0,
+  FfiSpannableKind_Quote = 1,
+  FfiSpannableKind_DeadQuote = 2,
+  FfiSpannableKind_UrlLink = 3,
+  FfiSpannableKind_BoardLink = 4,
+  FfiSpannableKind_SearchLink = 5,
+  FfiSpannableKind_ThreadLink = 6,
+  FfiSpannableKind_DeadCrossBoardQuote = 7,
+  FfiSpannableKind_EmailLink = 8,
+  FfiSpannableKind_Spoiler = 9,
+  FfiSpannableKind_GreenText = 10,
+  FfiSpannableKind_BoldText = 11,
+  FfiSpannableKind_Italic = 12,
+  FfiSpannableKind_Underline = 13,
+  FfiSpannableKind_Strikethrough = 14,
+  FfiSpannableKind_Superscript = 15,
+  FfiSpannableKind_Subscript = 16,
+  FfiSpannableKind_ListItem = 17,
+  FfiSpannableKind_SjisArt = 18,
+  FfiSpannableKind_Heading = 19,
+  FfiSpannableKind_TableCell = 20,
+  FfiSpannableKind_InlineCode = 21,
+  FfiSpannableKind_CodeBlock = 22,
+  FfiSpannableKind_Monospace = 23,
+  FfiSpannableKind_TextForegroundColor = 24,
+  FfiSpannableKind_TextBackgroundColor = 25,
+  FfiSpannableKind_ThemeJson = 26,
+  FfiSpannableKind_MediaLink = 27,
+  FfiSpannableKind_Fortune = 28,
+  FfiSpannableKind_DiceRoll = 29,
+  FfiSpannableKind_Capcode = 30,
+  FfiSpannableKind_SearchHighlight = 31,
+  FfiSpannableKind_FilterHighlight = 32,
+  FfiSpannableKind_Poll = 33,
+  FfiSpannableKind_TeX = 34,
+  FfiSpannableKind_Ruby = 35,
+  FfiSpannableKind_UnknownTag = 36,
+} FfiSpannableKind;
+
+typedef struct FfiPostParser FfiPostParser;
+
+/**
+ * Flattened Spannable. What the payload fields mean depends on the kind:
+ * - Quote/DeadQuote: number1 = post_no, flags = FFI_FLAG_IS_YOU | FFI_FLAG_IS_OP, text1 =
+ *   poster_name and text2 = comment_preview of the quote preview (null without one)
+ * - UrlLink/EmailLink: text1 = url/email
+ * - BoardLink: text1 = board_code
+ * - SearchLink: text1 = board_code, text2 = search_query
+ * - ThreadLink: text1 = board_code, number1 = post_no, number2 = thread_no
+ * - DeadCrossBoardQuote: text1 = board_code, number1 = post_no
+ * - ListItem: number1 = index, number2 = depth, flags = FFI_FLAG_ORDERED
+ * - Heading: number1 = level
+ * - TextForegroundColor/TextBackgroundColor: text1 = color hex (or number1 = ChanThemeColorId
+ *   when text1 is null)
+ * - ThemeJson: text1 = theme_name, flags = FFI_FLAG_IS_LIGHT_THEME
+ * - MediaLink: text1 = id, number1 = 0 YouTube/1 Streamable/2 Vimeo/3 SoundCloud
+ * - Fortune: text1 = color
+ * - DiceRoll: number1 = dice, number2 = total (as u64 two's complement)
+ * - Capcode: text1 = capcode
+ * - FilterHighlight: text1 = color
+ * - Poll: text1 = question, number1 = options count (the options are in ParsedPost polls)
+ * - TeX: flags = FFI_FLAG_DISPLAY_MODE, text1 = TeX source (TeXMode::Placeholder only)
+ * - Ruby: text1 = reading
+ * - UnknownTag: text1 = tag
+ * Unused string fields are null.
+ */
+typedef struct FfiSpannable {
+  uintptr_t start;
+  uintptr_t len;
+  FfiSpannableKind kind;
+  uint32_t flags;
+  uint64_t number1;
+  uint64_t number2;
+  char *text1;
+  char *text2;
+} FfiSpannable;
+
+typedef struct FfiParsedComment {
+  char *text;
+  FfiSpannable *spannables;
+  uintptr_t spannables_len;
+} FfiParsedComment;
+
+/**
+ * my_replies/thread_posts may be null when their len is 0
+ *
+ * # Safety
+ * my_replies/thread_posts must point to at least my_replies_len/thread_posts_len u64s (unless they
+ * are null). The returned parser must be released with post_parser_destroy().
+ */
+FfiPostParser *post_parser_create(const uint64_t *my_replies,
+                                  uintptr_t my_replies_len,
+                                  const uint64_t *thread_posts,
+                                  uintptr_t thread_posts_len);
+
+/**
+ * Returns null (see post_parser_last_error()) if any of the pointers is null or any of the strings
+ * is not valid utf-8
+ *
+ * # Safety
+ * post_parser must come from post_parser_create() and must not be used after
+ * post_parser_destroy(). The strings must be nul-terminated. The returned comment must be released
+ * with post_parser_parsed_comment_destroy().
+ */
+FfiParsedComment *post_parser_parse_comment(const FfiPostParser *post_parser,
+                                            const char *site_name,
+                                            const char *board_code,
+                                            uint64_t thread_no,
+                                            uint64_t post_no,
+                                            uint64_t post_sub_no,
+                                            const char *comment);
+
+/**
+ * # Safety
+ * parsed_comment must be null or come from post_parser_parse_comment() and must not be used (or
+ * destroyed again) afterwards. The fields of the comment must not be modified.
+ */
+void post_parser_parsed_comment_destroy(FfiParsedComment *parsed_comment);
+
+/**
+ * # Safety
+ * post_parser must be null or come from post_parser_create() and must not be used (or destroyed
+ * again) afterwards. The comments it parsed stay valid.
+ */
+void post_parser_destroy(FfiPostParser *post_parser);
+
+/**
+ * Why the last post_parser_parse_comment() call on this thread returned null, null if it didn't.
+ * The string is owned by the library and stays valid until the next call on this thread.
+ */
+const char *post_parser_last_error(void);
+
+#endif /* KUROBA_EX_POST_PARSER_H */
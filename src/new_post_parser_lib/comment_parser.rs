@@ -2,31 +2,65 @@ pub mod comment_parser {
   use std::collections::{HashMap, HashSet};
 
   use crate::rules::anchor::AnchorRuleHandler;
-  use crate::rules::span::SpanHandler;
-  use crate::rules::rule_handler::RuleHandler;
+  use crate::rules::span::{DeadLinkHandler, GreenTextHandler};
+  use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
   use crate::rules::line_break::LineBreakRuleHandler;
   use std::fmt;
-  use crate::{empty_set, set_of, TextPart, Attribute};
-  use crate::{PostRaw, PostParserContext, Element, ParsingRule, CommentParser, PostLink, SpannableData, Spannable, ParsedSpannableText};
+  use crate::{empty_set, set_of, TextParts, Attribute};
+  use crate::{PostRaw, PostParserContext, Element, ParsingRule, CommentParser, PostLink, SpannableData, Spannable, ParsedSpannableText, STagMeaning, ParsingRules, TextRule, QuoteFormat, RubyMode};
   use crate::rules::spoiler::SpoilerHandler;
   use crate::rules::table_row::TableRowHandler;
   use crate::rules::bold::BoldHandler;
   use crate::rules::abbr::AbbrHandler;
+  use crate::rules::ban_message::BanMessageHandler;
+  use crate::rules::capcode::{CapcodeHandler, CAPCODE_CLASSES};
+  use crate::rules::poster_id::PosterIdHandler;
+  use crate::util::poster_id::POSTER_ID_CLASSES;
   use std::rc::Rc;
   use crate::rules::style::StyleHandler;
+  use crate::rules::style_hint::StyleHintHandler;
   use linked_hash_map::LinkedHashMap;
   use std::fmt::Debug;
-  use crate::rules::pre::PreHandler;
+  use crate::rules::code_block::CodeBlockHandler;
   use crate::rules::table_data::TableDataHandler;
   use crate::rules::table::TableHandler;
+  use crate::rules::strikethrough::StrikethroughHandler;
+  use crate::rules::italic::ItalicHandler;
+  use crate::rules::underline::UnderlineHandler;
+  use crate::rules::superscript::SuperscriptHandler;
+  use crate::rules::subscript::SubscriptHandler;
+  use crate::rules::list::{ListHandler, ListItemHandler, new_list_stack};
+  use crate::rules::heading::HeadingHandler;
+  use crate::rules::sjis_art::SjisArtHandler;
+  use crate::rules::fortune::FortuneHandler;
+  use crate::rules::dice_roll::DiceRollHandler;
+  use crate::rules::poll::PollHandler;
+  use crate::rules::ruby::RubyHandler;
 
   const TAG: &str = "CommentParser";
+  const CLASS_ATTR: &str = "class";
+  // <span class="quote deadlink"> is a dead quote, not a greentext
+  const DEAD_LINK_PRIORITY: i32 = ParsingRule::ATTRIBUTE_PRIORITY + 10;
+  // After the rules of the other classes of the span so that the greentext spannable goes after
+  // theirs
+  const GREEN_TEXT_PRIORITY: i32 = ParsingRule::TAG_PRIORITY;
 
   impl fmt::Display for PostLink {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       return match self {
-        PostLink::Quote { post_no } => {
-          write!(f, "Quote(post_no: {})", post_no)
+        PostLink::Quote { post_no, is_you, is_op, preview: Option::None } => {
+          write!(f, "Quote(post_no: {}, is_you: {}, is_op: {})", post_no, is_you, is_op)
+        },
+        PostLink::Quote { post_no, is_you, is_op, preview: Option::Some(preview) } => {
+          write!(
+            f,
+            "Quote(post_no: {}, is_you: {}, is_op: {}, poster_name: {}, comment_preview: {})",
+            post_no,
+            is_you,
+            is_op,
+            preview.poster_name,
+            preview.comment_preview
+          )
         },
         PostLink::Dead { post_no } => {
           write!(f, "Dead(post_no: {})", post_no)
@@ -43,10 +77,28 @@ pub mod comment_parser {
         PostLink::ThreadLink { board_code, thread_no, post_no } => {
           write!(f, "ThreadLink(board_code: {}, thread_no: {}, post_no: {})", board_code, thread_no, post_no)
         }
+        PostLink::DeadCrossBoard { board_code, post_no } => {
+          write!(f, "DeadCrossBoard(board_code: {}, post_no: {})", board_code, post_no)
+        }
+        PostLink::Email { email } => {
+          write!(f, "Email(email: {})", email)
+        }
       }
     }
   }
 
+  impl PostLink {
+    /// Quote of a post of the current thread with the (You)/(OP) flags filled in
+    pub fn quote(post_raw: &PostRaw, post_parser_context: &PostParserContext, post_no: u64) -> PostLink {
+      return PostLink::Quote {
+        post_no,
+        is_you: post_parser_context.is_my_post(post_no),
+        is_op: post_raw.is_quoting_original_post(post_no),
+        preview: post_parser_context.quote_preview(post_no).cloned()
+      };
+    }
+  }
+
   impl fmt::Display for SpannableData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       return match self {
@@ -62,6 +114,33 @@ pub mod comment_parser {
         SpannableData::BoldText => {
           write!(f, "BoldText()")
         }
+        SpannableData::Italic => {
+          write!(f, "Italic()")
+        }
+        SpannableData::Underline => {
+          write!(f, "Underline()")
+        }
+        SpannableData::Strikethrough => {
+          write!(f, "Strikethrough()")
+        }
+        SpannableData::Superscript => {
+          write!(f, "Superscript()")
+        }
+        SpannableData::Subscript => {
+          write!(f, "Subscript()")
+        }
+        SpannableData::ListItem { depth, ordered, index } => {
+          write!(f, "ListItem(depth: {}, ordered: {}, index: {})", depth, ordered, index)
+        }
+        SpannableData::SjisArt => {
+          write!(f, "SjisArt()")
+        }
+        SpannableData::Heading { level } => {
+          write!(f, "Heading(level: {})", level)
+        }
+        SpannableData::TableCell => {
+          write!(f, "TableCell()")
+        }
         SpannableData::TextForegroundColorRaw { color_hex: raw_color } => {
           write!(f, "TextForegroundColorRaw(raw_color: {})", raw_color)
         }
@@ -83,13 +162,115 @@ pub mod comment_parser {
         SpannableData::Monospace => {
           write!(f, "Monospace()")
         }
+        SpannableData::InlineCode => {
+          write!(f, "InlineCode()")
+        }
+        SpannableData::CodeBlock => {
+          write!(f, "CodeBlock()")
+        }
         SpannableData::ThemeJson { theme_name, is_light_theme } => {
           write!(f, "ThemeJson(theme_name: {}, is_light_theme: {})", theme_name, is_light_theme)
         }
+        SpannableData::MediaLink { kind, id } => {
+          write!(f, "MediaLink(kind: {:?}, id: {})", kind, id)
+        }
+        SpannableData::StyleHint(style_hint) => {
+          write!(f, "StyleHint({:?})", style_hint)
+        }
+        SpannableData::Fortune { color } => {
+          write!(f, "Fortune(color: {})", color)
+        }
+        SpannableData::DiceRoll(post_roll) => {
+          write!(f, "DiceRoll({:?})", post_roll)
+        }
+        SpannableData::Poll(post_poll) => {
+          write!(f, "Poll({:?})", post_poll)
+        }
+        SpannableData::Unknown { tag } => {
+          write!(f, "Unknown(tag: {})", tag)
+        }
+        SpannableData::Ruby { reading } => {
+          write!(f, "Ruby(reading: {})", reading)
+        }
+        SpannableData::TeX { display_mode, source } => {
+          match source {
+            None => write!(f, "TeX(display_mode: {})", display_mode),
+            Some(source) => write!(f, "TeX(display_mode: {}, source: {})", display_mode, source)
+          }
+        }
+        SpannableData::Capcode { capcode, kind } => {
+          write!(f, "Capcode(capcode: {}, kind: {:?})", capcode, kind)
+        }
+        SpannableData::SearchHighlight => {
+          write!(f, "SearchHighlight()")
+        }
+        SpannableData::FilterHighlight { color } => {
+          write!(f, "FilterHighlight(color: {})", color)
+        }
+        SpannableData::BanMessage => {
+          write!(f, "BanMessage()")
+        }
+        SpannableData::ColoredQuote { role } => {
+          write!(f, "ColoredQuote(role: {:?})", role)
+        }
+        SpannableData::PosterId(poster_id) => {
+          write!(f, "PosterId(id: {})", poster_id.id)
+        }
+        SpannableData::Custom { kind, payload } => {
+          write!(f, "Custom(kind: {}, payload: {} bytes)", kind, payload.len())
+        }
       }
     }
   }
 
+  impl SpannableData {
+    /// Stable discriminant of the variant (see SpannableData), 255 for every Custom span (its own
+    /// kind is in the variant)
+    pub fn kind(&self) -> u32 {
+      // Same numbers as the discriminants of SpannableData
+      return match self {
+        SpannableData::Link(..) => 0,
+        SpannableData::Spoiler => 1,
+        SpannableData::GreenText => 2,
+        SpannableData::BoldText => 3,
+        SpannableData::Italic => 4,
+        SpannableData::Underline => 5,
+        SpannableData::Strikethrough => 6,
+        SpannableData::Superscript => 7,
+        SpannableData::Subscript => 8,
+        SpannableData::ListItem { .. } => 9,
+        SpannableData::SjisArt => 10,
+        SpannableData::Heading { .. } => 11,
+        SpannableData::TableCell => 12,
+        SpannableData::FontSize { .. } => 13,
+        SpannableData::FontWeight { .. } => 14,
+        SpannableData::InlineCode => 15,
+        SpannableData::CodeBlock => 16,
+        SpannableData::Monospace => 17,
+        SpannableData::TextForegroundColorRaw { .. } => 18,
+        SpannableData::TextBackgroundColorRaw { .. } => 19,
+        SpannableData::TextForegroundColorId { .. } => 20,
+        SpannableData::TextBackgroundColorId { .. } => 21,
+        SpannableData::ThemeJson { .. } => 22,
+        SpannableData::MediaLink { .. } => 23,
+        SpannableData::StyleHint(..) => 24,
+        SpannableData::Fortune { .. } => 25,
+        SpannableData::DiceRoll(..) => 26,
+        SpannableData::Poll(..) => 30,
+        SpannableData::TeX { .. } => 31,
+        SpannableData::Ruby { .. } => 32,
+        SpannableData::Unknown { .. } => 33,
+        SpannableData::Capcode { .. } => 27,
+        SpannableData::SearchHighlight => 28,
+        SpannableData::FilterHighlight { .. } => 29,
+        SpannableData::BanMessage => 34,
+        SpannableData::ColoredQuote { .. } => 35,
+        SpannableData::PosterId(..) => 36,
+        SpannableData::Custom { .. } => 255
+      };
+    }
+  }
+
   impl Spannable {
     pub fn is_valid(&self) -> bool {
       return self.len > 0
@@ -116,7 +297,7 @@ pub mod comment_parser {
 
   impl Debug for ParsingRule {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-      write!(f, "ParsingRule(tag: {}, req_attributes: {:?})", self.tag_name, self.required_attributes)
+      write!(f, "ParsingRule(tag: {}, req_attributes: {:?}, selector: {:?})", self.tag_name, self.required_attributes, self.selector)
     }
   }
 
@@ -125,7 +306,13 @@ pub mod comment_parser {
       ParsedSpannableText {
         original_text: String::from(comment_raw),
         parsed_text: comment_text,
-        spannables
+        spannables,
+        timed_out: false,
+        html_parser_error: Option::None,
+        was_truncated: false,
+        is_abbreviated: false,
+        is_deleted: false,
+        warnings: Vec::new()
       }
     }
 
@@ -133,50 +320,108 @@ pub mod comment_parser {
       return ParsedSpannableText {
         original_text: String::new(),
         parsed_text: Box::new(String::new()),
-        spannables: Box::new(Vec::new())
+        spannables: Box::new(Vec::new()),
+        timed_out: false,
+        html_parser_error: Option::None,
+        was_truncated: false,
+        is_abbreviated: false,
+        is_deleted: false,
+        warnings: Vec::new()
       }
     }
   }
 
   impl Attribute {
-    fn with_name(attr_name: &str) -> Attribute {
+    pub fn with_name(attr_name: &str) -> Attribute {
       return Attribute {
         attr_name: attr_name.to_string(),
         attr_value: Option::None
       }
     }
 
-    fn with_name_and_value(attr_name: &str, attr_value: &str) -> Attribute {
+    pub fn with_name_and_value(attr_name: &str, attr_value: &str) -> Attribute {
       return Attribute {
         attr_name: attr_name.to_string(),
         attr_value: Option::Some(attr_value.to_string())
       }
     }
 
-    fn with_class(attr_value: &str) -> Attribute {
+    pub fn with_class(attr_value: &str) -> Attribute {
       return Attribute {
-        attr_name: "class".to_string(),
+        attr_name: CLASS_ATTR.to_string(),
         attr_value: Option::Some(attr_value.to_string())
       }
     }
   }
 
   impl ParsingRule {
+    /// Priority of the rules that only match the tag name
+    pub const TAG_PRIORITY: i32 = 0;
+    /// Priority of the rules that require attributes (classes), they are more specific so they go
+    /// before the tag ones
+    pub const ATTRIBUTE_PRIORITY: i32 = 100;
+
     pub fn new(tag: &str, req_attributes: HashSet<Attribute>, handler: Rc<dyn RuleHandler>) -> ParsingRule {
+      let priority = if req_attributes.is_empty() {
+        ParsingRule::TAG_PRIORITY
+      } else {
+        ParsingRule::ATTRIBUTE_PRIORITY
+      };
+
       ParsingRule {
         tag_name: String::from(tag),
         required_attributes: req_attributes,
-        handler
+        handler,
+        priority,
+        selector: Option::None
       }
     }
 
-    pub fn high_priority(&self) -> bool {
-      return self.required_attributes.len() > 0;
+    /// Rule for the elements matching a simple css selector (see Element::matches()), e.g.
+    /// "span.quote.deadlink" or "a.quotelink[href]". Gets ATTRIBUTE_PRIORITY unless the selector is
+    /// just a tag name.
+    pub fn with_selector(selector: &str, handler: Rc<dyn RuleHandler>) -> ParsingRule {
+      let selector = selector.trim();
+      let tag_name_len = selector.find(|ch: char| ch == '.' || ch == '#' || ch == '[').unwrap_or(selector.len());
+
+      let tag_name = match &selector[..tag_name_len] {
+        "" => String::from("*"),
+        tag_name => tag_name.to_lowercase()
+      };
+
+      let priority = if tag_name_len == selector.len() {
+        ParsingRule::TAG_PRIORITY
+      } else {
+        ParsingRule::ATTRIBUTE_PRIORITY
+      };
+
+      return ParsingRule {
+        tag_name,
+        required_attributes: HashSet::new(),
+        handler,
+        priority,
+        selector: Option::Some(String::from(selector))
+      };
     }
 
+    /// Overrides the default priority (TAG_PRIORITY or ATTRIBUTE_PRIORITY). When multiple rules apply
+    /// to an element their pre_handle() is called from the highest priority to the lowest one until
+    /// one of them handles the element, post_handle() of all of them is called in the same order.
+    pub fn with_priority(mut self, priority: i32) -> ParsingRule {
+      self.priority = priority;
+      return self;
+    }
+
+    pub fn priority(&self) -> i32 {
+      return self.priority;
+    }
+
+    /// Whether the element matches the selector of the rule or has all of the required attributes.
+    /// The tag name is not checked, ParsingRules only gives the rules of the element's tag (and the
+    /// wildcard ones).
     pub fn applies(&self, element: &Element) -> bool {
-      if self.required_attributes.is_empty() {
-        return true
+      if let Option::Some(selector) = &self.selector {
+        return element.matches(selector);
       }
 
       for req_attribute in &self.required_attributes {
@@ -185,20 +430,28 @@ pub mod comment_parser {
         }
 
         let attr_name = &req_attribute.attr_name;
-        let attr_value = element.attributes.get(attr_name);
 
-        if req_attribute.attr_value.is_none() {
-          continue;
-        }
+        if attr_name == CLASS_ATTR {
+          if let Option::Some(req_class) = &req_attribute.attr_value {
+            // Elements may have multiple classes
+            if !element.has_class(req_class) {
+              return false;
+            }
 
-        let req_attr_value = req_attribute.attr_value.as_ref().unwrap();
-        if attr_value.is_none() {
-          return false;
+            continue;
+          }
         }
 
-        let attr_value = attr_value.unwrap();
-        if req_attr_value != attr_value {
-          return false;
+        let attr_value = match element.get_attribute(attr_name) {
+          Option::Some(attr_value) => attr_value,
+          // Required attributes must be present even when any value will do
+          Option::None => return false
+        };
+
+        if let Option::Some(req_attr_value) = &req_attribute.attr_value {
+          if attr_value != Option::Some(req_attr_value) {
+            return false;
+          }
         }
       }
 
@@ -207,96 +460,293 @@ pub mod comment_parser {
 
   }
 
-  impl CommentParser<'_> {
+  impl ParsingRules {
 
-    pub fn new(post_parser_context: &PostParserContext) -> CommentParser {
-      return CommentParser {
-        post_parser_context,
+    pub fn new() -> ParsingRules {
+      return ParsingRules {
         matching_rules: LinkedHashMap::new(),
-        replacement_rules: HashMap::new()
-      }
+        text_rules: Vec::new(),
+        quote_format: QuoteFormat::default()
+      };
+    }
+
+    pub fn with_default_rules() -> ParsingRules {
+      let mut parsing_rules = ParsingRules::new();
+      parsing_rules.add_default_rules();
+
+      return parsing_rules;
     }
 
+    // The rules of every tag are sorted by priority (highest first) so that they don't need to be
+    // sorted for every element, a rule goes after the rules with the same priority that were added
+    // before it
     fn add_matching_rule(&mut self, rule: Rc<ParsingRule>) {
       if !self.matching_rules.contains_key(&rule.tag_name) {
         self.matching_rules.insert(String::from(&rule.tag_name), Vec::new());
       }
 
-      self.matching_rules.get_mut(&rule.tag_name).unwrap().push(rule);
-    }
-
-    pub fn add_replacement_rule(&mut self, pattern: &str, value: &str) {
-      let result = self.replacement_rules.insert(String::from(pattern), String::from(value));
+      let rules = self.matching_rules.get_mut(&rule.tag_name).unwrap();
+      let index = rules.iter()
+        .position(|other_rule| other_rule.priority < rule.priority)
+        .unwrap_or(rules.len());
 
-      if result.is_some() {
-        panic!("add_replacement() pattern {} with value {} was already added! Old value: {}", pattern, value, result.unwrap());
-      }
+      rules.insert(index, rule);
     }
 
-    pub fn add_default_matching_rules(&mut self) {
+    /// Rules for 4chan-like html
+    pub fn add_default_rules(&mut self) -> &mut ParsingRules {
       // Wildcard rules go first
       self.add_matching_rule(Rc::new(ParsingRule::new("*", set_of!(Attribute::with_name("style")), Rc::new(StyleHandler::new()))));
+      // Matches every element (the handler checks the style and the classes itself) so that the
+      // elements styled only by a class get a hint too
+      self.add_matching_rule(Rc::new(
+        ParsingRule::new("*", empty_set!(), Rc::new(StyleHintHandler::new()))
+          .with_priority(ParsingRule::ATTRIBUTE_PRIORITY)
+      ));
 
       // Then go rules that require specific attributes
+      self.add_matching_rule(Rc::new(dead_link_rule(QuoteFormat::default())));
+      self.add_matching_rule(Rc::new(
+        ParsingRule::new("span", set_of!(Attribute::with_class("quote")), Rc::new(GreenTextHandler::new()))
+          .with_priority(GREEN_TEXT_PRIORITY)
+      ));
       self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class("abbr")), Rc::new(AbbrHandler::new()))));
-      self.add_matching_rule(Rc::new(ParsingRule::new("pre", set_of!(Attribute::with_name_and_value("*", "prettyprint")), Rc::new(PreHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class("sjis")), Rc::new(SjisArtHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class("fortune")), Rc::new(FortuneHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("div", set_of!(Attribute::with_class("poll")), Rc::new(PollHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class("public_ban")), Rc::new(BanMessageHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class("public_warning")), Rc::new(BanMessageHandler::new()))));
+
+      for class in POSTER_ID_CLASSES.iter() {
+        self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class(class)), Rc::new(PosterIdHandler::new()))));
+      }
+
+      for (class, capcode, kind) in CAPCODE_CLASSES.iter() {
+        self.add_matching_rule(Rc::new(ParsingRule::new("span", set_of!(Attribute::with_class(class)), Rc::new(CapcodeHandler::new(capcode, *kind)))));
+      }
 
       // Then go general rules for the whole tag
-      self.add_matching_rule(Rc::new(ParsingRule::new("span", empty_set!(), Rc::new(SpanHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("a", empty_set!(), Rc::new(AnchorRuleHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("br", empty_set!(), Rc::new(LineBreakRuleHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("s", empty_set!(), Rc::new(SpoilerHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("tr", empty_set!(), Rc::new(TableRowHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("td", empty_set!(), Rc::new(TableDataHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("th", empty_set!(), Rc::new(TableDataHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("b", empty_set!(), Rc::new(BanMessageHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("b", empty_set!(), Rc::new(BoldHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("b", empty_set!(), Rc::new(DiceRollHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("strong", empty_set!(), Rc::new(BanMessageHandler::new()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("strong", empty_set!(), Rc::new(BoldHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("i", empty_set!(), Rc::new(ItalicHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("em", empty_set!(), Rc::new(ItalicHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("u", empty_set!(), Rc::new(UnderlineHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("sup", empty_set!(), Rc::new(SuperscriptHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("sub", empty_set!(), Rc::new(SubscriptHandler::new()))));
+
+      for level in 1..=6u8 {
+        let tag_name = format!("h{}", level);
+        self.add_matching_rule(Rc::new(ParsingRule::new(&tag_name, empty_set!(), Rc::new(HeadingHandler::new(level)))));
+      }
+
+      let list_stack = new_list_stack();
+      self.add_matching_rule(Rc::new(ParsingRule::new("ul", empty_set!(), Rc::new(ListHandler::new(false, list_stack.clone())))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("ol", empty_set!(), Rc::new(ListHandler::new(true, list_stack.clone())))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("li", empty_set!(), Rc::new(ListItemHandler::new(list_stack)))));
+
+      self.add_matching_rule(Rc::new(ParsingRule::new("pre", empty_set!(), Rc::new(CodeBlockHandler::new_block()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("code", empty_set!(), Rc::new(CodeBlockHandler::new_inline()))));
       self.add_matching_rule(Rc::new(ParsingRule::new("table", empty_set!(), Rc::new(TableHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("strike", empty_set!(), Rc::new(StrikethroughHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("del", empty_set!(), Rc::new(StrikethroughHandler::new()))));
+      self.add_matching_rule(Rc::new(ParsingRule::new("ruby", empty_set!(), Rc::new(RubyHandler::new(RubyMode::Parenthesized)))));
+
+      return self;
     }
 
-    pub fn get_matching_rules(&self, element: &Element) -> Option<Vec<Rc<ParsingRule>>> {
-      let mut all_rules: Vec<Rc<ParsingRule>> = Vec::with_capacity(16);
+    pub fn add_text_rule(&mut self, text_rule: TextRule) -> &mut ParsingRules {
+      self.text_rules.push(Rc::new(text_rule));
+      return self;
+    }
 
-      for (_, rules) in &self.matching_rules {
-        for rule in rules {
-          if rule.tag_name != "*" && rule.tag_name != element.tag_name {
-            continue;
-          }
+    pub fn add_rule(&mut self, rule: ParsingRule) -> &mut ParsingRules {
+      self.add_matching_rule(Rc::new(rule));
+      return self;
+    }
 
-          let mut all_req_attributes_match = true;
-
-          for required_attribute in &rule.required_attributes {
-            if &required_attribute.attr_name != "*" {
-              let element_attr_value_maybe = element.attributes.get(&required_attribute.attr_name);
-
-              let element_attr_value = if let Option::None = element_attr_value_maybe {
-                all_req_attributes_match = false;
-                break;
-              } else {
-                element_attr_value_maybe.unwrap()
-              };
-
-              let required_attr_value = if let Option::None = required_attribute.attr_value {
-                continue;
-              } else {
-                required_attribute.attr_value.as_ref().unwrap()
-              };
-
-              if element_attr_value != required_attr_value {
-                all_req_attributes_match = false;
-                break;
-              }
-            }
-          }
+    /// Handler for every [tag_name] element
+    pub fn add_tag_handler(&mut self, tag_name: &str, handler: Rc<dyn RuleHandler>) -> &mut ParsingRules {
+      return self.add_rule(ParsingRule::new(tag_name, empty_set!(), handler));
+    }
 
-          if !all_req_attributes_match {
-            continue;
-          }
+    /// Handler for every element (no matter the tag) that has [class_name] class
+    pub fn add_class_handler(&mut self, class_name: &str, handler: Rc<dyn RuleHandler>) -> &mut ParsingRules {
+      return self.add_tag_class_handler("*", class_name, handler);
+    }
+
+    /// Handler for every [tag_name] element that has [class_name] class
+    pub fn add_tag_class_handler(
+      &mut self,
+      tag_name: &str,
+      class_name: &str,
+      handler: Rc<dyn RuleHandler>
+    ) -> &mut ParsingRules {
+      return self.add_rule(ParsingRule::new(tag_name, set_of!(Attribute::with_class(class_name)), handler));
+    }
+
+    /// Same as [add_tag_handler] but removes the existing handlers of [tag_name] first (rules that
+    /// require attributes are left intact)
+    pub fn replace_tag_handler(&mut self, tag_name: &str, handler: Rc<dyn RuleHandler>) -> &mut ParsingRules {
+      self.remove_tag_handlers(tag_name);
+      return self.add_tag_handler(tag_name, handler);
+    }
+
+    pub fn replace_class_handler(&mut self, class_name: &str, handler: Rc<dyn RuleHandler>) -> &mut ParsingRules {
+      self.remove_class_handlers(class_name);
+      return self.add_class_handler(class_name, handler);
+    }
+
+    pub fn replace_tag_class_handler(
+      &mut self,
+      tag_name: &str,
+      class_name: &str,
+      handler: Rc<dyn RuleHandler>
+    ) -> &mut ParsingRules {
+      self.remove_tag_class_handlers(tag_name, class_name);
+      return self.add_tag_class_handler(tag_name, class_name, handler);
+    }
+
+    /// Removes the general rules of [tag_name], the ones that neither require attributes nor have a
+    /// selector
+    pub fn remove_tag_handlers(&mut self, tag_name: &str) -> &mut ParsingRules {
+      return self.remove_rules(tag_name, |rule| rule.selector.is_none() && rule.required_attributes.is_empty());
+    }
+
+    pub fn remove_class_handlers(&mut self, class_name: &str) -> &mut ParsingRules {
+      return self.remove_tag_class_handlers("*", class_name);
+    }
+
+    pub fn remove_tag_class_handlers(&mut self, tag_name: &str, class_name: &str) -> &mut ParsingRules {
+      let class_attribute = Attribute::with_class(class_name);
+      return self.remove_rules(tag_name, |rule| rule.required_attributes.contains(&class_attribute));
+    }
+
+    /// Removes all the rules of [tag_name] no matter what attributes they require
+    pub fn remove_all_tag_rules(&mut self, tag_name: &str) -> &mut ParsingRules {
+      self.matching_rules.remove(tag_name);
+      return self;
+    }
+
+    fn remove_rules(&mut self, tag_name: &str, predicate: impl Fn(&ParsingRule) -> bool) -> &mut ParsingRules {
+      if let Option::Some(rules) = self.matching_rules.get_mut(tag_name) {
+        rules.retain(|rule| !predicate(rule));
+      }
+
+      return self;
+    }
+
+    /// Replaces the rules of the <s> tag. Default rules treat it as a spoiler (the 4chan way).
+    /// Replaces the <span class="deadlink"> and the general <a> handlers with ones that parse and
+    /// render the quotes with [quote_format]
+    pub fn set_quote_format(&mut self, quote_format: QuoteFormat) -> &mut ParsingRules {
+      self.quote_format = quote_format.clone();
+      self.remove_tag_class_handlers("span", "deadlink");
+      self.add_rule(dead_link_rule(quote_format.clone()));
+      return self.replace_tag_handler("a", Rc::new(AnchorRuleHandler::with_quote_format(quote_format)));
+    }
+
+    pub fn quote_format(&self) -> &QuoteFormat {
+      return &self.quote_format;
+    }
+
+    pub fn set_s_tag_meaning(&mut self, s_tag_meaning: STagMeaning) -> &mut ParsingRules {
+      let handler: Rc<dyn RuleHandler> = match s_tag_meaning {
+        STagMeaning::Spoiler => Rc::new(SpoilerHandler::new()),
+        STagMeaning::Strikethrough => Rc::new(StrikethroughHandler::new())
+      };
+
+      return self.replace_tag_handler("s", handler);
+    }
+
+    pub fn set_ruby_mode(&mut self, ruby_mode: RubyMode) -> &mut ParsingRules {
+      return self.replace_tag_handler("ruby", Rc::new(RubyHandler::new(ruby_mode)));
+    }
+
+  }
+
+  impl CommentParser<'_> {
+
+    pub fn new(post_parser_context: &PostParserContext) -> CommentParser {
+      return CommentParser {
+        post_parser_context,
+        parsing_rules: ParsingRules::new(),
+        replacement_rules: HashMap::new()
+      }
+    }
+
+    pub fn add_replacement_rule(&mut self, pattern: &str, value: &str) {
+      let result = self.replacement_rules.insert(String::from(pattern), String::from(value));
+
+      if result.is_some() {
+        panic!("add_replacement() pattern {} with value {} was already added! Old value: {}", pattern, value, result.unwrap());
+      }
+    }
+
+    pub fn add_default_matching_rules(&mut self) {
+      self.parsing_rules.add_default_rules();
+    }
+
+    pub fn parsing_rules(&mut self) -> &mut ParsingRules {
+      return &mut self.parsing_rules;
+    }
+
+    pub fn set_parsing_rules(&mut self, parsing_rules: ParsingRules) {
+      self.parsing_rules = parsing_rules;
+    }
+
+    pub fn set_s_tag_meaning(&mut self, s_tag_meaning: STagMeaning) {
+      self.parsing_rules.set_s_tag_meaning(s_tag_meaning);
+    }
+
+    /// Whether any of the rules of the element's own tag applies to it (wildcard rules are ignored)
+    pub fn has_tag_rule(&self, element: &Element) -> bool {
+      return self.get_matching_rules(element)
+        .iter()
+        .any(|rule| rule.tag_name != "*");
+    }
 
+    /// The wildcard rules and the rules of the element's tag that apply to it, from the highest
+    /// priority to the lowest one. Wildcard rules go first when the priorities are equal.
+    pub fn get_matching_rules(&self, element: &Element) -> Vec<Rc<ParsingRule>> {
+      let matching_rules = &self.parsing_rules.matching_rules;
+      let no_rules: &[Rc<ParsingRule>] = &[];
+
+      // Both are already sorted by priority (see ParsingRules::add_matching_rule()) so they are
+      // only merged
+      let mut wildcard_rules = matching_rules.get("*").map_or(no_rules, |rules| rules.as_slice()).iter().peekable();
+      let mut tag_rules = matching_rules.get(&*element.tag_name).map_or(no_rules, |rules| rules.as_slice()).iter().peekable();
+
+      let mut all_rules: Vec<Rc<ParsingRule>> = Vec::with_capacity(16);
+
+      loop {
+        let take_tag_rule = match (wildcard_rules.peek(), tag_rules.peek()) {
+          (Option::Some(wildcard_rule), Option::Some(tag_rule)) => tag_rule.priority > wildcard_rule.priority,
+          (Option::Some(_), Option::None) => false,
+          (Option::None, Option::Some(_)) => true,
+          (Option::None, Option::None) => break
+        };
+
+        let rule = if take_tag_rule {
+          tag_rules.next().unwrap()
+        } else {
+          wildcard_rules.next().unwrap()
+        };
+
+        if rule.applies(element) {
           all_rules.push(rule.clone());
         }
       }
 
-      return Option::Some(all_rules.to_vec());
+      return all_rules;
     }
 
     /// returns true if we managed to parse this node fully and don't need to go deeper for child nodes.
@@ -304,25 +754,30 @@ pub mod comment_parser {
       &self,
       post_raw: &PostRaw,
       element: &Element,
-      out_text_parts: &mut Vec<TextPart>,
+      out_text_parts: &mut TextParts,
       out_spannables: &mut Vec<Spannable>
     ) -> bool {
-      let rules_maybe = self.get_matching_rules(element);
-
-      let rules = match rules_maybe {
-        None => return false,
-        Some(_) => rules_maybe.unwrap()
-      };
-
-      for index in 0..2 {
-        let high_priority = index == 0;
+      let rules = self.get_matching_rules(element);
+      if rules.is_empty() {
+        return false;
+      }
 
-        for rule in &rules {
-          if rule.high_priority() == high_priority && rule.applies(element) {
-            if rule.handler.pre_handle(post_raw, self.post_parser_context, element, out_text_parts, out_spannables) {
-              return true
-            }
-          }
+      let prev_out_text_parts_index = out_text_parts.len();
+      let prev_out_spannables_index = out_spannables.len();
+
+      let mut ctx = HandlerCtx::new(
+        post_raw,
+        self.post_parser_context,
+        element,
+        prev_out_text_parts_index,
+        out_text_parts,
+        prev_out_spannables_index,
+        out_spannables
+      );
+
+      for rule in &rules {
+        if rule.handler.pre_handle(&mut ctx) {
+          return true
         }
       }
 
@@ -330,40 +785,58 @@ pub mod comment_parser {
     }
 
     /// Called after element's child nodes were all processed. Useful when you need to know the len of
-    /// child nodes text
+    /// child nodes text. See RuleHandler::wraps_child_spannables() for the order of the spannables.
     pub fn post_process_element(
       &self,
       post_raw: &PostRaw,
       element: &Element,
       prev_out_text_parts_index: usize,
-      out_text_parts: &mut Vec<TextPart>,
+      out_text_parts: &mut TextParts,
       prev_out_spannables_index: usize,
       out_spannables: &mut Vec<Spannable>
     ) {
-      let rules_maybe = self.get_matching_rules(element);
+      let rules = self.get_matching_rules(element);
+      if rules.is_empty() {
+        return;
+      }
 
-      let rules = match rules_maybe {
-        None => return,
-        Some(_) => rules_maybe.unwrap()
-      };
+      let mut ctx = HandlerCtx::new(
+        post_raw,
+        self.post_parser_context,
+        element,
+        prev_out_text_parts_index,
+        out_text_parts,
+        prev_out_spannables_index,
+        out_spannables
+      );
 
-      for index in 0..2 {
-        let high_priority = index == 0;
-
-        for rule in &rules {
-          if rule.high_priority() == high_priority && rule.applies(element) {
-            rule.handler.post_handle(
-              post_raw,
-              self.post_parser_context,
-              element,
-              prev_out_text_parts_index,
-              out_text_parts,
-              prev_out_spannables_index,
-              out_spannables
-            )
-          }
+      for rule in &rules {
+        let out_spannables_count = ctx.out_spannables.len();
+
+        rule.handler.post_handle(&mut ctx);
+
+        if rule.handler.wraps_child_spannables() {
+          move_before_child_spannables(prev_out_spannables_index, out_spannables_count, ctx.out_spannables);
         }
       }
     }
   }
-}
\ No newline at end of file
+
+  fn dead_link_rule(quote_format: QuoteFormat) -> ParsingRule {
+    let handler = Rc::new(DeadLinkHandler::with_quote_format(quote_format));
+
+    return ParsingRule::new("span", set_of!(Attribute::with_class("deadlink")), handler)
+      .with_priority(DEAD_LINK_PRIORITY);
+  }
+
+  /// Moves the spannables added after out_spannables_count in front of the spannables of the child
+  /// nodes (the ones after prev_out_spannables_index)
+  fn move_before_child_spannables(prev_out_spannables_index: usize, out_spannables_count: usize, out_spannables: &mut Vec<Spannable>) {
+    if out_spannables.len() <= out_spannables_count || prev_out_spannables_index >= out_spannables_count {
+      return;
+    }
+
+    let added_spannables_count = out_spannables.len() - out_spannables_count;
+    out_spannables[prev_out_spannables_index..].rotate_right(added_spannables_count);
+  }
+}
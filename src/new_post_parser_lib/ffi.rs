@@ -0,0 +1,563 @@
+//! C api of the parser (the header is generated with cbindgen, see cbindgen.toml). Every pointer
+//! returned by the library must be released with the matching *_destroy function.
+//! Strings are utf-8 and nul-terminated, spannable start/len are in unicode scalars.
+//! post_parser_parse_comment() returns null when it fails, post_parser_last_error() tells why.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::collections::HashSet;
+use std::ptr;
+use crate::{PostParser, PostParserContext, PostRaw, Spannable, SpannableData, PostLink, ParsedSpannableText, MediaLinkKind, CapcodeKind, ColoredQuoteRole};
+
+thread_local! {
+  // Why the last failed call on this thread failed, see post_parser_last_error()
+  static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(Option::None);
+}
+
+pub struct FfiPostParser {
+  // Borrows post_parser_context, must be dropped first
+  post_parser: Option<PostParser<'static>>,
+  post_parser_context: *mut PostParserContext
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FfiSpannableKind {
+  Unknown = 0,
+  Quote = 1,
+  DeadQuote = 2,
+  UrlLink = 3,
+  BoardLink = 4,
+  SearchLink = 5,
+  ThreadLink = 6,
+  DeadCrossBoardQuote = 7,
+  EmailLink = 8,
+  Spoiler = 9,
+  GreenText = 10,
+  BoldText = 11,
+  Italic = 12,
+  Underline = 13,
+  Strikethrough = 14,
+  Superscript = 15,
+  Subscript = 16,
+  ListItem = 17,
+  SjisArt = 18,
+  Heading = 19,
+  TableCell = 20,
+  InlineCode = 21,
+  CodeBlock = 22,
+  Monospace = 23,
+  TextForegroundColor = 24,
+  TextBackgroundColor = 25,
+  ThemeJson = 26,
+  MediaLink = 27,
+  Fortune = 28,
+  DiceRoll = 29,
+  Capcode = 30,
+  SearchHighlight = 31,
+  FilterHighlight = 32,
+  Poll = 33,
+  TeX = 34,
+  Ruby = 35,
+  UnknownTag = 36,
+  BanMessage = 37,
+  ColoredQuote = 38,
+  PosterId = 39,
+  Custom = 40,
+}
+
+pub const FFI_FLAG_IS_YOU: u32 = 1;
+pub const FFI_FLAG_IS_OP: u32 = 1 << 1;
+pub const FFI_FLAG_ORDERED: u32 = 1 << 2;
+pub const FFI_FLAG_IS_LIGHT_THEME: u32 = 1 << 3;
+pub const FFI_FLAG_DISPLAY_MODE: u32 = 1 << 4;
+
+/// Flattened Spannable. What the payload fields mean depends on the kind:
+/// - Quote/DeadQuote: number1 = post_no, flags = FFI_FLAG_IS_YOU | FFI_FLAG_IS_OP, text1 =
+///   poster_name and text2 = comment_preview of the quote preview (null without one)
+/// - UrlLink/EmailLink: text1 = url/email
+/// - BoardLink: text1 = board_code
+/// - SearchLink: text1 = board_code, text2 = search_query
+/// - ThreadLink: text1 = board_code, number1 = post_no, number2 = thread_no
+/// - DeadCrossBoardQuote: text1 = board_code, number1 = post_no
+/// - ListItem: number1 = index, number2 = depth, flags = FFI_FLAG_ORDERED
+/// - Heading: number1 = level
+/// - TextForegroundColor/TextBackgroundColor: text1 = color hex (or number1 = ChanThemeColorId
+///   when text1 is null)
+/// - ThemeJson: text1 = theme_name, flags = FFI_FLAG_IS_LIGHT_THEME
+/// - MediaLink: text1 = id, number1 = 0 YouTube/1 Streamable/2 Vimeo/3 SoundCloud
+/// - Fortune: text1 = color
+/// - DiceRoll: number1 = dice, number2 = total (as u64 two's complement)
+/// - Capcode: text1 = capcode, number1 = 0 Admin/1 Mod/2 Developer/3 Founder/4 Other
+/// - FilterHighlight: text1 = color
+/// - Poll: text1 = question, number1 = options count (the options are in ParsedPost polls)
+/// - TeX: flags = FFI_FLAG_DISPLAY_MODE, text1 = TeX source (TeXMode::Placeholder only)
+/// - Ruby: text1 = reading
+/// - UnknownTag: text1 = tag
+/// - ColoredQuote: number1 = 0 Orange/1 Pink/2 Purple/3 Red/4 Blue
+/// - PosterId: text1 = id, number1 = PosterId::color()
+/// - Custom: number1 = kind (the payload is only available through ParsedPost::to_bytes())
+/// Unused string fields are null.
+#[repr(C)]
+pub struct FfiSpannable {
+  pub start: usize,
+  pub len: usize,
+  pub kind: FfiSpannableKind,
+  pub flags: u32,
+  pub number1: u64,
+  pub number2: u64,
+  pub text1: *mut c_char,
+  pub text2: *mut c_char
+}
+
+#[repr(C)]
+pub struct FfiParsedComment {
+  pub text: *mut c_char,
+  pub spannables: *mut FfiSpannable,
+  pub spannables_len: usize
+}
+
+/// my_replies/thread_posts may be null when their len is 0
+///
+/// # Safety
+/// my_replies/thread_posts must point to at least my_replies_len/thread_posts_len u64s (unless they
+/// are null). The returned parser must be released with post_parser_destroy().
+#[no_mangle]
+pub unsafe extern "C" fn post_parser_create(
+  my_replies: *const u64,
+  my_replies_len: usize,
+  thread_posts: *const u64,
+  thread_posts_len: usize
+) -> *mut FfiPostParser {
+  let post_parser_context = Box::into_raw(Box::new(PostParserContext::new(
+    to_post_no_set(my_replies, my_replies_len),
+    to_post_no_set(thread_posts, thread_posts_len)
+  )));
+
+  let post_parser = PostParser::new(&*post_parser_context);
+
+  return Box::into_raw(Box::new(FfiPostParser {
+    post_parser: Option::Some(post_parser),
+    post_parser_context
+  }));
+}
+
+/// Returns null (see post_parser_last_error()) if any of the pointers is null or any of the strings
+/// is not valid utf-8
+///
+/// # Safety
+/// post_parser must come from post_parser_create() and must not be used after
+/// post_parser_destroy(). The strings must be nul-terminated. The returned comment must be released
+/// with post_parser_parsed_comment_destroy().
+#[no_mangle]
+pub unsafe extern "C" fn post_parser_parse_comment(
+  post_parser: *const FfiPostParser,
+  site_name: *const c_char,
+  board_code: *const c_char,
+  thread_no: u64,
+  post_no: u64,
+  post_sub_no: u64,
+  comment: *const c_char
+) -> *mut FfiParsedComment {
+  set_last_error(Option::None);
+
+  if post_parser.is_null() {
+    set_last_error(Option::Some("post_parser_parse_comment() post_parser is null"));
+    return ptr::null_mut();
+  }
+
+  let site_name = to_str(site_name);
+  let board_code = to_str(board_code);
+  let comment = to_str(comment);
+
+  if site_name.is_none() || board_code.is_none() || comment.is_none() {
+    set_last_error(Option::Some("post_parser_parse_comment() bad string argument"));
+    return ptr::null_mut();
+  }
+
+  let post_raw = PostRaw::new(
+    site_name.unwrap(),
+    board_code.unwrap(),
+    thread_no,
+    post_no,
+    post_sub_no,
+    comment.unwrap()
+  );
+
+  let post_comment_parsed = (*post_parser).post_parser.as_ref().unwrap().parse_comment(&post_raw);
+  return Box::into_raw(Box::new(to_ffi_parsed_comment(post_comment_parsed)));
+}
+
+/// # Safety
+/// parsed_comment must be null or come from post_parser_parse_comment() and must not be used (or
+/// destroyed again) afterwards. The fields of the comment must not be modified.
+#[no_mangle]
+pub unsafe extern "C" fn post_parser_parsed_comment_destroy(parsed_comment: *mut FfiParsedComment) {
+  if parsed_comment.is_null() {
+    return;
+  }
+
+  let parsed_comment = Box::from_raw(parsed_comment);
+  free_c_string(parsed_comment.text);
+
+  let spannables = Vec::from_raw_parts(
+    parsed_comment.spannables,
+    parsed_comment.spannables_len,
+    parsed_comment.spannables_len
+  );
+
+  for spannable in spannables {
+    free_c_string(spannable.text1);
+    free_c_string(spannable.text2);
+  }
+}
+
+/// # Safety
+/// post_parser must be null or come from post_parser_create() and must not be used (or destroyed
+/// again) afterwards. The comments it parsed stay valid.
+#[no_mangle]
+pub unsafe extern "C" fn post_parser_destroy(post_parser: *mut FfiPostParser) {
+  if post_parser.is_null() {
+    return;
+  }
+
+  let mut post_parser = Box::from_raw(post_parser);
+  post_parser.post_parser = Option::None;
+  drop(Box::from_raw(post_parser.post_parser_context));
+}
+
+/// Why the last post_parser_parse_comment() call on this thread returned null, null if it didn't.
+/// The string is owned by the library and stays valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn post_parser_last_error() -> *const c_char {
+  return LAST_ERROR.with(|last_error| {
+    return last_error.borrow().as_ref().map(|error| error.as_ptr()).unwrap_or(ptr::null());
+  });
+}
+
+fn set_last_error(error: Option<&str>) {
+  #[cfg(feature = "log")]
+  if let Option::Some(error) = error {
+    log::warn!("{}", error);
+  }
+
+  LAST_ERROR.with(|last_error| {
+    *last_error.borrow_mut() = error.and_then(|error| CString::new(error).ok());
+  });
+}
+
+unsafe fn to_post_no_set(post_nos: *const u64, len: usize) -> HashSet<u64> {
+  if post_nos.is_null() || len == 0 {
+    return HashSet::new();
+  }
+
+  return std::slice::from_raw_parts(post_nos, len).iter().cloned().collect();
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Option<&'a str> {
+  if string.is_null() {
+    return Option::None;
+  }
+
+  return CStr::from_ptr(string).to_str().ok();
+}
+
+unsafe fn free_c_string(string: *mut c_char) {
+  if !string.is_null() {
+    drop(CString::from_raw(string));
+  }
+}
+
+fn to_c_string(string: &str) -> *mut c_char {
+  // The parsed text can't contain nul characters (they are not valid in html) but just in case
+  return match CString::new(string.replace('\0', "")) {
+    Ok(c_string) => c_string.into_raw(),
+    Err(_) => ptr::null_mut()
+  };
+}
+
+fn to_ffi_parsed_comment(parsed_spannable_text: ParsedSpannableText) -> FfiParsedComment {
+  let spannables = parsed_spannable_text.spannables
+    .iter()
+    .map(|spannable| to_ffi_spannable(spannable))
+    .collect::<Vec<FfiSpannable>>()
+    .into_boxed_slice();
+
+  let spannables_len = spannables.len();
+
+  return FfiParsedComment {
+    text: to_c_string(parsed_spannable_text.parsed_text.as_str()),
+    spannables: Box::into_raw(spannables) as *mut FfiSpannable,
+    spannables_len
+  };
+}
+
+pub const PARSED_COMMENT_BUFFER_VERSION: u32 = 1;
+
+/// Encodes the parsed comment into one flat buffer (for the JNI api where passing one byte array
+/// is way cheaper than creating a java object per spannable). Everything is big endian (the java
+/// ByteBuffer default):
+/// u32 version, string text, u32 spannables count, spannables
+/// spannable: u32 start, u32 len, u32 kind (FfiSpannableKind), u32 flags, u64 number1, u64 number2,
+///   string text1, string text2
+/// string: i32 length in bytes (-1 for null), utf-8 bytes
+pub fn encode_parsed_comment(parsed_spannable_text: &ParsedSpannableText) -> Vec<u8> {
+  let mut buffer: Vec<u8> = Vec::with_capacity(
+    parsed_spannable_text.parsed_text.len() + parsed_spannable_text.spannables.len() * 48 + 12
+  );
+
+  buffer.extend_from_slice(&PARSED_COMMENT_BUFFER_VERSION.to_be_bytes());
+  encode_string(&mut buffer, Option::Some(parsed_spannable_text.parsed_text.as_str()));
+  buffer.extend_from_slice(&(parsed_spannable_text.spannables.len() as u32).to_be_bytes());
+
+  for spannable in parsed_spannable_text.spannables.iter() {
+    let flat_spannable = flatten_spannable(spannable);
+
+    buffer.extend_from_slice(&(flat_spannable.start as u32).to_be_bytes());
+    buffer.extend_from_slice(&(flat_spannable.len as u32).to_be_bytes());
+    buffer.extend_from_slice(&(flat_spannable.kind as u32).to_be_bytes());
+    buffer.extend_from_slice(&flat_spannable.flags.to_be_bytes());
+    buffer.extend_from_slice(&flat_spannable.number1.to_be_bytes());
+    buffer.extend_from_slice(&flat_spannable.number2.to_be_bytes());
+    encode_string(&mut buffer, flat_spannable.text1);
+    encode_string(&mut buffer, flat_spannable.text2);
+  }
+
+  return buffer;
+}
+
+fn encode_string(buffer: &mut Vec<u8>, string: Option<&str>) {
+  match string {
+    Option::None => buffer.extend_from_slice(&(-1i32).to_be_bytes()),
+    Option::Some(string) => {
+      buffer.extend_from_slice(&(string.len() as i32).to_be_bytes());
+      buffer.extend_from_slice(string.as_bytes());
+    }
+  }
+}
+
+fn to_ffi_spannable(spannable: &Spannable) -> FfiSpannable {
+  let flat_spannable = flatten_spannable(spannable);
+
+  return FfiSpannable {
+    start: flat_spannable.start,
+    len: flat_spannable.len,
+    kind: flat_spannable.kind,
+    flags: flat_spannable.flags,
+    number1: flat_spannable.number1,
+    number2: flat_spannable.number2,
+    text1: flat_spannable.text1.map(|text| to_c_string(text)).unwrap_or(ptr::null_mut()),
+    text2: flat_spannable.text2.map(|text| to_c_string(text)).unwrap_or(ptr::null_mut())
+  };
+}
+
+/// FfiSpannable with borrowed strings
+pub(crate) struct FlatSpannable<'a> {
+  pub(crate) start: usize,
+  pub(crate) len: usize,
+  pub(crate) kind: FfiSpannableKind,
+  pub(crate) flags: u32,
+  pub(crate) number1: u64,
+  pub(crate) number2: u64,
+  pub(crate) text1: Option<&'a str>,
+  pub(crate) text2: Option<&'a str>
+}
+
+pub(crate) fn flatten_spannable(spannable: &Spannable) -> FlatSpannable {
+  let mut flat_spannable = FlatSpannable {
+    start: spannable.start,
+    len: spannable.len,
+    kind: FfiSpannableKind::Unknown,
+    flags: 0,
+    number1: 0,
+    number2: 0,
+    text1: Option::None,
+    text2: Option::None
+  };
+
+  match &spannable.spannable_data {
+    SpannableData::Link(post_link) => {
+      match post_link {
+        PostLink::Quote { post_no, is_you, is_op, preview } => {
+          flat_spannable.kind = FfiSpannableKind::Quote;
+          flat_spannable.number1 = *post_no;
+
+          if let Option::Some(preview) = preview {
+            flat_spannable.text1 = Option::Some(&preview.poster_name);
+            flat_spannable.text2 = Option::Some(&preview.comment_preview);
+          }
+
+          if *is_you {
+            flat_spannable.flags |= FFI_FLAG_IS_YOU;
+          }
+
+          if *is_op {
+            flat_spannable.flags |= FFI_FLAG_IS_OP;
+          }
+        }
+        PostLink::Dead { post_no } => {
+          flat_spannable.kind = FfiSpannableKind::DeadQuote;
+          flat_spannable.number1 = *post_no;
+        }
+        PostLink::UrlLink { link } => {
+          flat_spannable.kind = FfiSpannableKind::UrlLink;
+          flat_spannable.text1 = Option::Some(link.as_str());
+        }
+        PostLink::BoardLink { board_code } => {
+          flat_spannable.kind = FfiSpannableKind::BoardLink;
+          flat_spannable.text1 = Option::Some(board_code.as_str());
+        }
+        PostLink::SearchLink { board_code, search_query } => {
+          flat_spannable.kind = FfiSpannableKind::SearchLink;
+          flat_spannable.text1 = Option::Some(board_code.as_str());
+          flat_spannable.text2 = Option::Some(search_query.as_str());
+        }
+        PostLink::ThreadLink { board_code, thread_no, post_no } => {
+          flat_spannable.kind = FfiSpannableKind::ThreadLink;
+          flat_spannable.text1 = Option::Some(board_code.as_str());
+          flat_spannable.number1 = *post_no;
+          flat_spannable.number2 = *thread_no;
+        }
+        PostLink::DeadCrossBoard { board_code, post_no } => {
+          flat_spannable.kind = FfiSpannableKind::DeadCrossBoardQuote;
+          flat_spannable.text1 = Option::Some(board_code.as_str());
+          flat_spannable.number1 = *post_no;
+        }
+        PostLink::Email { email } => {
+          flat_spannable.kind = FfiSpannableKind::EmailLink;
+          flat_spannable.text1 = Option::Some(email.as_str());
+        }
+      }
+    }
+    SpannableData::Spoiler => flat_spannable.kind = FfiSpannableKind::Spoiler,
+    SpannableData::GreenText => flat_spannable.kind = FfiSpannableKind::GreenText,
+    SpannableData::BoldText => flat_spannable.kind = FfiSpannableKind::BoldText,
+    SpannableData::Italic => flat_spannable.kind = FfiSpannableKind::Italic,
+    SpannableData::Underline => flat_spannable.kind = FfiSpannableKind::Underline,
+    SpannableData::Strikethrough => flat_spannable.kind = FfiSpannableKind::Strikethrough,
+    SpannableData::Superscript => flat_spannable.kind = FfiSpannableKind::Superscript,
+    SpannableData::Subscript => flat_spannable.kind = FfiSpannableKind::Subscript,
+    SpannableData::ListItem { depth, ordered, index } => {
+      flat_spannable.kind = FfiSpannableKind::ListItem;
+      flat_spannable.number1 = *index as u64;
+      flat_spannable.number2 = *depth as u64;
+
+      if *ordered {
+        flat_spannable.flags |= FFI_FLAG_ORDERED;
+      }
+    }
+    SpannableData::SjisArt => flat_spannable.kind = FfiSpannableKind::SjisArt,
+    SpannableData::Heading { level } => {
+      flat_spannable.kind = FfiSpannableKind::Heading;
+      flat_spannable.number1 = *level as u64;
+    }
+    SpannableData::TableCell => flat_spannable.kind = FfiSpannableKind::TableCell,
+    SpannableData::InlineCode => flat_spannable.kind = FfiSpannableKind::InlineCode,
+    SpannableData::CodeBlock => flat_spannable.kind = FfiSpannableKind::CodeBlock,
+    SpannableData::Monospace => flat_spannable.kind = FfiSpannableKind::Monospace,
+    SpannableData::TextForegroundColorRaw { color_hex } => {
+      flat_spannable.kind = FfiSpannableKind::TextForegroundColor;
+      flat_spannable.text1 = Option::Some(color_hex.as_str());
+    }
+    SpannableData::TextBackgroundColorRaw { color_hex } => {
+      flat_spannable.kind = FfiSpannableKind::TextBackgroundColor;
+      flat_spannable.text1 = Option::Some(color_hex.as_str());
+    }
+    SpannableData::TextForegroundColorId { color_id } => {
+      flat_spannable.kind = FfiSpannableKind::TextForegroundColor;
+      flat_spannable.number1 = color_id.clone() as u64;
+    }
+    SpannableData::TextBackgroundColorId { color_id } => {
+      flat_spannable.kind = FfiSpannableKind::TextBackgroundColor;
+      flat_spannable.number1 = color_id.clone() as u64;
+    }
+    SpannableData::ThemeJson { theme_name, is_light_theme } => {
+      flat_spannable.kind = FfiSpannableKind::ThemeJson;
+      flat_spannable.text1 = Option::Some(theme_name.as_str());
+
+      if *is_light_theme {
+        flat_spannable.flags |= FFI_FLAG_IS_LIGHT_THEME;
+      }
+    }
+    SpannableData::MediaLink { kind, id } => {
+      flat_spannable.kind = FfiSpannableKind::MediaLink;
+      flat_spannable.text1 = Option::Some(id.as_str());
+      flat_spannable.number1 = match kind {
+        MediaLinkKind::YouTube => 0,
+        MediaLinkKind::Streamable => 1,
+        MediaLinkKind::Vimeo => 2,
+        MediaLinkKind::SoundCloud => 3
+      };
+    }
+    SpannableData::Fortune { color } => {
+      flat_spannable.kind = FfiSpannableKind::Fortune;
+      flat_spannable.text1 = Option::Some(color.as_str());
+    }
+    SpannableData::DiceRoll(post_roll) => {
+      flat_spannable.kind = FfiSpannableKind::DiceRoll;
+      flat_spannable.number1 = post_roll.dice as u64;
+      flat_spannable.number2 = post_roll.total as u64;
+    }
+    SpannableData::Capcode { capcode, kind } => {
+      flat_spannable.kind = FfiSpannableKind::Capcode;
+      flat_spannable.text1 = Option::Some(capcode.as_str());
+      flat_spannable.number1 = match kind {
+        CapcodeKind::Admin => 0,
+        CapcodeKind::Mod => 1,
+        CapcodeKind::Developer => 2,
+        CapcodeKind::Founder => 3,
+        CapcodeKind::Other => 4
+      };
+    }
+    SpannableData::SearchHighlight => flat_spannable.kind = FfiSpannableKind::SearchHighlight,
+    SpannableData::FilterHighlight { color } => {
+      flat_spannable.kind = FfiSpannableKind::FilterHighlight;
+      flat_spannable.text1 = Option::Some(color.as_str());
+    }
+    SpannableData::Poll(post_poll) => {
+      flat_spannable.kind = FfiSpannableKind::Poll;
+      flat_spannable.text1 = Option::Some(post_poll.question.as_str());
+      flat_spannable.number1 = post_poll.options.len() as u64;
+    }
+    SpannableData::TeX { display_mode, source } => {
+      flat_spannable.kind = FfiSpannableKind::TeX;
+      flat_spannable.text1 = source.as_deref();
+      if *display_mode {
+        flat_spannable.flags |= FFI_FLAG_DISPLAY_MODE;
+      }
+    }
+    SpannableData::Ruby { reading } => {
+      flat_spannable.kind = FfiSpannableKind::Ruby;
+      flat_spannable.text1 = Option::Some(reading.as_str());
+    }
+    SpannableData::Unknown { tag } => {
+      flat_spannable.kind = FfiSpannableKind::UnknownTag;
+      flat_spannable.text1 = Option::Some(tag.as_str());
+    }
+    SpannableData::BanMessage => flat_spannable.kind = FfiSpannableKind::BanMessage,
+    SpannableData::ColoredQuote { role } => {
+      flat_spannable.kind = FfiSpannableKind::ColoredQuote;
+      flat_spannable.number1 = match role {
+        ColoredQuoteRole::Orange => 0,
+        ColoredQuoteRole::Pink => 1,
+        ColoredQuoteRole::Purple => 2,
+        ColoredQuoteRole::Red => 3,
+        ColoredQuoteRole::Blue => 4
+      };
+    }
+    SpannableData::PosterId(poster_id) => {
+      flat_spannable.kind = FfiSpannableKind::PosterId;
+      flat_spannable.text1 = Option::Some(poster_id.id.as_str());
+      flat_spannable.number1 = poster_id.color() as u64;
+    }
+    SpannableData::Custom { kind, .. } => {
+      flat_spannable.kind = FfiSpannableKind::Custom;
+      flat_spannable.number1 = *kind as u64;
+    }
+    // FontSize/FontWeight/StyleHint are not exposed (yet)
+    _ => {}
+  }
+
+  return flat_spannable;
+}
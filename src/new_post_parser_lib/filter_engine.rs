@@ -0,0 +1,114 @@
+pub mod filter_engine {
+  use regex::{Regex, RegexBuilder};
+  use crate::{FilterEngine, Filter, FilterPattern, FilterScope, FilterAction, FilterMatch, ParsedPost, Spannable, SpannableData};
+  use crate::parsing_error::ParsingError;
+
+  impl FilterEngine {
+    pub fn new() -> FilterEngine {
+      return FilterEngine { filters: Vec::new() };
+    }
+
+    /// Replaces the filter with the same filter_id if there is one. Fails if the regex pattern can't
+    /// be compiled.
+    pub fn add_filter(
+      &mut self,
+      filter_id: u64,
+      filter_pattern: FilterPattern,
+      scopes: Vec<FilterScope>,
+      action: FilterAction,
+      highlight_color: Option<String>
+    ) -> Result<(), ParsingError> {
+      let pattern = match &filter_pattern {
+        FilterPattern::Regex { pattern } => Regex::new(pattern),
+        FilterPattern::Plain { text, case_insensitive } => {
+          RegexBuilder::new(&regex::escape(text)).case_insensitive(*case_insensitive).build()
+        }
+      };
+
+      let pattern = match pattern {
+        Ok(pattern) => pattern,
+        Err(error) => return Err(ParsingError::new(&format!("Bad filter pattern {:?}: {}", filter_pattern, error)))
+      };
+
+      let filter = Filter { filter_id, pattern, scopes, action, highlight_color };
+
+      match self.filters.iter().position(|filter| filter.filter_id == filter_id) {
+        Option::Some(index) => self.filters[index] = filter,
+        Option::None => self.filters.push(filter)
+      }
+
+      return Ok(());
+    }
+
+    pub fn remove_filter(&mut self, filter_id: u64) {
+      self.filters.retain(|filter| filter.filter_id != filter_id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+      return self.filters.is_empty();
+    }
+
+    /// Adds a FilterHighlight spannable over every match of the filters that have a highlight color
+    /// and apply to the scope. Spannable offsets are in chars.
+    pub fn highlight(&self, filter_scope: FilterScope, text: &str, out_spannables: &mut Vec<Spannable>) {
+      for filter in &self.filters {
+        let color = match &filter.highlight_color {
+          Option::Some(color) if filter.scopes.contains(&filter_scope) => color,
+          _ => continue
+        };
+
+        let mut chars_count_before_match = 0;
+        let mut prev_match_end = 0;
+
+        for found in filter.pattern.find_iter(text) {
+          if found.start() == found.end() {
+            continue;
+          }
+
+          chars_count_before_match += text[prev_match_end..found.start()].chars().count();
+          let match_chars_count = found.as_str().chars().count();
+
+          out_spannables.push(Spannable {
+            start: chars_count_before_match,
+            len: match_chars_count,
+            spannable_data: SpannableData::FilterHighlight { color: color.clone() }
+          });
+
+          chars_count_before_match += match_chars_count;
+          prev_match_end = found.end();
+        }
+      }
+    }
+
+    /// Filters that match the parsed text of the post (comment, subject, name, file names) in
+    /// their scopes, in the order they were added
+    pub fn evaluate(&self, parsed_post: &ParsedPost) -> Vec<FilterMatch> {
+      let mut filter_matches = Vec::new();
+
+      for filter in &self.filters {
+        let is_match = filter.scopes.iter().any(|filter_scope| {
+          return match filter_scope {
+            FilterScope::Comment => filter.pattern.is_match(parsed_post.post_comment_parsed.parsed_text.as_str()),
+            FilterScope::Subject => parsed_post.metadata.subject_parsed
+              .as_ref()
+              .map(|subject_parsed| filter.pattern.is_match(subject_parsed.parsed_text.as_str()))
+              .unwrap_or(false),
+            FilterScope::Name => parsed_post.metadata.poster_info.name
+              .as_ref()
+              .map(|name| filter.pattern.is_match(name))
+              .unwrap_or(false),
+            FilterScope::Filename => parsed_post.metadata.files
+              .iter()
+              .any(|file| filter.pattern.is_match(&file.original_name))
+          };
+        });
+
+        if is_match {
+          filter_matches.push(FilterMatch { filter_id: filter.filter_id, action: filter.action });
+        }
+      }
+
+      return filter_matches;
+    }
+  }
+}
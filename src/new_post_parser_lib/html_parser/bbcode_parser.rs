@@ -0,0 +1,136 @@
+use crate::html_parser::node::Node;
+use crate::html_parser::html_parser_error::HtmlParserError;
+use crate::{BbCodeParser, Element};
+use regex::Regex;
+
+lazy_static! {
+  // [b], [/b], [quote=Anonymous]
+  static ref BBCODE_TAG_PATTERN: Regex = Regex::new(r"\[(/?)([a-zA-Z]+)(?:=[^\]\[]*)?\]").unwrap();
+}
+
+struct OpenBbCodeTag {
+  bbcode_tag_name: String,
+  element: Element
+}
+
+impl BbCodeParser {
+  pub fn new() -> BbCodeParser {
+    return BbCodeParser {};
+  }
+
+  /// Converts BBCode into the same node tree HtmlParser produces so that the html rule handlers
+  /// work for it too:
+  /// [b] -> <b>, [i] -> <i>, [u] -> <u>, [s] -> <del>, [spoiler] -> <s>, [code] -> <pre>,
+  /// [quote] -> <span class="quote">, line breaks -> <br>.
+  /// Unknown tags and closing tags without a matching opening tag are kept as text, tag values
+  /// ([quote=Anonymous]) are ignored. The text inside of [code] is not parsed. Never fails, the
+  /// Result is there so that it can be used interchangeably with HtmlParser::parse().
+  pub fn parse(&self, text: &str) -> Result<Vec<Node>, HtmlParserError> {
+    let mut out_nodes: Vec<Node> = Vec::with_capacity(16);
+    let mut open_tags: Vec<OpenBbCodeTag> = Vec::with_capacity(8);
+    let mut text_start = 0;
+
+    for captures in BBCODE_TAG_PATTERN.captures_iter(text) {
+      let whole_match = captures.get(0).unwrap();
+      let is_closing_tag = !captures.get(1).unwrap().as_str().is_empty();
+      let bbcode_tag_name = captures.get(2).unwrap().as_str().to_lowercase();
+
+      let inside_code = open_tags.last()
+        .map(|open_tag| open_tag.bbcode_tag_name == "code")
+        .unwrap_or(false);
+
+      if inside_code && !(is_closing_tag && bbcode_tag_name == "code") {
+        continue;
+      }
+
+      let element = match self.create_element(&bbcode_tag_name) {
+        Option::Some(element) => element,
+        Option::None => continue
+      };
+
+      if is_closing_tag {
+        let index_maybe = open_tags.iter()
+          .rposition(|open_tag| open_tag.bbcode_tag_name == bbcode_tag_name);
+
+        let index = match index_maybe {
+          Option::Some(index) => index,
+          Option::None => continue
+        };
+
+        self.append_text(&text[text_start..whole_match.start()], &mut open_tags, &mut out_nodes);
+        self.close_tags(index, &mut open_tags, &mut out_nodes);
+      } else {
+        self.append_text(&text[text_start..whole_match.start()], &mut open_tags, &mut out_nodes);
+        open_tags.push(OpenBbCodeTag { bbcode_tag_name, element });
+      }
+
+      text_start = whole_match.end();
+    }
+
+    self.append_text(&text[text_start..], &mut open_tags, &mut out_nodes);
+
+    // Tags that were never closed end where the text ends
+    self.close_tags(0, &mut open_tags, &mut out_nodes);
+
+    return Result::Ok(out_nodes);
+  }
+
+  fn create_element(&self, bbcode_tag_name: &str) -> Option<Element> {
+    let (tag_name, class) = match bbcode_tag_name {
+      "b" => ("b", Option::None),
+      "i" => ("i", Option::None),
+      "u" => ("u", Option::None),
+      "s" => ("del", Option::None),
+      "spoiler" => ("s", Option::None),
+      "code" => ("pre", Option::None),
+      "quote" => ("span", Option::Some("quote")),
+      _ => return Option::None
+    };
+
+    let element = Element::new(tag_name, false);
+
+    return match class {
+      Option::None => Option::Some(element),
+      Option::Some(class) => Option::Some(element.with_attr("class", class))
+    };
+  }
+
+  /// Line breaks become <br> elements, except for the ones inside of [code] which are kept as is
+  fn append_text(&self, text: &str, open_tags: &mut Vec<OpenBbCodeTag>, out_nodes: &mut Vec<Node>) {
+    if text.is_empty() {
+      return;
+    }
+
+    let inside_code = open_tags.iter().any(|open_tag| open_tag.bbcode_tag_name == "code");
+    if inside_code {
+      self.append_node(Node::Text(text.replace("\r\n", "\n")), open_tags, out_nodes);
+      return;
+    }
+
+    for (index, line) in text.split('\n').enumerate() {
+      if index > 0 {
+        self.append_node(Node::Element(Element::new("br", true)), open_tags, out_nodes);
+      }
+
+      let line = line.strip_suffix('\r').unwrap_or(line);
+      if !line.is_empty() {
+        self.append_node(Node::Text(String::from(line)), open_tags, out_nodes);
+      }
+    }
+  }
+
+  /// Closes the tag at [index] and all the tags that were opened after it
+  fn close_tags(&self, index: usize, open_tags: &mut Vec<OpenBbCodeTag>, out_nodes: &mut Vec<Node>) {
+    while open_tags.len() > index {
+      let open_tag = open_tags.pop().unwrap();
+      self.append_node(Node::Element(open_tag.element), open_tags, out_nodes);
+    }
+  }
+
+  fn append_node(&self, node: Node, open_tags: &mut Vec<OpenBbCodeTag>, out_nodes: &mut Vec<Node>) {
+    match open_tags.last_mut() {
+      None => out_nodes.push(node),
+      Some(open_tag) => open_tag.element.children.push(node)
+    }
+  }
+}
@@ -1,44 +1,282 @@
-use crate::html_parser::node::Node;
+use crate::html_parser::node::{Node, BorrowedNode};
 use std::fmt;
-use crate::Element;
+use crate::{Element, BorrowedElement};
+use linked_hash_map::LinkedHashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::borrow::Cow;
+use crate::html_parser::name_interner::intern_name;
 
 const CLASS_ATTR: &str = "class";
+const ID_ATTR: &str = "id";
 
 impl Element {
+  /// Used by the front-ends that build the node tree out of something other than html
+  /// (BbCodeParser, MarkdownParser, PlainTextParser)
+  pub fn new(tag_name: &str, is_void_element: bool) -> Element {
+    return Element {
+      tag_name: intern_name(tag_name),
+      attributes: LinkedHashMap::new(),
+      children: Vec::new(),
+      is_void_element
+    };
+  }
+
+  pub fn with_attr(mut self, attr_name: &str, attr_value: &str) -> Element {
+    self.attributes.insert(intern_name(attr_name), Option::Some(String::from(attr_value)));
+    return self;
+  }
+
+  /// Checks whether one of the element's classes matches [class_name] (ignoring case)
   pub fn has_class(&self, class_name: &str) -> bool {
-    let class_attr_maybe = self.attributes.get(CLASS_ATTR);
-    if class_attr_maybe.is_none() {
+    return self.classes().any(|class| class.eq_ignore_ascii_case(class_name));
+  }
+
+  pub fn has_any_class(&self, class_names: &[&str]) -> bool {
+    return class_names.iter().any(|class_name| self.has_class(class_name));
+  }
+
+  /// Whitespace separated values of the "class" attribute
+  pub fn classes(&self) -> impl Iterator<Item=&str> {
+    return self.attr(CLASS_ATTR)
+      .unwrap_or("")
+      .split_ascii_whitespace();
+  }
+
+  pub fn id(&self) -> Option<&str> {
+    return self.attr(ID_ATTR);
+  }
+
+  /// Same as [get_attr_value] but returns a &str
+  pub fn attr(&self, attr_name: &str) -> Option<&str> {
+    return self.get_attr_value(attr_name).map(|attr_value| attr_value.as_str());
+  }
+
+  pub fn attr_or<'a>(&'a self, attr_name: &str, default: &'a str) -> &'a str {
+    return self.attr(attr_name).unwrap_or(default);
+  }
+
+  /// Checks the element against a simple (compound) css selector, e.g. "span.quote", "a[href]",
+  /// "s", ".deadlink", "#p123" or "a.quotelink[href=\"#p1\"]". Combinators (descendant, child
+  /// etc.) are not supported and a selector containing them never matches.
+  pub fn matches(&self, selector: &str) -> bool {
+    let selector = selector.trim();
+    if selector.is_empty() {
+      return false;
+    }
+
+    let mut chars = selector.chars().peekable();
+
+    let tag_name = take_identifier(&mut chars);
+    if !tag_name.is_empty() && tag_name != "*" && !self.tag_name.eq_ignore_ascii_case(&tag_name) {
       return false;
     }
 
-    return class_attr_maybe.unwrap().to_lowercase() == class_name.to_lowercase();
+    while let Option::Some(ch) = chars.next() {
+      let matches = match ch {
+        '.' => self.has_class(&take_identifier(&mut chars)),
+        '#' => self.id() == Option::Some(take_identifier(&mut chars).as_str()),
+        '[' => {
+          let attr_name = take_identifier(&mut chars).to_lowercase();
+          let mut expected_value: Option<String> = Option::None;
+
+          if chars.peek() == Option::Some(&'=') {
+            chars.next();
+            expected_value = Option::Some(take_attr_value(&mut chars));
+          }
+
+          if chars.next() != Option::Some(']') {
+            return false;
+          }
+
+          match expected_value {
+            Option::None => self.attributes.contains_key(attr_name.as_str()),
+            Option::Some(expected_value) => self.attr(&attr_name) == Option::Some(expected_value.as_str())
+          }
+        }
+        _ => false
+      };
+
+      if !matches {
+        return false;
+      }
+    }
+
+    return true;
   }
 
+  /// Returns None for both missing and boolean (valueless) attributes
   pub fn get_attr_value(&self, attr_name: &str) -> Option<&String> {
-    return self.attributes.get(attr_name);
+    return self.attributes.get(attr_name).and_then(|attr_value| attr_value.as_ref());
+  }
+
+  /// None when the attribute is missing, Some(None) for boolean attributes (<input disabled>)
+  pub fn get_attribute(&self, attr_name: &str) -> Option<Option<&String>> {
+    return self.attributes.get(attr_name).map(|attr_value| attr_value.as_ref());
+  }
+
+  /// The descendants matching the [selector] (see [matches]) in document order. The descendants of
+  /// a matched element are not checked.
+  pub fn select(&self, selector: &str) -> Vec<&Element> {
+    let mut selected = Vec::new();
+    let mut stack = vec![self.children.iter()];
+
+    while let Option::Some(children) = stack.last_mut() {
+      match children.next() {
+        Option::None => {
+          stack.pop();
+        }
+        Option::Some(Node::Element(element)) => {
+          if element.matches(selector) {
+            selected.push(element);
+          } else {
+            stack.push(element.children.iter());
+          }
+        }
+        Option::Some(_) => {}
+      }
+    }
+
+    return selected;
+  }
+
+  /// The first descendant matching the [selector] in document order
+  pub fn select_first(&self, selector: &str) -> Option<&Element> {
+    let mut stack = vec![self.children.iter()];
+
+    while let Option::Some(children) = stack.last_mut() {
+      match children.next() {
+        Option::None => {
+          stack.pop();
+        }
+        Option::Some(Node::Element(element)) => {
+          if element.matches(selector) {
+            return Option::Some(element);
+          }
+
+          stack.push(element.children.iter());
+        }
+        Option::Some(_) => {}
+      }
+    }
+
+    return Option::None;
   }
 
+  /// The texts of all the descendants in document order
   pub fn collect_text(&self) -> String {
     let mut output = String::with_capacity(16);
+    let mut stack = vec![self.children.iter()];
 
-    for child in self.children.iter() {
-      self.collect_text_internal(child, &mut output);
+    while let Option::Some(children) = stack.last_mut() {
+      match children.next() {
+        Option::None => {
+          stack.pop();
+        }
+        Option::Some(Node::Text(text)) => output.push_str(text),
+        Option::Some(Node::Element(element)) => stack.push(element.children.iter()),
+        Option::Some(Node::Comment(_)) => {}
+      }
     }
 
     return output;
   }
 
-  fn collect_text_internal(&self, node: &Node, output: &mut String) {
-    match node {
-      Node::Text(text) => output.push_str(text),
-      Node::Element(element) => {
-        for child in element.children.iter() {
-          self.collect_text_internal(&child, output);
+}
+
+impl BorrowedElement<'_> {
+  pub fn attr(&self, attr_name: &str) -> Option<&str> {
+    return self.attributes.get(attr_name).and_then(|attr_value| attr_value.as_deref());
+  }
+
+  /// Deeply nested trees are converted without recursion
+  pub fn into_owned(mut self) -> Element {
+    let children = std::mem::take(&mut self.children);
+
+    // The elements that are being converted (with the children converted so far) along with the
+    // children that are yet to be converted
+    let mut stack: Vec<(Element, std::vec::IntoIter<BorrowedNode>)> = Vec::with_capacity(8);
+    stack.push((self.into_owned_shallow(children.len()), children.into_iter()));
+
+    loop {
+      let (element, remaining_children) = stack.last_mut().unwrap();
+
+      match remaining_children.next() {
+        Option::Some(BorrowedNode::Text(text)) => element.children.push(Node::Text(text.into_owned())),
+        Option::Some(BorrowedNode::Comment(text)) => element.children.push(Node::Comment(text.into_owned())),
+        Option::Some(BorrowedNode::Element(mut child)) => {
+          let child_children = std::mem::take(&mut child.children);
+          stack.push((child.into_owned_shallow(child_children.len()), child_children.into_iter()));
+        }
+        Option::None => {
+          let (element, _) = stack.pop().unwrap();
+
+          match stack.last_mut() {
+            Option::Some((parent, _)) => parent.children.push(Node::Element(element)),
+            Option::None => return element
+          }
         }
       }
     }
   }
 
+  /// Everything but the children, those are converted by [into_owned]
+  fn into_owned_shallow(&mut self, children_count: usize) -> Element {
+    let attributes = std::mem::take(&mut self.attributes).into_iter()
+      .map(|(attr_name, attr_value)| (attr_name, attr_value.map(|attr_value| attr_value.into_owned())))
+      .collect::<LinkedHashMap<Cow<'static, str>, Option<String>>>();
+
+    return Element {
+      tag_name: std::mem::take(&mut self.tag_name),
+      attributes,
+      children: Vec::with_capacity(children_count),
+      is_void_element: self.is_void_element
+    };
+  }
+}
+
+// The drop glue of the compiler is recursive which overflows the stack on deeply nested trees, the
+// descendants are dropped one by one instead
+impl Drop for Element {
+  fn drop(&mut self) {
+    if !has_grandchildren(self.children.iter().map(|child| match child {
+      Node::Element(element) => element.children.len(),
+      _ => 0
+    })) {
+      return;
+    }
+
+    let mut nodes = std::mem::take(&mut self.children);
+
+    while let Option::Some(node) = nodes.pop() {
+      if let Node::Element(mut element) = node {
+        nodes.append(&mut element.children);
+      }
+    }
+  }
+}
+
+impl Drop for BorrowedElement<'_> {
+  fn drop(&mut self) {
+    if !has_grandchildren(self.children.iter().map(|child| match child {
+      BorrowedNode::Element(element) => element.children.len(),
+      _ => 0
+    })) {
+      return;
+    }
+
+    let mut nodes = std::mem::take(&mut self.children);
+
+    while let Option::Some(node) = nodes.pop() {
+      if let BorrowedNode::Element(mut element) = node {
+        nodes.append(&mut element.children);
+      }
+    }
+  }
+}
+
+fn has_grandchildren(mut children_counts: impl Iterator<Item = usize>) -> bool {
+  return children_counts.any(|children_count| children_count > 0);
 }
 
 impl fmt::Display for Element {
@@ -52,4 +290,47 @@ impl fmt::Display for Element {
       self.is_void_element
     )
   }
-}
\ No newline at end of file
+}
+
+fn take_identifier(chars: &mut Peekable<Chars>) -> String {
+  let mut identifier = String::new();
+
+  while let Option::Some(&ch) = chars.peek() {
+    if ch == '.' || ch == '#' || ch == '[' || ch == ']' || ch == '=' {
+      break;
+    }
+
+    identifier.push(ch);
+    chars.next();
+  }
+
+  return identifier;
+}
+
+fn take_attr_value(chars: &mut Peekable<Chars>) -> String {
+  let quote = match chars.peek() {
+    Option::Some(&ch) if ch == '"' || ch == '\'' => {
+      chars.next();
+      Option::Some(ch)
+    }
+    _ => Option::None
+  };
+
+  let mut value = String::new();
+
+  while let Option::Some(&ch) = chars.peek() {
+    if quote == Option::Some(ch) {
+      chars.next();
+      break;
+    }
+
+    if quote.is_none() && ch == ']' {
+      break;
+    }
+
+    value.push(ch);
+    chars.next();
+  }
+
+  return value;
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HtmlParserErrorKind {
+  /// "</tag" without the closing ">"
+  TagEndNotFound,
+  /// "<>"
+  EmptyTag,
+  /// A tag that only consists of attributes, e.g. "<a=b>"
+  TagHasNoName,
+  /// The html has more nodes than ParserLimits::max_nodes
+  TooManyNodes,
+  /// The elements are nested deeper than ParserLimits::max_depth
+  TooDeep,
+  /// Parsing took longer than ParserLimits::max_parse_millis
+  Timeout
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlParserError {
+  pub kind: HtmlParserErrorKind,
+  // utf-16 code units (not u8!) from the start of the html
+  pub position: usize
+}
+
+impl HtmlParserError {
+  pub fn new(kind: HtmlParserErrorKind, position: usize) -> HtmlParserError {
+    return HtmlParserError { kind, position };
+  }
+}
+
+impl fmt::Display for HtmlParserError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "HtmlParserError(kind: {:?}, position: {})", self.kind, self.position)
+  }
+}
@@ -0,0 +1,193 @@
+use crate::html_parser::node::Node;
+use crate::html_parser::html_parser_error::HtmlParserError;
+use crate::{MarkdownParser, Element};
+use regex::Regex;
+
+lazy_static! {
+  // [text](url)
+  static ref MARKDOWN_LINK_PATTERN: Regex = Regex::new(r"^\[([^\]\[]+)\]\(([^()\s]+)\)").unwrap();
+  // >>123 and >>>/g/ at the start of a line are quotes, not greentext
+  static ref QUOTE_LINE_PATTERN: Regex = Regex::new(r"^>>(\d|>/)").unwrap();
+}
+
+const CODE_FENCE: &str = "```";
+
+impl MarkdownParser {
+  pub fn new() -> MarkdownParser {
+    return MarkdownParser {};
+  }
+
+  /// Converts markdown into the same node tree HtmlParser produces so that the html rule handlers
+  /// work for it too:
+  /// **text**/__text__ -> <b>, *text*/_text_ -> <i>, ~~text~~ -> <del>, `text` -> <code>,
+  /// ```fenced blocks``` -> <pre>, [text](url) -> <a href="url">, lines starting with ">" (both
+  /// greentext and blockquotes, the ">" is kept) -> <span class="quote">, line breaks -> <br>.
+  /// Everything else (including unclosed delimiters) is kept as text. Never fails, the Result is
+  /// there so that it can be used interchangeably with HtmlParser::parse().
+  pub fn parse(&self, text: &str) -> Result<Vec<Node>, HtmlParserError> {
+    let mut out_nodes: Vec<Node> = Vec::with_capacity(16);
+    let mut code_block_lines: Option<Vec<&str>> = Option::None;
+    let mut is_first_line = true;
+
+    for line in text.split('\n') {
+      let line = line.strip_suffix('\r').unwrap_or(line);
+
+      if let Option::Some(lines) = code_block_lines.as_mut() {
+        if line.trim_start().starts_with(CODE_FENCE) {
+          self.append_code_block(lines, &mut is_first_line, &mut out_nodes);
+          code_block_lines = Option::None;
+        } else {
+          lines.push(line);
+        }
+
+        continue;
+      }
+
+      if line.trim_start().starts_with(CODE_FENCE) {
+        code_block_lines = Option::Some(Vec::new());
+        continue;
+      }
+
+      if !is_first_line {
+        out_nodes.push(Node::Element(Element::new("br", true)));
+      }
+
+      is_first_line = false;
+
+      if line.starts_with('>') && !QUOTE_LINE_PATTERN.is_match(line) {
+        let mut quote_element = Element::new("span", false).with_attr("class", "quote");
+        quote_element.children = self.parse_inline(line);
+
+        out_nodes.push(Node::Element(quote_element));
+        continue;
+      }
+
+      out_nodes.extend(self.parse_inline(line));
+    }
+
+    // Code blocks that were never closed end where the text ends
+    if let Option::Some(lines) = code_block_lines.as_ref() {
+      self.append_code_block(lines, &mut is_first_line, &mut out_nodes);
+    }
+
+    return Result::Ok(out_nodes);
+  }
+
+  fn append_code_block(&self, lines: &Vec<&str>, is_first_line: &mut bool, out_nodes: &mut Vec<Node>) {
+    if !*is_first_line {
+      out_nodes.push(Node::Element(Element::new("br", true)));
+    }
+
+    *is_first_line = false;
+
+    let mut pre_element = Element::new("pre", false);
+    if !lines.is_empty() {
+      pre_element.children.push(Node::Text(lines.join("\n")));
+    }
+
+    out_nodes.push(Node::Element(pre_element));
+  }
+
+  fn parse_inline(&self, text: &str) -> Vec<Node> {
+    let mut out_nodes: Vec<Node> = Vec::new();
+    let mut text_buffer = String::new();
+    let mut index = 0;
+
+    while index < text.len() {
+      let rest = &text[index..];
+      let current_char = rest.chars().next().unwrap();
+
+      if current_char == '\\' {
+        // \* is a literal *
+        if let Option::Some(escaped_char) = rest[1..].chars().next().filter(|ch| ch.is_ascii_punctuation()) {
+          text_buffer.push(escaped_char);
+          index += 1 + escaped_char.len_utf8();
+          continue;
+        }
+      }
+
+      if let Option::Some((element, consumed)) = self.parse_inline_element(text, index) {
+        if !text_buffer.is_empty() {
+          out_nodes.push(Node::Text(std::mem::take(&mut text_buffer)));
+        }
+
+        out_nodes.push(Node::Element(element));
+        index += consumed;
+        continue;
+      }
+
+      text_buffer.push(current_char);
+      index += current_char.len_utf8();
+    }
+
+    if !text_buffer.is_empty() {
+      out_nodes.push(Node::Text(text_buffer));
+    }
+
+    return out_nodes;
+  }
+
+  /// The element that starts at [index] of [text] and how many bytes it takes
+  fn parse_inline_element(&self, text: &str, index: usize) -> Option<(Element, usize)> {
+    let rest = &text[index..];
+
+    if rest.starts_with('`') {
+      let inner = find_delimited(rest, "`")?;
+
+      let mut code_element = Element::new("code", false);
+      code_element.children.push(Node::Text(String::from(inner)));
+
+      return Option::Some((code_element, inner.len() + 2));
+    }
+
+    if rest.starts_with('[') {
+      let captures = MARKDOWN_LINK_PATTERN.captures(rest)?;
+
+      let mut anchor_element = Element::new("a", false).with_attr("href", captures.get(2).unwrap().as_str());
+      anchor_element.children.push(Node::Text(String::from(captures.get(1).unwrap().as_str())));
+
+      return Option::Some((anchor_element, captures.get(0).unwrap().end()));
+    }
+
+    let (delimiter, tag_name) = if rest.starts_with("**") {
+      ("**", "b")
+    } else if rest.starts_with("__") {
+      ("__", "b")
+    } else if rest.starts_with("~~") {
+      ("~~", "del")
+    } else if rest.starts_with('*') {
+      ("*", "i")
+    } else if rest.starts_with('_') {
+      ("_", "i")
+    } else {
+      return Option::None;
+    };
+
+    // snake_case_words are not italic
+    let prev_char = text[..index].chars().next_back();
+    if delimiter.starts_with('_') && prev_char.map(|ch| ch.is_alphanumeric()).unwrap_or(false) {
+      return Option::None;
+    }
+
+    let inner = find_delimited(rest, delimiter)?;
+
+    let mut element = Element::new(tag_name, false);
+    element.children = self.parse_inline(inner);
+
+    return Option::Some((element, inner.len() + delimiter.len() * 2));
+  }
+}
+
+/// The text between the [delimiter] [text] starts with and the next one. None when there is no
+/// closing delimiter or when the text is empty or starts/ends with a whitespace ("2 * 3 * 4").
+fn find_delimited<'a>(text: &'a str, delimiter: &str) -> Option<&'a str> {
+  let after_opening = &text[delimiter.len()..];
+  let inner = &after_opening[..after_opening.find(delimiter)?];
+
+  let starts_or_ends_with_whitespace = inner.starts_with(char::is_whitespace) || inner.ends_with(char::is_whitespace);
+  if inner.is_empty() || starts_or_ends_with_whitespace {
+    return Option::None;
+  }
+
+  return Option::Some(inner);
+}
@@ -0,0 +1,31 @@
+use std::borrow::Cow;
+
+/// Tag and attribute names that are commonly found in posts (sorted, binary searched)
+const KNOWN_NAMES: &[&str] = &[
+  "a", "abbr", "alt", "b", "big", "blockquote", "br", "class", "code", "color", "data-board",
+  "data-post", "del", "div", "em", "font", "h1", "h2", "h3", "h4", "h5", "h6", "height", "hr", "href",
+  "i", "id", "img", "ins", "li", "ol", "onclick", "p", "pre", "rel", "s", "script", "small", "span",
+  "src", "strike", "strong", "style", "sub", "sup", "table", "target", "tbody", "td", "textarea",
+  "th", "title", "tr", "u", "ul", "wbr", "width"
+];
+
+/// Returns the static copy of a known tag/attribute name so that the thousands of "span"s, "a"s
+/// and "class"es of a thread do not allocate a String each. Unknown names are copied.
+pub fn intern_name(name: &str) -> Cow<'static, str> {
+  return match KNOWN_NAMES.binary_search(&name) {
+    Ok(index) => Cow::Borrowed(KNOWN_NAMES[index]),
+    Err(_) => Cow::Owned(String::from(name))
+  };
+}
+
+#[test]
+fn test_intern_name() {
+  let mut sorted_names = KNOWN_NAMES.to_vec();
+  sorted_names.sort();
+  assert_eq!(KNOWN_NAMES, sorted_names.as_slice());
+
+  assert!(matches!(intern_name("span"), Cow::Borrowed("span")));
+  assert!(matches!(intern_name("data-post"), Cow::Borrowed("data-post")));
+  assert!(matches!(intern_name("marquee"), Cow::Owned(_)));
+  assert_eq!("SPAN", intern_name("SPAN"));
+}
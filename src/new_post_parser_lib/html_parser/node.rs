@@ -1,10 +1,35 @@
 use std::fmt;
-use crate::Element;
+use std::borrow::Cow;
+use crate::{Element, BorrowedElement};
 
-#[derive(Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Node {
   Text(String),
-  Element(Element)
+  Element(Element),
+  // <!-- text -->
+  Comment(String)
+}
+
+/// Node of HtmlParser::parse_borrowed(), texts are slices of the parsed html unless entities were
+/// decoded in them
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedNode<'a> {
+  Text(Cow<'a, str>),
+  Element(BorrowedElement<'a>),
+  // <!-- text -->
+  Comment(Cow<'a, str>)
+}
+
+impl BorrowedNode<'_> {
+  /// Copies the borrowed texts, texts that were already copied (decoded entities) are moved
+  pub fn into_owned(self) -> Node {
+    return match self {
+      BorrowedNode::Text(text) => Node::Text(text.into_owned()),
+      BorrowedNode::Element(element) => Node::Element(element.into_owned()),
+      BorrowedNode::Comment(text) => Node::Comment(text.into_owned())
+    };
+  }
 }
 
 impl fmt::Display for Node {
@@ -16,6 +41,9 @@ impl fmt::Display for Node {
       Node::Element(element) => {
         write!(f, "Element(element={})", element)
       }
+      Node::Comment(text) => {
+        write!(f, "Comment(text={})", text)
+      }
     }
   }
 }
\ No newline at end of file
@@ -1,226 +1,169 @@
-use crate::html_parser::node::Node;
+use crate::html_parser::node::{Node, BorrowedNode};
 use std::str;
-use std::collections::{HashSet};
 use linked_hash_map::LinkedHashMap;
-use crate::{Element, HtmlParser};
-
-lazy_static! {
-  static ref VOID_ELEMENTS: HashSet<&'static str> = {
-    let mut set = HashSet::new();
-
-    set.insert("area");
-    set.insert("base");
-    set.insert("br");
-    set.insert("wbr");
-    set.insert("col");
-    set.insert("hr");
-    set.insert("img");
-    set.insert("input");
-    set.insert("link");
-    set.insert("meta");
-    set.insert("param");
-
-    return set;
-  };
-}
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+use crate::{BorrowedElement, HtmlParser, ParserLimits};
+use crate::html_parser::html_parser_error::{HtmlParserError, HtmlParserErrorKind};
+use crate::html_parser::tokenizer::Token;
+use crate::util::helpers::now;
+
+// Reading the clock is not free so the deadline is only checked once per this many tokens
+const DEADLINE_CHECK_INTERVAL: usize = 64;
 
 impl HtmlParser {
   pub fn new() -> HtmlParser {
-    return HtmlParser {};
+    return HtmlParser {
+      limits: ParserLimits::default(),
+      decode_entities: false,
+      lowercase_names: true
+    };
   }
 
-  pub fn parse(&self, html: &str) -> Result<Vec<Node>, &str> {
-    let (result_nodes, _) = self.parse_internal(
-      &html.encode_utf16().collect::<Vec<u16>>(),
-      0,
-    );
+  pub fn decode_entities(mut self, decode_entities: bool) -> HtmlParser {
+    self.decode_entities = decode_entities;
+    return self;
+  }
 
-    return Result::Ok(result_nodes);
+  pub fn lowercase_names(mut self, lowercase_names: bool) -> HtmlParser {
+    self.lowercase_names = lowercase_names;
+    return self;
   }
 
-  //noinspection DuplicatedCode
-  fn parse_internal(&self, html: &Vec<u16>, start: usize) -> (Vec<Node>, usize) {
-    let mut local_offset = start;
-    let mut out_nodes: Vec<Node> = Vec::with_capacity(16);
-    let mut current_buffer: Vec<u16> = Vec::with_capacity(16);
+  /// Only max_nodes, max_depth and max_parse_millis are enforced, see ParserLimits
+  pub fn limits(mut self, limits: ParserLimits) -> HtmlParser {
+    self.limits = limits;
+    return self;
+  }
 
-    while local_offset < html.len() {
-      let curr_char = html[local_offset as usize] as u16;
+  pub fn parse(&self, html: &str) -> Result<Vec<Node>, HtmlParserError> {
+    let nodes = self.parse_borrowed(html)?;
+    return Result::Ok(nodes.into_iter().map(|node| node.into_owned()).collect());
+  }
 
-      if curr_char == '<' as u16 {
-        if current_buffer.len() > 0 {
-          let u16_string = String::from_utf16_lossy(&current_buffer.as_slice());
+  /// Same as [parse] but the texts and attribute values of the nodes borrow from the html instead
+  /// of being copied (texts with decoded entities are the only exception)
+  pub fn parse_borrowed<'a>(&'a self, html: &'a str) -> Result<Vec<BorrowedNode<'a>>, HtmlParserError> {
+    let mut out_nodes: Vec<BorrowedNode> = Vec::with_capacity(16);
+    let mut open_elements: Vec<BorrowedElement> = Vec::with_capacity(8);
 
-          out_nodes.push(Node::Text(u16_string));
-          current_buffer.clear();
-        }
+    // The budget is not enforced on wasm32-unknown-unknown, there is no clock
+    let deadline = match (self.limits.max_parse_millis, now()) {
+      (Option::Some(max_parse_millis), Option::Some(now)) => Option::Some(now + Duration::from_millis(max_parse_millis)),
+      _ => Option::None
+    };
 
-        local_offset += 1;
+    let mut tokenizer = self.tokenize(html);
+    let mut tokens_count: usize = 0;
+    let mut nodes_count: usize = 0;
 
-        let next_char = html[local_offset as usize] as u16;
-        if next_char == '/' as u16 {
-          let offset = self.skip_tag_end(html, local_offset);
-          local_offset = offset;
+    while let Option::Some(token_result) = tokenizer.next() {
+      let token = token_result?;
+      tokens_count += 1;
 
-          return (out_nodes, local_offset);
+      if let Option::Some(deadline) = deadline {
+        if tokens_count % DEADLINE_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+          return Result::Err(HtmlParserError::new(HtmlParserErrorKind::Timeout, tokenizer.position()));
         }
-
-        let (element, offset) = self.parse_tag(html, local_offset);
-        out_nodes.push(Node::Element(element));
-        local_offset = offset;
-
-        continue;
       }
 
-      current_buffer.push(curr_char);
-      local_offset += 1;
-    }
-
-    if current_buffer.len() > 0 {
-      let u16_string = String::from_utf16_lossy(&current_buffer.as_slice());
-
-      out_nodes.push(Node::Text(u16_string));
-      current_buffer.clear();
-    }
-
-    return (out_nodes, local_offset);
-  }
-
-  fn parse_tag(&self, html: &Vec<u16>, start: usize) -> (Element, usize) {
-    let mut local_offset = start;
-    let mut tag_raw: Vec<u16> = Vec::with_capacity(32);
+      if let Token::EndTag(_) = token {
+        // Closing tags don't create nodes
+      } else {
+        nodes_count += 1;
 
-    while local_offset < html.len() {
-      let ch = html[local_offset as usize] as u16;
-      if ch == '>' as u16 {
-        break;
+        if self.limits.max_nodes.map_or(false, |max_nodes| nodes_count > max_nodes) {
+          return Result::Err(HtmlParserError::new(HtmlParserErrorKind::TooManyNodes, tokenizer.position()));
+        }
       }
 
-      tag_raw.push(ch);
-      local_offset += 1;
+      match token {
+        Token::StartTag(element) => {
+          // <a>1<a>2 is the same as <a>1</a><a>2 (elements like that can't be nested)
+          let implicitly_closed_index = self.find_implicitly_closed_element(&element.tag_name, &open_elements);
+          if let Option::Some(index) = implicitly_closed_index {
+            self.close_elements(index, &mut open_elements, &mut out_nodes);
+          }
+
+          if self.limits.max_depth.map_or(false, |max_depth| open_elements.len() >= max_depth) {
+            return Result::Err(HtmlParserError::new(HtmlParserErrorKind::TooDeep, tokenizer.position()));
+          }
+
+          if element.is_void_element {
+            self.append_node(BorrowedNode::Element(element), &mut open_elements, &mut out_nodes);
+          } else {
+            open_elements.push(element);
+          }
+        }
+        Token::EndTag(tag_name) => {
+          // A closing tag closes the innermost open element with the same name along with all the
+          // elements that were opened inside of it and were not closed (<span><b>1</span>2 is the
+          // same as <span><b>1</b></span>2). Closing tags without a matching open element are ignored.
+          let index_maybe = open_elements.iter()
+            .rposition(|element| element.tag_name.eq_ignore_ascii_case(&tag_name));
+
+          if let Option::Some(index) = index_maybe {
+            self.close_elements(index, &mut open_elements, &mut out_nodes);
+          }
+        }
+        Token::Text(text) => {
+          self.append_node(BorrowedNode::Text(text), &mut open_elements, &mut out_nodes);
+        }
+        Token::Comment(text) => {
+          self.append_node(BorrowedNode::Comment(text), &mut open_elements, &mut out_nodes);
+        }
+      }
     }
 
-    // Skip the ">"
-    local_offset += 1;
-
-    let element = self.create_tag(&String::from_utf16_lossy(tag_raw.as_slice()));
-    if element.is_void_element {
-      return (element, local_offset);
+    // Elements that were never closed end where the html ends
+    while let Option::Some(element) = open_elements.pop() {
+      self.append_node(BorrowedNode::Element(element), &mut open_elements, &mut out_nodes);
     }
 
-    let (child_nodes, new_offset) = self.parse_internal(
-      html,
-      local_offset,
-    );
-
-    let updated_element = Element {
-      tag_name: element.tag_name,
-      attributes: element.attributes,
-      children: child_nodes,
-      is_void_element: false,
-    };
-
-    return (updated_element, new_offset);
+    return Result::Ok(out_nodes);
   }
 
-  fn skip_tag_end(&self, html: &Vec<u16>, start: usize) -> usize {
-    let mut local_offset = start;
-
-    while local_offset < html.len() {
-      let ch = html[local_offset as usize] as u16;
-      if ch == '>' as u16 {
-        return local_offset + 1;
-      }
-
-      local_offset += 1;
+  /// Closes the element at [index] and all the elements that were opened after it
+  fn close_elements<'a>(&self, index: usize, open_elements: &mut Vec<BorrowedElement<'a>>, out_nodes: &mut Vec<BorrowedNode<'a>>) {
+    while open_elements.len() > index {
+      let element = open_elements.pop().unwrap();
+      self.append_node(BorrowedNode::Element(element), open_elements, out_nodes);
     }
-
-    panic!("Failed to find tag end");
   }
 
-  fn create_tag(&self, tag_raw: &String) -> Element {
-    let tag_parts = self.split_into_parts_by_separator(&tag_raw, ' ' as u16);
-    if tag_parts.is_empty() {
-      panic!("tag_parts is empty! tag_raw={}", tag_raw);
-    }
-
-    let mut tag_name_maybe: Option<String> = Option::None;
-    let mut attributes: LinkedHashMap<String, String> = LinkedHashMap::new();
-
-    for tag_part in tag_parts {
-      if !tag_part.contains("=") {
-        tag_name_maybe = Option::Some(String::from(tag_part));
-        continue;
-      }
-
-      let attribute_split_vec = self.split_into_parts_by_separator(&tag_part, '=' as u16);
-      let attr_name = attribute_split_vec[0].as_str();
-      let mut attr_value = attribute_split_vec[1].as_str();
+  fn find_implicitly_closed_element(&self, tag_name: &str, open_elements: &Vec<BorrowedElement>) -> Option<usize> {
+    let tag_name = tag_name.to_lowercase();
+
+    // An element is only closed implicitly when there is no scope boundary between it and the new
+    // element (<li> of a nested list must not close the <li> of the outer list)
+    let scope_boundaries: &[&str] = match tag_name.as_str() {
+      "a" | "p" => &[],
+      "li" => &["ul", "ol"],
+      "tr" => &["table", "thead", "tbody", "tfoot"],
+      "td" | "th" => &["tr", "table"],
+      _ => return Option::None
+    };
 
-      if attr_value.starts_with('\"') {
-        attr_value = &attr_value[1..]
-      }
+    for (index, element) in open_elements.iter().enumerate().rev() {
+      let element_tag_name = element.tag_name.to_lowercase();
 
-      if attr_value.ends_with("\"") {
-        attr_value = &attr_value[..(attr_value.len() - 1)]
+      if element_tag_name == tag_name {
+        return Option::Some(index);
       }
 
-      if attr_name.is_empty() || attr_value.is_empty() {
-        continue;
+      if scope_boundaries.contains(&element_tag_name.as_str()) {
+        return Option::None;
       }
-
-      attributes.insert(String::from(attr_name), String::from(attr_value));
     }
 
-    if tag_name_maybe.is_none() {
-      panic!("Tag has no name!")
-    }
-
-    let tag_name = tag_name_maybe.unwrap();
-    let is_void_element = VOID_ELEMENTS.contains(&tag_name.as_str());
-
-    return Element {
-      tag_name: tag_name,
-      attributes,
-      children: Vec::with_capacity(4),
-      is_void_element
-    };
+    return Option::None;
   }
 
-  fn split_into_parts_by_separator(&self, tag_raw: &String, separator: u16) -> Vec<String> {
-    let mut is_inside_string = false;
-    let mut offset: usize = 0;
-    let mut tag_parts: Vec<String> = Vec::with_capacity(4);
-    let mut current_tag_part: Vec<u16> = Vec::with_capacity(16);
-    let tag_bytes = tag_raw.encode_utf16().collect::<Vec<u16>>();
-
-    while offset < tag_bytes.len() {
-      let ch = tag_bytes[offset as usize] as u16;
-
-      if ch == '\"' as u16 {
-        is_inside_string = !is_inside_string;
-      }
-
-      if ch == separator && !is_inside_string {
-        let u16_string = String::from_utf16_lossy(&current_tag_part.as_slice());
-        tag_parts.push(u16_string.clone());
-        current_tag_part.clear();
-
-        offset += 1;
-        continue;
-      }
-
-      current_tag_part.push(ch);
-      offset += 1;
-    }
-
-    if current_tag_part.len() > 0 {
-      let u16_string = String::from_utf16_lossy(&current_tag_part.as_slice());
-      tag_parts.push(u16_string.clone());
-      current_tag_part.clear();
+  fn append_node<'a>(&self, node: BorrowedNode<'a>, open_elements: &mut Vec<BorrowedElement<'a>>, out_nodes: &mut Vec<BorrowedNode<'a>>) {
+    match open_elements.last_mut() {
+      None => out_nodes.push(node),
+      Some(parent_element) => parent_element.children.push(node)
     }
-
-    return tag_parts;
   }
 
   // Debug stuff
@@ -235,17 +178,7 @@ impl HtmlParser {
 
   #[allow(dead_code)]
   fn debug_print_nodes_internal(&self, nodes: &Vec<Node>, iterator: &mut dyn FnMut(String)) {
-    for node in nodes {
-      match node {
-        Node::Text(text) => {
-          iterator(format!("{}", text));
-        }
-        Node::Element(element) => {
-          iterator(format!("<{}{}>", &element.tag_name, self.debug_format_attributes(&element.attributes)));
-          self.debug_print_nodes_internal(&element.children, iterator);
-        }
-      }
-    }
+    self.debug_concat_into_string_internal(nodes, iterator);
   }
 
   #[allow(dead_code)]
@@ -264,21 +197,29 @@ impl HtmlParser {
 
   #[allow(dead_code)]
   pub fn debug_concat_into_string_internal(&self, nodes: &Vec<Node>, iterator: &mut dyn FnMut(String)) {
-    for node in nodes {
-      match node {
-        Node::Text(text) => {
+    let mut stack = vec![nodes.iter()];
+
+    while let Option::Some(remaining_nodes) = stack.last_mut() {
+      match remaining_nodes.next() {
+        Option::None => {
+          stack.pop();
+        }
+        Option::Some(Node::Text(text)) => {
           iterator(format!("{}", text));
         }
-        Node::Element(element) => {
+        Option::Some(Node::Element(element)) => {
           iterator(format!("<{}{}>", &element.tag_name, self.debug_format_attributes(&element.attributes)));
-          self.debug_concat_into_string_internal(&element.children, iterator);
+          stack.push(element.children.iter());
+        }
+        Option::Some(Node::Comment(text)) => {
+          iterator(format!("<!--{}-->", text));
         }
       }
     }
   }
 
   #[allow(dead_code)]
-  fn debug_format_attributes(&self, attributes: &LinkedHashMap<String, String>) -> String {
+  fn debug_format_attributes(&self, attributes: &LinkedHashMap<Cow<'static, str>, Option<String>>) -> String {
     let mut result_string = String::new();
 
     if attributes.is_empty() {
@@ -286,7 +227,10 @@ impl HtmlParser {
     }
 
     for (attr_key, attr_value) in attributes {
-      result_string.push_str(format!(", {}={}", attr_key, attr_value).as_str());
+      match attr_value {
+        None => result_string.push_str(format!(", {}", attr_key).as_str()),
+        Some(attr_value) => result_string.push_str(format!(", {}={}", attr_key, attr_value).as_str())
+      }
     }
 
     return result_string;
@@ -0,0 +1,80 @@
+use crate::html_parser::node::Node;
+use crate::html_parser::html_parser_error::HtmlParserError;
+use crate::{PlainTextParser, Element};
+use regex::Regex;
+
+lazy_static! {
+  static ref POST_QUOTE_PATTERN: Regex = Regex::new(r">>(\d+)").unwrap();
+  // >>123 and >>>/g/ at the start of a line are quotes, not greentext
+  static ref QUOTE_LINE_PATTERN: Regex = Regex::new(r"^>>(\d|>/)").unwrap();
+}
+
+impl PlainTextParser {
+  pub fn new() -> PlainTextParser {
+    return PlainTextParser {};
+  }
+
+  /// Converts an unformatted comment (archive "com" fields, html that failed to parse) into the
+  /// same node tree HtmlParser produces: lines starting with ">" -> <span class="quote">,
+  /// >>123 -> <a class="quotelink" href="#p123">, line breaks -> <br>. Urls and >>>/g/ links are
+  /// found in the text nodes by the comment parser like with html. Never fails, the Result is
+  /// there so that it can be used interchangeably with HtmlParser::parse().
+  pub fn parse(&self, text: &str) -> Result<Vec<Node>, HtmlParserError> {
+    let mut out_nodes: Vec<Node> = Vec::with_capacity(16);
+
+    for (index, line) in text.split('\n').enumerate() {
+      let line = line.strip_suffix('\r').unwrap_or(line);
+
+      if index > 0 {
+        out_nodes.push(Node::Element(Element::new("br", true)));
+      }
+
+      if line.starts_with('>') && !QUOTE_LINE_PATTERN.is_match(line) {
+        let mut quote_element = Element::new("span", false).with_attr("class", "quote");
+        quote_element.children = self.parse_line(line);
+
+        out_nodes.push(Node::Element(quote_element));
+        continue;
+      }
+
+      out_nodes.extend(self.parse_line(line));
+    }
+
+    return Result::Ok(out_nodes);
+  }
+
+  fn parse_line(&self, line: &str) -> Vec<Node> {
+    let mut out_nodes: Vec<Node> = Vec::new();
+    let mut text_start = 0;
+
+    for captures in POST_QUOTE_PATTERN.captures_iter(line) {
+      let whole_match = captures.get(0).unwrap();
+      let post_no = captures.get(1).unwrap().as_str();
+
+      // >>>123 is not a quote, neither is a number that doesn't fit into u64
+      if line[..whole_match.start()].ends_with('>') || post_no.parse::<u64>().is_err() {
+        continue;
+      }
+
+      if text_start < whole_match.start() {
+        out_nodes.push(Node::Text(String::from(&line[text_start..whole_match.start()])));
+      }
+
+      let href = format!("#p{}", post_no);
+      let mut quote_link_element = Element::new("a", false)
+        .with_attr("class", "quotelink")
+        .with_attr("href", &href);
+
+      quote_link_element.children.push(Node::Text(String::from(whole_match.as_str())));
+      out_nodes.push(Node::Element(quote_link_element));
+
+      text_start = whole_match.end();
+    }
+
+    if text_start < line.len() {
+      out_nodes.push(Node::Text(String::from(&line[text_start..])));
+    }
+
+    return out_nodes;
+  }
+}
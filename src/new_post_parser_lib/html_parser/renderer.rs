@@ -0,0 +1,142 @@
+use crate::html_parser::node::Node;
+use crate::html_parser::tokenizer::{RAW_TEXT_ELEMENTS, VOID_ELEMENTS};
+use crate::Element;
+
+/// Serializes the nodes back into html. Text nodes are expected to be decoded (the tree was parsed
+/// with decode_entities(true)) so they are escaped again, attribute values are never decoded by the
+/// parser so they are written as is, except for the double quotes which are written as &quot;.
+/// Parsing the result produces the same tree (a double quote in an attribute value comes back as
+/// &quot;, the same value once the attribute is decoded).
+pub fn render_nodes(nodes: &[Node]) -> String {
+  let mut output = String::with_capacity(64);
+
+  for node in nodes {
+    render_node(node, false, &mut output);
+  }
+
+  return output;
+}
+
+impl Node {
+  pub fn to_html(&self) -> String {
+    let mut output = String::with_capacity(32);
+    render_node(self, false, &mut output);
+
+    return output;
+  }
+}
+
+impl Element {
+  pub fn to_html(&self) -> String {
+    let mut output = String::with_capacity(32);
+    render_element(self, &mut output);
+
+    return output;
+  }
+}
+
+fn render_node(node: &Node, is_raw_text: bool, output: &mut String) {
+  match node {
+    Node::Text(text) => {
+      if is_raw_text {
+        output.push_str(text);
+      } else {
+        escape_text(text, output);
+      }
+    }
+    Node::Element(element) => render_element(element, output),
+    Node::Comment(text) => {
+      output.push_str("<!--");
+      output.push_str(text);
+      output.push_str("-->");
+    }
+  }
+}
+
+// Deeply nested trees are rendered without recursion
+fn render_element(element: &Element, output: &mut String) {
+  if !render_start_tag(element, output) {
+    return;
+  }
+
+  // The elements that are being rendered along with the children that are yet to be rendered
+  let mut stack = vec![(element, is_raw_text_element(element), element.children.iter())];
+
+  while let Option::Some((current_element, is_raw_text, children)) = stack.last_mut() {
+    match children.next() {
+      Option::Some(Node::Element(child)) => {
+        if render_start_tag(child, output) {
+          stack.push((child, is_raw_text_element(child), child.children.iter()));
+        }
+      }
+      Option::Some(node) => render_node(node, *is_raw_text, output),
+      Option::None => {
+        output.push_str("</");
+        output.push_str(&current_element.tag_name);
+        output.push('>');
+
+        stack.pop();
+      }
+    }
+  }
+}
+
+/// Returns false when the element has no children and no closing tag (void and self-closing
+/// elements)
+fn render_start_tag(element: &Element, output: &mut String) -> bool {
+  output.push('<');
+  output.push_str(&element.tag_name);
+
+  for (attr_name, attr_value) in element.attributes.iter() {
+    output.push(' ');
+    output.push_str(attr_name);
+
+    if let Option::Some(attr_value) = attr_value {
+      output.push_str("=\"");
+      escape_attr_value(attr_value, output);
+      output.push('"');
+    }
+  }
+
+  if element.is_void_element {
+    if VOID_ELEMENTS.contains(element.tag_name.to_lowercase().as_str()) {
+      output.push('>');
+    } else {
+      // Self-closing <span class="x"/>, without the slash the following nodes would become its
+      // children
+      output.push_str("/>");
+    }
+
+    return false;
+  }
+
+  output.push('>');
+  return true;
+}
+
+// Contents of <script> and <style> are never decoded
+fn is_raw_text_element(element: &Element) -> bool {
+  let lowercase_tag_name = element.tag_name.to_lowercase();
+  return lowercase_tag_name != "textarea" && RAW_TEXT_ELEMENTS.contains(lowercase_tag_name.as_str());
+}
+
+// Values with double quotes come from single quoted attributes (title='a "b" c')
+fn escape_attr_value(attr_value: &str, output: &mut String) {
+  for ch in attr_value.chars() {
+    match ch {
+      '"' => output.push_str("&quot;"),
+      _ => output.push(ch)
+    }
+  }
+}
+
+pub(crate) fn escape_text(text: &str, output: &mut String) {
+  for ch in text.chars() {
+    match ch {
+      '&' => output.push_str("&amp;"),
+      '<' => output.push_str("&lt;"),
+      '>' => output.push_str("&gt;"),
+      _ => output.push(ch)
+    }
+  }
+}
@@ -0,0 +1,79 @@
+use crate::Element;
+use linked_hash_map::LinkedHashMap;
+
+const STYLE_ATTR: &str = "style";
+
+impl Element {
+  /// Declarations of the inline style attribute in declaration order, property names are lowercased.
+  /// When a property is declared more than once the last declaration wins (like in css) and takes
+  /// its place in the order. Empty when there is no style.
+  pub fn parsed_style(&self) -> LinkedHashMap<String, String> {
+    let mut style_map = LinkedHashMap::new();
+
+    if let Option::Some(style) = self.get_attr_value(STYLE_ATTR) {
+      for (name, value) in parse_inline_style(style) {
+        style_map.insert(name, value);
+      }
+    }
+
+    return style_map;
+  }
+}
+
+/// Splits an inline style attribute ("color: red; font-family: 'a;b'") into lowercased property
+/// names and trimmed values. ';' and ':' inside of quotes or parentheses (url(http://...)) do not
+/// split anything. Declarations without a name or a value are skipped.
+pub fn parse_inline_style(style: &str) -> Vec<(String, String)> {
+  let mut declarations = Vec::new();
+
+  for declaration in split_outside_of_quotes(style, ';') {
+    // Only the first ':' separates the name from the value
+    let separator_index = match split_outside_of_quotes(declaration, ':').first() {
+      Some(name) if name.len() < declaration.len() => name.len(),
+      _ => continue
+    };
+
+    let name = declaration[..separator_index].trim();
+    let value = declaration[separator_index + 1..].trim();
+
+    if name.is_empty() || value.is_empty() {
+      continue;
+    }
+
+    declarations.push((name.to_ascii_lowercase(), String::from(value)));
+  }
+
+  return declarations;
+}
+
+fn split_outside_of_quotes(text: &str, separator: char) -> Vec<&str> {
+  let mut parts = Vec::new();
+  let mut quote: Option<char> = Option::None;
+  let mut parentheses_depth = 0;
+  let mut part_start = 0;
+
+  for (index, ch) in text.char_indices() {
+    match quote {
+      Some(quote_char) => {
+        if ch == quote_char {
+          quote = Option::None;
+        }
+      }
+      None => {
+        match ch {
+          '"' | '\'' => quote = Option::Some(ch),
+          '(' => parentheses_depth += 1,
+          ')' if parentheses_depth > 0 => parentheses_depth -= 1,
+          _ if ch == separator && parentheses_depth == 0 => {
+            parts.push(&text[part_start..index]);
+            part_start = index + ch.len_utf8();
+          }
+          _ => {}
+        }
+      }
+    }
+  }
+
+  parts.push(&text[part_start..]);
+  return parts;
+}
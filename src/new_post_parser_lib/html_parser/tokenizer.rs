@@ -0,0 +1,427 @@
+use std::collections::{HashSet};
+use linked_hash_map::LinkedHashMap;
+use crate::{BorrowedElement, HtmlParser};
+use crate::html_parser::html_parser_error::{HtmlParserError, HtmlParserErrorKind};
+use crate::html_parser::name_interner::intern_name;
+use std::borrow::Cow;
+
+// "<" is already consumed at this point
+const COMMENT_START: &str = "!--";
+const COMMENT_END: &str = "-->";
+const CDATA_START: &str = "![CDATA[";
+const CDATA_END: &str = "]]>";
+
+lazy_static! {
+  pub(crate) static ref VOID_ELEMENTS: HashSet<&'static str> = {
+    let mut set = HashSet::new();
+
+    set.insert("area");
+    set.insert("base");
+    set.insert("br");
+    set.insert("wbr");
+    set.insert("col");
+    set.insert("hr");
+    set.insert("img");
+    set.insert("input");
+    set.insert("link");
+    set.insert("meta");
+    set.insert("param");
+
+    return set;
+  };
+
+  // Contents of these elements are collected verbatim until the matching closing tag
+  pub(crate) static ref RAW_TEXT_ELEMENTS: HashSet<&'static str> = {
+    let mut set = HashSet::new();
+
+    set.insert("script");
+    set.insert("style");
+    set.insert("textarea");
+
+    return set;
+  };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+  /// An element without children. Void and self-closing elements (is_void_element == true) are never
+  /// followed by a matching EndTag.
+  StartTag(BorrowedElement<'a>),
+  EndTag(Cow<'static, str>),
+  /// Borrows from the html unless entities had to be decoded
+  Text(Cow<'a, str>),
+  Comment(Cow<'a, str>)
+}
+
+/// Pull-based tokenizer. Yields tokens one by one without building the node tree. Stops after the
+/// first error. Texts, comments and attribute values are slices of the html, nothing is copied
+/// unless entities are decoded.
+pub struct HtmlTokenizer<'a> {
+  html_parser: &'a HtmlParser,
+  html: &'a str,
+  // Bytes from the start of the html, always on a char boundary
+  offset: usize,
+  failed: bool,
+  // Set right after the start tag of a raw text element (<script>, <style>, <textarea>) was read
+  raw_text_element: Option<String>
+}
+
+impl HtmlParser {
+  pub fn tokenize<'a>(&'a self, html: &'a str) -> HtmlTokenizer<'a> {
+    return HtmlTokenizer {
+      html_parser: self,
+      html,
+      offset: 0,
+      failed: false,
+      raw_text_element: Option::None
+    };
+  }
+}
+
+impl<'a> Iterator for HtmlTokenizer<'a> {
+  type Item = Result<Token<'a>, HtmlParserError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed || self.offset >= self.html.len() {
+      return Option::None;
+    }
+
+    let token_result = self.next_token();
+    if token_result.is_err() {
+      self.failed = true;
+    }
+
+    return Option::Some(token_result);
+  }
+}
+
+impl<'a> HtmlTokenizer<'a> {
+
+  fn next_token(&mut self) -> Result<Token<'a>, HtmlParserError> {
+    if let Option::Some(raw_text_element) = self.raw_text_element.take() {
+      let raw_text_token = self.read_raw_text(&raw_text_element);
+      if raw_text_token.is_some() {
+        return Result::Ok(raw_text_token.unwrap());
+      }
+
+      // Empty element, the closing tag follows right away
+    }
+
+    if !self.is_tag_start(self.offset) {
+      return Result::Ok(self.read_text());
+    }
+
+    // Skip the "<"
+    self.offset += 1;
+
+    let rest = &self.html[self.offset..];
+
+    if rest.starts_with('/') {
+      return self.read_end_tag();
+    }
+
+    if rest.starts_with(COMMENT_START) {
+      return Result::Ok(self.read_comment());
+    }
+
+    if rest.starts_with(CDATA_START) {
+      return Result::Ok(self.read_cdata());
+    }
+
+    return self.read_start_tag();
+  }
+
+  /// Returns the html from [start] up to the first occurrence of [pattern] (or up to the end of the
+  /// html) and moves the offset right after the pattern
+  fn read_until(&mut self, start: usize, pattern: &str) -> &'a str {
+    let html = self.html;
+
+    return match html[start..].find(pattern) {
+      Option::Some(index) => {
+        self.offset = start + index + pattern.len();
+        &html[start..(start + index)]
+      }
+      Option::None => {
+        self.offset = html.len();
+        &html[start..]
+      }
+    };
+  }
+
+  /// Whether a tag (or a comment) starts at [offset]. A "<" that is not followed by a tag name,
+  /// "/", "!" or ">" ("1 < 2", "<3", "<" at the end of the html) is a part of the text.
+  fn is_tag_start(&self, offset: usize) -> bool {
+    let mut chars = self.html[offset..].chars();
+
+    if chars.next() != Option::Some('<') {
+      return false;
+    }
+
+    return match chars.next() {
+      Option::None => false,
+      Option::Some(ch) => ch.is_ascii_alphabetic() || ch == '/' || ch == '!' || ch == '>'
+    };
+  }
+
+  fn read_text(&mut self) -> Token<'a> {
+    let html = self.html;
+    let start = self.offset;
+
+    // Looks for the next "<" after [offset] (the text may start with a "<" that is not a tag)
+    let find_next_lt = |offset: usize| {
+      return html[(offset + 1)..].find('<').map(|index| offset + 1 + index).unwrap_or(html.len());
+    };
+
+    self.offset = if html[start..].starts_with('<') {
+      find_next_lt(start)
+    } else {
+      html[start..].find('<').map(|index| start + index).unwrap_or(html.len())
+    };
+
+    while self.offset < html.len() && !self.is_tag_start(self.offset) {
+      self.offset = find_next_lt(self.offset);
+    }
+
+    return self.create_text_token(&html[start..self.offset]);
+  }
+
+  fn create_text_token(&self, text: &'a str) -> Token<'a> {
+    if !self.html_parser.decode_entities {
+      return Token::Text(Cow::Borrowed(text));
+    }
+
+    return Token::Text(html_escape::decode_html_entities(text));
+  }
+
+  /// Everything up to the "</tag_name" is text, even "<" and comments. Entities are only decoded
+  /// inside of <textarea> (browsers do the same). Returns None when the element is empty.
+  fn read_raw_text(&mut self, tag_name: &str) -> Option<Token<'a>> {
+    let html = self.html;
+    let start = self.offset;
+    let end_tag_start = format!("</{}", tag_name);
+
+    // "<" is ascii so every match is on a char boundary
+    while self.offset < html.len() && !self.starts_with_ignore_case(self.offset, &end_tag_start) {
+      self.offset += html[self.offset..].find('<').unwrap_or(html.len() - self.offset).max(1);
+    }
+
+    self.offset = self.offset.min(html.len());
+
+    if self.offset == start {
+      return Option::None;
+    }
+
+    let text = &html[start..self.offset];
+
+    if tag_name == "textarea" {
+      return Option::Some(self.create_text_token(text));
+    }
+
+    return Option::Some(Token::Text(Cow::Borrowed(text)));
+  }
+
+  /// <![CDATA[text]]> becomes a text node with its content taken verbatim
+  fn read_cdata(&mut self) -> Token<'a> {
+    let start = self.offset + CDATA_START.len();
+    return Token::Text(Cow::Borrowed(self.read_until(start, CDATA_END)));
+  }
+
+  fn read_end_tag(&mut self) -> Result<Token<'a>, HtmlParserError> {
+    let start = self.offset;
+
+    return match self.html[start..].find('>') {
+      Option::Some(index) => {
+        // Skip the "/"
+        let tag_name = &self.html[(start + 1)..(start + index)];
+
+        // Skip the ">"
+        self.offset = start + index + 1;
+
+        Result::Ok(Token::EndTag(self.normalize_name(tag_name.trim())))
+      }
+      Option::None => {
+        self.offset = self.html.len();
+        Result::Err(HtmlParserError::new(HtmlParserErrorKind::TagEndNotFound, self.utf16_position(start)))
+      }
+    };
+  }
+
+  fn read_start_tag(&mut self) -> Result<Token<'a>, HtmlParserError> {
+    let start = self.offset;
+    let tag_raw = self.read_until(start, ">");
+
+    let (tag_raw, is_self_closing) = self.strip_self_closing_slash(tag_raw);
+
+    let mut element = self.create_tag(tag_raw, start)?;
+    if is_self_closing {
+      // <span class="x"/> has no children so it must not consume sibling nodes
+      element.is_void_element = true;
+    }
+
+    let lowercase_tag_name = element.tag_name.to_lowercase();
+
+    if !element.is_void_element && RAW_TEXT_ELEMENTS.contains(lowercase_tag_name.as_str()) {
+      self.raw_text_element = Option::Some(lowercase_tag_name);
+    }
+
+    return Result::Ok(Token::StartTag(element));
+  }
+
+  /// Comments may contain anything (including "<", ">" and quotes) so they are not parsed as tags.
+  /// Unterminated comment consumes the rest of the html (that's what browsers do).
+  fn read_comment(&mut self) -> Token<'a> {
+    let start = self.offset + COMMENT_START.len();
+    return Token::Comment(Cow::Borrowed(self.read_until(start, COMMENT_END)));
+  }
+
+  /// "br/", "br /" and "span class=\"x\"/" are self-closing (XHTML syntax). The slash is removed
+  /// from the tag so that it doesn't end up in the tag name or in the last attribute value. A slash
+  /// at the end of an unquoted attribute value (a href=//boards.4chan.org/g/) is kept.
+  fn strip_self_closing_slash(&self, tag_raw: &'a str) -> (&'a str, bool) {
+    let trimmed_tag_raw = tag_raw.trim_end();
+    if !trimmed_tag_raw.ends_with('/') {
+      return (tag_raw, false);
+    }
+
+    let tag_raw_without_slash = &trimmed_tag_raw[..trimmed_tag_raw.len() - 1];
+    let last_part = tag_raw_without_slash.rsplit(' ').next().unwrap_or("");
+
+    let is_unquoted_attr_value = last_part.contains('=')
+      && !last_part.ends_with('"')
+      && !last_part.ends_with('\'');
+
+    if is_unquoted_attr_value {
+      return (tag_raw, false);
+    }
+
+    return (tag_raw_without_slash.trim_end(), true);
+  }
+
+  fn starts_with_ignore_case(&self, start: usize, pattern: &str) -> bool {
+    let html_bytes = self.html.as_bytes();
+    let end = start + pattern.len();
+
+    return end <= html_bytes.len() && html_bytes[start..end].eq_ignore_ascii_case(pattern.as_bytes());
+  }
+
+  /// HtmlParserError positions are in utf-16 code units, they are only computed for errors
+  /// Position (utf-16 code units) right after the last read token
+  pub fn position(&self) -> usize {
+    return self.utf16_position(self.offset);
+  }
+
+  fn utf16_position(&self, byte_offset: usize) -> usize {
+    return self.html[..byte_offset].encode_utf16().count();
+  }
+
+  fn create_tag(&self, tag_raw: &'a str, start: usize) -> Result<BorrowedElement<'a>, HtmlParserError> {
+    let mut offset = skip_whitespaces(tag_raw, 0);
+
+    if offset >= tag_raw.len() {
+      return Result::Err(HtmlParserError::new(HtmlParserErrorKind::EmptyTag, self.utf16_position(start)));
+    }
+
+    let mut tag_name_maybe: Option<Cow<'static, str>> = Option::None;
+    let mut attributes: LinkedHashMap<Cow<'static, str>, Option<Cow<'a, str>>> = LinkedHashMap::new();
+    let mut is_first_part = true;
+
+    while offset < tag_raw.len() {
+      let name_start = offset;
+
+      offset = tag_raw[name_start..]
+        .find(|ch: char| ch.is_whitespace() || ch == '=')
+        .map(|index| name_start + index)
+        .unwrap_or(tag_raw.len());
+
+      let name = &tag_raw[name_start..offset];
+      offset = skip_whitespaces(tag_raw, offset);
+
+      let has_value = tag_raw[offset..].starts_with('=');
+
+      let name = self.normalize_name(name);
+
+      if is_first_part {
+        is_first_part = false;
+
+        if !has_value {
+          tag_name_maybe = Option::Some(name);
+          continue;
+        }
+      }
+
+      if !has_value {
+        // Boolean attribute: <input disabled>
+        attributes.insert(name, Option::None);
+        continue;
+      }
+
+      // Skip the "="
+      offset = skip_whitespaces(tag_raw, offset + 1);
+
+      let (attr_value, new_offset) = read_attr_value(tag_raw, offset);
+      offset = skip_whitespaces(tag_raw, new_offset);
+
+      if name.is_empty() {
+        continue;
+      }
+
+      attributes.insert(name, Option::Some(Cow::Borrowed(attr_value)));
+    }
+
+    if tag_name_maybe.is_none() {
+      return Result::Err(HtmlParserError::new(HtmlParserErrorKind::TagHasNoName, self.utf16_position(start)));
+    }
+
+    let tag_name = tag_name_maybe.unwrap();
+    let is_void_element = VOID_ELEMENTS.contains(tag_name.to_lowercase().as_str());
+
+    return Result::Ok(BorrowedElement {
+      tag_name,
+      attributes,
+      children: Vec::with_capacity(4),
+      is_void_element
+    });
+  }
+
+  fn normalize_name(&self, name: &str) -> Cow<'static, str> {
+    if self.html_parser.lowercase_names && name.chars().any(|ch| ch.is_uppercase()) {
+      return intern_name(&name.to_lowercase());
+    }
+
+    return intern_name(name);
+  }
+
+}
+
+/// Supports "double quoted", 'single quoted' and unquoted values. Unquoted value ends at the first
+/// whitespace, quoted one may contain whitespaces, "=" and the other kind of quotes. Returns the
+/// value and the byte offset right after it.
+fn read_attr_value(tag_raw: &str, start: usize) -> (&str, usize) {
+  let rest = &tag_raw[start..];
+
+  let quote = match rest.chars().next() {
+    Option::None => return ("", start),
+    Option::Some(first_char) => first_char
+  };
+
+  if quote == '"' || quote == '\'' {
+    let value_start = start + 1;
+
+    return match tag_raw[value_start..].find(quote) {
+      // Skip the closing quote
+      Option::Some(index) => (&tag_raw[value_start..(value_start + index)], value_start + index + 1),
+      // There is no closing quote
+      Option::None => (&tag_raw[value_start..], tag_raw.len())
+    };
+  }
+
+  let end = rest.find(char::is_whitespace).map(|index| start + index).unwrap_or(tag_raw.len());
+  return (&tag_raw[start..end], end);
+}
+
+fn skip_whitespaces(tag_raw: &str, start: usize) -> usize {
+  return tag_raw[start..]
+    .find(|ch: char| !ch.is_whitespace())
+    .map(|index| start + index)
+    .unwrap_or(tag_raw.len());
+}
@@ -0,0 +1,78 @@
+use crate::html_parser::node::Node;
+use crate::Element;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisitAction {
+  Continue,
+  /// Do not visit the children of the current element. leave_element() is not called for it either.
+  SkipChildren,
+  /// Stop walking the tree. leave_element() is still called for all the elements that were entered.
+  Stop
+}
+
+/// All the callbacks receive the depth of the node (top level nodes have depth 0)
+pub trait NodeVisitor {
+  fn visit_element(&mut self, _element: &Element, _depth: usize) -> VisitAction {
+    return VisitAction::Continue;
+  }
+
+  /// Called once all the children of the element were visited
+  fn leave_element(&mut self, _element: &Element, _depth: usize) {
+  }
+
+  fn visit_text(&mut self, _text: &String, _depth: usize) -> VisitAction {
+    return VisitAction::Continue;
+  }
+
+  fn visit_comment(&mut self, _text: &String, _depth: usize) -> VisitAction {
+    return VisitAction::Continue;
+  }
+}
+
+/// Walks the nodes depth-first. Returns false when the walking was stopped by the visitor. The tree
+/// is walked without recursion so the depth of it is only limited by the memory.
+pub fn walk(nodes: &[Node], visitor: &mut impl NodeVisitor) -> bool {
+  // The entered elements along with their siblings that are yet to be visited
+  let mut entered_elements: Vec<(&Element, std::slice::Iter<Node>)> = Vec::with_capacity(8);
+  let mut remaining_nodes = nodes.iter();
+
+  loop {
+    let node = match remaining_nodes.next() {
+      Option::Some(node) => node,
+      Option::None => {
+        match entered_elements.pop() {
+          Option::None => return true,
+          Option::Some((element, remaining_siblings)) => {
+            visitor.leave_element(element, entered_elements.len());
+            remaining_nodes = remaining_siblings;
+            continue;
+          }
+        }
+      }
+    };
+
+    let depth = entered_elements.len();
+
+    let visit_action = match node {
+      Node::Text(text) => visitor.visit_text(text, depth),
+      Node::Comment(text) => visitor.visit_comment(text, depth),
+      Node::Element(element) => {
+        let visit_action = visitor.visit_element(element, depth);
+        if visit_action == VisitAction::Continue {
+          let remaining_siblings = std::mem::replace(&mut remaining_nodes, element.children.iter());
+          entered_elements.push((element, remaining_siblings));
+        }
+
+        visit_action
+      }
+    };
+
+    if visit_action == VisitAction::Stop {
+      while let Option::Some((element, _)) = entered_elements.pop() {
+        visitor.leave_element(element, entered_elements.len());
+      }
+
+      return false;
+    }
+  }
+}
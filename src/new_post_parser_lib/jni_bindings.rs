@@ -0,0 +1,111 @@
+//! JNI entry points (the "jni" feature) for com.github.k1rakishou.core_parser.comment.NativePostParser:
+//!
+//! ```java
+//! class NativePostParser {
+//!   static native byte[] parseComment(
+//!     String siteName, String boardCode, long threadNo, long postNo, long postSubNo, String comment,
+//!     long[] threadPosts, long[] myReplies
+//!   );
+//! }
+//! ```
+//!
+//! The result is the flat buffer of ffi::encode_parsed_comment(). Panics never cross the boundary,
+//! they (and the JNI errors) are rethrown as java exceptions and null is returned.
+
+use std::collections::HashSet;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use jni::JNIEnv;
+use jni::errors::Result as JniResult;
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::{jbyteArray, jlong, jlongArray};
+use crate::{PostParser, PostParserContext, PostRaw};
+use crate::ffi::encode_parsed_comment;
+
+const RUNTIME_EXCEPTION_CLASS: &str = "java/lang/RuntimeException";
+// Only the result array is created in the frame (the argument strings are borrowed)
+const LOCAL_FRAME_CAPACITY: i32 = 4;
+
+#[no_mangle]
+pub extern "system" fn Java_com_github_k1rakishou_core_1parser_comment_NativePostParser_parseComment(
+  env: JNIEnv,
+  _class: JClass,
+  site_name: JString,
+  board_code: JString,
+  thread_no: jlong,
+  post_no: jlong,
+  post_sub_no: jlong,
+  comment: JString,
+  thread_posts: jlongArray,
+  my_replies: jlongArray
+) -> jbyteArray {
+  let result = catch_unwind(AssertUnwindSafe(|| {
+    return env.with_local_frame(LOCAL_FRAME_CAPACITY, || {
+      let post_raw = PostRaw::new(
+        &to_string(&env, site_name)?,
+        &to_string(&env, board_code)?,
+        thread_no as u64,
+        post_no as u64,
+        post_sub_no as u64,
+        &to_string(&env, comment)?
+      );
+
+      let post_parser_context = PostParserContext::new(
+        to_post_no_set(&env, my_replies)?,
+        to_post_no_set(&env, thread_posts)?
+      );
+
+      let post_parser = PostParser::new(&post_parser_context);
+      let parsed_comment = post_parser.parse_comment(&post_raw);
+      let byte_array = env.byte_array_from_slice(&encode_parsed_comment(&parsed_comment))?;
+
+      return Ok(JObject::from(byte_array));
+    });
+  }));
+
+  return match result {
+    Ok(Ok(byte_array)) => byte_array.into_inner() as jbyteArray,
+    Ok(Err(error)) => {
+      throw_if_not_pending(&env, &format!("parseComment() jni error: {}", error));
+      ptr::null_mut()
+    }
+    Err(panic) => {
+      let message = panic.downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("unknown"));
+
+      throw_if_not_pending(&env, &format!("parseComment() panicked: {}", message));
+      ptr::null_mut()
+    }
+  };
+}
+
+fn to_string(env: &JNIEnv, string: JString) -> JniResult<String> {
+  return Ok(env.get_string(string)?.into());
+}
+
+fn to_post_no_set(env: &JNIEnv, post_nos: jlongArray) -> JniResult<HashSet<u64>> {
+  if post_nos.is_null() {
+    return Ok(HashSet::new());
+  }
+
+  let len = env.get_array_length(post_nos)?;
+  let mut buffer = vec![0 as jlong; len as usize];
+  env.get_long_array_region(post_nos, 0, &mut buffer)?;
+
+  return Ok(buffer.into_iter().map(|post_no| post_no as u64).collect());
+}
+
+fn throw_if_not_pending(env: &JNIEnv, message: &str) {
+  // A failed JNI call usually leaves its own exception pending which is more useful than ours
+  if env.exception_check().unwrap_or(false) {
+    return;
+  }
+
+  if let Err(_error) = env.throw_new(RUNTIME_EXCEPTION_CLASS, message) {
+    // Nothing else can be done about it, the caller gets null either way
+    #[cfg(feature = "log")]
+    log::warn!("throw_if_not_pending() failed to throw \"{}\": {}", message, _error);
+  }
+}
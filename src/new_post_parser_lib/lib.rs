@@ -6,17 +6,38 @@ extern crate lazy_static;
 
 use std::collections::{HashSet, HashMap};
 use linked_hash_map::LinkedHashMap;
-use crate::html_parser::node::Node;
+use crate::html_parser::node::{Node, BorrowedNode};
 use crate::rules::rule_handler::RuleHandler;
+use crate::html_parser::html_parser_error::HtmlParserError;
 use core::{fmt};
 use std::rc::Rc;
 use std::fmt::{Debug};
+use std::borrow::Cow;
+use std::ops::Deref;
+use crate::site_url_resolver::SiteUrlResolver;
+use regex::Regex;
 
 mod post_parser;
 mod comment_parser;
-mod parsing_error;
+pub mod parsing_error;
+mod parser_profile;
+mod quote_format;
+mod parsed_spannable_text_builder;
+mod post_metadata_parser;
+mod thread_parser;
+mod quote_graph;
+mod parsed_post_serializer;
+mod filter_engine;
+mod page_scraper;
+mod parser_warnings;
+pub mod site_url_resolver;
+pub mod ffi;
+#[cfg(feature = "jni")]
+pub mod jni_bindings;
+#[cfg(feature = "wasm")]
+pub mod wasm_bindings;
 
-mod rules {
+pub mod rules {
   pub mod anchor;
   pub mod line_break;
   pub mod rule_handler;
@@ -27,14 +48,42 @@ mod rules {
   pub mod table_data;
   pub mod bold;
   pub mod abbr;
+  pub mod ban_message;
+  pub mod capcode;
+  pub mod poster_id;
   pub mod style;
-  pub mod pre;
+  pub mod code_block;
+  pub mod strikethrough;
+  pub mod italic;
+  pub mod underline;
+  pub mod superscript;
+  pub mod subscript;
+  pub mod list;
+  pub mod heading;
+  pub mod sjis_art;
+  pub mod simple_span;
+  pub mod style_hint;
+  pub mod fortune;
+  pub mod dice_roll;
+  pub mod poll;
+  pub mod ruby;
+  pub mod text_rule;
+  pub mod backlink;
 }
 
 pub mod html_parser {
   pub mod node;
   pub mod element;
   pub mod parser;
+  pub mod bbcode_parser;
+  pub mod markdown_parser;
+  pub mod plain_text_parser;
+  pub mod html_parser_error;
+  pub mod name_interner;
+  pub mod tokenizer;
+  pub mod visitor;
+  pub mod renderer;
+  pub mod style;
 }
 
 pub mod util {
@@ -43,46 +92,178 @@ pub mod util {
   pub mod color_decoder;
   pub mod style_tag_value_decoder;
   pub mod theme_json_extractor;
+  pub mod spannable_normalizer;
+  pub mod linkifier;
+  pub mod media_link_extractor;
+  pub mod post_image_extractor;
+  pub mod poster_id;
+  pub mod span_offset_converter;
+  pub mod spannable_tree;
+  pub mod style_hint_decoder;
+  pub mod text_cleanup;
+  pub mod whitespace_normalizer;
+  pub mod text_truncator;
+  pub mod word_breaks;
+  pub mod tex;
+  pub mod search_highlighter;
+  #[cfg(feature = "serde")]
+  pub mod serde_helpers;
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextPart {
-  text: String,
+  // Synthetic text (line breaks, quote suffixes, etc.) points into the static strings instead of
+  // allocating a new String for every occurrence
+  text: Cow<'static, str>,
+  // The length of the text in every SpanOffsetMode that can be summed up over the parts (graphemes
+  // can't, a cluster may continue in the next part), computed once on construction
   characters_count: usize,
-  bytes_count: usize
+  bytes_count: usize,
+  utf16_count: usize
 }
 
 impl TextPart {
-  fn new(text: String) -> TextPart {
-    let characters_count = text.chars().count();
+  pub fn new(text: String) -> TextPart {
+    return TextPart::with_text(Cow::Owned(text));
+  }
+
+  pub(crate) fn from_static(text: &'static str) -> TextPart {
+    return TextPart::with_text(Cow::Borrowed(text));
+  }
+
+  fn with_text(text: Cow<'static, str>) -> TextPart {
     let bytes_count = text.len();
 
+    let (characters_count, utf16_count) = if text.is_ascii() {
+      (bytes_count, bytes_count)
+    } else {
+      text.chars().fold((0, 0), |(characters_count, utf16_count), ch| {
+        return (characters_count + 1, utf16_count + ch.len_utf16());
+      })
+    };
+
     return TextPart {
       text,
       characters_count,
-      bytes_count
+      bytes_count,
+      utf16_count
+    };
+  }
+
+  pub fn text(&self) -> &str {
+    return &self.text;
+  }
+
+  pub fn characters_count(&self) -> usize {
+    return self.characters_count;
+  }
+
+  pub fn bytes_count(&self) -> usize {
+    return self.bytes_count;
+  }
+
+  pub fn utf16_count(&self) -> usize {
+    return self.utf16_count;
+  }
+
+  /// Length of the text in the units of [span_offset_mode], None for SpanOffsetMode::Graphemes
+  pub fn len_in(&self, span_offset_mode: &SpanOffsetMode) -> Option<usize> {
+    return match span_offset_mode {
+      SpanOffsetMode::Utf8Bytes => Option::Some(self.bytes_count),
+      SpanOffsetMode::Utf16CodeUnits => Option::Some(self.utf16_count),
+      SpanOffsetMode::UnicodeScalars => Option::Some(self.characters_count),
+      SpanOffsetMode::Graphemes => Option::None
     };
   }
 }
 
+/// The text parts a comment is built of plus the running length (in chars) of the parts, so the
+/// handlers get the offset of any part in O(1) instead of summing up all the parts before it. Derefs
+/// into a slice of the parts, they can only be added and removed at the end.
+#[derive(Debug)]
+pub struct TextParts {
+  parts: Vec<TextPart>,
+  // chars_offsets[i] is the length of parts[..i], there is one more offset than parts
+  chars_offsets: Vec<usize>
+}
+
+impl TextParts {
+  pub fn new() -> TextParts {
+    return TextParts::with_capacity(0);
+  }
+
+  pub fn with_capacity(capacity: usize) -> TextParts {
+    let mut chars_offsets = Vec::with_capacity(capacity + 1);
+    chars_offsets.push(0);
+
+    return TextParts { parts: Vec::with_capacity(capacity), chars_offsets };
+  }
+
+  pub fn push(&mut self, text_part: TextPart) {
+    self.chars_offsets.push(self.chars_count() + text_part.characters_count);
+    self.parts.push(text_part);
+  }
+
+  pub fn pop(&mut self) -> Option<TextPart> {
+    let text_part = self.parts.pop()?;
+    self.chars_offsets.pop();
+
+    return Option::Some(text_part);
+  }
+
+  /// Length of all the parts
+  pub fn chars_count(&self) -> usize {
+    return self.chars_offsets[self.parts.len()];
+  }
+
+  /// Length of the parts before [index] (the offset of the part at [index])
+  pub fn chars_before(&self, index: usize) -> usize {
+    return self.chars_offsets[index.min(self.parts.len())];
+  }
+
+  /// Length of the parts starting with the one at [index]
+  pub fn chars_from(&self, index: usize) -> usize {
+    return self.chars_count() - self.chars_before(index);
+  }
+}
+
+impl Default for TextParts {
+  fn default() -> Self {
+    return TextParts::new();
+  }
+}
+
+impl Deref for TextParts {
+  type Target = [TextPart];
+
+  fn deref(&self) -> &[TextPart] {
+    return &self.parts;
+  }
+}
+
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SiteDescriptor {
   pub site_name: String
 }
 
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoardDescriptor {
   pub site_descriptor: SiteDescriptor,
   pub board_code: String
 }
 
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ThreadDescriptor {
   pub board_descriptor: BoardDescriptor,
   pub thread_no: u64
 }
 
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PostDescriptor {
   pub thread_descriptor: ThreadDescriptor,
   pub post_no: u64,
@@ -131,6 +312,156 @@ impl PostDescriptor {
 pub struct PostRaw {
   pub post_descriptor: PostDescriptor,
   pub com: String,
+  pub metadata: PostMetadataRaw,
+}
+
+/// Everything besides the comment, as it comes from the site api (4chan's thread json field names).
+/// Html fields (sub, name) are html escaped.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct PostMetadataRaw {
+  pub sub: Option<String>,
+  // Html of the whole name block for sites that only provide it that way
+  // (<span class="name">Anonymous</span> <span class="postertrip">!!xyz</span>), used instead of
+  // name/trip/capcode when present
+  pub name_html: Option<String>,
+  pub name: Option<String>,
+  pub trip: Option<String>,
+  pub capcode: Option<String>,
+  // Poster id
+  pub id: Option<String>,
+  pub country: Option<String>,
+  pub country_name: Option<String>,
+  pub board_flag: Option<String>,
+  pub flag_name: Option<String>,
+  // Html of the flag for sites that only provide it that way (<span class="flag flag-us"
+  // title="United States"></span>), used when there are no country/board_flag fields
+  pub flag_html: Option<String>,
+  // Unix timestamp (seconds)
+  pub time: Option<i64>,
+  pub files: Vec<PostFileRaw>,
+  // Html of the file block(s) for sites that only provide it that way (<div class="fileText"> and
+  // <a class="fileThumb">), parsed into ParsedPostMetadata::images and used for files when there
+  // are no files
+  pub files_html: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Hash)]
+pub struct PostFileRaw {
+  // Original file name without the extension
+  pub filename: String,
+  // With the dot (".png")
+  pub ext: String,
+  // Server file name
+  pub tim: String,
+  pub fsize: u64,
+  pub w: u32,
+  pub h: u32,
+  pub md5: Option<String>,
+  pub spoiler: bool,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParsedPostMetadata {
+  pub subject_parsed: Option<ParsedSpannableText>,
+  pub poster_info: PosterInfo,
+  // "Anonymous !!xyz ## Mod" with a Capcode spannable for the capcode
+  pub poster_info_parsed: ParsedSpannableText,
+  pub poster_id: Option<PosterId>,
+  pub flag: Option<PostFlag>,
+  pub timestamp: Option<i64>,
+  pub files: Vec<PostFile>,
+  // Files of PostMetadataRaw::files_html
+  pub images: Vec<PostImage>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PosterInfo {
+  pub name: Option<String>,
+  // "!abc"
+  pub tripcode: Option<String>,
+  // "!!abc"
+  pub secure_tripcode: Option<String>,
+  // "Mod", "Admin" (without the "## ")
+  pub capcode: Option<String>,
+}
+
+/// Per thread id of the poster ("(ID: XYZ)"), see util/poster_id.rs
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PosterId {
+  pub id: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostFlag {
+  // Country code ("US"), board flag code ("AC") or troll flag code ("AN"), always uppercase when
+  // parsed from html
+  pub code: String,
+  pub name: Option<String>,
+  pub kind: PostFlagKind,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PostFlagKind {
+  // Geo location flag
+  Country,
+  // Flag the poster picked from the board's list (4chan /pol/, /mlp/ etc)
+  Board,
+  // Old 4chan /pol/ flags that the poster picked instead of the country flag
+  Troll
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostFile {
+  // With the extension, html entities decoded
+  pub original_name: String,
+  pub server_name: String,
+  // Without the dot ("png")
+  pub extension: String,
+  pub size: u64,
+  pub width: u32,
+  pub height: u32,
+  pub md5: Option<String>,
+  pub is_spoiler: bool,
+}
+
+/// A file of the file block html of a post (see PostMetadataRaw::files_html), unlike PostFile it
+/// has the urls since there is no api to build them from
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostImage {
+  // With the extension, html entities decoded
+  pub original_name: String,
+  // Without the extension
+  pub server_name: String,
+  // Without the dot ("png")
+  pub extension: String,
+  pub size: u64,
+  pub width: u32,
+  pub height: u32,
+  // As they are in the html, usually protocol relative ("//i.4cdn.org/g/1600000000000.png")
+  pub full_url: Option<String>,
+  pub thumbnail_url: Option<String>,
+  pub is_spoiler: bool,
+  pub kind: PostImageKind,
+  // Seconds, for the videos/audio of the sites that show it ("(1.2 MB, 1280x720, 00:01:23)")
+  pub duration: Option<u32>,
+  // Sites that let the poster mark a file as nsfw on sfw boards (separately from spoilers)
+  pub is_nsfw: bool,
+}
+
+/// Guessed from the extension of the file
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PostImageKind {
+  Image,
+  Video,
+  Audio,
+  Other
 }
 
 impl PostRaw {
@@ -173,9 +504,15 @@ impl PostRaw {
         post_no,
         post_sub_no
       },
-      com: String::from(raw_comment)
+      com: String::from(raw_comment),
+      metadata: PostMetadataRaw::default()
     };
   }
+
+  pub fn with_metadata(mut self, metadata: PostMetadataRaw) -> PostRaw {
+    self.metadata = metadata;
+    return self;
+  }
 }
 
 #[derive(Debug)]
@@ -186,25 +523,285 @@ pub struct ThreadRaw {
 #[derive(Debug)]
 pub struct PostParserContext {
   my_replies: HashSet<u64>,
-  thread_posts: HashSet<u64>
+  thread_posts: HashSet<u64>,
+  span_offset_mode: SpanOffsetMode,
+  search_highlight_options: SearchHighlightOptions,
+  // Compiled from search_highlight_options, None when there is nothing to highlight
+  search_highlight_pattern: Option<Regex>,
+  // The parsed text is cut after this many chars (catalog previews), None to keep the whole text
+  max_parsed_length: Option<usize>,
+  // Appended to the parsed text when it was cut
+  truncation_ellipsis: String,
+  quote_suffixes: QuoteSuffixes,
+  // post_no -> preview of the post, empty unless the caller has them
+  quote_previews: HashMap<u64, QuotePreview>
+}
+
+/// Suffixes the parser appends to the texts of the quote links. They are covered by the link
+/// spannables so the client doesn't need to insert them (and shift the spannables that follow).
+/// Everything is enabled by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteSuffixes {
+  /// " (OP)" for the quotes of the original post of the thread
+  pub op: bool,
+  /// " (You)" for the quotes of the user's posts, " (Me)" when the user quotes their own post
+  pub you: bool,
+  /// " (DEAD)" for the quotes of deleted posts
+  pub dead: bool,
+  pub cross_thread: CrossThreadSuffix
+}
+
+/// What is appended to the links into other threads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrossThreadSuffix {
+  None,
+  /// ">>>/g/123 →"
+  Arrow,
+  /// ">>>/g/123 (Cross-thread)"
+  Label
 }
 
-#[derive(Clone, PartialEq)]
+/// Terms of the current search, every occurrence of them in the parsed text gets a SearchHighlight
+/// spannable
+#[derive(Debug, Clone, Default)]
+pub struct SearchHighlightOptions {
+  pub terms: Vec<String>,
+  pub case_insensitive: bool,
+  // Only highlight occurrences that are not a part of a longer word
+  pub whole_word: bool
+}
+
+/// The units Spannable start/len are measured in
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpanOffsetMode {
+  Utf8Bytes,
+  // What Java/Kotlin strings (and Android's SpannableString) use
+  Utf16CodeUnits,
+  // Rust chars, the default
+  UnicodeScalars,
+  // User perceived characters ("👍🏽" is one grapheme but two scalars)
+  Graphemes
+}
+
+/// Implements Drop (deep trees are dropped without recursion) so the fields can't be moved out by
+/// destructuring, take them with std::mem::take() instead
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Element {
-  pub tag_name: String,
-  pub attributes: LinkedHashMap<String, String>,
+  // Known names are interned (see name_interner), only unknown ones are allocated
+  pub tag_name: Cow<'static, str>,
+  // Boolean attributes (<input disabled>) have no value
+  #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_helpers::linked_hash_map_as_pairs"))]
+  pub attributes: LinkedHashMap<Cow<'static, str>, Option<String>>,
   pub children: Vec<Node>,
   pub is_void_element: bool,
 }
 
+/// Element of HtmlParser::parse_borrowed(), attribute values are slices of the parsed html. Same as
+/// Element it implements Drop and can't be destructured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorrowedElement<'a> {
+  pub tag_name: Cow<'static, str>,
+  // Boolean attributes (<input disabled>) have no value
+  pub attributes: LinkedHashMap<Cow<'static, str>, Option<Cow<'a, str>>>,
+  pub children: Vec<BorrowedNode<'a>>,
+  pub is_void_element: bool,
+}
+
+/// Protection against pathological (crafted) comments. HtmlParser aborts with a TooManyNodes,
+/// TooDeep or Timeout error when one of the limits is exceeded, PostParser then shows the comment as
+/// plain text. See ParserLimits::default() for the defaults.
+#[derive(Debug, Clone)]
+pub struct ParserLimits {
+  /// Wall-clock budget for parsing a single post. When exceeded while the html is parsed the
+  /// parsing is aborted, when exceeded while the tree is walked the parser stops and returns
+  /// whatever was produced so far. Disabled when None.
+  pub max_parse_millis: Option<u64>,
+  /// Elements, texts and comments of the html. Disabled when None.
+  pub max_nodes: Option<usize>,
+  /// How deep the elements may be nested. Disabled when None. The tree is built, walked, rendered
+  /// and dropped without recursion so any depth works with the parser itself, only the derived
+  /// Clone, PartialEq and Debug of the nodes are recursive.
+  pub max_depth: Option<usize>
+}
+
+/// Cumulative counters of a PostParser for in-app diagnostics ("stats" feature), see
+/// PostParser::stats(). Allocations are not counted, that needs a counting global allocator (see
+/// tests/allocation_tests.rs) which a library can't install.
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PostParserStats {
+  pub posts_parsed: u64,
+  /// Html elements, texts and comments that were walked
+  pub nodes_parsed: u64,
+  /// Zero on wasm32-unknown-unknown (no clock)
+  pub parse_time: std::time::Duration
+}
+
+/// Site-injected junk that is removed from the parsed text (spannables are moved accordingly).
+/// Nothing is removed by default.
+#[derive(Debug, Clone, Default)]
+pub struct TextCleanupOptions {
+  /// "[Embed]" and "(embed)" markers
+  pub strip_embed_markers: bool,
+  /// ">>123 (You) (You)" -> ">>123 (You)"
+  pub strip_duplicate_you_markers: bool,
+  /// " (OP)" markers of quotes
+  pub strip_op_markers: bool,
+  /// Any other text to remove
+  pub strip_patterns: Vec<Regex>
+}
+
+/// How the line breaks of a site are converted into "\n" of the parsed text. <br> is always a line
+/// break. The default keeps the "\n" of the text and does nothing else.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBreakMode {
+  /// "\n" of the text nodes are line breaks. When false they are replaced with spaces like browsers
+  /// do (for the sites where only the tags mean a new line), except for the ones inside of
+  /// <pre>/<code>/<textarea>.
+  pub text_newlines: bool,
+  /// <p> and <div> start on a new line and the text after them starts on a new line too
+  pub block_elements: bool,
+  /// Runs of blank lines are shortened to this many. None keeps them all.
+  pub max_blank_lines: Option<usize>
+}
+
+/// What the <wbr> tags (word break opportunities in long words and links) become in the parsed
+/// text. Soft hyphens are always removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordBreakMode {
+  /// Removed, the default
+  Strip,
+  /// "\u{200B}" so that the text view can wrap the long words there. Detected links stay whole and
+  /// their urls don't contain it.
+  ZeroWidthSpace
+}
+
+/// What happens with the [math]...[/math] (inline) and [eqn]...[/eqn] (display mode) TeX regions of
+/// the comments. 4chan only renders them on /sci/ so they are ignored by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TeXMode {
+  /// The tags are kept as is, the default
+  Disabled,
+  /// The tags are removed and the TeX source is covered with a TeX spannable
+  Source,
+  /// The whole region is replaced with "\u{FFFC}" that is covered with a TeX spannable which has
+  /// the TeX source
+  Placeholder
+}
+
+/// Whitespace normalization of the parsed text. The text of code blocks, inline code and sjis art
+/// is always kept as is. The default preserves everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WhitespacePolicy {
+  /// Runs of spaces/tabs are shortened to their first character
+  pub collapse_runs: bool,
+  /// Spaces/tabs at the ends of the lines are removed
+  pub trim_line_ends: bool,
+  /// Whitespace (including line breaks) at the start and at the end of the text is removed
+  pub trim_post_ends: bool
+}
+
+/// What the <s> tag means on a site. On 4chan it's a spoiler, on most of the other sites it's
+/// just a strikethrough text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum STagMeaning {
+  Spoiler,
+  Strikethrough
+}
+
+/// What happens with the elements that have no rule of their own tag (wildcard rules like the style
+/// attribute ones don't count). <p> and <div> are never unknown since they only separate blocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnknownTagPolicy {
+  /// The tag is dropped but the text of the children is kept, the default
+  KeepChildren,
+  /// The element is dropped together with all of its children
+  DropSubtree,
+  /// Same as KeepChildren but the text of the children is covered with an Unknown spannable
+  Marker
+}
+
+/// What the <ruby> annotations (furigana) become in the parsed text. The base text gets a Ruby
+/// spannable with the reading either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RubyMode {
+  /// "漢字(かんじ)", the default
+  Parenthesized,
+  /// "漢字", the <rt> readings are only kept in the spannables
+  BaseOnly
+}
+
+/// How post quotes are written in the comments of a site: ">>123" and ">>>/g/123" on 4chan,
+/// "#123" on Futaba, "№123" on some russian boards
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteFormat {
+  pub post_quote_prefix: String,
+  /// Followed by "board_code/post_no", None when the site has no cross board quotes
+  pub cross_board_quote_prefix: Option<String>,
+  pub label_style: QuoteLabelStyle
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuoteLabelStyle {
+  /// The quote text is kept as it is in the comment
+  Original,
+  /// Quotes are rewritten into >>123 and >>>/g/123 so that they look the same for every site
+  FourChan
+}
+
+/// Markup the comments of a site are written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentFormat {
+  Html,
+  // [b]text[/b], parsed with BbCodeParser
+  BbCode,
+  // **text**, parsed with MarkdownParser
+  Markdown,
+  // Unformatted text, parsed with PlainTextParser
+  PlainText
+}
+
+/// Everything that differs between the supported sites: which tags/classes are converted into what,
+/// whether html entities are decoded and what needs to be replaced in the raw comment before parsing.
+pub struct ParserProfile {
+  pub site_name: String,
+  pub parsing_rules: ParsingRules,
+  pub decode_entities: bool,
+  /// (pattern, value) pairs, every [pattern] in the raw comment is replaced with [value]
+  pub replacement_rules: Vec<(String, String)>,
+  pub comment_format: CommentFormat,
+  pub line_break_mode: LineBreakMode,
+  pub whitespace_policy: WhitespacePolicy,
+  /// (class, role) pairs, <span class="[class]"> becomes a ColoredQuote spannable with [role]
+  /// (orangetext, pinktext and other greentext alternates)
+  pub colored_quote_classes: Vec<(String, ColoredQuoteRole)>
+}
+
 pub struct PostParser<'a> {
   post_parser_context: &'a PostParserContext,
+  parser_limits: ParserLimits,
+  decode_entities: bool,
+  comment_format: CommentFormat,
+  line_break_mode: LineBreakMode,
+  whitespace_policy: WhitespacePolicy,
+  word_break_mode: WordBreakMode,
+  tex_mode: TeXMode,
+  unknown_tag_policy: UnknownTagPolicy,
+  // Called with every element that has no rule of its own, see UnknownTagPolicy
+  unknown_tag_callback: Option<Box<dyn Fn(&PostRaw, &Element)>>,
+  site_url_resolvers: Vec<Box<dyn SiteUrlResolver>>,
+  merge_adjacent_spannables: bool,
+  text_cleanup_options: TextCleanupOptions,
+  filter_engine: Option<FilterEngine>,
   pub comment_parser: Box<CommentParser<'a>>,
+  #[cfg(feature = "stats")]
+  stats: std::cell::Cell<PostParserStats>,
 }
 
 pub struct CommentParser<'a> {
   post_parser_context: &'a PostParserContext,
-  matching_rules: LinkedHashMap<String, Vec<Rc<ParsingRule>>>,
+  parsing_rules: ParsingRules,
   /// [Key] what pattern in the comment text needs to be replaced with [Value]
   replacement_rules: HashMap<String, String>
 }
@@ -215,24 +812,302 @@ pub struct Attribute {
   attr_value: Option<String>
 }
 
+/// Maps elements to the rule handlers that convert them into text parts and spannables. A rule is
+/// registered for a tag (or "*" for every tag) and applies to the elements that have its required
+/// attributes or match its selector (see ParsingRule::with_selector()). The rules of every tag are
+/// kept sorted by priority, rules with the same priority in the order they were added in. For an
+/// element they are merged with the wildcard rules and run from the highest priority to the lowest
+/// one, see ParsingRule::with_priority().
+pub struct ParsingRules {
+  matching_rules: LinkedHashMap<String, Vec<Rc<ParsingRule>>>,
+  // Applied to the final text in the order they were added in
+  text_rules: Vec<Rc<TextRule>>,
+  // Quote format of the span/a handlers, used where quotes are recognized without the handlers
+  // (catalog snippets)
+  quote_format: QuoteFormat
+}
+
+/// Text level rule: every match of the pattern in the final text (after all the element rules) is
+/// converted into spannables by the handler
+pub struct TextRule {
+  pattern: Regex,
+  handler: Box<dyn Fn(&regex::Captures) -> TextRuleOutput>
+}
+
+pub struct TextRuleOutput {
+  /// Capture group index -> spannable that covers the group's text (0 is the whole match)
+  pub spans: Vec<(usize, SpannableData)>,
+  /// None keeps the matched text as is, Some(group) replaces the whole match with the group's text
+  /// (strips markup like "[spoiler]" and "[/spoiler]")
+  pub keep_group: Option<usize>
+}
+
 pub struct ParsingRule {
   tag_name: String,
   required_attributes: HashSet<Attribute>,
-  handler: Rc<dyn RuleHandler>
+  handler: Rc<dyn RuleHandler>,
+  // Rules that apply to an element are run from the highest priority to the lowest one, rules with
+  // the same priority in the order they were added
+  priority: i32,
+  // Checked with Element::matches() instead of required_attributes, see ParsingRule::with_selector()
+  selector: Option<String>
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParsedPost {
   pub post_descriptor: PostDescriptor,
   pub post_comment_parsed: ParsedSpannableText,
+  pub metadata: ParsedPostMetadata,
+  /// Media links (youtube etc) of the comment so that the client can fetch their titles/durations
+  /// without re-parsing the text
+  pub media_links: Vec<MediaLink>,
+  /// Dice rolls of the comment in the order of appearance
+  pub rolls: Vec<PostRoll>,
+  /// Polls of the comment in the order of appearance
+  pub polls: Vec<PostPoll>,
+  /// Post numbers of the current thread this post quotes (alive and dead ones)
+  pub replies_to: HashSet<u64>,
+  /// Filters of the PostParser's FilterEngine that matched the post, in the order they were added
+  pub filter_matches: Vec<FilterMatch>,
+  /// The site cut the comment ("Comment too long. Click here to view the full text."), the full
+  /// text is only on the thread page
+  pub is_comment_abbreviated: bool,
+  /// The comment has the deleted post warning of the site
+  pub is_deleted: bool,
+  /// The comment has a BanMessage spannable (the poster was banned/warned for this post)
+  pub has_ban_message: bool,
+}
+
+/// User filters (hide/remove/highlight posts by the comment, subject, name or file name) that are
+/// evaluated while the posts are parsed so the client does not have to parse the html twice
+#[derive(Debug, Clone, Default)]
+pub struct FilterEngine {
+  filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone)]
+struct Filter {
+  filter_id: u64,
+  pattern: Regex,
+  scopes: Vec<FilterScope>,
+  action: FilterAction,
+  // Matches in the comment/subject get a FilterHighlight spannable with this color
+  highlight_color: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterPattern {
+  Regex { pattern: String },
+  Plain { text: String, case_insensitive: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterScope {
+  Comment,
+  Subject,
+  Name,
+  // Original file names (with the extension)
+  Filename,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterAction {
+  Hide,
+  Remove,
+  Highlight,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterMatch {
+  pub filter_id: u64,
+  pub action: FilterAction,
+}
+
+/// Css selectors of the html pages of a site without a json api, see PageScraper. Every selector is
+/// a simple (compound) selector (see Element::matches()), the post selector is matched against the
+/// whole page and the rest against the descendants of a post (or a file). An empty selector never
+/// matches so the field is just not extracted.
+#[derive(Debug, Clone)]
+pub struct SiteSelectors {
+  pub post: String,
+  /// Attribute of the post element with the post number, the digits at the end of the value are used
+  /// (id="p123", id="reply_123")
+  pub post_no_attr: String,
+  pub subject: String,
+  /// The element with the name, tripcode and capcode, see PostMetadataRaw::name_html
+  pub name_block: String,
+  pub poster_id: String,
+  pub flag: String,
+  pub time: String,
+  /// Attribute of the time element with either a unix timestamp or an ISO 8601 date
+  pub time_attr: String,
+  pub comment: String,
+  pub file: String,
+  /// Element of a file with the link to the file ("File: <a href="/b/src/123.png">") and the size
+  /// and the dimensions in its text ("(12 KB, 200x300)")
+  pub file_info: String,
+  /// Element of a file with the original file name in the title attribute or in the text
+  pub file_name: String,
+  /// Present when the file is spoilered
+  pub file_spoiler: String,
+  /// A thread of the catalog page, the rest of the catalog selectors are matched against the thread
+  /// element itself and its descendants
+  pub catalog_thread: String,
+  /// Same as post_no_attr but of the catalog thread element
+  pub catalog_thread_no_attr: String,
+  pub catalog_subject: String,
+  /// The subject and the counts are removed from the comment html when they are inside of it
+  pub catalog_comment: String,
+  /// Element with the reply and the image counts in its text ("R: 12 / I: 3")
+  pub catalog_counts: String,
+  pub catalog_last_modified: String,
+  /// Same as time_attr but of the catalog_last_modified element
+  pub catalog_last_modified_attr: String,
+  /// A thread of a board index page (the OP and the last replies)
+  pub index_thread: String,
+  /// Element with the links to the pages of the board index ("[1] [2] [3]")
+  pub pagination: String,
+  /// The element of the current page inside of the pagination element
+  pub pagination_current: String,
+}
+
+/// A board index page, see PageScraper::scrape_board_page()
+#[derive(Debug)]
+pub struct BoardPage {
+  /// The posts of every thread of the page (the OP and the last replies) in page order
+  pub threads: Vec<Vec<PostRaw>>,
+  /// Pages are numbered from 1 like on the sites
+  pub current_page: Option<u32>,
+  pub total_pages: Option<u32>,
+  /// As it is in the page so it may be relative ("2.html"), None on the last page
+  pub next_page_url: Option<String>,
+}
+
+/// A thread of a catalog page, see PageScraper::scrape_catalog(). Html fields are html escaped like
+/// PostRaw::com.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogThreadRaw {
+  pub thread_no: u64,
+  pub subject_html: Option<String>,
+  pub comment_html: String,
+  pub reply_count: u32,
+  pub image_count: u32,
+  // Unix timestamp (seconds) of the last bump
+  pub last_modified: Option<i64>,
 }
 
+/// Extracts PostRaws out of the html pages of the sites that have no json api so that they can go
+/// through PostParser like the posts of the other sites.
+pub struct PageScraper {
+  site_name: String,
+  board_code: String,
+  selectors: SiteSelectors,
+}
+
+/// Thread wide helpers on top of PostParser. An instance also keeps the parsed posts of a thread
+/// between updates so that only the new/changed posts are re-parsed (see ThreadParser::update()).
+pub struct ThreadParser {
+  my_replies: HashSet<u64>,
+  span_offset_mode: SpanOffsetMode,
+  // In thread order
+  parsed_posts: LinkedHashMap<u64, ParsedPost>,
+  post_hashes: HashMap<u64, ParsedPostHash>,
+  filter_engine: FilterEngine,
+  // Incremented every time the filters change so that all the posts are re-parsed
+  filters_version: u64,
+}
+
+struct ParsedPostHash {
+  // Hash of the comment and the metadata
+  post_hash: u64,
+  // Hash of the context state the post was parsed with (whether the quoted posts exist, whether
+  // they are the user's posts and the filters version)
+  context_hash: u64,
+}
+
+/// Result of ThreadParser::update(), all lists are in thread order
+#[derive(Debug, PartialEq)]
+pub struct ThreadUpdate {
+  /// Posts that weren't parsed before
+  pub added: Vec<u64>,
+  /// Previously parsed posts that were re-parsed because their comment/metadata or context changed
+  pub invalidated: Vec<u64>,
+  /// Previously parsed posts that are no longer in the thread
+  pub removed: Vec<u64>,
+}
+
+/// Quotes between the posts of a thread in both directions, see ThreadParser::build_quote_graph().
+/// Threads can have circular quotes (a post quoting itself, two posts edited/faked to quote each
+/// other), the recursive utilities stop at max_depth and visit every post once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteGraph {
+  /// ParsedPost::replies_to of every post
+  pub replies_to: HashMap<u64, HashSet<u64>>,
+  /// See ThreadParser::build_replies_from()
+  pub replies_from: HashMap<u64, HashSet<u64>>,
+  /// Quote cycles found in the thread (for debugging), e.g. [3, 5] when 3 quotes 5 and 5 quotes 3
+  pub cycles: Vec<Vec<u64>>,
+  max_depth: usize,
+}
+
+/// Plain text beginning of a comment for the catalog cells, see PostParser::parse_for_catalog()
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CatalogSnippet {
+  pub text: String,
+  /// The text was cut and ends with the truncation ellipsis
+  pub was_truncated: bool
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParsedSpannableText {
   pub original_text: String,
   pub parsed_text: Box<String>,
-  pub spannables: Box<Vec<Spannable>>
+  pub spannables: Box<Vec<Spannable>>,
+  /// Parsing took longer than ParserLimits::max_parse_millis so the text and spannables only contain
+  /// what was parsed before the budget ran out
+  pub timed_out: bool,
+  /// The comment html could not be parsed, parsed_text contains the unparsed comment
+  pub html_parser_error: Option<HtmlParserError>,
+  /// The parsed text was longer than PostParserContext::max_parsed_length and was cut
+  pub was_truncated: bool,
+  /// The html had the "Comment too long" link of the site (removed from the text)
+  pub is_abbreviated: bool,
+  /// The html had the deleted post warning of the site (removed from the text)
+  pub is_deleted: bool,
+  /// Unexpected markup the parser ran into (a site probably changed its html). Not included in
+  /// ParsedPost::to_bytes().
+  pub warnings: Vec<ParserWarning>
+}
+
+/// See parser_warnings.rs
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParserWarning {
+  /// Handler (or other part of the parser) that reported the warning
+  pub source: String,
+  pub message: String,
+  /// Html of the element the warning is about
+  pub html: Option<String>,
+  /// Offset (in chars) in the parsed text before it was post processed (cleanup, truncation, etc)
+  pub position: Option<usize>
+}
+
+/// Builds a ParsedSpannableText that did not come from a comment ("N posts omitted", placeholder
+/// posts etc). The spans are given in chars and go through the same normalization and offset
+/// conversion as the ones of parsed comments, see parsed_spannable_text_builder.rs
+pub struct ParsedSpannableTextBuilder {
+  text: String,
+  // text.chars().count()
+  text_length: usize,
+  spannables: Vec<Spannable>,
+  span_offset_mode: SpanOffsetMode
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Spannable {
   // unicode characters (not u8!)
   pub start: usize,
@@ -243,6 +1118,7 @@ pub struct Spannable {
 
 /// When changing this DO NOT FORGET to also change com.github.k1rakishou.core_themes.ChanThemeColorId !!!
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChanThemeColorId {
   PostSubjectColor = 0,
   PostNameColor = 1,
@@ -285,34 +1161,190 @@ impl fmt::Display for ChanThemeColorId {
   }
 }
 
+/// The discriminants are stable (they are also the tags of the binary format and the values of
+/// kind()), new variants get the next free number and existing ones are never renumbered.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u32)]
 pub enum SpannableData {
-  Link(PostLink),
-  Spoiler,
-  GreenText,
-  BoldText,
+  Link(PostLink) = 0,
+  Spoiler = 1,
+  GreenText = 2,
+  BoldText = 3,
+  Italic = 4,
+  Underline = 5,
+  Strikethrough = 6,
+  Superscript = 7,
+  Subscript = 8,
+  // <li>, depth starts with 1 (top level list), index starts with 1 (or with the "start" attribute
+  // of <ol>)
+  ListItem { depth: usize, ordered: bool, index: usize } = 9,
+  // <span class="sjis">, shift_jis/ascii art
+  SjisArt = 10,
+  // <h1>-<h6>, level is 1-6
+  Heading { level: u8 } = 11,
+  // Contents of a <td> without the separator
+  TableCell = 12,
   // font-size:22px;font-size:150%;
-  FontSize { size: String },
+  FontSize { size: String } = 13,
   // TODO: FontWeight is not implemented yet
   // font-weight:600;font-weight:bold
-  FontWeight { weight: String },
+  FontWeight { weight: String } = 14,
+  // <code>
+  InlineCode = 15,
+  // <pre>, <pre class="prettyprint">
+  CodeBlock = 16,
   // color:#fd4d32
-  Monospace,
-  TextForegroundColorRaw { color_hex: String },
-  TextBackgroundColorRaw { color_hex: String },
-  TextForegroundColorId { color_id: ChanThemeColorId },
-  TextBackgroundColorId { color_id: ChanThemeColorId },
-  ThemeJson { theme_name: String, is_light_theme: bool }
+  Monospace = 17,
+  TextForegroundColorRaw { color_hex: String } = 18,
+  TextBackgroundColorRaw { color_hex: String } = 19,
+  TextForegroundColorId { color_id: ChanThemeColorId } = 20,
+  TextBackgroundColorId { color_id: ChanThemeColorId } = 21,
+  ThemeJson { theme_name: String, is_light_theme: bool } = 22,
+  // Covers the same text as the Link(UrlLink) spannable of the media url
+  MediaLink { kind: MediaLinkKind, id: String } = 23,
+  // Site css (style="color:#789922", <span class="fortune">) translated into client theme terms
+  StyleHint(SpannableStyleHint) = 24,
+  // <span class="fortune" style="color:#fd4d32">Your fortune: ...</span>
+  Fortune { color: String } = 25,
+  // <b>Rolled 5, 3 = 8</b>
+  DiceRoll(PostRoll) = 26,
+  // <div class="poll">, covers the placeholder character the markup was replaced with
+  Poll(PostPoll) = 30,
+  // [math]x^2[/math] or [eqn]x^2[/eqn] (display_mode), see TeXMode
+  TeX { display_mode: bool, source: Option<String> } = 31,
+  // Base text of a <ruby> annotation, see RubyMode
+  Ruby { reading: String } = 32,
+  // Element that no rule handles, only emitted with UnknownTagPolicy::Marker
+  Unknown { tag: String } = 33,
+  // "## Mod" of the poster info, <span class="capcodeAdmin"> of the comment
+  Capcode { capcode: String, kind: CapcodeKind } = 27,
+  // Occurrence of one of the PostParserContext search highlight terms
+  SearchHighlight = 28,
+  // Text matched by a FilterEngine filter that has a highlight color
+  FilterHighlight { color: String } = 29,
+  // "(USER WAS BANNED FOR THIS POST)" and the like, see rules/ban_message.rs
+  BanMessage = 34,
+  // Greentext alternates ("<text" orangetext etc), see ParserProfile::colored_quote_classes
+  ColoredQuote { role: ColoredQuoteRole } = 35,
+  // <span class="posteruid id_XYZ">(ID: XYZ)</span> of the comment
+  PosterId(PosterId) = 36,
+  // App specific spans (third-eye links etc) produced by the caller's own RuleHandlers/TextRules.
+  // [kind] is up to the app, the payload goes through the serializer as is.
+  Custom { kind: u32, payload: Vec<u8> } = 255
+}
+
+/// Theme color of a ColoredQuote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColoredQuoteRole {
+  Orange,
+  Pink,
+  Purple,
+  Red,
+  Blue
+}
+
+/// Who the capcode belongs to so that the client can pick the theme color. See rules/capcode.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapcodeKind {
+  Admin,
+  Mod,
+  Developer,
+  Founder,
+  // "Manager", "Verified" etc
+  Other
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostRoll {
+  pub dice: u32,
+  // Only known when the site shows it ("Rolled 5, 3 = 8 (2d6)")
+  pub sides: Option<u32>,
+  pub results: Vec<u32>,
+  // May differ from the sum of the results when the roll has a modifier (1d20+2)
+  pub total: i64
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostPoll {
+  // Empty when the poll markup has no question
+  pub question: String,
+  // Option text and its vote count (0 when the site does not show the counts)
+  pub options: Vec<(String, u64)>
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpannableStyleHint {
+  pub color_role: Option<ChanThemeColorId>,
+  pub background_role: Option<ChanThemeColorId>,
+  pub is_bold: bool,
+  pub is_italic: bool
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MediaLinkKind {
+  YouTube,
+  Streamable,
+  Vimeo,
+  SoundCloud
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaLink {
+  pub kind: MediaLinkKind,
+  // Video id for YouTube/Streamable/Vimeo, "artist/track" for SoundCloud
+  pub id: String
+}
+
+/// Already known parts of a quoted post for the inline quote previews
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuotePreview {
+  pub poster_name: String,
+  pub comment_preview: String
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PostLink {
-  Quote { post_no: u64 },
+  // is_you - the quoted post is one of the user's posts, is_op - the quoted post is the OP of the
+  // thread. The same flags the "(You)"/"(OP)" suffixes are added for. preview - what the caller
+  // passed for the quoted post in PostParserContext::with_quote_previews()
+  Quote { post_no: u64, is_you: bool, is_op: bool, preview: Option<QuotePreview> },
   Dead { post_no: u64 },
   UrlLink { link: String },
   BoardLink { board_code: String },
   SearchLink { board_code: String, search_query: String },
-  ThreadLink { board_code: String, thread_no: u64, post_no: u64 }
+  ThreadLink { board_code: String, thread_no: u64, post_no: u64 },
+  // >>>/a/12345 that points to a post that no longer exists, the thread is unknown so the post can
+  // only be looked up in the archives
+  DeadCrossBoard { board_code: String, post_no: u64 },
+  // mailto: links (including the sage/email field links), the email is stored without the scheme
+  Email { email: String }
+}
+
+pub struct HtmlParser {
+  limits: ParserLimits,
+  // Decode html entities (&gt; &#039; &#x27; etc) of Node::Text nodes while parsing
+  decode_entities: bool,
+  // Convert tag and attribute names to lowercase (<SPAN CLASS="quote"> -> <span class="quote">) so
+  // that the rules match regardless of the input casing
+  lowercase_names: bool
 }
 
-pub struct HtmlParser {}
\ No newline at end of file
+/// Front-end for the sites that use BBCode instead of html, produces the same node tree as HtmlParser
+pub struct BbCodeParser {}
+
+/// Front-end for the sites and archives that return markdown, produces the same node tree as HtmlParser
+pub struct MarkdownParser {}
+
+/// Front-end for unformatted comments (and the fallback for html that failed to parse), produces
+/// the same node tree as HtmlParser
+pub struct PlainTextParser {}
\ No newline at end of file
@@ -0,0 +1,443 @@
+pub mod page_scraper {
+  use regex::Regex;
+  use crate::{PageScraper, SiteSelectors, PostRaw, PostMetadataRaw, PostFileRaw, CatalogThreadRaw, BoardPage, HtmlParser, Element, ParserWarning, PosterId};
+  use crate::html_parser::node::Node;
+  use crate::html_parser::html_parser_error::HtmlParserError;
+  use crate::html_parser::renderer::{render_nodes, escape_text};
+  use crate::parser_warnings::report_warning;
+  use crate::util::post_image_extractor::{parse_file_size, parse_dimensions, file_name_of_url, split_extension};
+
+  const TAG: &str = "PageScraper";
+  const MD5_ATTR: &str = "data-md5";
+
+  lazy_static! {
+    static ref TRAILING_DIGITS_PATTERN: Regex = Regex::new(r"(\d+)$").unwrap();
+    // "R: 12 / I: 3", "Replies: 12 Images: 3"
+    static ref REPLY_COUNT_PATTERN: Regex = Regex::new(r"(?i)\bR(?:eplies)?:\s*(\d+)").unwrap();
+    static ref IMAGE_COUNT_PATTERN: Regex = Regex::new(r"(?i)\bI(?:mages)?:\s*(\d+)").unwrap();
+    // 2020-09-13T12:26:40Z, 2020-09-13T15:26:40+03:00, 2020-09-13 12:26:40
+    static ref ISO_8601_PATTERN: Regex = Regex::new(
+      r"^(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2})(?::(\d{2})(?:\.\d+)?)?(Z|[+-]\d{2}:?\d{2})?$"
+    ).unwrap();
+  }
+
+  impl SiteSelectors {
+    pub fn four_chan() -> SiteSelectors {
+      return SiteSelectors {
+        post: String::from("div.post"),
+        post_no_attr: String::from("id"),
+        subject: String::from("span.subject"),
+        name_block: String::from("span.nameBlock"),
+        poster_id: String::from("span.posteruid"),
+        flag: String::from("span.flag"),
+        time: String::from("span.dateTime"),
+        time_attr: String::from("data-utc"),
+        comment: String::from("blockquote.postMessage"),
+        file: String::from("div.file"),
+        file_info: String::from("div.fileText"),
+        file_name: String::from("a"),
+        file_spoiler: String::from("a.imgspoiler"),
+        // The catalog page of 4chan is rendered by js out of the catalog json
+        catalog_thread: String::new(),
+        catalog_thread_no_attr: String::new(),
+        catalog_subject: String::new(),
+        catalog_comment: String::new(),
+        catalog_counts: String::new(),
+        catalog_last_modified: String::new(),
+        catalog_last_modified_attr: String::new(),
+        index_thread: String::from("div.thread"),
+        pagination: String::from("div.pagelist"),
+        pagination_current: String::from("strong")
+      };
+    }
+
+    /// vichan/tinyboard and their forks
+    pub fn vichan() -> SiteSelectors {
+      return SiteSelectors {
+        post: String::from("div.post"),
+        post_no_attr: String::from("id"),
+        subject: String::from("span.subject"),
+        name_block: String::from("p.intro"),
+        poster_id: String::from("span.poster_id"),
+        flag: String::from("img.flag"),
+        time: String::from("time"),
+        time_attr: String::from("datetime"),
+        comment: String::from("div.body"),
+        file: String::from("div.file"),
+        file_info: String::from("p.fileinfo"),
+        file_name: String::from("a.postfilename"),
+        file_spoiler: String::new(),
+        catalog_thread: String::from("div.mix"),
+        catalog_thread_no_attr: String::from("data-id"),
+        catalog_subject: String::from("p.intro"),
+        catalog_comment: String::from("div.replies"),
+        catalog_counts: String::from("strong"),
+        catalog_last_modified: String::from("div.mix"),
+        catalog_last_modified_attr: String::from("data-bump"),
+        index_thread: String::from("div.thread"),
+        pagination: String::from("div.pages"),
+        pagination_current: String::from("a.selected")
+      };
+    }
+
+    pub fn for_site_name(site_name: &str) -> Option<SiteSelectors> {
+      return match site_name.to_lowercase().as_str() {
+        "4chan" | "4channel" => Option::Some(SiteSelectors::four_chan()),
+        "vichan" | "lainchan" | "8kun" => Option::Some(SiteSelectors::vichan()),
+        _ => Option::None
+      };
+    }
+  }
+
+  impl PageScraper {
+    pub fn new(site_name: &str, board_code: &str, selectors: SiteSelectors) -> PageScraper {
+      return PageScraper {
+        site_name: String::from(site_name),
+        board_code: String::from(board_code),
+        selectors
+      };
+    }
+
+    /// All the posts of a thread page in page order, the first post is the OP (its number is the
+    /// thread number). Posts without a number are skipped.
+    pub fn scrape_thread(&self, html: &str) -> Result<Vec<PostRaw>, HtmlParserError> {
+      let page = parse_page(html)?;
+      return Result::Ok(self.scrape_posts(&page));
+    }
+
+    /// The threads of a board index page along with the pagination info
+    pub fn scrape_board_page(&self, html: &str) -> Result<BoardPage, HtmlParserError> {
+      let page = parse_page(html)?;
+
+      let threads = page.select(&self.selectors.index_thread)
+        .into_iter()
+        .map(|thread_element| self.scrape_posts(thread_element))
+        .filter(|posts| !posts.is_empty())
+        .collect::<Vec<Vec<PostRaw>>>();
+
+      let mut board_page = BoardPage {
+        threads,
+        current_page: Option::None,
+        total_pages: Option::None,
+        next_page_url: Option::None
+      };
+
+      if let Option::Some(pagination) = page.select_first(&self.selectors.pagination) {
+        self.scrape_pagination(pagination, &mut board_page);
+      }
+
+      return Result::Ok(board_page);
+    }
+
+    fn scrape_posts(&self, element: &Element) -> Vec<PostRaw> {
+      let mut thread_no: Option<u64> = Option::None;
+      let mut posts = Vec::new();
+
+      for post_element in element.select(&self.selectors.post) {
+        let post_no = match self.post_no(post_element) {
+          Option::Some(post_no) => post_no,
+          Option::None => {
+            report_warning(ParserWarning::new(TAG, String::from("post without a number")).with_element(post_element));
+            continue;
+          }
+        };
+
+        let mut file_elements = post_element.select(&self.selectors.file);
+
+        // vichan puts the files of the OP (all of them) before the OP element
+        if thread_no.is_none() && file_elements.is_empty() {
+          file_elements = select_outside(element, &self.selectors.file, &self.selectors.post);
+        }
+
+        let thread_no = *thread_no.get_or_insert(post_no);
+        posts.push(self.scrape_post(post_element, &file_elements, thread_no, post_no));
+      }
+
+      return posts;
+    }
+
+    /// The numbers of the pages are the texts of the pagination links ("[1] [2] [3]"), the link of
+    /// the page after the current one is the next page
+    fn scrape_pagination(&self, pagination: &Element, board_page: &mut BoardPage) {
+      let current_page = pagination.select_first(&self.selectors.pagination_current)
+        .and_then(|current| page_number(current));
+
+      let mut total_pages: Option<u32> = current_page;
+      let mut next_page_url: Option<String> = Option::None;
+
+      for page_link in pagination.select("a[href]") {
+        let page = match page_number(page_link) {
+          Option::Some(page) => page,
+          Option::None => continue
+        };
+
+        total_pages = Option::Some(total_pages.map_or(page, |total_pages| total_pages.max(page)));
+
+        if next_page_url.is_none() && current_page.map(|current_page| current_page + 1) == Option::Some(page) {
+          next_page_url = page_link.attr("href").map(|href| String::from(href));
+        }
+      }
+
+      board_page.current_page = current_page;
+      board_page.total_pages = total_pages;
+      board_page.next_page_url = next_page_url;
+    }
+
+    /// All the threads of a catalog page in page order. Threads without a number are skipped.
+    pub fn scrape_catalog(&self, html: &str) -> Result<Vec<CatalogThreadRaw>, HtmlParserError> {
+      let page = parse_page(html)?;
+      let mut threads = Vec::new();
+
+      for thread_element in page.select(&self.selectors.catalog_thread) {
+        let thread_no = match thread_element.attr(&self.selectors.catalog_thread_no_attr).and_then(|value| trailing_number(value)) {
+          Option::Some(thread_no) => thread_no,
+          Option::None => {
+            report_warning(ParserWarning::new(TAG, String::from("catalog thread without a number")).with_element(thread_element));
+            continue;
+          }
+        };
+
+        threads.push(self.scrape_catalog_thread(thread_element, thread_no));
+      }
+
+      return Result::Ok(threads);
+    }
+
+    fn scrape_catalog_thread(&self, thread_element: &Element, thread_no: u64) -> CatalogThreadRaw {
+      let selectors = &self.selectors;
+
+      let counts_text = select_self_or_first(thread_element, &selectors.catalog_counts)
+        .map(|counts| counts.collect_text())
+        .unwrap_or_default();
+
+      let count = |pattern: &Regex| {
+        return pattern.captures(&counts_text)
+          .and_then(|captures| captures[1].parse::<u32>().ok())
+          .unwrap_or(0);
+      };
+
+      let comment_html = select_self_or_first(thread_element, &selectors.catalog_comment)
+        .map(|comment| inner_html(&without_descendants(comment, &[&selectors.catalog_subject, &selectors.catalog_counts])))
+        .unwrap_or_default();
+
+      return CatalogThreadRaw {
+        thread_no,
+        subject_html: select_self_or_first(thread_element, &selectors.catalog_subject)
+          .map(|subject| inner_html(subject))
+          .filter(|subject_html| !subject_html.is_empty()),
+        comment_html,
+        reply_count: count(&REPLY_COUNT_PATTERN),
+        image_count: count(&IMAGE_COUNT_PATTERN),
+        last_modified: select_self_or_first(thread_element, &selectors.catalog_last_modified)
+          .and_then(|last_modified| last_modified.attr(&selectors.catalog_last_modified_attr))
+          .and_then(|value| parse_timestamp(value))
+      };
+    }
+
+    fn scrape_post(&self, post_element: &Element, file_elements: &Vec<&Element>, thread_no: u64, post_no: u64) -> PostRaw {
+      let selectors = &self.selectors;
+
+      let comment = post_element.select_first(&selectors.comment)
+        .map(|comment| inner_html(comment))
+        .unwrap_or_default();
+
+      let mut post_raw = PostRaw::new(&self.site_name, &self.board_code, thread_no, post_no, 0, &comment);
+
+      post_raw.metadata = PostMetadataRaw {
+        sub: post_element.select_first(&selectors.subject).map(|subject| inner_html(subject)),
+        name_html: post_element.select_first(&selectors.name_block).map(|name_block| name_block.to_html()),
+        id: post_element.select_first(&selectors.poster_id)
+          .and_then(|poster_id| PosterId::of_element(poster_id))
+          .map(|poster_id| poster_id.id),
+        flag_html: post_element.select_first(&selectors.flag).map(|flag| flag.to_html()),
+        time: post_element.select_first(&selectors.time)
+          .and_then(|time| time.attr(&selectors.time_attr))
+          .and_then(|value| parse_timestamp(value)),
+        files: file_elements.iter().filter_map(|file| self.scrape_file(file)).collect(),
+        files_html: Option::Some(file_elements.iter().map(|file| file.to_html()).collect::<String>())
+          .filter(|files_html| !files_html.is_empty()),
+        ..PostMetadataRaw::default()
+      };
+
+      return post_raw;
+    }
+
+    fn post_no(&self, element: &Element) -> Option<u64> {
+      return element.attr(&self.selectors.post_no_attr)
+        .and_then(|value| trailing_number(value));
+    }
+
+    /// None when there is no link to the file (deleted files)
+    fn scrape_file(&self, file: &Element) -> Option<PostFileRaw> {
+      let file_info = file.select_first(&self.selectors.file_info)?;
+      let href = file_info.select_first("a[href]")?.attr("href")?;
+
+      let server_file_name = file_name_of_url(href);
+      let (tim, ext) = split_extension(server_file_name);
+
+      let original_name = file_info.select_first(&self.selectors.file_name)
+        .map(|file_name| match file_name.attr("title") {
+          Option::Some(title) => String::from(html_escape::decode_html_entities(title)),
+          Option::None => file_name.collect_text()
+        })
+        .map(|original_name| String::from(original_name.trim()))
+        .filter(|original_name| !original_name.is_empty())
+        .unwrap_or_else(|| String::from(server_file_name));
+
+      let info_text = file_info.collect_text();
+      let (w, h) = parse_dimensions(&info_text).unwrap_or((0, 0));
+
+      // PostFileRaw fields are html escaped like the json of the sites
+      let mut filename = String::with_capacity(original_name.len());
+      escape_text(split_extension(&original_name).0, &mut filename);
+
+      return Option::Some(PostFileRaw {
+        filename,
+        ext: String::from(ext),
+        tim: String::from(tim),
+        fsize: parse_file_size(&info_text).unwrap_or(0),
+        w,
+        h,
+        md5: file.select_first(&format!("[{}]", MD5_ATTR))
+          .and_then(|element| element.attr(MD5_ATTR))
+          .map(|md5| String::from(md5)),
+        spoiler: file.select_first(&self.selectors.file_spoiler).is_some()
+      });
+    }
+  }
+
+  /// The whole page as the children of a synthetic root element so that it can be selected from
+  pub(crate) fn parse_page(html: &str) -> Result<Element, HtmlParserError> {
+    let nodes = HtmlParser::new()
+      .decode_entities(true)
+      .parse(html)?;
+
+    let mut page = Element::new("#page", false);
+    page.children = nodes;
+
+    return Result::Ok(page);
+  }
+
+  fn page_number(element: &Element) -> Option<u32> {
+    return element.collect_text().trim().parse::<u32>().ok();
+  }
+
+  /// Same as Element::select() but the descendants of the elements matching [excluded] are skipped
+  fn select_outside<'a>(element: &'a Element, selector: &str, excluded: &str) -> Vec<&'a Element> {
+    let mut selected = Vec::new();
+    let mut stack = vec![element.children.iter()];
+
+    while let Option::Some(children) = stack.last_mut() {
+      match children.next() {
+        Option::None => {
+          stack.pop();
+        }
+        Option::Some(Node::Element(child)) => {
+          if child.matches(excluded) {
+            continue;
+          }
+
+          if child.matches(selector) {
+            selected.push(child);
+          } else {
+            stack.push(child.children.iter());
+          }
+        }
+        Option::Some(_) => {}
+      }
+    }
+
+    return selected;
+  }
+
+  fn select_self_or_first<'a>(element: &'a Element, selector: &str) -> Option<&'a Element> {
+    if element.matches(selector) {
+      return Option::Some(element);
+    }
+
+    return element.select_first(selector);
+  }
+
+  /// A copy of the element without the descendants matching any of the [selectors]
+  fn without_descendants(element: &Element, selectors: &[&str]) -> Element {
+    let mut copy = element.clone();
+    let mut stack = vec![&mut copy];
+
+    while let Option::Some(element) = stack.pop() {
+      element.children.retain(|child| match child {
+        Node::Element(child) => !selectors.iter().any(|selector| child.matches(selector)),
+        _ => true
+      });
+
+      for child in element.children.iter_mut() {
+        if let Node::Element(child) = child {
+          stack.push(child);
+        }
+      }
+    }
+
+    return copy;
+  }
+
+  /// Unix timestamp or an ISO 8601 date
+  fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+
+    return match value.parse::<i64>() {
+      Ok(timestamp) => Option::Some(timestamp),
+      Err(_) => parse_iso_8601(value)
+    };
+  }
+
+  /// The nodes are parsed with decoded entities so the html has to be escaped again for PostParser
+  pub(crate) fn inner_html(element: &Element) -> String {
+    return String::from(render_nodes(&element.children).trim());
+  }
+
+  pub(crate) fn trailing_number(value: &str) -> Option<u64> {
+    return TRAILING_DIGITS_PATTERN.captures(value.trim())
+      .and_then(|captures| captures[1].parse::<u64>().ok());
+  }
+
+  /// Unix timestamp (seconds) of an ISO 8601 date, dates without a timezone are treated as UTC
+  pub(crate) fn parse_iso_8601(value: &str) -> Option<i64> {
+    let captures = ISO_8601_PATTERN.captures(value)?;
+    let field = |index: usize| captures.get(index).map_or(0, |value| value.as_str().parse::<i64>().unwrap_or(0));
+
+    let days = days_from_civil(field(1), field(2), field(3));
+    let mut timestamp = days * 86400 + field(4) * 3600 + field(5) * 60 + field(6);
+
+    if let Option::Some(timezone) = captures.get(7).map(|timezone| timezone.as_str()) {
+      if timezone != "Z" {
+        let digits = timezone[1..].replace(':', "");
+        let offset = digits[..2].parse::<i64>().ok()? * 3600 + digits[2..].parse::<i64>().ok()? * 60;
+
+        // 15:26:40+03:00 is 12:26:40 UTC
+        timestamp -= if timezone.starts_with('-') { -offset } else { offset };
+      }
+    }
+
+    return Option::Some(timestamp);
+  }
+
+  // Days since 1970-01-01 of a proleptic gregorian calendar date (Howard Hinnant's algorithm)
+  fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    return era * 146097 + day_of_era - 719468;
+  }
+
+  #[test]
+  fn test_parse_iso_8601() {
+    assert_eq!(Option::Some(1600000000), parse_iso_8601("2020-09-13T12:26:40Z"));
+    assert_eq!(Option::Some(1600000000), parse_iso_8601("2020-09-13T15:26:40+03:00"));
+    assert_eq!(Option::Some(1600000000), parse_iso_8601("2020-09-13T07:56:40-0430"));
+    assert_eq!(Option::Some(1600000000), parse_iso_8601("2020-09-13 12:26:40"));
+    assert_eq!(Option::Some(951782400), parse_iso_8601("2000-02-29T00:00Z"));
+    assert_eq!(Option::None, parse_iso_8601("13.09.2020 12:26"));
+  }
+}
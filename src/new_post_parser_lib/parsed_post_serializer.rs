@@ -0,0 +1,1021 @@
+pub mod parsed_post_serializer {
+  use std::collections::HashSet;
+  use crate::{ParsedPost, QuotePreview, PostDescriptor, ParsedSpannableText, Spannable, SpannableData, PostLink, ChanThemeColorId, MediaLinkKind, MediaLink, SpannableStyleHint, PostRoll, PostPoll, ParsedPostMetadata, PosterInfo, PostFlag, PostFlagKind, PostFile, PostImage, PostImageKind, CapcodeKind, ColoredQuoteRole, PosterId, FilterMatch, FilterAction};
+  use crate::html_parser::html_parser_error::{HtmlParserError, HtmlParserErrorKind};
+  use crate::parsing_error::ParsingError;
+
+  const MAGIC: &[u8; 4] = b"KPPB";
+  // Must be incremented on every format change, from_bytes() rejects other versions so cached posts
+  // of an older version are simply re-parsed
+  pub const PARSED_POST_FORMAT_VERSION: u16 = 18;
+
+  impl ParsedPost {
+    /// Compact binary encoding of the whole parse result (for passing across JNI in one array and
+    /// for the disk cache). Integers are LEB128 varints (i64 are zigzag encoded), strings are
+    /// length-prefixed utf-8, enums are one byte tags.
+    pub fn to_bytes(&self) -> Vec<u8> {
+      let mut writer = ByteWriter { buffer: Vec::with_capacity(self.post_comment_parsed.parsed_text.len() * 2 + 64) };
+
+      writer.buffer.extend_from_slice(MAGIC);
+      writer.buffer.extend_from_slice(&PARSED_POST_FORMAT_VERSION.to_be_bytes());
+
+      write_post_descriptor(&mut writer, &self.post_descriptor);
+      write_parsed_spannable_text(&mut writer, &self.post_comment_parsed);
+      write_metadata(&mut writer, &self.metadata);
+
+      writer.write_len(self.media_links.len());
+      for media_link in &self.media_links {
+        write_media_link_kind(&mut writer, &media_link.kind);
+        writer.write_string(&media_link.id);
+      }
+
+      writer.write_len(self.rolls.len());
+      for post_roll in &self.rolls {
+        write_post_roll(&mut writer, post_roll);
+      }
+
+      writer.write_len(self.polls.len());
+      for post_poll in &self.polls {
+        write_post_poll(&mut writer, post_poll);
+      }
+
+      // Sorted so that the same post is always encoded into the same bytes
+      let mut replies_to = self.replies_to.iter().cloned().collect::<Vec<u64>>();
+      replies_to.sort();
+
+      writer.write_len(replies_to.len());
+      for reply_to in replies_to {
+        writer.write_u64(reply_to);
+      }
+
+      writer.write_len(self.filter_matches.len());
+      for filter_match in &self.filter_matches {
+        writer.write_u64(filter_match.filter_id);
+        write_filter_action(&mut writer, &filter_match.action);
+      }
+
+      return writer.buffer;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<ParsedPost, ParsingError> {
+      let mut reader = ByteReader { bytes, offset: 0 };
+
+      if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(ParsingError::new("Not a serialized ParsedPost"));
+      }
+
+      let version_bytes = reader.read_bytes(2)?;
+      let version = u16::from_be_bytes([version_bytes[0], version_bytes[1]]);
+      if version != PARSED_POST_FORMAT_VERSION {
+        return Err(ParsingError::new(&format!("Unsupported version {} (expected {})", version, PARSED_POST_FORMAT_VERSION)));
+      }
+
+      let post_descriptor = read_post_descriptor(&mut reader)?;
+      let post_comment_parsed = read_parsed_spannable_text(&mut reader)?;
+      let metadata = read_metadata(&mut reader)?;
+
+      let media_links_count = reader.read_len()?;
+      let mut media_links = Vec::with_capacity(media_links_count);
+      for _ in 0..media_links_count {
+        let kind = read_media_link_kind(&mut reader)?;
+        media_links.push(MediaLink { kind, id: reader.read_string()? });
+      }
+
+      let rolls_count = reader.read_len()?;
+      let mut rolls = Vec::with_capacity(rolls_count);
+      for _ in 0..rolls_count {
+        rolls.push(read_post_roll(&mut reader)?);
+      }
+
+      let polls_count = reader.read_len()?;
+      let mut polls = Vec::with_capacity(polls_count);
+      for _ in 0..polls_count {
+        polls.push(read_post_poll(&mut reader)?);
+      }
+
+      let replies_to_count = reader.read_len()?;
+      let mut replies_to = HashSet::with_capacity(replies_to_count);
+      for _ in 0..replies_to_count {
+        replies_to.insert(reader.read_u64()?);
+      }
+
+      let filter_matches_count = reader.read_len()?;
+      let mut filter_matches = Vec::with_capacity(filter_matches_count);
+      for _ in 0..filter_matches_count {
+        let filter_id = reader.read_u64()?;
+        filter_matches.push(FilterMatch { filter_id, action: read_filter_action(&mut reader)? });
+      }
+
+      if reader.offset != bytes.len() {
+        return Err(ParsingError::new(&format!("{} trailing bytes", bytes.len() - reader.offset)));
+      }
+
+      // Same as in ParsedPost::new()
+      let is_comment_abbreviated = post_comment_parsed.is_abbreviated;
+      let is_deleted = post_comment_parsed.is_deleted;
+      let has_ban_message = post_comment_parsed.spannables
+        .iter()
+        .any(|spannable| spannable.spannable_data == SpannableData::BanMessage);
+
+      return Ok(ParsedPost {
+        post_descriptor,
+        post_comment_parsed,
+        metadata,
+        media_links,
+        rolls,
+        polls,
+        replies_to,
+        filter_matches,
+        is_comment_abbreviated,
+        is_deleted,
+        has_ban_message
+      });
+    }
+  }
+
+  struct ByteWriter {
+    buffer: Vec<u8>
+  }
+
+  impl ByteWriter {
+    fn write_u64(&mut self, mut value: u64) {
+      loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+          self.buffer.push(byte);
+          return;
+        }
+
+        self.buffer.push(byte | 0x80);
+      }
+    }
+
+    fn write_i64(&mut self, value: i64) {
+      self.write_u64(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn write_len(&mut self, len: usize) {
+      self.write_u64(len as u64);
+    }
+
+    fn write_u8(&mut self, value: u8) {
+      self.buffer.push(value);
+    }
+
+    fn write_bool(&mut self, value: bool) {
+      self.buffer.push(value as u8);
+    }
+
+    fn write_string(&mut self, value: &str) {
+      self.write_len(value.len());
+      self.buffer.extend_from_slice(value.as_bytes());
+    }
+
+    fn write_option_string(&mut self, value: &Option<String>) {
+      match value {
+        Option::None => self.write_bool(false),
+        Option::Some(value) => {
+          self.write_bool(true);
+          self.write_string(value);
+        }
+      }
+    }
+  }
+
+  struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize
+  }
+
+  impl<'a> ByteReader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], ParsingError> {
+      if self.bytes.len() - self.offset < count {
+        return Err(ParsingError::new(&format!("Unexpected end of data at {} (need {} bytes)", self.offset, count)));
+      }
+
+      let bytes = &self.bytes[self.offset..(self.offset + count)];
+      self.offset += count;
+
+      return Ok(bytes);
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ParsingError> {
+      return Ok(self.read_bytes(1)?[0]);
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ParsingError> {
+      let mut value: u64 = 0;
+      let mut shift = 0;
+
+      loop {
+        let byte = self.read_u8()?;
+        if shift > 63 {
+          return Err(ParsingError::new(&format!("Varint is too long at {}", self.offset)));
+        }
+
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+          return Ok(value);
+        }
+
+        shift += 7;
+      }
+    }
+
+    fn read_i64(&mut self) -> Result<i64, ParsingError> {
+      let value = self.read_u64()?;
+      return Ok(((value >> 1) as i64) ^ -((value & 1) as i64));
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ParsingError> {
+      let value = self.read_u64()?;
+      if value > u32::MAX as u64 {
+        return Err(ParsingError::new(&format!("Value {} does not fit into u32", value)));
+      }
+
+      return Ok(value as u32);
+    }
+
+    fn read_len(&mut self) -> Result<usize, ParsingError> {
+      let len = self.read_u64()? as usize;
+      // Every element takes at least one byte, so a length larger than the rest of the data can only
+      // come from corrupted data (and must not be used for allocating)
+      if len > self.bytes.len() - self.offset {
+        return Err(ParsingError::new(&format!("Bad length {} at {}", len, self.offset)));
+      }
+
+      return Ok(len);
+    }
+
+    fn read_bool(&mut self) -> Result<bool, ParsingError> {
+      return match self.read_u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        value => Err(ParsingError::new(&format!("Bad bool {} at {}", value, self.offset)))
+      };
+    }
+
+    fn read_string(&mut self) -> Result<String, ParsingError> {
+      let len = self.read_len()?;
+      let bytes = self.read_bytes(len)?;
+
+      return match std::str::from_utf8(bytes) {
+        Ok(string) => Ok(String::from(string)),
+        Err(error) => Err(ParsingError::new(&format!("Bad string at {}: {}", self.offset, error)))
+      };
+    }
+
+    fn read_option_string(&mut self) -> Result<Option<String>, ParsingError> {
+      if !self.read_bool()? {
+        return Ok(Option::None);
+      }
+
+      return Ok(Option::Some(self.read_string()?));
+    }
+  }
+
+  fn bad_tag(name: &str, tag: u8, reader: &ByteReader) -> ParsingError {
+    return ParsingError::new(&format!("Bad {} tag {} at {}", name, tag, reader.offset));
+  }
+
+  fn write_post_descriptor(writer: &mut ByteWriter, post_descriptor: &PostDescriptor) {
+    writer.write_string(post_descriptor.site_name());
+    writer.write_string(post_descriptor.board_code());
+    writer.write_u64(post_descriptor.thread_no());
+    writer.write_u64(post_descriptor.post_no());
+    writer.write_u64(post_descriptor.post_sub_no());
+  }
+
+  fn read_post_descriptor(reader: &mut ByteReader) -> Result<PostDescriptor, ParsingError> {
+    let site_name = reader.read_string()?;
+    let board_code = reader.read_string()?;
+    let thread_no = reader.read_u64()?;
+    let post_no = reader.read_u64()?;
+    let post_sub_no = reader.read_u64()?;
+
+    return Ok(PostDescriptor::new(&site_name, &board_code, thread_no, post_no, post_sub_no));
+  }
+
+  fn write_parsed_spannable_text(writer: &mut ByteWriter, parsed_spannable_text: &ParsedSpannableText) {
+    writer.write_string(&parsed_spannable_text.original_text);
+    writer.write_string(&parsed_spannable_text.parsed_text);
+    writer.write_bool(parsed_spannable_text.timed_out);
+    writer.write_bool(parsed_spannable_text.was_truncated);
+    writer.write_bool(parsed_spannable_text.is_abbreviated);
+    writer.write_bool(parsed_spannable_text.is_deleted);
+
+    match &parsed_spannable_text.html_parser_error {
+      Option::None => writer.write_bool(false),
+      Option::Some(html_parser_error) => {
+        writer.write_bool(true);
+        writer.write_u8(match html_parser_error.kind {
+          HtmlParserErrorKind::TagEndNotFound => 0,
+          HtmlParserErrorKind::EmptyTag => 1,
+          HtmlParserErrorKind::TagHasNoName => 2,
+          HtmlParserErrorKind::TooManyNodes => 3,
+          HtmlParserErrorKind::TooDeep => 4,
+          HtmlParserErrorKind::Timeout => 5
+        });
+        writer.write_u64(html_parser_error.position as u64);
+      }
+    }
+
+    writer.write_len(parsed_spannable_text.spannables.len());
+    for spannable in parsed_spannable_text.spannables.iter() {
+      writer.write_u64(spannable.start as u64);
+      writer.write_u64(spannable.len as u64);
+      write_spannable_data(writer, &spannable.spannable_data);
+    }
+  }
+
+  fn read_parsed_spannable_text(reader: &mut ByteReader) -> Result<ParsedSpannableText, ParsingError> {
+    let original_text = reader.read_string()?;
+    let parsed_text = reader.read_string()?;
+    let timed_out = reader.read_bool()?;
+    let was_truncated = reader.read_bool()?;
+    let is_abbreviated = reader.read_bool()?;
+    let is_deleted = reader.read_bool()?;
+
+    let html_parser_error = if reader.read_bool()? {
+      let kind = match reader.read_u8()? {
+        0 => HtmlParserErrorKind::TagEndNotFound,
+        1 => HtmlParserErrorKind::EmptyTag,
+        2 => HtmlParserErrorKind::TagHasNoName,
+        3 => HtmlParserErrorKind::TooManyNodes,
+        4 => HtmlParserErrorKind::TooDeep,
+        5 => HtmlParserErrorKind::Timeout,
+        tag => return Err(bad_tag("HtmlParserErrorKind", tag, reader))
+      };
+
+      Option::Some(HtmlParserError::new(kind, reader.read_u64()? as usize))
+    } else {
+      Option::None
+    };
+
+    let spannables_count = reader.read_len()?;
+    let mut spannables = Vec::with_capacity(spannables_count);
+    for _ in 0..spannables_count {
+      let start = reader.read_u64()? as usize;
+      let len = reader.read_u64()? as usize;
+      let spannable_data = read_spannable_data(reader)?;
+
+      spannables.push(Spannable { start, len, spannable_data });
+    }
+
+    return Ok(ParsedSpannableText {
+      original_text,
+      parsed_text: Box::new(parsed_text),
+      spannables: Box::new(spannables),
+      timed_out,
+      html_parser_error,
+      was_truncated,
+      is_abbreviated,
+      is_deleted,
+      warnings: Vec::new()
+    });
+  }
+
+  fn write_spannable_data(writer: &mut ByteWriter, spannable_data: &SpannableData) {
+    match spannable_data {
+      SpannableData::Link(post_link) => {
+        writer.write_u8(0);
+        write_post_link(writer, post_link);
+      }
+      SpannableData::Spoiler => writer.write_u8(1),
+      SpannableData::GreenText => writer.write_u8(2),
+      SpannableData::BoldText => writer.write_u8(3),
+      SpannableData::Italic => writer.write_u8(4),
+      SpannableData::Underline => writer.write_u8(5),
+      SpannableData::Strikethrough => writer.write_u8(6),
+      SpannableData::Superscript => writer.write_u8(7),
+      SpannableData::Subscript => writer.write_u8(8),
+      SpannableData::ListItem { depth, ordered, index } => {
+        writer.write_u8(9);
+        writer.write_u64(*depth as u64);
+        writer.write_bool(*ordered);
+        writer.write_u64(*index as u64);
+      }
+      SpannableData::SjisArt => writer.write_u8(10),
+      SpannableData::Heading { level } => {
+        writer.write_u8(11);
+        writer.write_u8(*level);
+      }
+      SpannableData::TableCell => writer.write_u8(12),
+      SpannableData::FontSize { size } => {
+        writer.write_u8(13);
+        writer.write_string(size);
+      }
+      SpannableData::FontWeight { weight } => {
+        writer.write_u8(14);
+        writer.write_string(weight);
+      }
+      SpannableData::InlineCode => writer.write_u8(15),
+      SpannableData::CodeBlock => writer.write_u8(16),
+      SpannableData::Monospace => writer.write_u8(17),
+      SpannableData::TextForegroundColorRaw { color_hex } => {
+        writer.write_u8(18);
+        writer.write_string(color_hex);
+      }
+      SpannableData::TextBackgroundColorRaw { color_hex } => {
+        writer.write_u8(19);
+        writer.write_string(color_hex);
+      }
+      SpannableData::TextForegroundColorId { color_id } => {
+        writer.write_u8(20);
+        writer.write_u8(color_id.clone() as u8);
+      }
+      SpannableData::TextBackgroundColorId { color_id } => {
+        writer.write_u8(21);
+        writer.write_u8(color_id.clone() as u8);
+      }
+      SpannableData::ThemeJson { theme_name, is_light_theme } => {
+        writer.write_u8(22);
+        writer.write_string(theme_name);
+        writer.write_bool(*is_light_theme);
+      }
+      SpannableData::MediaLink { kind, id } => {
+        writer.write_u8(23);
+        write_media_link_kind(writer, kind);
+        writer.write_string(id);
+      }
+      SpannableData::StyleHint(style_hint) => {
+        writer.write_u8(24);
+        write_option_color_id(writer, &style_hint.color_role);
+        write_option_color_id(writer, &style_hint.background_role);
+        writer.write_bool(style_hint.is_bold);
+        writer.write_bool(style_hint.is_italic);
+      }
+      SpannableData::Fortune { color } => {
+        writer.write_u8(25);
+        writer.write_string(color);
+      }
+      SpannableData::DiceRoll(post_roll) => {
+        writer.write_u8(26);
+        write_post_roll(writer, post_roll);
+      }
+      SpannableData::Capcode { capcode, kind } => {
+        writer.write_u8(27);
+        writer.write_string(capcode);
+        write_capcode_kind(writer, kind);
+      }
+      SpannableData::SearchHighlight => writer.write_u8(28),
+      SpannableData::FilterHighlight { color } => {
+        writer.write_u8(29);
+        writer.write_string(color);
+      }
+      SpannableData::Poll(post_poll) => {
+        writer.write_u8(30);
+        write_post_poll(writer, post_poll);
+      }
+      SpannableData::TeX { display_mode, source } => {
+        writer.write_u8(31);
+        writer.write_bool(*display_mode);
+        writer.write_option_string(source);
+      }
+      SpannableData::Ruby { reading } => {
+        writer.write_u8(32);
+        writer.write_string(reading);
+      }
+      SpannableData::Unknown { tag } => {
+        writer.write_u8(33);
+        writer.write_string(tag);
+      }
+      SpannableData::BanMessage => writer.write_u8(34),
+      SpannableData::ColoredQuote { role } => {
+        writer.write_u8(35);
+        writer.write_u8(match role {
+          ColoredQuoteRole::Orange => 0,
+          ColoredQuoteRole::Pink => 1,
+          ColoredQuoteRole::Purple => 2,
+          ColoredQuoteRole::Red => 3,
+          ColoredQuoteRole::Blue => 4
+        });
+      }
+      SpannableData::PosterId(poster_id) => {
+        writer.write_u8(36);
+        writer.write_string(&poster_id.id);
+      }
+      SpannableData::Custom { kind, payload } => {
+        writer.write_u8(255);
+        writer.write_u64(*kind as u64);
+        writer.write_len(payload.len());
+        writer.buffer.extend_from_slice(payload);
+      }
+    }
+  }
+
+  fn read_spannable_data(reader: &mut ByteReader) -> Result<SpannableData, ParsingError> {
+    let spannable_data = match reader.read_u8()? {
+      0 => SpannableData::Link(read_post_link(reader)?),
+      1 => SpannableData::Spoiler,
+      2 => SpannableData::GreenText,
+      3 => SpannableData::BoldText,
+      4 => SpannableData::Italic,
+      5 => SpannableData::Underline,
+      6 => SpannableData::Strikethrough,
+      7 => SpannableData::Superscript,
+      8 => SpannableData::Subscript,
+      9 => SpannableData::ListItem {
+        depth: reader.read_u64()? as usize,
+        ordered: reader.read_bool()?,
+        index: reader.read_u64()? as usize
+      },
+      10 => SpannableData::SjisArt,
+      11 => SpannableData::Heading { level: reader.read_u8()? },
+      12 => SpannableData::TableCell,
+      13 => SpannableData::FontSize { size: reader.read_string()? },
+      14 => SpannableData::FontWeight { weight: reader.read_string()? },
+      15 => SpannableData::InlineCode,
+      16 => SpannableData::CodeBlock,
+      17 => SpannableData::Monospace,
+      18 => SpannableData::TextForegroundColorRaw { color_hex: reader.read_string()? },
+      19 => SpannableData::TextBackgroundColorRaw { color_hex: reader.read_string()? },
+      20 => SpannableData::TextForegroundColorId { color_id: read_color_id(reader)? },
+      21 => SpannableData::TextBackgroundColorId { color_id: read_color_id(reader)? },
+      22 => SpannableData::ThemeJson {
+        theme_name: reader.read_string()?,
+        is_light_theme: reader.read_bool()?
+      },
+      23 => SpannableData::MediaLink {
+        kind: read_media_link_kind(reader)?,
+        id: reader.read_string()?
+      },
+      24 => SpannableData::StyleHint(SpannableStyleHint {
+        color_role: read_option_color_id(reader)?,
+        background_role: read_option_color_id(reader)?,
+        is_bold: reader.read_bool()?,
+        is_italic: reader.read_bool()?
+      }),
+      25 => SpannableData::Fortune { color: reader.read_string()? },
+      26 => SpannableData::DiceRoll(read_post_roll(reader)?),
+      27 => SpannableData::Capcode { capcode: reader.read_string()?, kind: read_capcode_kind(reader)? },
+      28 => SpannableData::SearchHighlight,
+      29 => SpannableData::FilterHighlight { color: reader.read_string()? },
+      30 => SpannableData::Poll(read_post_poll(reader)?),
+      31 => SpannableData::TeX { display_mode: reader.read_bool()?, source: reader.read_option_string()? },
+      32 => SpannableData::Ruby { reading: reader.read_string()? },
+      33 => SpannableData::Unknown { tag: reader.read_string()? },
+      34 => SpannableData::BanMessage,
+      35 => {
+        let role = match reader.read_u8()? {
+          0 => ColoredQuoteRole::Orange,
+          1 => ColoredQuoteRole::Pink,
+          2 => ColoredQuoteRole::Purple,
+          3 => ColoredQuoteRole::Red,
+          4 => ColoredQuoteRole::Blue,
+          tag => return Err(bad_tag("ColoredQuoteRole", tag, reader))
+        };
+
+        SpannableData::ColoredQuote { role }
+      }
+      36 => SpannableData::PosterId(PosterId { id: reader.read_string()? }),
+      255 => {
+        let kind = reader.read_u32()?;
+        let payload_len = reader.read_len()?;
+
+        SpannableData::Custom { kind, payload: reader.read_bytes(payload_len)?.to_vec() }
+      }
+      tag => return Err(bad_tag("SpannableData", tag, reader))
+    };
+
+    return Ok(spannable_data);
+  }
+
+  fn write_post_link(writer: &mut ByteWriter, post_link: &PostLink) {
+    match post_link {
+      PostLink::Quote { post_no, is_you, is_op, preview } => {
+        writer.write_u8(0);
+        writer.write_u64(*post_no);
+        writer.write_bool(*is_you);
+        writer.write_bool(*is_op);
+
+        match preview {
+          Option::None => writer.write_bool(false),
+          Option::Some(preview) => {
+            writer.write_bool(true);
+            writer.write_string(&preview.poster_name);
+            writer.write_string(&preview.comment_preview);
+          }
+        }
+      }
+      PostLink::Dead { post_no } => {
+        writer.write_u8(1);
+        writer.write_u64(*post_no);
+      }
+      PostLink::UrlLink { link } => {
+        writer.write_u8(2);
+        writer.write_string(link);
+      }
+      PostLink::BoardLink { board_code } => {
+        writer.write_u8(3);
+        writer.write_string(board_code);
+      }
+      PostLink::SearchLink { board_code, search_query } => {
+        writer.write_u8(4);
+        writer.write_string(board_code);
+        writer.write_string(search_query);
+      }
+      PostLink::ThreadLink { board_code, thread_no, post_no } => {
+        writer.write_u8(5);
+        writer.write_string(board_code);
+        writer.write_u64(*thread_no);
+        writer.write_u64(*post_no);
+      }
+      PostLink::DeadCrossBoard { board_code, post_no } => {
+        writer.write_u8(6);
+        writer.write_string(board_code);
+        writer.write_u64(*post_no);
+      }
+      PostLink::Email { email } => {
+        writer.write_u8(7);
+        writer.write_string(email);
+      }
+    }
+  }
+
+  fn read_post_link(reader: &mut ByteReader) -> Result<PostLink, ParsingError> {
+    let post_link = match reader.read_u8()? {
+      0 => PostLink::Quote {
+        post_no: reader.read_u64()?,
+        is_you: reader.read_bool()?,
+        is_op: reader.read_bool()?,
+        preview: if reader.read_bool()? {
+          Option::Some(QuotePreview { poster_name: reader.read_string()?, comment_preview: reader.read_string()? })
+        } else {
+          Option::None
+        }
+      },
+      1 => PostLink::Dead { post_no: reader.read_u64()? },
+      2 => PostLink::UrlLink { link: reader.read_string()? },
+      3 => PostLink::BoardLink { board_code: reader.read_string()? },
+      4 => PostLink::SearchLink {
+        board_code: reader.read_string()?,
+        search_query: reader.read_string()?
+      },
+      5 => PostLink::ThreadLink {
+        board_code: reader.read_string()?,
+        thread_no: reader.read_u64()?,
+        post_no: reader.read_u64()?
+      },
+      6 => PostLink::DeadCrossBoard {
+        board_code: reader.read_string()?,
+        post_no: reader.read_u64()?
+      },
+      7 => PostLink::Email { email: reader.read_string()? },
+      tag => return Err(bad_tag("PostLink", tag, reader))
+    };
+
+    return Ok(post_link);
+  }
+
+  fn read_color_id(reader: &mut ByteReader) -> Result<ChanThemeColorId, ParsingError> {
+    let color_id = match reader.read_u8()? {
+      0 => ChanThemeColorId::PostSubjectColor,
+      1 => ChanThemeColorId::PostNameColor,
+      2 => ChanThemeColorId::AccentColor,
+      3 => ChanThemeColorId::PostInlineQuoteColor,
+      4 => ChanThemeColorId::PostQuoteColor,
+      5 => ChanThemeColorId::BackColorSecondary,
+      6 => ChanThemeColorId::PostLinkColor,
+      7 => ChanThemeColorId::TextColorPrimary,
+      tag => return Err(bad_tag("ChanThemeColorId", tag, reader))
+    };
+
+    return Ok(color_id);
+  }
+
+  fn write_option_color_id(writer: &mut ByteWriter, color_id: &Option<ChanThemeColorId>) {
+    match color_id {
+      Option::None => writer.write_bool(false),
+      Option::Some(color_id) => {
+        writer.write_bool(true);
+        writer.write_u8(color_id.clone() as u8);
+      }
+    }
+  }
+
+  fn read_option_color_id(reader: &mut ByteReader) -> Result<Option<ChanThemeColorId>, ParsingError> {
+    if !reader.read_bool()? {
+      return Ok(Option::None);
+    }
+
+    return Ok(Option::Some(read_color_id(reader)?));
+  }
+
+  fn write_media_link_kind(writer: &mut ByteWriter, kind: &MediaLinkKind) {
+    writer.write_u8(match kind {
+      MediaLinkKind::YouTube => 0,
+      MediaLinkKind::Streamable => 1,
+      MediaLinkKind::Vimeo => 2,
+      MediaLinkKind::SoundCloud => 3
+    });
+  }
+
+  fn read_media_link_kind(reader: &mut ByteReader) -> Result<MediaLinkKind, ParsingError> {
+    let kind = match reader.read_u8()? {
+      0 => MediaLinkKind::YouTube,
+      1 => MediaLinkKind::Streamable,
+      2 => MediaLinkKind::Vimeo,
+      3 => MediaLinkKind::SoundCloud,
+      tag => return Err(bad_tag("MediaLinkKind", tag, reader))
+    };
+
+    return Ok(kind);
+  }
+
+  fn write_flag_kind(writer: &mut ByteWriter, kind: &PostFlagKind) {
+    writer.write_u8(match kind {
+      PostFlagKind::Country => 0,
+      PostFlagKind::Board => 1,
+      PostFlagKind::Troll => 2
+    });
+  }
+
+  fn read_flag_kind(reader: &mut ByteReader) -> Result<PostFlagKind, ParsingError> {
+    let kind = match reader.read_u8()? {
+      0 => PostFlagKind::Country,
+      1 => PostFlagKind::Board,
+      2 => PostFlagKind::Troll,
+      tag => return Err(bad_tag("PostFlagKind", tag, reader))
+    };
+
+    return Ok(kind);
+  }
+
+  fn write_image_kind(writer: &mut ByteWriter, kind: &PostImageKind) {
+    writer.write_u8(match kind {
+      PostImageKind::Image => 0,
+      PostImageKind::Video => 1,
+      PostImageKind::Audio => 2,
+      PostImageKind::Other => 3
+    });
+  }
+
+  fn read_image_kind(reader: &mut ByteReader) -> Result<PostImageKind, ParsingError> {
+    let kind = match reader.read_u8()? {
+      0 => PostImageKind::Image,
+      1 => PostImageKind::Video,
+      2 => PostImageKind::Audio,
+      3 => PostImageKind::Other,
+      tag => return Err(bad_tag("PostImageKind", tag, reader))
+    };
+
+    return Ok(kind);
+  }
+
+  fn write_capcode_kind(writer: &mut ByteWriter, kind: &CapcodeKind) {
+    writer.write_u8(match kind {
+      CapcodeKind::Admin => 0,
+      CapcodeKind::Mod => 1,
+      CapcodeKind::Developer => 2,
+      CapcodeKind::Founder => 3,
+      CapcodeKind::Other => 4
+    });
+  }
+
+  fn read_capcode_kind(reader: &mut ByteReader) -> Result<CapcodeKind, ParsingError> {
+    let kind = match reader.read_u8()? {
+      0 => CapcodeKind::Admin,
+      1 => CapcodeKind::Mod,
+      2 => CapcodeKind::Developer,
+      3 => CapcodeKind::Founder,
+      4 => CapcodeKind::Other,
+      tag => return Err(bad_tag("CapcodeKind", tag, reader))
+    };
+
+    return Ok(kind);
+  }
+
+  fn write_filter_action(writer: &mut ByteWriter, action: &FilterAction) {
+    writer.write_u8(match action {
+      FilterAction::Hide => 0,
+      FilterAction::Remove => 1,
+      FilterAction::Highlight => 2
+    });
+  }
+
+  fn read_filter_action(reader: &mut ByteReader) -> Result<FilterAction, ParsingError> {
+    let action = match reader.read_u8()? {
+      0 => FilterAction::Hide,
+      1 => FilterAction::Remove,
+      2 => FilterAction::Highlight,
+      tag => return Err(bad_tag("FilterAction", tag, reader))
+    };
+
+    return Ok(action);
+  }
+
+  fn write_post_roll(writer: &mut ByteWriter, post_roll: &PostRoll) {
+    writer.write_u64(post_roll.dice as u64);
+
+    match post_roll.sides {
+      Option::None => writer.write_bool(false),
+      Option::Some(sides) => {
+        writer.write_bool(true);
+        writer.write_u64(sides as u64);
+      }
+    }
+
+    writer.write_len(post_roll.results.len());
+    for result in &post_roll.results {
+      writer.write_u64(*result as u64);
+    }
+
+    writer.write_i64(post_roll.total);
+  }
+
+  fn read_post_roll(reader: &mut ByteReader) -> Result<PostRoll, ParsingError> {
+    let dice = reader.read_u32()?;
+
+    let sides = if reader.read_bool()? {
+      Option::Some(reader.read_u32()?)
+    } else {
+      Option::None
+    };
+
+    let results_count = reader.read_len()?;
+    let mut results = Vec::with_capacity(results_count);
+    for _ in 0..results_count {
+      results.push(reader.read_u32()?);
+    }
+
+    let total = reader.read_i64()?;
+
+    return Ok(PostRoll { dice, sides, results, total });
+  }
+
+  fn write_post_poll(writer: &mut ByteWriter, post_poll: &PostPoll) {
+    writer.write_string(&post_poll.question);
+
+    writer.write_len(post_poll.options.len());
+    for (option, votes) in &post_poll.options {
+      writer.write_string(option);
+      writer.write_u64(*votes);
+    }
+  }
+
+  fn read_post_poll(reader: &mut ByteReader) -> Result<PostPoll, ParsingError> {
+    let question = reader.read_string()?;
+
+    let options_count = reader.read_len()?;
+    let mut options = Vec::with_capacity(options_count);
+    for _ in 0..options_count {
+      let option = reader.read_string()?;
+      options.push((option, reader.read_u64()?));
+    }
+
+    return Ok(PostPoll { question, options });
+  }
+
+  fn write_metadata(writer: &mut ByteWriter, metadata: &ParsedPostMetadata) {
+    match &metadata.subject_parsed {
+      Option::None => writer.write_bool(false),
+      Option::Some(subject_parsed) => {
+        writer.write_bool(true);
+        write_parsed_spannable_text(writer, subject_parsed);
+      }
+    }
+
+    writer.write_option_string(&metadata.poster_info.name);
+    writer.write_option_string(&metadata.poster_info.tripcode);
+    writer.write_option_string(&metadata.poster_info.secure_tripcode);
+    writer.write_option_string(&metadata.poster_info.capcode);
+    write_parsed_spannable_text(writer, &metadata.poster_info_parsed);
+    writer.write_option_string(&metadata.poster_id.as_ref().map(|poster_id| poster_id.id.clone()));
+
+    match &metadata.flag {
+      Option::None => writer.write_bool(false),
+      Option::Some(flag) => {
+        writer.write_bool(true);
+        writer.write_string(&flag.code);
+        writer.write_option_string(&flag.name);
+        write_flag_kind(writer, &flag.kind);
+      }
+    }
+
+    match metadata.timestamp {
+      Option::None => writer.write_bool(false),
+      Option::Some(timestamp) => {
+        writer.write_bool(true);
+        writer.write_i64(timestamp);
+      }
+    }
+
+    writer.write_len(metadata.files.len());
+    for file in &metadata.files {
+      writer.write_string(&file.original_name);
+      writer.write_string(&file.server_name);
+      writer.write_string(&file.extension);
+      writer.write_u64(file.size);
+      writer.write_u64(file.width as u64);
+      writer.write_u64(file.height as u64);
+      writer.write_option_string(&file.md5);
+      writer.write_bool(file.is_spoiler);
+    }
+
+    writer.write_len(metadata.images.len());
+    for post_image in &metadata.images {
+      writer.write_string(&post_image.original_name);
+      writer.write_string(&post_image.server_name);
+      writer.write_string(&post_image.extension);
+      writer.write_u64(post_image.size);
+      writer.write_u64(post_image.width as u64);
+      writer.write_u64(post_image.height as u64);
+      writer.write_option_string(&post_image.full_url);
+      writer.write_option_string(&post_image.thumbnail_url);
+      writer.write_bool(post_image.is_spoiler);
+      write_image_kind(writer, &post_image.kind);
+
+      match post_image.duration {
+        Option::None => writer.write_bool(false),
+        Option::Some(duration) => {
+          writer.write_bool(true);
+          writer.write_u64(duration as u64);
+        }
+      }
+
+      writer.write_bool(post_image.is_nsfw);
+    }
+  }
+
+  fn read_metadata(reader: &mut ByteReader) -> Result<ParsedPostMetadata, ParsingError> {
+    let subject_parsed = if reader.read_bool()? {
+      Option::Some(read_parsed_spannable_text(reader)?)
+    } else {
+      Option::None
+    };
+
+    let poster_info = PosterInfo {
+      name: reader.read_option_string()?,
+      tripcode: reader.read_option_string()?,
+      secure_tripcode: reader.read_option_string()?,
+      capcode: reader.read_option_string()?
+    };
+
+    let poster_info_parsed = read_parsed_spannable_text(reader)?;
+    let poster_id = reader.read_option_string()?.map(|id| PosterId { id });
+
+    let flag = if reader.read_bool()? {
+      Option::Some(PostFlag {
+        code: reader.read_string()?,
+        name: reader.read_option_string()?,
+        kind: read_flag_kind(reader)?
+      })
+    } else {
+      Option::None
+    };
+
+    let timestamp = if reader.read_bool()? {
+      Option::Some(reader.read_i64()?)
+    } else {
+      Option::None
+    };
+
+    let files_count = reader.read_len()?;
+    let mut files = Vec::with_capacity(files_count);
+    for _ in 0..files_count {
+      files.push(PostFile {
+        original_name: reader.read_string()?,
+        server_name: reader.read_string()?,
+        extension: reader.read_string()?,
+        size: reader.read_u64()?,
+        width: reader.read_u32()?,
+        height: reader.read_u32()?,
+        md5: reader.read_option_string()?,
+        is_spoiler: reader.read_bool()?
+      });
+    }
+
+    let images_count = reader.read_len()?;
+    let mut images = Vec::with_capacity(images_count);
+    for _ in 0..images_count {
+      images.push(PostImage {
+        original_name: reader.read_string()?,
+        server_name: reader.read_string()?,
+        extension: reader.read_string()?,
+        size: reader.read_u64()?,
+        width: reader.read_u32()?,
+        height: reader.read_u32()?,
+        full_url: reader.read_option_string()?,
+        thumbnail_url: reader.read_option_string()?,
+        is_spoiler: reader.read_bool()?,
+        kind: read_image_kind(reader)?,
+        duration: if reader.read_bool()? { Option::Some(reader.read_u32()?) } else { Option::None },
+        is_nsfw: reader.read_bool()?
+      });
+    }
+
+    return Ok(ParsedPostMetadata {
+      subject_parsed,
+      poster_info,
+      poster_info_parsed,
+      poster_id,
+      flag,
+      timestamp,
+      files,
+      images
+    });
+  }
+}
@@ -0,0 +1,68 @@
+use crate::{ParsedSpannableTextBuilder, ParsedSpannableText, Spannable, SpannableData, SpanOffsetMode};
+use crate::util::spannable_normalizer::normalize_spannables;
+use crate::util::span_offset_converter::convert_span_offsets;
+
+impl ParsedSpannableText {
+  pub fn builder() -> ParsedSpannableTextBuilder {
+    return ParsedSpannableTextBuilder::new();
+  }
+}
+
+impl ParsedSpannableTextBuilder {
+  pub fn new() -> ParsedSpannableTextBuilder {
+    return ParsedSpannableTextBuilder {
+      text: String::new(),
+      text_length: 0,
+      spannables: Vec::new(),
+      span_offset_mode: SpanOffsetMode::UnicodeScalars
+    };
+  }
+
+  /// The units the spannables of the built text are measured in (should be the same as
+  /// PostParserContext::span_offset_mode() of the parsed posts)
+  pub fn with_span_offset_mode(mut self, span_offset_mode: SpanOffsetMode) -> ParsedSpannableTextBuilder {
+    self.span_offset_mode = span_offset_mode;
+    return self;
+  }
+
+  pub fn append(mut self, text: &str) -> ParsedSpannableTextBuilder {
+    self.text.push_str(text);
+    self.text_length += text.chars().count();
+    return self;
+  }
+
+  /// Appends the text and a spannable covering it
+  pub fn append_span(mut self, text: &str, spannable_data: SpannableData) -> ParsedSpannableTextBuilder {
+    let start = self.text_length;
+    self = self.append(text);
+
+    self.spannables.push(Spannable { start, len: self.text_length - start, spannable_data });
+    return self;
+  }
+
+  /// Adds a spannable over the text appended so far (start and len are in chars). Use it for spans
+  /// covering several appended parts, spans nested into other spans can just be appended.
+  /// Spans that go past the end of the text are clamped when the text is built.
+  pub fn add_span(mut self, start: usize, len: usize, spannable_data: SpannableData) -> ParsedSpannableTextBuilder {
+    self.spannables.push(Spannable { start, len, spannable_data });
+    return self;
+  }
+
+  /// Length of the text appended so far in chars
+  pub fn text_length(&self) -> usize {
+    return self.text_length;
+  }
+
+  pub fn build(self) -> ParsedSpannableText {
+    let mut spannables = self.spannables;
+
+    // Same as for the parsed comments: empty spans are dropped, the rest are clamped to the text,
+    // sorted and split so that they never cross each other. Nothing is merged, the caller asked for
+    // these exact spans.
+    normalize_spannables(self.text_length, &mut spannables, false);
+    convert_span_offsets(&self.text, &mut spannables, &self.span_offset_mode);
+
+    let original_text = self.text.clone();
+    return ParsedSpannableText::new(&original_text, Box::new(self.text), Box::new(spannables));
+  }
+}
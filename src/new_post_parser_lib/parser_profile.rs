@@ -0,0 +1,238 @@
+use crate::{ParserProfile, ParsingRules, SiteDescriptor, STagMeaning, SpannableData, CommentFormat, LineBreakMode, WhitespacePolicy, ColoredQuoteRole};
+use crate::rules::simple_span::SimpleSpanHandler;
+use crate::rules::backlink::BacklinkHandler;
+use std::rc::Rc;
+
+impl ParserProfile {
+
+  /// 4chan (the default one)
+  pub fn four_chan() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_s_tag_meaning(STagMeaning::Spoiler);
+
+    return ParserProfile {
+      site_name: String::from("4chan"),
+      parsing_rules,
+      decode_entities: true,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::Html,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  /// 2ch.hk
+  pub fn dvach() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .set_s_tag_meaning(STagMeaning::Strikethrough)
+      .add_tag_class_handler("span", "spoiler", Rc::new(SimpleSpanHandler::new(SpannableData::Spoiler)))
+      .add_tag_class_handler("span", "unkfunc", Rc::new(SimpleSpanHandler::new(SpannableData::GreenText)))
+      .add_tag_class_handler("span", "s", Rc::new(SimpleSpanHandler::new(SpannableData::Strikethrough)))
+      .add_tag_class_handler("span", "u", Rc::new(SimpleSpanHandler::new(SpannableData::Underline)));
+
+    return ParserProfile {
+      site_name: String::from("2ch.hk"),
+      parsing_rules,
+      decode_entities: true,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::Html,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  /// vichan and its forks (8kun, lainchan etc)
+  pub fn vichan() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .set_s_tag_meaning(STagMeaning::Strikethrough)
+      .add_tag_class_handler("span", "spoiler", Rc::new(SimpleSpanHandler::new(SpannableData::Spoiler)))
+      // ==text== (red bold text)
+      .add_tag_class_handler("span", "heading", Rc::new(SimpleSpanHandler::new(SpannableData::BoldText)))
+      .add_tag_class_handler("span", "heading", Rc::new(SimpleSpanHandler::new(heading_color())))
+      // (((text)))
+      .add_tag_class_handler("span", "detected", Rc::new(SimpleSpanHandler::new(SpannableData::BoldText)));
+
+    return ParserProfile {
+      site_name: String::from("vichan"),
+      parsing_rules,
+      decode_entities: true,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::Html,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      // <text
+      colored_quote_classes: vec![
+        (String::from("orangeQuote"), ColoredQuoteRole::Orange),
+        (String::from("rquote"), ColoredQuoteRole::Pink)
+      ]
+    };
+  }
+
+  pub fn lainchan() -> ParserProfile {
+    let mut parser_profile = ParserProfile::vichan();
+    parser_profile.site_name = String::from("lainchan");
+
+    return parser_profile;
+  }
+
+  /// wakaba/kusaba based sites
+  pub fn wakaba() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .set_s_tag_meaning(STagMeaning::Strikethrough)
+      .add_tag_class_handler("span", "spoiler", Rc::new(SimpleSpanHandler::new(SpannableData::Spoiler)))
+      .add_tag_class_handler("span", "unkfunc", Rc::new(SimpleSpanHandler::new(SpannableData::GreenText)));
+
+    return ParserProfile {
+      site_name: String::from("wakaba"),
+      parsing_rules,
+      decode_entities: true,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::Html,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  /// FoolFuuka based archives. Greentext is <span class="greentext">, quotes are
+  /// <a class="backlink" data-board="g" data-post="123"> that point to the archive itself.
+  pub fn foolfuuka() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .set_s_tag_meaning(STagMeaning::Strikethrough)
+      .add_tag_class_handler("span", "greentext", Rc::new(SimpleSpanHandler::new(SpannableData::GreenText)))
+      .add_tag_class_handler("span", "spoiler", Rc::new(SimpleSpanHandler::new(SpannableData::Spoiler)))
+      .add_tag_class_handler("a", "backlink", Rc::new(BacklinkHandler::new()));
+
+    return ParserProfile {
+      site_name: String::from("foolfuuka"),
+      parsing_rules,
+      decode_entities: true,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::Html,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  pub fn desuarchive() -> ParserProfile {
+    let mut parser_profile = ParserProfile::foolfuuka();
+    parser_profile.site_name = String::from("desuarchive");
+
+    return parser_profile;
+  }
+
+  pub fn four_plebs() -> ParserProfile {
+    let mut parser_profile = ParserProfile::foolfuuka();
+    parser_profile.site_name = String::from("4plebs");
+
+    return parser_profile;
+  }
+
+  /// warosu runs Fuuka which uses the same markup for greentext and quotes
+  pub fn warosu() -> ParserProfile {
+    let mut parser_profile = ParserProfile::foolfuuka();
+    parser_profile.site_name = String::from("warosu");
+
+    return parser_profile;
+  }
+
+  /// Textboards and older engines that deliver comments as BBCode ([b], [spoiler], [code] etc).
+  /// BbCodeParser converts [spoiler] into <s>.
+  pub fn bbcode() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_s_tag_meaning(STagMeaning::Spoiler);
+
+    return ParserProfile {
+      site_name: String::from("bbcode"),
+      parsing_rules,
+      decode_entities: false,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::BbCode,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  /// Sites and archives that return markdown (**bold**, `code`, > quotes etc)
+  pub fn markdown() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_s_tag_meaning(STagMeaning::Strikethrough);
+
+    return ParserProfile {
+      site_name: String::from("markdown"),
+      parsing_rules,
+      decode_entities: false,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::Markdown,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  /// Archive apis that only return the unformatted comment (e.g. the plain "com" field of warosu)
+  pub fn plain_text() -> ParserProfile {
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_s_tag_meaning(STagMeaning::Strikethrough);
+
+    return ParserProfile {
+      site_name: String::from("plain_text"),
+      parsing_rules,
+      decode_entities: false,
+      replacement_rules: vec![],
+      comment_format: CommentFormat::PlainText,
+      line_break_mode: LineBreakMode::default(),
+      whitespace_policy: WhitespacePolicy::default(),
+      colored_quote_classes: vec![]
+    };
+  }
+
+  /// Returns None for unsupported sites
+  pub fn for_site_name(site_name: &str) -> Option<ParserProfile> {
+    return match site_name.to_lowercase().as_str() {
+      "4chan" | "4channel" => Option::Some(ParserProfile::four_chan()),
+      "2ch.hk" | "dvach" => Option::Some(ParserProfile::dvach()),
+      "lainchan" => Option::Some(ParserProfile::lainchan()),
+      "vichan" | "8kun" => Option::Some(ParserProfile::vichan()),
+      "wakaba" => Option::Some(ParserProfile::wakaba()),
+      "desuarchive" => Option::Some(ParserProfile::desuarchive()),
+      "4plebs" => Option::Some(ParserProfile::four_plebs()),
+      "warosu" => Option::Some(ParserProfile::warosu()),
+      "foolfuuka" => Option::Some(ParserProfile::foolfuuka()),
+      _ => Option::None
+    };
+  }
+
+  pub fn for_site(site_descriptor: &SiteDescriptor) -> Option<ParserProfile> {
+    return ParserProfile::for_site_name(&site_descriptor.site_name);
+  }
+
+}
+
+fn heading_color() -> SpannableData {
+  return SpannableData::TextForegroundColorRaw { color_hex: String::from("#AF0A0F") };
+}
+
+impl Default for LineBreakMode {
+  fn default() -> Self {
+    return LineBreakMode {
+      text_newlines: true,
+      block_elements: false,
+      max_blank_lines: Option::None
+    };
+  }
+}
+
+impl Default for ParserProfile {
+  fn default() -> Self {
+    return ParserProfile::four_chan();
+  }
+}
@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::fmt;
+use crate::{ParserWarning, Element, TextParts};
+
+thread_local! {
+  // One list per collect_warnings() call that is currently running on this thread (metadata html
+  // may be parsed while a comment is being parsed), the innermost one gets the warnings
+  static COLLECTED_WARNINGS: RefCell<Vec<Vec<ParserWarning>>> = RefCell::new(Vec::new());
+}
+
+impl ParserWarning {
+  pub fn new(source: &str, message: String) -> ParserWarning {
+    return ParserWarning {
+      source: String::from(source),
+      message,
+      html: Option::None,
+      position: Option::None
+    };
+  }
+
+  /// The html of the element the warning is about
+  pub fn with_element(mut self, element: &Element) -> ParserWarning {
+    self.html = Option::Some(element.to_html());
+    return self;
+  }
+
+  pub fn with_position(mut self, position: usize) -> ParserWarning {
+    self.position = Option::Some(position);
+    return self;
+  }
+
+  /// Position is where the text that was produced so far ends
+  pub fn with_position_after(self, out_text_parts: &TextParts) -> ParserWarning {
+    let position = out_text_parts.chars_count();
+    return self.with_position(position);
+  }
+}
+
+impl fmt::Display for ParserWarning {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}: {}", self.source, self.message)?;
+
+    if let Option::Some(position) = self.position {
+      write!(f, " (position: {})", position)?;
+    }
+
+    if let Option::Some(html) = &self.html {
+      write!(f, " html={}", html)?;
+    }
+
+    return Ok(());
+  }
+}
+
+/// Runs [block] and returns the warnings that were reported on this thread while it was running
+pub(crate) fn collect_warnings<T>(block: impl FnOnce() -> T) -> (T, Vec<ParserWarning>) {
+  COLLECTED_WARNINGS.with(|collected_warnings| collected_warnings.borrow_mut().push(Vec::new()));
+  let result = block();
+  let warnings = COLLECTED_WARNINGS.with(|collected_warnings| collected_warnings.borrow_mut().pop().unwrap_or_default());
+
+  return (result, warnings);
+}
+
+/// Unexpected markup, bad data etc. The warning is added to the result of the parse that is
+/// currently running (ParsedSpannableText::warnings) and with the "log" feature also logged.
+/// Warnings reported outside of a parse are only logged.
+pub(crate) fn report_warning(warning: ParserWarning) {
+  #[cfg(feature = "log")]
+  log::warn!("{}", warning);
+
+  COLLECTED_WARNINGS.with(|collected_warnings| {
+    if let Option::Some(warnings) = collected_warnings.borrow_mut().last_mut() {
+      warnings.push(warning);
+    }
+  });
+}
+
+#[test]
+fn test_collect_warnings() {
+  report_warning(ParserWarning::new("Test", String::from("dropped")));
+
+  let (result, warnings) = collect_warnings(|| {
+    report_warning(ParserWarning::new("Test", String::from("outer")).with_position(1));
+
+    let (_, inner_warnings) = collect_warnings(|| report_warning(ParserWarning::new("Test", String::from("inner"))));
+    assert_eq!(vec![ParserWarning::new("Test", String::from("inner"))], inner_warnings);
+
+    return 42;
+  });
+
+  assert_eq!(42, result);
+  assert_eq!(vec![ParserWarning::new("Test", String::from("outer")).with_position(1)], warnings);
+  assert_eq!("Test: outer (position: 1)", warnings[0].to_string());
+}
@@ -0,0 +1,368 @@
+pub mod post_metadata_parser {
+  use crate::{PostParser, PostRaw, ParsedPostMetadata, PostFlag, PostFlagKind, PostFile, PostFileRaw, PostImage, PostMetadataRaw, PosterInfo, HtmlParser, Element, ParsedSpannableText, Spannable, SpannableData, FilterScope, ParserWarning, CapcodeKind, PosterId};
+  use crate::html_parser::visitor::{NodeVisitor, VisitAction, walk};
+  use crate::parser_warnings::report_warning;
+  use crate::util::post_image_extractor::extract_post_images;
+  use crate::util::poster_id::POSTER_ID_CLASSES;
+
+  const TAG: &str = "PostMetadataParser";
+  const CAPCODE_PREFIX: &str = "## ";
+  const COUNTRY_FLAG_CLASS_PREFIX: &str = "flag-";
+  const BOARD_FLAG_CLASS_PREFIX: &str = "bfl-";
+  const TROLL_FLAG_PATH: &str = "/troll/";
+
+  impl PostParser<'_> {
+    /// Everything besides the comment: the subject is parsed like the comment (so it gets
+    /// spannables too), the rest is decoded and converted into client friendly structures
+    pub fn parse_metadata(&self, post_raw: &PostRaw) -> ParsedPostMetadata {
+      let metadata = &post_raw.metadata;
+
+      let subject_parsed = non_empty(&metadata.sub)
+        .map(|subject| self.parse_html_text_in_scope(post_raw, subject, Option::Some(FilterScope::Subject)));
+
+      let (poster_info, name_html_poster_id) = match non_empty(&metadata.name_html) {
+        Some(name_html) => parse_name_html(name_html),
+        None => (poster_info_from_fields(metadata), Option::None)
+      };
+
+      let poster_id = non_empty(&metadata.id)
+        .map(|id| PosterId::new(id))
+        .or(name_html_poster_id);
+
+      let images = non_empty(&metadata.files_html)
+        .map(|files_html| extract_post_images(files_html))
+        .unwrap_or_default();
+
+      let files = if metadata.files.is_empty() {
+        images.iter().map(|post_image| file_of_image(post_image)).collect()
+      } else {
+        metadata.files.iter().map(|file_raw| parse_file(file_raw)).collect()
+      };
+
+      return ParsedPostMetadata {
+        subject_parsed,
+        poster_info_parsed: poster_info_to_parsed_text(&poster_info),
+        poster_info,
+        poster_id,
+        flag: parse_flag(metadata),
+        timestamp: metadata.time,
+        files,
+        images
+      };
+    }
+  }
+
+  fn poster_info_from_fields(metadata: &PostMetadataRaw) -> PosterInfo {
+    let mut poster_info = PosterInfo::default();
+    poster_info.name = non_empty(&metadata.name).map(|name| decode_text(name));
+    poster_info.capcode = non_empty(&metadata.capcode).map(|capcode| capcode_display_name(capcode));
+
+    if let Option::Some(trip) = non_empty(&metadata.trip) {
+      set_tripcode(&mut poster_info, decode_text(trip));
+    }
+
+    return poster_info;
+  }
+
+  /// Name block html of 4chan/vichan/dvach style sites:
+  /// <span class="name">Anonymous</span> <span class="postertrip">!!xyz</span>
+  /// <strong class="capcode">## Mod</strong>
+  pub fn parse_poster_info_html(name_html: &str) -> PosterInfo {
+    return parse_name_html(name_html).0;
+  }
+
+  /// parse_poster_info_html() plus the <span class="posteruid id_XYZ">(ID: XYZ)</span> of the name
+  /// block
+  fn parse_name_html(name_html: &str) -> (PosterInfo, Option<PosterId>) {
+    let html_parser = HtmlParser::new()
+      .decode_entities(true);
+
+    let nodes = match html_parser.parse(name_html) {
+      Ok(nodes) => nodes,
+      Err(error) => {
+        report_warning(ParserWarning::new(TAG, format!("Failed to parse name html ({}), error={}", name_html, error)));
+        return (PosterInfo::default(), Option::None);
+      }
+    };
+
+    let mut poster_info_visitor = PosterInfoVisitor {
+      poster_info: PosterInfo::default(),
+      poster_id: Option::None,
+      current_field: Option::None,
+      current_text: String::new()
+    };
+
+    walk(&nodes, &mut poster_info_visitor);
+    return (poster_info_visitor.poster_info, poster_info_visitor.poster_id);
+  }
+
+  #[derive(Clone, Copy, PartialEq)]
+  enum PosterInfoField {
+    Name,
+    Tripcode,
+    Capcode
+  }
+
+  struct PosterInfoVisitor {
+    poster_info: PosterInfo,
+    poster_id: Option<PosterId>,
+    current_field: Option<PosterInfoField>,
+    current_text: String
+  }
+
+  impl PosterInfoVisitor {
+    fn field_of(element: &Element) -> Option<PosterInfoField> {
+      if element.has_any_class(&["name", "postername", "ananimas"]) {
+        return Option::Some(PosterInfoField::Name);
+      }
+
+      if element.has_any_class(&["postertrip", "trip"]) {
+        return Option::Some(PosterInfoField::Tripcode);
+      }
+
+      if element.has_class("capcode") {
+        return Option::Some(PosterInfoField::Capcode);
+      }
+
+      return Option::None;
+    }
+  }
+
+  impl NodeVisitor for PosterInfoVisitor {
+    fn visit_element(&mut self, element: &Element, _: usize) -> VisitAction {
+      if self.current_field.is_none() && element.has_any_class(POSTER_ID_CLASSES) {
+        if self.poster_id.is_none() {
+          self.poster_id = PosterId::of_element(element);
+        }
+
+        return VisitAction::SkipChildren;
+      }
+
+      if self.current_field.is_none() {
+        self.current_field = PosterInfoVisitor::field_of(element);
+        self.current_text.clear();
+      }
+
+      return VisitAction::Continue;
+    }
+
+    fn leave_element(&mut self, element: &Element, _: usize) {
+      let field = match self.current_field {
+        Some(field) if PosterInfoVisitor::field_of(element) == Option::Some(field) => field,
+        _ => return
+      };
+
+      self.current_field = Option::None;
+
+      let text = self.current_text.trim().to_string();
+      if text.is_empty() {
+        return;
+      }
+
+      match field {
+        PosterInfoField::Name => self.poster_info.name = Option::Some(text),
+        PosterInfoField::Tripcode => set_tripcode(&mut self.poster_info, text),
+        PosterInfoField::Capcode => {
+          let capcode = text.trim_start_matches('#').trim();
+          self.poster_info.capcode = Option::Some(capcode_display_name(capcode));
+        }
+      }
+    }
+
+    fn visit_text(&mut self, text: &String, _: usize) -> VisitAction {
+      if self.current_field.is_some() {
+        self.current_text.push_str(text);
+      }
+
+      return VisitAction::Continue;
+    }
+  }
+
+  fn set_tripcode(poster_info: &mut PosterInfo, tripcode: String) {
+    if tripcode.starts_with("!!") {
+      poster_info.secure_tripcode = Option::Some(tripcode);
+    } else {
+      poster_info.tripcode = Option::Some(tripcode);
+    }
+  }
+
+  // "mod" -> "Mod", "admin_highlight" -> "Admin"
+  fn capcode_display_name(capcode: &str) -> String {
+    let capcode = capcode.trim_end_matches("_highlight");
+
+    let mut chars = capcode.chars();
+    return match chars.next() {
+      None => String::new(),
+      Some(first_char) => first_char.to_uppercase().chain(chars).collect()
+    };
+  }
+
+  /// "Anonymous !xyz !!xyz ## Mod"
+  pub fn poster_info_to_parsed_text(poster_info: &PosterInfo) -> ParsedSpannableText {
+    let mut text_parts: Vec<&str> = Vec::with_capacity(4);
+
+    for part in [&poster_info.name, &poster_info.tripcode, &poster_info.secure_tripcode].iter() {
+      if let Option::Some(part) = part {
+        text_parts.push(part);
+      }
+    }
+
+    let mut text = text_parts.join(" ");
+    let mut spannables = Vec::new();
+
+    if let Option::Some(capcode) = &poster_info.capcode {
+      if !text.is_empty() {
+        text.push(' ');
+      }
+
+      let capcode_text = format!("{}{}", CAPCODE_PREFIX, capcode);
+      spannables.push(Spannable {
+        start: text.chars().count(),
+        len: capcode_text.chars().count(),
+        spannable_data: SpannableData::Capcode { capcode: capcode.clone(), kind: CapcodeKind::from_capcode(capcode) }
+      });
+
+      text.push_str(&capcode_text);
+    }
+
+    return ParsedSpannableText::new(&text, Box::new(text.clone()), Box::new(spannables));
+  }
+
+  fn parse_flag(metadata: &PostMetadataRaw) -> Option<PostFlag> {
+    if let Option::Some(country) = non_empty(&metadata.country) {
+      return Option::Some(PostFlag {
+        code: String::from(country),
+        name: non_empty(&metadata.country_name).map(|name| decode_text(name)),
+        kind: PostFlagKind::Country
+      });
+    }
+
+    if let Option::Some(board_flag) = non_empty(&metadata.board_flag) {
+      return Option::Some(PostFlag {
+        code: String::from(board_flag),
+        name: non_empty(&metadata.flag_name).map(|name| decode_text(name)),
+        kind: PostFlagKind::Board
+      });
+    }
+
+    if let Option::Some(flag_html) = non_empty(&metadata.flag_html) {
+      return parse_flag_html(flag_html);
+    }
+
+    return Option::None;
+  }
+
+  /// Flag html of the sites without the flag fields, the first flag that is found is returned:
+  /// - <span class="flag flag-us" title="United States"></span> (4chan style country flag)
+  /// - <span class="bfl bfl-ac" title="Anarcho-Capitalist"></span> (4chan board flag)
+  /// - <img class="countryFlag" src="//s.4cdn.org/image/country/troll/an.gif" title="Anarchist">
+  ///   (troll flag, the code is the file name)
+  /// - <img class="flag" src="/static/flags/us.png" title="United States"> (vichan)
+  pub fn parse_flag_html(flag_html: &str) -> Option<PostFlag> {
+    let html_parser = HtmlParser::new()
+      .decode_entities(true);
+
+    let nodes = match html_parser.parse(flag_html) {
+      Ok(nodes) => nodes,
+      Err(error) => {
+        report_warning(ParserWarning::new(TAG, format!("Failed to parse flag html ({}), error={}", flag_html, error)));
+        return Option::None;
+      }
+    };
+
+    let mut flag_visitor = FlagVisitor { flag: Option::None };
+    walk(&nodes, &mut flag_visitor);
+
+    return flag_visitor.flag;
+  }
+
+  struct FlagVisitor {
+    flag: Option<PostFlag>
+  }
+
+  impl FlagVisitor {
+    fn flag_of(element: &Element) -> Option<(String, PostFlagKind)> {
+      for class in element.classes() {
+        if let Option::Some(code) = class.strip_prefix(COUNTRY_FLAG_CLASS_PREFIX) {
+          return Option::Some((code.to_uppercase(), PostFlagKind::Country));
+        }
+
+        if let Option::Some(code) = class.strip_prefix(BOARD_FLAG_CLASS_PREFIX) {
+          return Option::Some((code.to_uppercase(), PostFlagKind::Board));
+        }
+      }
+
+      if element.tag_name != "img" || !element.has_any_class(&["flag", "countryFlag"]) {
+        return Option::None;
+      }
+
+      // "//s.4cdn.org/image/country/troll/an.gif" -> "AN"
+      let src = element.attr("src")?;
+      let file_name = src.rsplit('/').next()?;
+      let code = file_name.split('.').next().filter(|code| !code.is_empty())?;
+
+      let kind = if src.contains(TROLL_FLAG_PATH) {
+        PostFlagKind::Troll
+      } else {
+        PostFlagKind::Country
+      };
+
+      return Option::Some((code.to_uppercase(), kind));
+    }
+  }
+
+  impl NodeVisitor for FlagVisitor {
+    fn visit_element(&mut self, element: &Element, _: usize) -> VisitAction {
+      let (code, kind) = match FlagVisitor::flag_of(element) {
+        Option::None => return VisitAction::Continue,
+        Option::Some(flag) => flag
+      };
+
+      let name = element.attr("title")
+        .map(|title| title.trim())
+        .filter(|title| !title.is_empty())
+        .map(|title| decode_text(title));
+
+      self.flag = Option::Some(PostFlag { code, name, kind });
+      return VisitAction::Stop;
+    }
+  }
+
+  fn parse_file(file_raw: &PostFileRaw) -> PostFile {
+    let extension = file_raw.ext.trim_start_matches('.');
+
+    return PostFile {
+      original_name: format!("{}{}", decode_text(&file_raw.filename), file_raw.ext),
+      server_name: file_raw.tim.clone(),
+      extension: String::from(extension),
+      size: file_raw.fsize,
+      width: file_raw.w,
+      height: file_raw.h,
+      md5: file_raw.md5.clone(),
+      is_spoiler: file_raw.spoiler
+    };
+  }
+
+  fn file_of_image(post_image: &PostImage) -> PostFile {
+    return PostFile {
+      original_name: post_image.original_name.clone(),
+      server_name: post_image.server_name.clone(),
+      extension: post_image.extension.clone(),
+      size: post_image.size,
+      width: post_image.width,
+      height: post_image.height,
+      md5: Option::None,
+      is_spoiler: post_image.is_spoiler
+    };
+  }
+
+  fn non_empty(value: &Option<String>) -> Option<&str> {
+    return value.as_ref()
+      .map(|value| value.as_str())
+      .filter(|value| !value.is_empty());
+  }
+
+  fn decode_text(text: &str) -> String {
+    return String::from(html_escape::decode_html_entities(text));
+  }
+}
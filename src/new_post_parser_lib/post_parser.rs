@@ -1,37 +1,478 @@
 pub mod post_parser {
-  use crate::{PostRaw, PostParserContext, PostParser, CommentParser, ParsedPost, ParsedSpannableText, Spannable, SpannableData, PostLink, HtmlParser, TextPart, PostDescriptor};
+  use crate::{PostRaw, PostParserContext, PostParser, CommentParser, ParsedPost, ParsedSpannableText, Spannable, SpannableData, PostLink, HtmlParser, TextPart, TextParts, PostDescriptor, ParserLimits, Element, ParserProfile, SpanOffsetMode, PostRoll, PostPoll, TextCleanupOptions, ParsedPostMetadata, SearchHighlightOptions, FilterEngine, FilterScope, LineBreakMode, WhitespacePolicy, CommentFormat, BbCodeParser, MarkdownParser, PlainTextParser, QuoteFormat, CatalogSnippet, QuoteSuffixes, QuotePreview, WordBreakMode, TeXMode, UnknownTagPolicy, ParserWarning};
+  use crate::parser_warnings::{collect_warnings, report_warning};
+  #[cfg(feature = "stats")]
+  use crate::PostParserStats;
   use crate::html_parser::node::Node;
-  use std::collections::HashSet;
+  use crate::html_parser::html_parser_error::HtmlParserError;
+  use crate::html_parser::visitor::{NodeVisitor, VisitAction, walk};
+  use std::collections::{HashSet, HashMap};
   use std::fmt;
+  use std::cell::Cell;
+  use std::time::{Duration, Instant};
   use regex::Regex;
-  use crate::util::helpers::{SumBy, MapJoin};
+  use crate::util::helpers::{MapJoin, now};
   use crate::util::theme_json_extractor::detect_and_extract_theme_json;
+  use crate::util::spannable_normalizer::normalize_spannables;
+  use crate::util::linkifier::find_links;
+  use crate::util::span_offset_converter::convert_span_offsets;
+  use crate::util::text_truncator::truncate_text;
+  use crate::util::text_cleanup::{cleanup_text, collapse_blank_lines};
+  use crate::util::whitespace_normalizer::normalize_whitespace;
+  use crate::util::word_breaks::join_word_breaks;
+  use crate::util::tex::extract_tex;
+  use crate::rules::text_rule::apply_text_rules;
+  use crate::rules::ban_message::is_ban_message;
+  use crate::rules::simple_span::SimpleSpanHandler;
+  use std::rc::Rc;
+  use crate::util::search_highlighter::{create_search_highlight_pattern, highlight_search_terms};
+  use crate::util::media_link_extractor::{extract_media_links, collect_media_links};
+  use crate::site_url_resolver::{SiteUrlResolver, FourChanUrlResolver};
+
+  const TAG: &str = "PostParser";
 
   lazy_static! {
-    static ref LINK_PATTERN: Regex = Regex::new(r"https?://[-a-zA-Z0-9@:%._\+~#=]{1,256}\.[a-zA-Z0-9()]{1,6}\b(?:[-\w0-9()@:%_\+.~#?&//=!]*)").unwrap();
+    // ">>>/g/" in plain text. Matches ">>>/g/123" too but those are skipped.
+    static ref BOARD_LINK_TEXT_PATTERN: Regex = Regex::new(r">>>/(\w+)/(\d*)").unwrap();
+  }
+
+  const DEFAULT_MAX_DEPTH: usize = 256;
+
+  impl Default for ParserLimits {
+    /// Only the nesting depth is limited (256) since no real post comes anywhere close to it
+    fn default() -> Self {
+      return ParserLimits {
+        max_parse_millis: Option::None,
+        max_nodes: Option::None,
+        max_depth: Option::Some(DEFAULT_MAX_DEPTH)
+      };
+    }
+  }
+
+  // Reading the clock is not free so the deadline is only checked once per this many nodes
+  const PARSE_BUDGET_CHECK_INTERVAL: u32 = 64;
+
+  struct ParseBudget {
+    deadline: Option<Instant>,
+    nodes_until_check: Cell<u32>,
+    exceeded: Cell<bool>
+  }
+
+  impl ParseBudget {
+    fn new(parser_limits: &ParserLimits) -> ParseBudget {
+      // The budget is not enforced on wasm32-unknown-unknown, there is no clock
+      let deadline = match (parser_limits.max_parse_millis, now()) {
+        (Option::Some(max_parse_millis), Option::Some(now)) => Option::Some(now + Duration::from_millis(max_parse_millis)),
+        _ => Option::None
+      };
+
+      return ParseBudget {
+        deadline,
+        nodes_until_check: Cell::new(PARSE_BUDGET_CHECK_INTERVAL),
+        exceeded: Cell::new(false)
+      };
+    }
+
+    fn is_exceeded(&self) -> bool {
+      if self.exceeded.get() {
+        return true;
+      }
+
+      let deadline = match self.deadline {
+        None => return false,
+        Some(deadline) => deadline
+      };
+
+      let nodes_until_check = self.nodes_until_check.get() - 1;
+      if nodes_until_check > 0 {
+        self.nodes_until_check.set(nodes_until_check);
+        return false;
+      }
+
+      self.nodes_until_check.set(PARSE_BUDGET_CHECK_INTERVAL);
+
+      if Instant::now() >= deadline {
+        self.exceeded.set(true);
+      }
+
+      return self.exceeded.get();
+    }
+  }
+
+  /// Converts the nodes into text parts and spannables using the comment parser's rules. When the
+  /// parse budget is exceeded the walking is stopped but the elements that were already entered
+  /// are still post processed so their spannables end at the current position.
+  struct CommentNodeVisitor<'a> {
+    post_parser: &'a PostParser<'a>,
+    post_raw: &'a PostRaw,
+    parse_budget: &'a ParseBudget,
+    out_text_parts: &'a mut TextParts,
+    out_spannables: &'a mut Vec<Spannable>,
+    // (out_text_parts.len(), out_spannables.len(), is_unknown_tag) at the moment each currently
+    // entered element was visited. We need them during post process phase to figure out what was
+    // added into out_text_parts/out_spannables
+    prev_indexes_stack: Vec<(usize, usize, bool)>,
+    // How many of the currently entered elements keep the text as is (<pre> etc)
+    preformatted_depth: usize,
+    // A block element (LineBreakMode::block_elements) has ended, the next text starts on a new line
+    pending_block_break: bool,
+    // Elements, texts and comments that were walked (PostParserStats::nodes_parsed)
+    nodes_visited: u64,
+    // The comment has the "Comment too long" link / the deleted post warning (see CommentMarker)
+    is_abbreviated: bool,
+    is_deleted: bool
+  }
+
+  const PREFORMATTED_TAGS: &[&str] = &["pre", "code", "textarea"];
+  const BLOCK_TAGS: &[&str] = &["p", "div"];
+  // Have no rule of their own (only the rules of their classes) and yet are not unknown
+  const GENERIC_INLINE_TAGS: &[&str] = &["span"];
+
+  /// Boilerplate the sites add into the comment html, it's removed from the text and turned into
+  /// the flags of ParsedSpannableText
+  enum CommentMarker {
+    // <span class="abbr">Comment too long. <a href="...">Click here</a> to view the full text.</span>
+    Abbreviated,
+    // <strong class="warning">[Deleted]</strong> (the same markup with a ban message is handled by
+    // BanMessageHandler)
+    Deleted
+  }
+
+  fn comment_marker(element: &Element) -> Option<CommentMarker> {
+    if element.tag_name == "span" && element.has_class("abbr") {
+      return Option::Some(CommentMarker::Abbreviated);
+    }
+
+    if element.tag_name == "strong" && element.has_class("warning") && !is_ban_message(element) {
+      return Option::Some(CommentMarker::Deleted);
+    }
+
+    return Option::None;
+  }
+
+  impl CommentNodeVisitor<'_> {
+    fn is_block_element(&self, element: &Element) -> bool {
+      return self.post_parser.line_break_mode.block_elements && BLOCK_TAGS.contains(&element.tag_name.as_ref());
+    }
+
+    /// Adds a line break unless the text is empty or already ends with one
+    fn start_new_line(&mut self) {
+      let ends_with_line_break = match self.out_text_parts.last() {
+        Option::None => true,
+        Option::Some(text_part) => text_part.text.ends_with('\n')
+      };
+
+      if !ends_with_line_break {
+        self.out_text_parts.push(TextPart::from_static("\n"));
+      }
+    }
+
+    /// Reports the element to the unknown tag callback when no rule of its tag handles it
+    fn check_unknown_tag(&self, element: &Element) -> bool {
+      let post_parser = self.post_parser;
+      if post_parser.unknown_tag_policy == UnknownTagPolicy::KeepChildren && post_parser.unknown_tag_callback.is_none() {
+        return false;
+      }
+
+      let tag_name: &str = &element.tag_name;
+      if BLOCK_TAGS.contains(&tag_name) || GENERIC_INLINE_TAGS.contains(&tag_name) || post_parser.comment_parser.has_tag_rule(element) {
+        return false;
+      }
+
+      if let Option::Some(unknown_tag_callback) = &post_parser.unknown_tag_callback {
+        unknown_tag_callback(self.post_raw, element);
+      }
+
+      return true;
+    }
+
+    fn flush_pending_block_break(&mut self) {
+      if self.pending_block_break {
+        self.pending_block_break = false;
+        self.start_new_line();
+      }
+    }
+  }
+
+  impl NodeVisitor for CommentNodeVisitor<'_> {
+    fn visit_element(&mut self, element: &Element, _: usize) -> VisitAction {
+      if self.parse_budget.is_exceeded() {
+        return VisitAction::Stop;
+      }
+
+      self.nodes_visited += 1;
+      self.flush_pending_block_break();
+
+      if self.is_block_element(element) {
+        self.start_new_line();
+      }
+
+      match comment_marker(element) {
+        Option::Some(CommentMarker::Abbreviated) => {
+          self.is_abbreviated = true;
+          return VisitAction::SkipChildren;
+        }
+        Option::Some(CommentMarker::Deleted) => {
+          self.is_deleted = true;
+          return VisitAction::SkipChildren;
+        }
+        Option::None => {}
+      }
+
+      let prev_out_text_parts_index = self.out_text_parts.len();
+      let prev_out_spannables_index = self.out_spannables.len();
+
+      let comment_parser = &self.post_parser.comment_parser;
+      if comment_parser.pre_process_element(self.post_raw, element, self.out_text_parts, self.out_spannables) {
+        // Element was fully processed, no need to check the child elements
+        return VisitAction::SkipChildren;
+      }
+
+      let is_unknown_tag = self.check_unknown_tag(element);
+      if is_unknown_tag && self.post_parser.unknown_tag_policy == UnknownTagPolicy::DropSubtree {
+        return VisitAction::SkipChildren;
+      }
+
+      if PREFORMATTED_TAGS.contains(&element.tag_name.as_ref()) {
+        self.preformatted_depth += 1;
+      }
+
+      self.prev_indexes_stack.push((prev_out_text_parts_index, prev_out_spannables_index, is_unknown_tag));
+      return VisitAction::Continue;
+    }
+
+    fn leave_element(&mut self, element: &Element, _: usize) {
+      let (prev_out_text_parts_index, prev_out_spannables_index, is_unknown_tag) = self.prev_indexes_stack.pop().unwrap();
+
+      if PREFORMATTED_TAGS.contains(&element.tag_name.as_ref()) {
+        self.preformatted_depth -= 1;
+      }
+
+      if self.is_block_element(element) {
+        self.pending_block_break = true;
+      }
+
+      if element.children.is_empty() {
+        return;
+      }
+
+      if is_unknown_tag && self.post_parser.unknown_tag_policy == UnknownTagPolicy::Marker {
+        let start = self.out_text_parts.chars_before(prev_out_text_parts_index);
+        let len = self.out_text_parts.chars_from(prev_out_text_parts_index);
+
+        let spannable = Spannable {
+          start,
+          len,
+          spannable_data: SpannableData::Unknown { tag: element.tag_name.to_string() }
+        };
+
+        if spannable.is_valid() {
+          self.out_spannables.push(spannable);
+        }
+      }
+
+      self.post_parser.comment_parser.post_process_element(
+        self.post_raw,
+        element,
+        prev_out_text_parts_index,
+        self.out_text_parts,
+        prev_out_spannables_index,
+        self.out_spannables
+      );
+    }
+
+    fn visit_text(&mut self, text: &String, _: usize) -> VisitAction {
+      if self.parse_budget.is_exceeded() {
+        return VisitAction::Stop;
+      }
+
+      self.nodes_visited += 1;
+      self.flush_pending_block_break();
+
+      let text = if self.post_parser.line_break_mode.text_newlines || self.preformatted_depth > 0 {
+        text.clone()
+      } else {
+        text.replace("\r\n", " ").replace(|ch| ch == '\n' || ch == '\r', " ")
+      };
+
+      self.post_parser.detect_links(self.post_raw, self.out_text_parts, &text, self.out_spannables);
+      self.post_parser.detect_board_links(self.out_text_parts, &text, self.out_spannables);
+      self.out_text_parts.push(TextPart::new(text));
+
+      return VisitAction::Continue;
+    }
+
+    fn visit_comment(&mut self, _: &String, _: usize) -> VisitAction {
+      // Comments are never displayed
+      if self.parse_budget.is_exceeded() {
+        return VisitAction::Stop;
+      }
+
+      self.nodes_visited += 1;
+      return VisitAction::Continue;
+    }
+  }
+
+  /// Collects the text of the comment for a catalog snippet: quotes are skipped, every run of
+  /// whitespace and line breaks becomes a single space. Stops as soon as the text is longer than
+  /// max_length since the rest would be cut anyway.
+  struct CatalogNodeVisitor<'a> {
+    quote_format: &'a QuoteFormat,
+    max_length: usize,
+    out_text: String,
+    chars_count: usize,
+    // Whitespace (or a line break) was skipped, a space goes before the next char
+    pending_space: bool
+  }
+
+  const CATALOG_SKIPPED_TAGS: &[&str] = &["script", "style"];
+  const CATALOG_LINE_BREAK_TAGS: &[&str] = &["br", "p", "div", "li", "tr"];
+
+  impl CatalogNodeVisitor<'_> {
+    fn is_quote(&self, element: &Element) -> bool {
+      if element.tag_name != "a" {
+        return false;
+      }
+
+      return element.has_any_class(&["quotelink", "backlink"])
+        || self.quote_format.parse_quote(&element.collect_text()).is_some();
+    }
+  }
+
+  impl NodeVisitor for CatalogNodeVisitor<'_> {
+    fn visit_element(&mut self, element: &Element, _: usize) -> VisitAction {
+      if self.chars_count > self.max_length {
+        return VisitAction::Stop;
+      }
+
+      if CATALOG_LINE_BREAK_TAGS.contains(&element.tag_name.as_ref()) {
+        self.pending_space = true;
+      }
+
+      if CATALOG_SKIPPED_TAGS.contains(&element.tag_name.as_ref()) || self.is_quote(element) {
+        return VisitAction::SkipChildren;
+      }
+
+      return VisitAction::Continue;
+    }
+
+    fn visit_text(&mut self, text: &String, _: usize) -> VisitAction {
+      for ch in text.chars() {
+        if self.chars_count > self.max_length {
+          return VisitAction::Stop;
+        }
+
+        if ch.is_whitespace() {
+          self.pending_space = true;
+          continue;
+        }
+
+        if self.pending_space && !self.out_text.is_empty() {
+          self.out_text.push(' ');
+          self.chars_count += 1;
+        }
+
+        self.pending_space = false;
+        self.out_text.push(ch);
+        self.chars_count += 1;
+      }
+
+      return VisitAction::Continue;
+    }
   }
 
   impl PostParserContext {
     pub fn new(
       my_replies: HashSet<u64>,
       thread_posts: HashSet<u64>
+    ) -> PostParserContext {
+      return PostParserContext::new_with_span_offset_mode(my_replies, thread_posts, SpanOffsetMode::UnicodeScalars);
+    }
+
+    pub fn new_with_span_offset_mode(
+      my_replies: HashSet<u64>,
+      thread_posts: HashSet<u64>,
+      span_offset_mode: SpanOffsetMode
     ) -> PostParserContext {
       return PostParserContext {
         my_replies,
-        thread_posts
+        thread_posts,
+        span_offset_mode,
+        search_highlight_options: SearchHighlightOptions::default(),
+        search_highlight_pattern: Option::None,
+        max_parsed_length: Option::None,
+        truncation_ellipsis: String::from("…"),
+        quote_suffixes: QuoteSuffixes::default(),
+        quote_previews: HashMap::new()
       }
     }
 
+    pub fn with_search_highlight(mut self, search_highlight_options: SearchHighlightOptions) -> PostParserContext {
+      self.search_highlight_pattern = create_search_highlight_pattern(&search_highlight_options);
+      self.search_highlight_options = search_highlight_options;
+      return self;
+    }
+
+    /// Only the first [max_parsed_length] chars of the parsed text are kept (the rest is replaced
+    /// with the truncation ellipsis), for views that only show the beginning of the comment
+    pub fn with_max_parsed_length(mut self, max_parsed_length: usize) -> PostParserContext {
+      self.max_parsed_length = Option::Some(max_parsed_length);
+      return self;
+    }
+
+    /// "…" by default
+    pub fn with_truncation_ellipsis(mut self, truncation_ellipsis: &str) -> PostParserContext {
+      self.truncation_ellipsis = String::from(truncation_ellipsis);
+      return self;
+    }
+
+    pub fn with_quote_suffixes(mut self, quote_suffixes: QuoteSuffixes) -> PostParserContext {
+      self.quote_suffixes = quote_suffixes;
+      return self;
+    }
+
+    pub fn quote_suffixes(&self) -> &QuoteSuffixes {
+      return &self.quote_suffixes;
+    }
+
+    /// Previews of the posts that may be quoted (post_no -> preview), they are put into the
+    /// PostLink::Quote spannables so the client doesn't need to look the quoted posts up
+    pub fn with_quote_previews(mut self, quote_previews: HashMap<u64, QuotePreview>) -> PostParserContext {
+      self.quote_previews = quote_previews;
+      return self;
+    }
+
+    pub fn quote_preview(&self, post_no: u64) -> Option<&QuotePreview> {
+      return self.quote_previews.get(&post_no);
+    }
+
+    pub fn max_parsed_length(&self) -> Option<usize> {
+      return self.max_parsed_length;
+    }
+
+    pub fn search_highlight_options(&self) -> &SearchHighlightOptions {
+      return &self.search_highlight_options;
+    }
+
+    pub fn span_offset_mode(&self) -> &SpanOffsetMode {
+      return &self.span_offset_mode;
+    }
+
     pub fn is_internal_thread_post(&self, quote_post_id: u64) -> bool {
       return self.thread_posts.contains(&quote_post_id);
     }
 
+    /// Whether the post was made by the user (my_replies)
+    pub fn is_my_post(&self, post_no: u64) -> bool {
+      return self.my_replies.contains(&post_no);
+    }
+
     pub fn is_reply_to_my_post(&self, quote_post_id: u64) -> bool {
-      return self.my_replies.contains(&quote_post_id);
+      return self.is_my_post(quote_post_id);
     }
 
     pub fn is_my_reply_to_my_own_post(&self, source_post_id: u64, quote_post_id: u64) -> bool {
-      return self.my_replies.contains(&source_post_id) && self.my_replies.contains(&quote_post_id);
+      return self.is_my_post(source_post_id) && self.is_my_post(quote_post_id);
     }
 
   }
@@ -45,7 +486,8 @@ pub mod post_parser {
   impl ParsedPost {
     pub fn new(
       post_raw: &PostRaw,
-      post_comment_parsed: ParsedSpannableText
+      post_comment_parsed: ParsedSpannableText,
+      metadata: ParsedPostMetadata
     ) -> ParsedPost {
       let post_descriptor = PostDescriptor::new(
         post_raw.site_name(),
@@ -55,36 +497,194 @@ pub mod post_parser {
         post_raw.post_sub_no()
       );
 
+      let media_links = collect_media_links(&post_comment_parsed.spannables);
+      let rolls = post_comment_parsed.spannables
+        .iter()
+        .filter_map(|spannable| match &spannable.spannable_data {
+          SpannableData::DiceRoll(post_roll) => Option::Some(post_roll.clone()),
+          _ => Option::None
+        })
+        .collect::<Vec<PostRoll>>();
+      let polls = post_comment_parsed.spannables
+        .iter()
+        .filter_map(|spannable| match &spannable.spannable_data {
+          SpannableData::Poll(post_poll) => Option::Some(post_poll.clone()),
+          _ => Option::None
+        })
+        .collect::<Vec<PostPoll>>();
+
+      let replies_to = post_comment_parsed.spannables
+        .iter()
+        .filter_map(|spannable| match &spannable.spannable_data {
+          SpannableData::Link(PostLink::Quote { post_no, .. }) => Option::Some(*post_no),
+          SpannableData::Link(PostLink::Dead { post_no }) => Option::Some(*post_no),
+          _ => Option::None
+        })
+        .collect::<HashSet<u64>>();
+
+      let is_comment_abbreviated = post_comment_parsed.is_abbreviated;
+      let is_deleted = post_comment_parsed.is_deleted;
+      let has_ban_message = post_comment_parsed.spannables
+        .iter()
+        .any(|spannable| spannable.spannable_data == SpannableData::BanMessage);
+
       return ParsedPost {
         post_descriptor,
-        post_comment_parsed
+        post_comment_parsed,
+        metadata,
+        media_links,
+        rolls,
+        polls,
+        replies_to,
+        filter_matches: Vec::new(),
+        is_comment_abbreviated,
+        is_deleted,
+        has_ban_message
       }
     }
   }
 
   impl PostParser<'_> {
     pub fn new(post_parser_context: &PostParserContext) -> PostParser {
+      return PostParser::new_with_limits(post_parser_context, ParserLimits::default());
+    }
+
+    pub fn new_with_limits(post_parser_context: &PostParserContext, parser_limits: ParserLimits) -> PostParser {
+      return PostParser::new_with_profile(post_parser_context, ParserProfile::four_chan(), parser_limits);
+    }
+
+    pub fn new_with_profile(
+      post_parser_context: &PostParserContext,
+      parser_profile: ParserProfile,
+      parser_limits: ParserLimits
+    ) -> PostParser {
+      let mut parsing_rules = parser_profile.parsing_rules;
+      for (class_name, role) in parser_profile.colored_quote_classes.iter() {
+        let spannable_data = SpannableData::ColoredQuote { role: *role };
+        parsing_rules.add_tag_class_handler("span", class_name, Rc::new(SimpleSpanHandler::new(spannable_data)));
+      }
+
       let mut comment_parser = CommentParser::new(post_parser_context);
+      comment_parser.set_parsing_rules(parsing_rules);
 
-      comment_parser.add_default_matching_rules();
-      comment_parser.add_replacement_rule("<wbr>", "");
+      for (pattern, value) in parser_profile.replacement_rules.iter() {
+        comment_parser.add_replacement_rule(pattern, value);
+      }
 
       return PostParser {
         post_parser_context,
-        comment_parser: Box::new(comment_parser)
+        parser_limits,
+        decode_entities: parser_profile.decode_entities,
+        comment_format: parser_profile.comment_format,
+        line_break_mode: parser_profile.line_break_mode,
+        whitespace_policy: parser_profile.whitespace_policy,
+        word_break_mode: WordBreakMode::Strip,
+        tex_mode: TeXMode::Disabled,
+        unknown_tag_policy: UnknownTagPolicy::KeepChildren,
+        unknown_tag_callback: Option::None,
+        site_url_resolvers: vec![Box::new(FourChanUrlResolver::new())],
+        merge_adjacent_spannables: true,
+        text_cleanup_options: TextCleanupOptions::default(),
+        filter_engine: Option::None,
+        comment_parser: Box::new(comment_parser),
+        #[cfg(feature = "stats")]
+        stats: Cell::new(PostParserStats::default())
       };
     }
 
+    /// Whether nested/adjacent spannables of the same kind (<s>a</s><s>b</s>) are merged into one
+    pub fn set_merge_adjacent_spannables(&mut self, merge_adjacent_spannables: bool) {
+      self.merge_adjacent_spannables = merge_adjacent_spannables;
+    }
+
+    pub fn set_line_break_mode(&mut self, line_break_mode: LineBreakMode) {
+      self.line_break_mode = line_break_mode;
+    }
+
+    pub fn set_whitespace_policy(&mut self, whitespace_policy: WhitespacePolicy) {
+      self.whitespace_policy = whitespace_policy;
+    }
+
+    pub fn set_word_break_mode(&mut self, word_break_mode: WordBreakMode) {
+      self.word_break_mode = word_break_mode;
+    }
+
+    pub fn set_tex_mode(&mut self, tex_mode: TeXMode) {
+      self.tex_mode = tex_mode;
+    }
+
+    pub fn set_unknown_tag_policy(&mut self, unknown_tag_policy: UnknownTagPolicy) {
+      self.unknown_tag_policy = unknown_tag_policy;
+    }
+
+    /// [unknown_tag_callback] is called with every element that has no rule of its own (no matter
+    /// the UnknownTagPolicy) so that the app can notice when a site changes its markup
+    pub fn set_unknown_tag_callback(&mut self, unknown_tag_callback: Box<dyn Fn(&PostRaw, &Element)>) {
+      self.unknown_tag_callback = Option::Some(unknown_tag_callback);
+    }
+
+    pub fn set_text_cleanup_options(&mut self, text_cleanup_options: TextCleanupOptions) {
+      self.text_cleanup_options = text_cleanup_options;
+    }
+
+    /// The filters are evaluated for every parsed post (ParsedPost::filter_matches) and the matches
+    /// of the ones with a highlight color get FilterHighlight spannables
+    pub fn set_filter_engine(&mut self, filter_engine: FilterEngine) {
+      self.filter_engine = Option::Some(filter_engine);
+    }
+
+    /// Resolvers are tried in the order they were added in, the first one that recognizes the url wins
+    pub fn add_site_url_resolver(&mut self, site_url_resolver: Box<dyn SiteUrlResolver>) {
+      self.site_url_resolvers.push(site_url_resolver);
+    }
+
     pub fn parse_post(&self, post_raw: &PostRaw) -> ParsedPost {
-      return ParsedPost::new(
+      let mut parsed_post = ParsedPost::new(
         post_raw,
-        self.parse_comment(post_raw)
-      )
+        self.parse_comment(post_raw),
+        self.parse_metadata(post_raw)
+      );
+
+      if let Option::Some(filter_engine) = &self.filter_engine {
+        parsed_post.filter_matches = filter_engine.evaluate(&parsed_post);
+      }
+
+      return parsed_post;
+    }
+
+    /// Lightweight alternative of parse_comment() for the catalog cells: the comment is converted
+    /// into a single line of plain text (quotes are removed, line breaks become spaces) which is
+    /// cut after max_length chars with the PostParserContext truncation ellipsis. No spannables are
+    /// built and the tree is only walked until the text is long enough.
+    pub fn parse_for_catalog(&self, post_raw: &PostRaw, max_length: usize) -> CatalogSnippet {
+      let comment_raw = self.pre_process_comment(&post_raw.com);
+
+      let nodes = match self.parse_nodes(comment_raw.as_str()) {
+        Ok(nodes) => nodes,
+        Err(_) => PlainTextParser::new().parse(comment_raw.as_str()).unwrap_or_default()
+      };
+
+      let mut catalog_node_visitor = CatalogNodeVisitor {
+        quote_format: self.comment_parser.parsing_rules.quote_format(),
+        max_length,
+        out_text: String::with_capacity(max_length + 16),
+        chars_count: 0,
+        pending_space: false
+      };
+
+      walk(&nodes, &mut catalog_node_visitor);
+
+      let text = catalog_node_visitor.out_text;
+      let truncation_ellipsis = self.post_parser_context.truncation_ellipsis.as_str();
+
+      return match truncate_text(&text, &mut Vec::new(), max_length, truncation_ellipsis) {
+        Option::Some(truncated_text) => CatalogSnippet { text: truncated_text, was_truncated: true },
+        Option::None => CatalogSnippet { text, was_truncated: false }
+      };
     }
 
     pub fn iterate_comment_nodes(&self, post_comment: &String, iterator: &dyn Fn(&Node, &String)) {
-      let html_parser = HtmlParser::new();
-      let html_parsing_result = html_parser.parse(post_comment);
+      let html_parsing_result = self.parse_nodes(post_comment);
 
       if html_parsing_result.is_err() {
         return;
@@ -93,48 +693,199 @@ pub mod post_parser {
       self.iterate_element(&html_parsing_result.unwrap(), post_comment, iterator);
     }
 
+    /// Builds the node tree with the front-end of the profile's comment format
+    fn parse_nodes(&self, comment_raw: &str) -> Result<Vec<Node>, HtmlParserError> {
+      let mut nodes = match self.comment_format {
+        CommentFormat::Html => HtmlParser::new()
+          .decode_entities(self.decode_entities)
+          // max_parse_millis is left to the ParseBudget so that a slow post still produces partial text
+          .limits(ParserLimits { max_parse_millis: Option::None, ..self.parser_limits.clone() })
+          .parse(comment_raw)?,
+        CommentFormat::BbCode => BbCodeParser::new().parse(comment_raw)?,
+        CommentFormat::Markdown => MarkdownParser::new().parse(comment_raw)?,
+        CommentFormat::PlainText => PlainTextParser::new().parse(comment_raw)?
+      };
+
+      join_word_breaks(&mut nodes, &self.word_break_mode);
+      return Result::Ok(nodes);
+    }
+
     pub fn parse_comment(&self, post_raw: &PostRaw) -> ParsedSpannableText {
-      let comment_raw = self.pre_process_comment(post_raw);
+      return self.parse_html_text_in_scope(post_raw, &post_raw.com, Option::Some(FilterScope::Comment));
+    }
+
+    /// Parses any html text of the post (comment, subject) with the comment rules
+    pub fn parse_html_text(&self, post_raw: &PostRaw, html_text: &str) -> ParsedSpannableText {
+      return self.parse_html_text_in_scope(post_raw, html_text, Option::None);
+    }
+
+    /// filter_scope - which filters of the FilterEngine may highlight the text
+    pub(crate) fn parse_html_text_in_scope(
+      &self,
+      post_raw: &PostRaw,
+      html_text: &str,
+      filter_scope: Option<FilterScope>
+    ) -> ParsedSpannableText {
+      let (mut parsed_spannable_text, warnings) = collect_warnings(|| {
+        return self.parse_html_text_internal(post_raw, html_text, filter_scope);
+      });
+
+      parsed_spannable_text.warnings = warnings;
+      return parsed_spannable_text;
+    }
+
+    fn parse_html_text_internal(
+      &self,
+      post_raw: &PostRaw,
+      html_text: &str,
+      filter_scope: Option<FilterScope>
+    ) -> ParsedSpannableText {
+      #[cfg(feature = "stats")]
+      let parse_started_at = now();
+
+      let comment_raw = self.pre_process_comment(html_text);
       if comment_raw.is_empty() {
         return ParsedSpannableText::empty();
       }
 
-      let html_parser = HtmlParser::new();
+      let (nodes, html_parser_error) = match self.parse_nodes(comment_raw.as_str()) {
+        Ok(nodes) => (nodes, Option::None),
+        Err(html_parser_error) => {
+          report_warning(ParserWarning::new(TAG, format!("Failed to parse comment_raw html, error={}", html_parser_error)));
 
-      let html_parsing_result = html_parser.parse(comment_raw.as_str());
-      if html_parsing_result.is_err() {
-        let parser_error_message = format!(
-          "Failed to parse comment_raw html, error={:?}",
-          html_parsing_result.err()
-        );
+          // Parse the comment as plain text so that the user at least sees something (with working
+          // greentext, quotes and links)
+          let nodes = PlainTextParser::new().parse(comment_raw.as_str()).unwrap_or_default();
+          (nodes, Option::Some(html_parser_error))
+        }
+      };
+
+      let parse_budget = ParseBudget::new(&self.parser_limits);
+
+      let mut out_text_parts = TextParts::with_capacity(16);
+      let mut out_spannables: Vec<Spannable> = Vec::with_capacity(8);
+
+      let mut comment_node_visitor = CommentNodeVisitor {
+        post_parser: self,
+        post_raw,
+        parse_budget: &parse_budget,
+        out_text_parts: &mut out_text_parts,
+        out_spannables: &mut out_spannables,
+        prev_indexes_stack: Vec::with_capacity(8),
+        preformatted_depth: 0,
+        pending_block_break: false,
+        nodes_visited: 0,
+        is_abbreviated: false,
+        is_deleted: false
+      };
+
+      walk(&nodes, &mut comment_node_visitor);
+      let nodes_visited = comment_node_visitor.nodes_visited;
+      let is_abbreviated = comment_node_visitor.is_abbreviated;
+      let is_deleted = comment_node_visitor.is_deleted;
 
-        let post_comment_parsed = ParsedSpannableText::new(
-          comment_raw.as_str(),
-          Box::new(parser_error_message),
-          Box::new(Vec::new())
-        );
+      let mut total_size = out_text_parts.chars_count();
+      let mut total_text = Box::new(out_text_parts.iter().map_join_cap(total_size, "", &|text_part| text_part.text.as_ref()));
 
-        return post_comment_parsed;
+      if let Option::Some(cleaned_text) = cleanup_text(&total_text, &mut out_spannables, &self.text_cleanup_options) {
+        total_size = cleaned_text.chars().count();
+        total_text = Box::new(cleaned_text);
       }
 
-      let mut out_text_parts: Vec<TextPart> = Vec::with_capacity(16);
-      let mut out_spannables: Vec<Spannable> = Vec::with_capacity(8);
-      self.process_element(post_raw, &html_parsing_result.unwrap(), &mut out_text_parts, &mut out_spannables);
+      if let Option::Some(normalized_text) = normalize_whitespace(&total_text, &mut out_spannables, &self.whitespace_policy) {
+        total_size = normalized_text.chars().count();
+        total_text = Box::new(normalized_text);
+      }
 
-      let total_size = out_text_parts.iter().sum_by(&|text_part| text_part.characters_count as i32) as usize;
-      let total_text = Box::new(out_text_parts.iter().map_join_cap(total_size, "", &|text_part| text_part.text.as_str()));
+      if let Option::Some(max_blank_lines) = self.line_break_mode.max_blank_lines {
+        if let Option::Some(collapsed_text) = collapse_blank_lines(&total_text, &mut out_spannables, max_blank_lines) {
+          total_size = collapsed_text.chars().count();
+          total_text = Box::new(collapsed_text);
+        }
+      }
+
+      if let Option::Some(new_text) = extract_tex(&total_text, &mut out_spannables, &self.tex_mode) {
+        total_size = new_text.chars().count();
+        total_text = Box::new(new_text);
+      }
+
+      if let Option::Some(new_text) = apply_text_rules(&total_text, &self.comment_parser.parsing_rules.text_rules, &mut out_spannables) {
+        total_size = new_text.chars().count();
+        total_text = Box::new(new_text);
+      }
 
       detect_and_extract_theme_json(&total_text, &mut out_spannables);
+      extract_media_links(&mut out_spannables);
+
+      if let Option::Some(search_highlight_pattern) = &self.post_parser_context.search_highlight_pattern {
+        highlight_search_terms(&total_text, search_highlight_pattern, &self.post_parser_context.search_highlight_options, &mut out_spannables);
+      }
+
+      if let (Option::Some(filter_engine), Option::Some(filter_scope)) = (&self.filter_engine, filter_scope) {
+        filter_engine.highlight(filter_scope, &total_text, &mut out_spannables);
+      }
+
+      let mut was_truncated = false;
+
+      if let Option::Some(max_parsed_length) = self.post_parser_context.max_parsed_length {
+        let truncation_ellipsis = self.post_parser_context.truncation_ellipsis.as_str();
 
-      return ParsedSpannableText::new(
+        if let Option::Some(truncated_text) = truncate_text(&total_text, &mut out_spannables, max_parsed_length, truncation_ellipsis) {
+          total_size = truncated_text.chars().count();
+          total_text = Box::new(truncated_text);
+          was_truncated = true;
+        }
+      }
+
+      normalize_spannables(total_size, &mut out_spannables, self.merge_adjacent_spannables);
+      convert_span_offsets(&total_text, &mut out_spannables, self.post_parser_context.span_offset_mode());
+
+      let mut parsed_spannable_text = ParsedSpannableText::new(
         comment_raw.as_str(),
         total_text,
         Box::new(out_spannables)
       );
+
+      parsed_spannable_text.timed_out = parse_budget.exceeded.get();
+      parsed_spannable_text.html_parser_error = html_parser_error;
+      parsed_spannable_text.was_truncated = was_truncated;
+      parsed_spannable_text.is_abbreviated = is_abbreviated;
+      parsed_spannable_text.is_deleted = is_deleted;
+
+      #[cfg(feature = "stats")]
+      self.record_stats(nodes_visited, parse_started_at);
+      #[cfg(not(feature = "stats"))]
+      let _ = nodes_visited;
+
+      return parsed_spannable_text;
+    }
+
+    /// Counters of everything this parser has parsed so far with parse_comment() and
+    /// parse_html_text() (parse_for_catalog() is not counted)
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> PostParserStats {
+      return self.stats.get();
     }
 
-    fn pre_process_comment(&self, post_raw: &PostRaw) -> String {
-      let comment_raw = &post_raw.com;
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&self) {
+      self.stats.set(PostParserStats::default());
+    }
+
+    #[cfg(feature = "stats")]
+    fn record_stats(&self, nodes_parsed: u64, parse_started_at: Option<Instant>) {
+      let mut stats = self.stats.get();
+      stats.posts_parsed += 1;
+      stats.nodes_parsed += nodes_parsed;
+
+      if let (Option::Some(parse_started_at), Option::Some(parse_ended_at)) = (parse_started_at, now()) {
+        stats.parse_time += parse_ended_at - parse_started_at;
+      }
+
+      self.stats.set(stats);
+    }
+
+    fn pre_process_comment(&self, comment_raw: &str) -> String {
       if comment_raw.is_empty() {
         return String::from("");
       }
@@ -148,117 +899,108 @@ pub mod post_parser {
       return result_comment_raw;
     }
 
-    fn process_element(
+    fn iterate_element(
       &self,
-      post_raw: &PostRaw,
       nodes: &Vec<Node>,
-      out_text_parts: &mut Vec<TextPart>,
-      out_spannables: &mut Vec<Spannable>
+      post_comment: &String,
+      iterator: &dyn Fn(&Node, &String)
     ) {
-      for node in nodes {
-        match node {
-          Node::Text(text) => {
-            let unescaped_text = String::from(html_escape::decode_html_entities(text.as_str()));
-            self.detect_links(out_text_parts, &unescaped_text, out_spannables);
-
-            out_text_parts.push(TextPart::new(unescaped_text));
-          },
-          Node::Element(element) => {
-            // store the current last indexes of out_text_parts/out_spannables because we may need
-            // them during post process phase to figure out what was added into
-            // out_text_parts/out_spannables
-            let prev_out_text_parts_index = out_text_parts.len().checked_sub(1).unwrap_or(0);
-            let prev_out_spannables_index = out_spannables.len().checked_sub(1).unwrap_or(0);
-
-            if self.comment_parser.pre_process_element(post_raw, &element, out_text_parts, out_spannables) {
-              // Element was fully processed, no need to check the child elements
-              continue;
-            }
-
-            if !element.children.is_empty() {
-              self.process_element(post_raw, &element.children, out_text_parts, out_spannables);
-
-              self.comment_parser.post_process_element(
-                post_raw,
-                &element,
-                prev_out_text_parts_index,
-                out_text_parts,
-                prev_out_spannables_index,
-                out_spannables
-              )
+      let mut stack = vec![nodes.iter()];
+
+      while let Option::Some(remaining_nodes) = stack.last_mut() {
+        match remaining_nodes.next() {
+          Option::None => {
+            stack.pop();
+          }
+          Option::Some(node) => {
+            iterator(node, post_comment);
+
+            if let Node::Element(element) = node {
+              stack.push(element.children.iter());
             }
-          },
+          }
         }
       }
     }
 
-    fn iterate_element(
+    pub fn detect_links(
       &self,
-      nodes: &Vec<Node>,
-      post_comment: &String,
-      iterator: &dyn Fn(&Node, &String)
+      post_raw: &PostRaw,
+      out_text_parts: &TextParts,
+      text: &String,
+      out_spannables: &mut Vec<Spannable>
     ) {
-      for node in nodes {
-        match node {
-          Node::Text(_) => {
-            iterator(&node, post_comment);
-          },
-          Node::Element(element) => {
-            iterator(&node, post_comment);
-
-            if !element.children.is_empty() {
-              self.iterate_element(&element.children, &post_comment, &iterator);
-            }
-          },
-        }
+      let found_links = find_links(text);
+      if found_links.is_empty() {
+        return;
       }
-    }
 
-    pub fn detect_links(&self, out_text_parts: &mut Vec<TextPart>, text: &String, out_spannables: &mut Vec<Spannable>) {
-      let mut capture_locations = LINK_PATTERN.capture_locations();
-      let mut offset: usize = 0;
+      let total_text_chars_count = out_text_parts.chars_count();
 
-      let total_text_chars_count = out_text_parts
-        .iter()
-        .sum_by(&|string| string.characters_count as i32) as usize;
+      // Offsets of the found links are in bytes, spannables need them in characters
+      let mut chars_count_before_link = 0;
+      let mut prev_link_end = 0;
 
-      let text_bytes = text.as_bytes();
+      for found_link in found_links {
+        if found_link.start < prev_link_end {
+          // Overlaps with the previous link
+          continue;
+        }
 
-      loop {
-        LINK_PATTERN.captures_read_at(&mut capture_locations, text, offset);
+        chars_count_before_link += text[prev_link_end..found_link.start].chars().count();
+        let link_chars_count = found_link.text.chars().count();
 
-        let capture_maybe = capture_locations.get(0);
-        if capture_maybe.is_none() {
-          break;
+        let link_spannable = Spannable {
+          start: total_text_chars_count + chars_count_before_link,
+          len: link_chars_count,
+          spannable_data: SpannableData::Link(self.resolve_url(post_raw, found_link.link))
+        };
+
+        if link_spannable.is_valid() {
+          out_spannables.push(link_spannable);
         }
 
-        let (bytes_capture_start, bytes_capture_end) = capture_maybe.unwrap();
-        if bytes_capture_start >= bytes_capture_end || bytes_capture_end <= 0 {
-          break;
+        chars_count_before_link += link_chars_count;
+        prev_link_end = found_link.end;
+      }
+    }
+
+    fn resolve_url(&self, post_raw: &PostRaw, url: String) -> PostLink {
+      for site_url_resolver in self.site_url_resolvers.iter() {
+        if let Option::Some(post_link) = site_url_resolver.resolve(&url, post_raw, self.post_parser_context) {
+          return post_link;
         }
+      }
+
+      return PostLink::UrlLink { link: url };
+    }
 
-        let actual_link = String::from_utf8_lossy(&text_bytes[bytes_capture_start..bytes_capture_end]);
+    /// Converts ">>>/g/" text into board links
+    pub fn detect_board_links(&self, out_text_parts: &TextParts, text: &String, out_spannables: &mut Vec<Spannable>) {
+      if !text.contains(">>>/") {
+        return;
+      }
 
-        // [bytes_capture_start] and [bytes_capture_end] do not account unicode characters being
-        // present in [text]. If there are then they will be incorrect. To fix that we need to count
-        // the amount of unicode characters between [0..bytes_capture_start] and
-        // [bytes_capture_start..bytes_capture_end]. This is slow and uses memory but I have no idea how to do it
-        // correctly.
+      let total_text_chars_count = out_text_parts.chars_count();
 
-        let start = String::from_utf8_lossy(&text.as_bytes()[0..bytes_capture_start]).chars().count();
-        let len = String::from_utf8_lossy(&text.as_bytes()[bytes_capture_start..bytes_capture_end]).chars().count();
+      for captures in BOARD_LINK_TEXT_PATTERN.captures_iter(text) {
+        if !captures.get(2).unwrap().as_str().is_empty() {
+          // Cross board post quote, not a board link
+          continue;
+        }
 
-        let link_spannable = Spannable {
-          start: total_text_chars_count + start,
-          len,
-          spannable_data: SpannableData::Link(PostLink::UrlLink { link: actual_link.to_string() })
+        let whole_match = captures.get(0).unwrap();
+        let board_code = captures.get(1).unwrap().as_str();
+
+        let board_link_spannable = Spannable {
+          start: total_text_chars_count + text[..whole_match.start()].chars().count(),
+          len: whole_match.as_str().chars().count(),
+          spannable_data: SpannableData::Link(PostLink::BoardLink { board_code: String::from(board_code) })
         };
 
-        if link_spannable.is_valid() {
-          out_spannables.push(link_spannable);
+        if board_link_spannable.is_valid() {
+          out_spannables.push(board_link_spannable);
         }
-
-        offset = bytes_capture_end;
       }
     }
 
@@ -0,0 +1,106 @@
+use crate::{QuoteFormat, QuoteLabelStyle, QuoteSuffixes, CrossThreadSuffix};
+
+impl QuoteFormat {
+
+  /// ">>123" and ">>>/g/123", used by most of the sites
+  pub fn four_chan() -> QuoteFormat {
+    return QuoteFormat {
+      post_quote_prefix: String::from(">>"),
+      cross_board_quote_prefix: Option::Some(String::from(">>>/")),
+      label_style: QuoteLabelStyle::Original
+    };
+  }
+
+  /// "#123" (Futaba)
+  pub fn futaba() -> QuoteFormat {
+    return QuoteFormat {
+      post_quote_prefix: String::from("#"),
+      cross_board_quote_prefix: Option::None,
+      label_style: QuoteLabelStyle::Original
+    };
+  }
+
+  /// "№123" (some russian boards)
+  pub fn numero() -> QuoteFormat {
+    return QuoteFormat {
+      post_quote_prefix: String::from("№"),
+      cross_board_quote_prefix: Option::None,
+      label_style: QuoteLabelStyle::Original
+    };
+  }
+
+  pub fn with_label_style(mut self, label_style: QuoteLabelStyle) -> QuoteFormat {
+    self.label_style = label_style;
+    return self;
+  }
+
+  /// Returns the board code (only for cross board quotes) and the post number of a quote text.
+  /// None when the text is not a quote in this format.
+  pub fn parse_quote(&self, quote_text: &str) -> Option<(Option<String>, u64)> {
+    let quote_text = quote_text.trim();
+
+    // Cross board quotes go first since on most of the sites they start with the post quote prefix
+    if let Option::Some(cross_board_quote_prefix) = &self.cross_board_quote_prefix {
+      if let Option::Some(board_and_post) = quote_text.strip_prefix(cross_board_quote_prefix.as_str()) {
+        let mut parts = board_and_post.splitn(2, '/');
+
+        let board_code = parts.next().unwrap_or("");
+        let post_no = parts.next().and_then(|post_no| post_no.parse::<u64>().ok())?;
+
+        if board_code.is_empty() || !board_code.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+          return Option::None;
+        }
+
+        return Option::Some((Option::Some(String::from(board_code)), post_no));
+      }
+    }
+
+    let post_no = quote_text
+      .strip_prefix(self.post_quote_prefix.as_str())?
+      .parse::<u64>()
+      .ok()?;
+
+    return Option::Some((Option::None, post_no));
+  }
+
+  /// The text of a quote in the parsed comment
+  pub fn quote_label(&self, quote_text: &str, board_code: Option<&str>, post_no: u64) -> String {
+    return match self.label_style {
+      QuoteLabelStyle::Original => String::from(quote_text),
+      QuoteLabelStyle::FourChan => match board_code {
+        Option::Some(board_code) => format!(">>>/{}/{}", board_code, post_no),
+        Option::None => format!(">>{}", post_no)
+      }
+    };
+  }
+
+}
+
+impl Default for QuoteFormat {
+  fn default() -> Self {
+    return QuoteFormat::four_chan();
+  }
+}
+
+impl QuoteSuffixes {
+  /// No suffixes at all, the quote texts are kept as they are in the comment
+  pub fn none() -> QuoteSuffixes {
+    return QuoteSuffixes { op: false, you: false, dead: false, cross_thread: CrossThreadSuffix::None };
+  }
+}
+
+impl Default for QuoteSuffixes {
+  fn default() -> Self {
+    return QuoteSuffixes { op: true, you: true, dead: true, cross_thread: CrossThreadSuffix::Arrow };
+  }
+}
+
+impl CrossThreadSuffix {
+  pub fn text(&self) -> Option<&'static str> {
+    return match self {
+      CrossThreadSuffix::None => Option::None,
+      CrossThreadSuffix::Arrow => Option::Some(" →"),
+      CrossThreadSuffix::Label => Option::Some(" (Cross-thread)")
+    };
+  }
+}
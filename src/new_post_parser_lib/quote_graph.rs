@@ -0,0 +1,152 @@
+pub mod quote_graph {
+  use std::collections::{HashMap, HashSet};
+  use crate::{QuoteGraph, ParsedPost, ThreadParser};
+
+  #[derive(Clone, Copy, PartialEq)]
+  enum VisitState {
+    // On the stack of the current path
+    InProgress,
+    Done
+  }
+
+  impl ThreadParser {
+    /// See QuoteGraph. [max_depth] is how deep QuoteGraph::reply_tree() and QuoteGraph::quote_tree()
+    /// go.
+    pub fn build_quote_graph(parsed_posts: &[ParsedPost], max_depth: usize) -> QuoteGraph {
+      return QuoteGraph::new(parsed_posts, max_depth);
+    }
+  }
+
+  impl QuoteGraph {
+    pub fn new(parsed_posts: &[ParsedPost], max_depth: usize) -> QuoteGraph {
+      let mut post_nos = Vec::with_capacity(parsed_posts.len());
+      let mut replies_to: HashMap<u64, HashSet<u64>> = HashMap::with_capacity(parsed_posts.len());
+
+      for parsed_post in parsed_posts {
+        let post_no = parsed_post.post_descriptor.post_no();
+
+        post_nos.push(post_no);
+        replies_to.insert(post_no, parsed_post.replies_to.clone());
+      }
+
+      let replies_from = ThreadParser::build_replies_from(parsed_posts);
+      let cycles = find_cycles(&post_nos, &replies_to);
+
+      return QuoteGraph {
+        replies_to,
+        replies_from,
+        cycles,
+        max_depth
+      };
+    }
+
+    pub fn max_depth(&self) -> usize {
+      return self.max_depth;
+    }
+
+    /// Posts that (directly or through other replies) reply to [post_no] with their distance from it
+    /// (1 for the direct replies). Every post is listed once (with the shortest distance) so cycles
+    /// are walked only once, posts further than max_depth are not included.
+    pub fn reply_tree(&self, post_no: u64) -> Vec<(u64, usize)> {
+      return walk_breadth_first(post_no, &self.replies_from, self.max_depth);
+    }
+
+    /// Same as reply_tree() but in the other direction: the posts [post_no] (directly or through the
+    /// quoted posts) quotes
+    pub fn quote_tree(&self, post_no: u64) -> Vec<(u64, usize)> {
+      return walk_breadth_first(post_no, &self.replies_to, self.max_depth);
+    }
+
+    /// Whether [post_no] is a part of one of the detected quote cycles
+    pub fn is_in_cycle(&self, post_no: u64) -> bool {
+      return self.cycles.iter().any(|cycle| cycle.contains(&post_no));
+    }
+  }
+
+  fn walk_breadth_first(post_no: u64, edges: &HashMap<u64, HashSet<u64>>, max_depth: usize) -> Vec<(u64, usize)> {
+    let mut result = Vec::new();
+    let mut visited = HashSet::new();
+    visited.insert(post_no);
+
+    let mut current_level = vec![post_no];
+    let mut depth = 0;
+
+    while !current_level.is_empty() && depth < max_depth {
+      depth += 1;
+      let mut next_level = Vec::new();
+
+      for current in current_level {
+        for next in sorted_edges(edges, current) {
+          if visited.insert(next) {
+            result.push((next, depth));
+            next_level.push(next);
+          }
+        }
+      }
+
+      current_level = next_level;
+    }
+
+    return result;
+  }
+
+  /// Depth first search over the quotes, every quote of a post that is on the current path closes a
+  /// cycle (a post quoting itself is a cycle of one post). The cycles are listed in the quote order
+  /// starting with the post that was reached first. Only one cycle per such quote is reported, not
+  /// every possible cycle of the graph. Iterative so long quote chains can't overflow the stack.
+  fn find_cycles(post_nos: &Vec<u64>, replies_to: &HashMap<u64, HashSet<u64>>) -> Vec<Vec<u64>> {
+    let mut cycles = Vec::new();
+    let mut visit_states: HashMap<u64, VisitState> = HashMap::with_capacity(post_nos.len());
+
+    for start_post_no in post_nos {
+      if visit_states.contains_key(start_post_no) {
+        continue;
+      }
+
+      // (post_no, its quotes, index of the next quote to check)
+      let mut stack: Vec<(u64, Vec<u64>, usize)> = vec![(*start_post_no, sorted_edges(replies_to, *start_post_no), 0)];
+      visit_states.insert(*start_post_no, VisitState::InProgress);
+
+      while let Option::Some((post_no, quotes, next_index)) = stack.last_mut() {
+        if *next_index >= quotes.len() {
+          visit_states.insert(*post_no, VisitState::Done);
+          stack.pop();
+          continue;
+        }
+
+        let quoted_post_no = quotes[*next_index];
+        *next_index += 1;
+
+        match visit_states.get(&quoted_post_no) {
+          Option::Some(VisitState::Done) => {}
+          Option::Some(VisitState::InProgress) => {
+            let cycle_start = stack.iter()
+              .position(|(stack_post_no, _, _)| *stack_post_no == quoted_post_no)
+              .unwrap();
+
+            cycles.push(stack[cycle_start..].iter().map(|(stack_post_no, _, _)| *stack_post_no).collect());
+          }
+          Option::None => {
+            // Posts of other threads or deleted posts have no quotes of their own
+            if replies_to.contains_key(&quoted_post_no) {
+              visit_states.insert(quoted_post_no, VisitState::InProgress);
+              stack.push((quoted_post_no, sorted_edges(replies_to, quoted_post_no), 0));
+            }
+          }
+        }
+      }
+    }
+
+    return cycles;
+  }
+
+  // Sorted so that the results don't depend on the HashSet order
+  fn sorted_edges(edges: &HashMap<u64, HashSet<u64>>, post_no: u64) -> Vec<u64> {
+    let mut result = edges.get(&post_no)
+      .map(|post_nos| post_nos.iter().cloned().collect::<Vec<u64>>())
+      .unwrap_or_default();
+
+    result.sort();
+    return result;
+  }
+}
@@ -1,5 +1,4 @@
-use crate::rules::rule_handler::RuleHandler;
-use crate::{PostRaw, PostParserContext, Element, TextPart, Spannable};
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
 
 const TAG: &str = "AbbrHandler";
 
@@ -13,28 +12,8 @@ impl AbbrHandler {
 
 impl RuleHandler for AbbrHandler {
 
-  fn pre_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: &mut Vec<TextPart>,
-    _: &mut Vec<Spannable>
-  ) -> bool {
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
     return true;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: usize,
-    _: &mut Vec<TextPart>,
-    _: usize,
-    _: &mut Vec<Spannable>
-  ) {
-    // no-op
-  }
-
 }
\ No newline at end of file
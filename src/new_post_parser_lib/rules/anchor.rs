@@ -1,13 +1,13 @@
-use crate::rules::rule_handler::RuleHandler;
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
 use crate::html_parser::node::Node;
 use crate::parsing_error::ParsingError;
 use regex::Regex;
-use crate::{PostRaw, PostParserContext, Element, Spannable, PostLink, SpannableData, TextPart};
-use crate::util::helpers::SumBy;
+use crate::{PostRaw, PostParserContext, Element, Spannable, PostLink, SpannableData, TextPart, TextParts, QuoteFormat, ParserWarning};
+use crate::parser_warnings::report_warning;
 
 const TAG: &str = "AnchorRuleHandler";
 const HREF: &str = "href";
-const CROSS_THREAD_POSTFIX: &str = " →";
+const MAILTO_SCHEME: &str = "mailto:";
 const OP_POSTFIX: &str = " (OP)";
 const ME_POSTFIX: &str = " (Me)";
 const YOU_POSTFIX: &str = " (You)";
@@ -15,120 +15,143 @@ const DEAD_POSTFIX: &str = " (DEAD)";
 
 lazy_static! {
   static ref BOARD_LINK_PATTERN: Regex = Regex::new(r"//.*/(\w+)/$").unwrap();
-  static ref BOARD_LINK_WITH_SEARCH_PATTERN: Regex = Regex::new(r"//.*/(\w+)/catalog#s=(\w+)$").unwrap();
+  static ref BOARD_LINK_WITH_SEARCH_PATTERN: Regex = Regex::new(r"//.*/(\w+)/catalog#s=([^#&]+)$").unwrap();
   static ref CROSS_THREAD_LINK_PATTERN: Regex = Regex::new(r"/(\w+)/\w+/(\d+)#p(\d+)$").unwrap();
+  static ref THREAD_LINK_PATTERN: Regex = Regex::new(r"/(\w+)/thread/(\d+)(?:/[^/#]*)?$").unwrap();
+  static ref VICHAN_THREAD_LINK_PATTERN: Regex = Regex::new(r"/(\w+)/res/(\d+)\.html#q?(\d+)$").unwrap();
 }
 
-pub struct AnchorRuleHandler {}
+pub struct AnchorRuleHandler {
+  quote_format: QuoteFormat
+}
 
 impl AnchorRuleHandler {
   pub fn new() -> AnchorRuleHandler {
-    return AnchorRuleHandler {};
+    return AnchorRuleHandler::with_quote_format(QuoteFormat::default());
+  }
+
+  /// [quote_format] decides how the labels of the post quotes are rendered
+  pub fn with_quote_format(quote_format: QuoteFormat) -> AnchorRuleHandler {
+    return AnchorRuleHandler { quote_format };
   }
 }
 
 impl RuleHandler for AnchorRuleHandler {
-  fn pre_handle(
-    &self,
-    post_raw: &PostRaw,
-    post_parser_context: &PostParserContext,
-    element: &Element,
-    out_text_parts: &mut Vec<TextPart>,
-    out_spannables: &mut Vec<Spannable>
-  ) -> bool {
-    if element.children.len() != 1 {
-      eprintln!("{} element.children.len() != 1, len={}", TAG, element.children.len() > 1);
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    if ctx.element.children.len() != 1 {
+      report_warning(
+        ParserWarning::new(TAG, format!("element.children.len() != 1, len={}", ctx.element.children.len()))
+          .with_element(ctx.element)
+          .with_position_after(ctx.out_text_parts)
+      );
       return false;
     }
 
-    let link_text_child = element.children.first().unwrap();
+    let link_text_child = ctx.element.children.first().unwrap();
     match link_text_child {
       Node::Text(text) => {
-        handle_href_attr(element, post_raw, post_parser_context, out_text_parts, out_spannables, text)
+        handle_href_attr(ctx.element, ctx.post_raw, ctx.post_parser_context, &self.quote_format, ctx.out_text_parts, ctx.out_spannables, text)
       },
       Node::Element(element) => {
-        eprintln!("{} UNKNOWN TAG: tag_name=<a>, element={}", TAG, element)
+        // Let the children be processed by their own handlers so that the visible text is not lost
+        report_warning(
+          ParserWarning::new(TAG, format!("unexpected <{}> inside <a> tag", element.tag_name))
+            .with_element(element)
+            .with_position_after(ctx.out_text_parts)
+        );
+        return false;
+      }
+      Node::Comment(_) => {
+        report_warning(
+          ParserWarning::new(TAG, String::from("unexpected comment node inside <a> tag"))
+            .with_element(ctx.element)
+            .with_position_after(ctx.out_text_parts)
+        );
       }
     }
 
     return true;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: usize,
-    _: &mut Vec<TextPart>,
-    _: usize,
-    _: &mut Vec<Spannable>
-  ) {
-    // no-op
-  }
-
 }
 
 fn handle_href_attr<'a>(
   element: &Element,
   post_raw: &PostRaw,
   post_parser_context: &PostParserContext,
-  out_text_parts: &mut Vec<TextPart>,
+  quote_format: &QuoteFormat,
+  out_text_parts: &mut TextParts,
   out_spannables: &mut Vec<Spannable>,
   text: &String
 ) {
-  let href_value_maybe = element.attributes.get(HREF);
+  let href_value_maybe = element.get_attr_value(HREF);
   if href_value_maybe.is_none() {
-    eprintln!("{} <a> tag has no \"{}\" attribute", TAG, HREF);
+    report_warning(
+      ParserWarning::new(TAG, format!("<a> tag has no \"{}\" attribute", HREF))
+        .with_element(element)
+        .with_position_after(out_text_parts)
+    );
     return;
   }
 
   let link_raw = href_value_maybe.unwrap();
-  let post_link_result = link_raw_to_post_link(post_parser_context, &link_raw);
+  let post_link_result = link_raw_to_post_link(post_raw, post_parser_context, &link_raw);
 
   match post_link_result {
     Err(err) => {
-      eprintln!("{} Failed to convert quoteRaw=\"{}\" into postNo, err={}", TAG, link_raw, err);
+      report_warning(
+        ParserWarning::new(TAG, format!("Failed to convert quoteRaw=\"{}\" into postNo, err={}", link_raw, err))
+          .with_element(element)
+          .with_position_after(out_text_parts)
+      );
     }
     Ok(post_link) => {
-      let unescaped_text = String::from(html_escape::decode_html_entities(text));
-      let total_text_length = out_text_parts.iter().sum_by(&|string| string.characters_count as i32) as usize;
+      let unescaped_text = text.clone();
+      let total_text_length = out_text_parts.chars_count();
 
       match &post_link {
-        PostLink::Quote { .. } | PostLink::Dead { .. } => {
+        PostLink::Quote { .. } | PostLink::Dead { .. } | PostLink::DeadCrossBoard { .. } => {
+          let unescaped_text = match &post_link {
+            PostLink::Quote { post_no, .. } | PostLink::Dead { post_no } => {
+              quote_format.quote_label(&unescaped_text, Option::None, *post_no)
+            }
+            PostLink::DeadCrossBoard { board_code, post_no } => {
+              quote_format.quote_label(&unescaped_text, Option::Some(board_code), *post_no)
+            }
+            _ => unescaped_text
+          };
+
           handle_single_post_quote(
             post_raw,
             post_parser_context,
             out_text_parts,
             out_spannables,
             post_link,
-            &unescaped_text,
+            unescaped_text,
             total_text_length
           );
         },
         PostLink::UrlLink { .. } |
+        PostLink::Email { .. } |
         PostLink::BoardLink { .. } |
         PostLink::SearchLink  { .. } |
         PostLink::ThreadLink { .. } => {
-          let result_text = if let PostLink::ThreadLink { .. } = post_link {
-            String::from(unescaped_text + CROSS_THREAD_POSTFIX)
-          } else {
-            unescaped_text
-          };
+          let first_text_part_index = out_text_parts.len();
+          out_text_parts.push(TextPart::new(unescaped_text));
 
-          let result_text_part = TextPart::new(result_text);
+          if let PostLink::ThreadLink { .. } = post_link {
+            push_cross_thread_suffix(post_parser_context, out_text_parts);
+          }
 
           let spannable = Spannable {
             start: total_text_length,
-            len: result_text_part.characters_count,
+            len: out_text_parts.chars_from(first_text_part_index),
             spannable_data: SpannableData::Link(post_link)
           };
 
           if spannable.is_valid() {
             out_spannables.push(spannable);
           }
-
-          out_text_parts.push(result_text_part);
         }
       }
     }
@@ -138,16 +161,18 @@ fn handle_href_attr<'a>(
 pub fn handle_single_post_quote(
   post_raw: &PostRaw,
   post_parser_context: &PostParserContext,
-  out_text_parts: &mut Vec<TextPart>,
+  out_text_parts: &mut TextParts,
   out_spannables: &mut Vec<Spannable>,
   post_link: PostLink,
-  unescaped_text: &String,
+  unescaped_text: String,
   span_start: usize
 ) {
   let quote_post_id = match post_link {
-    PostLink::Quote { post_no } => post_no,
+    PostLink::Quote { post_no, .. } => post_no,
     PostLink::Dead { post_no } => post_no,
+    PostLink::DeadCrossBoard { post_no, .. } => post_no,
     wrong_post_link@ PostLink::UrlLink {..} |
+    wrong_post_link@ PostLink::Email {..} |
     wrong_post_link@ PostLink::BoardLink {..} |
     wrong_post_link@ PostLink::SearchLink {..} |
     wrong_post_link@ PostLink::ThreadLink {..} => {
@@ -157,8 +182,9 @@ pub fn handle_single_post_quote(
 
   let is_dead = match post_link {
     PostLink::Quote { .. } => false,
-    PostLink::Dead { .. } => true,
+    PostLink::Dead { .. } | PostLink::DeadCrossBoard { .. } => true,
     wrong_post_link@ PostLink::UrlLink {..} |
+    wrong_post_link@ PostLink::Email {..} |
     wrong_post_link@ PostLink::BoardLink {..} |
     wrong_post_link@ PostLink::SearchLink {..} |
     wrong_post_link@ PostLink::ThreadLink {..} => {
@@ -166,53 +192,90 @@ pub fn handle_single_post_quote(
     }
   };
 
-  let mut quote_text_suffixes = String::new();
+  // Suffixes are the same for every quote so they are stored as separate static text parts instead
+  // of being appended to the quote text
+  let first_text_part_index = out_text_parts.len();
+  out_text_parts.push(TextPart::new(unescaped_text));
 
-  if post_raw.is_quoting_original_post(quote_post_id) {
-    quote_text_suffixes.push_str(OP_POSTFIX);
-  }
+  // Post numbers of other boards have nothing to do with the posts of the current thread
+  let is_cross_board = match post_link {
+    PostLink::DeadCrossBoard { .. } => true,
+    _ => false
+  };
 
-  if post_parser_context.is_my_reply_to_my_own_post(post_raw.post_no(), quote_post_id) {
-    quote_text_suffixes.push_str(ME_POSTFIX);
-  } else if post_parser_context.is_reply_to_my_post(quote_post_id) {
-    quote_text_suffixes.push_str(YOU_POSTFIX);
-  }
+  let quote_suffixes = post_parser_context.quote_suffixes();
+
+  if !is_cross_board {
+    if quote_suffixes.op && post_raw.is_quoting_original_post(quote_post_id) {
+      out_text_parts.push(TextPart::from_static(OP_POSTFIX));
+    }
 
-  if is_dead {
-    quote_text_suffixes.push_str(DEAD_POSTFIX);
+    if quote_suffixes.you {
+      if post_parser_context.is_my_reply_to_my_own_post(post_raw.post_no(), quote_post_id) {
+        out_text_parts.push(TextPart::from_static(ME_POSTFIX));
+      } else if post_parser_context.is_reply_to_my_post(quote_post_id) {
+        out_text_parts.push(TextPart::from_static(YOU_POSTFIX));
+      }
+    }
   }
 
-  let quote_text_result = format!("{}{}", String::from(unescaped_text), quote_text_suffixes);
+  if quote_suffixes.dead && is_dead {
+    out_text_parts.push(TextPart::from_static(DEAD_POSTFIX));
+  }
 
   let spannable = Spannable {
     start: span_start,
-    len: quote_text_result.len(),
+    len: out_text_parts.chars_from(first_text_part_index),
     spannable_data: SpannableData::Link(post_link)
   };
 
   if spannable.is_valid() {
     out_spannables.push(spannable);
   }
+}
 
-  out_text_parts.push(TextPart::new(quote_text_result));
+/// " →" (or whatever PostParserContext::quote_suffixes() says) after the text of a link into another
+/// thread
+pub(crate) fn push_cross_thread_suffix(post_parser_context: &PostParserContext, out_text_parts: &mut TextParts) {
+  if let Option::Some(suffix) = post_parser_context.quote_suffixes().cross_thread.text() {
+    out_text_parts.push(TextPart::from_static(suffix));
+  }
 }
 
 fn link_raw_to_post_link(
+  post_raw: &PostRaw,
   post_parser_context: &PostParserContext,
   link_raw: &str
 ) -> Result<PostLink, ParsingError> {
   if link_raw.starts_with("#p") {
     // Normal in-thread post quote: "#p333790203"
     let quote_str = &link_raw[2..];
-    let post_no = quote_str.parse::<u64>().unwrap();
+    let post_no = match quote_str.parse::<u64>() {
+      Ok(post_no) => post_no,
+      Err(_) => return Result::Err(ParsingError::new(&format!("Bad post quote: {}", link_raw)))
+    };
 
     return if post_parser_context.is_internal_thread_post(post_no) {
-      Result::Ok(PostLink::Quote { post_no })
+      Result::Ok(PostLink::quote(post_raw, post_parser_context, post_no))
     } else {
       Result::Ok(PostLink::Dead { post_no })
     }
   }
 
+  let has_mailto_scheme = link_raw.len() > MAILTO_SCHEME.len() && link_raw.get(..MAILTO_SCHEME.len())
+    .map(|scheme| scheme.eq_ignore_ascii_case(MAILTO_SCHEME))
+    .unwrap_or(false);
+
+  if has_mailto_scheme {
+    // Email link: "mailto:sage" or "mailto:anon@example.com?subject=hi"
+    let email = link_raw[MAILTO_SCHEME.len()..].split('?').next().unwrap_or("");
+    if !email.is_empty() {
+      return Result::Ok(PostLink::Email { email: String::from(email) });
+    }
+
+    // Fallthrough
+  }
+
   if link_raw.starts_with("//") {
     // Board link: "//boards.4channel.org/jp/"
     let board_link_captures_maybe = BOARD_LINK_PATTERN.captures(link_raw);
@@ -293,9 +356,66 @@ fn link_raw_to_post_link(
     }
   }
 
-  let thread_link = PostLink::UrlLink {
+  // Thread link without a post: "/g/thread/81404563" or "/g/thread/81404563/thread-subject"
+  let thread_link_captures_maybe = THREAD_LINK_PATTERN.captures(link_raw);
+  if thread_link_captures_maybe.is_some() {
+    let captures = thread_link_captures_maybe.unwrap();
+
+    let board_code = captures.get(1).unwrap().as_str();
+    let thread_no_result = captures.get(2).unwrap().as_str().parse::<u64>();
+
+    if thread_no_result.is_ok() {
+      let thread_no = thread_no_result.unwrap();
+
+      let thread_link = PostLink::ThreadLink {
+        board_code: String::from(board_code),
+        thread_no,
+        post_no: thread_no
+      };
+
+      return Result::Ok(thread_link);
+    }
+
+    // Fallthrough
+  }
+
+  // vichan link: "/tech/res/100.html#123", used for both in-thread and cross-thread quotes
+  let vichan_thread_link_captures_maybe = VICHAN_THREAD_LINK_PATTERN.captures(link_raw);
+  if vichan_thread_link_captures_maybe.is_some() {
+    let captures = vichan_thread_link_captures_maybe.unwrap();
+
+    let board_code = captures.get(1).unwrap().as_str();
+    let thread_no_result = captures.get(2).unwrap().as_str().parse::<u64>();
+    let post_no_result = captures.get(3).unwrap().as_str().parse::<u64>();
+
+    if thread_no_result.is_ok() && post_no_result.is_ok() {
+      let thread_no = thread_no_result.unwrap();
+      let post_no = post_no_result.unwrap();
+
+      if board_code == post_raw.board_code() && thread_no == post_raw.thread_no() {
+        return if post_parser_context.is_internal_thread_post(post_no) {
+          Result::Ok(PostLink::quote(post_raw, post_parser_context, post_no))
+        } else {
+          Result::Ok(PostLink::Dead { post_no })
+        }
+      }
+
+      let thread_link = PostLink::ThreadLink {
+        board_code: String::from(board_code),
+        thread_no,
+        post_no
+      };
+
+      return Result::Ok(thread_link);
+    }
+
+    // Fallthrough
+  }
+
+  // Everything else (http(s), tel:, ftp: etc) is passed through as is
+  let url_link = PostLink::UrlLink {
     link: String::from(link_raw)
   };
 
-  return Result::Ok(thread_link);
+  return Result::Ok(url_link);
 }
\ No newline at end of file
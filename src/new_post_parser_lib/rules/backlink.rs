@@ -0,0 +1,104 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::rules::anchor::{handle_single_post_quote, push_cross_thread_suffix};
+use crate::{PostRaw, PostParserContext, Element, Spannable, PostLink, SpannableData, TextPart, ParserWarning};
+use crate::parser_warnings::report_warning;
+use regex::Regex;
+
+const TAG: &str = "BacklinkHandler";
+
+lazy_static! {
+  // "https://desuarchive.org/g/thread/123/#456", "/g/thread/123#p456"
+  static ref BACKLINK_THREAD_PATTERN: Regex = Regex::new(r"/(\w+)/thread/(\d+)").unwrap();
+  static ref BACKLINK_POST_PATTERN: Regex = Regex::new(r"#p?(\d+)$").unwrap();
+}
+
+/// FoolFuuka (and Fuuka) archive quotes:
+/// <a href="https://desuarchive.org/g/thread/123/#456" class="backlink" data-board="g" data-post="456">&gt;&gt;456</a>
+/// The hrefs point to the archive itself so they can't be handled by AnchorRuleHandler. The board
+/// and the post come from the data- attributes (or from the href when they are missing).
+pub struct BacklinkHandler {}
+
+impl BacklinkHandler {
+  pub fn new() -> BacklinkHandler {
+    return BacklinkHandler {};
+  }
+
+  fn to_post_link(&self, post_raw: &PostRaw, post_parser_context: &PostParserContext, element: &Element) -> Option<PostLink> {
+    let href = element.attr_or("href", "");
+    let thread_captures = BACKLINK_THREAD_PATTERN.captures(href);
+
+    let post_no = match element.attr("data-post") {
+      Option::Some(post_no) => post_no.parse::<u64>().ok()?,
+      Option::None => BACKLINK_POST_PATTERN.captures(href)?.get(1).unwrap().as_str().parse::<u64>().ok()?
+    };
+
+    let board_code = element.attr("data-board")
+      .or_else(|| thread_captures.as_ref().map(|captures| captures.get(1).unwrap().as_str()))
+      .unwrap_or(post_raw.board_code().as_str());
+
+    let thread_no = match thread_captures.as_ref() {
+      Option::Some(captures) => Option::Some(captures.get(2).unwrap().as_str().parse::<u64>().ok()?),
+      Option::None => Option::None
+    };
+
+    let is_same_board = board_code == post_raw.board_code();
+    let is_same_thread = is_same_board && thread_no.map(|thread_no| thread_no == post_raw.thread_no()).unwrap_or(true);
+
+    if is_same_thread || (is_same_board && post_parser_context.is_internal_thread_post(post_no)) {
+      return if post_parser_context.is_internal_thread_post(post_no) {
+        Option::Some(PostLink::quote(post_raw, post_parser_context, post_no))
+      } else {
+        Option::Some(PostLink::Dead { post_no })
+      };
+    }
+
+    return match thread_no {
+      Option::Some(thread_no) => Option::Some(PostLink::ThreadLink { board_code: String::from(board_code), thread_no, post_no }),
+      // Another board and the thread is unknown, can only be looked up in the archives
+      Option::None => Option::Some(PostLink::DeadCrossBoard { board_code: String::from(board_code), post_no })
+    };
+  }
+}
+
+impl RuleHandler for BacklinkHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    let post_link = match self.to_post_link(ctx.post_raw, ctx.post_parser_context, ctx.element) {
+      Option::Some(post_link) => post_link,
+      Option::None => {
+        report_warning(
+          ParserWarning::new(TAG, String::from("failed to extract the quoted post"))
+            .with_element(ctx.element)
+            .with_position_after(ctx.out_text_parts)
+        );
+        // Let AnchorRuleHandler process it
+        return false;
+      }
+    };
+
+    let text = ctx.element.collect_text();
+    let total_text_length = ctx.text_len();
+
+    if let PostLink::ThreadLink { .. } = post_link {
+      let first_text_part_index = ctx.out_text_parts.len();
+      ctx.out_text_parts.push(TextPart::new(text));
+      push_cross_thread_suffix(ctx.post_parser_context, ctx.out_text_parts);
+
+      let spannable = Spannable {
+        start: total_text_length,
+        len: ctx.out_text_parts.chars_from(first_text_part_index),
+        spannable_data: SpannableData::Link(post_link)
+      };
+
+      if spannable.is_valid() {
+        ctx.out_spannables.push(spannable);
+      }
+
+      return true;
+    }
+
+    handle_single_post_quote(ctx.post_raw, ctx.post_parser_context, ctx.out_text_parts, ctx.out_spannables, post_link, text, total_text_length);
+    return true;
+  }
+
+}
@@ -0,0 +1,64 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Element, Spannable, TextPart, SpannableData};
+use regex::Regex;
+
+const TAG: &str = "BanMessageHandler";
+
+lazy_static! {
+  // "(USER WAS BANNED FOR THIS POST)", "(USER WAS WARNED FOR THIS POST)", "(User was banned for this post. Reason: ...)"
+  static ref BAN_MESSAGE_PATTERN: Regex = Regex::new(r"(?i)^\s*\(\s*user\s+was\s+(?:perma)?(?:banned|warned)\b[^)]*\)\s*$").unwrap();
+}
+
+/// Ban/warn messages the mods append to the post:
+///
+/// <strong style="color: red;">(USER WAS BANNED FOR THIS POST)</strong> (4chan, older posts use <b>)
+/// <strong class="warning">(USER WAS BANNED FOR THIS POST)</strong>
+/// <span class="public_ban">(USER WAS BANNED FOR THIS POST)</span> (vichan)
+///
+/// The text is emitted as is and covered by a single BanMessage spannable (instead of the bold text
+/// and the color of the markup).
+pub struct BanMessageHandler {}
+
+impl BanMessageHandler {
+  pub fn new() -> BanMessageHandler {
+    return BanMessageHandler {};
+  }
+}
+
+/// vichan marks the message with a class, everything else is recognized by the text
+pub(crate) fn is_ban_message(element: &Element) -> bool {
+  if element.has_any_class(&["public_ban", "public_warning"]) {
+    return true;
+  }
+
+  return BAN_MESSAGE_PATTERN.is_match(&element.collect_text());
+}
+
+impl RuleHandler for BanMessageHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    if !is_ban_message(ctx.element) {
+      return false;
+    }
+
+    let text = ctx.element.collect_text();
+    let text = text.trim();
+
+    let start = ctx.text_len();
+
+    let spannable = Spannable {
+      start,
+      len: text.chars().count(),
+      spannable_data: SpannableData::BanMessage
+    };
+
+    ctx.out_text_parts.push(TextPart::new(String::from(text)));
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+
+    return true;
+  }
+
+}
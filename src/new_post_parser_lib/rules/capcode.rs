@@ -0,0 +1,82 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData, CapcodeKind};
+
+const TAG: &str = "CapcodeHandler";
+
+/// Class of the inline capcode span and the capcode it stands for
+pub const CAPCODE_CLASSES: &[(&str, &str, CapcodeKind)] = &[
+  ("capcodeAdmin", "Admin", CapcodeKind::Admin),
+  ("capcodeMod", "Mod", CapcodeKind::Mod),
+  ("capcodeDeveloper", "Developer", CapcodeKind::Developer),
+  ("capcodeFounder", "Founder", CapcodeKind::Founder)
+];
+
+impl CapcodeKind {
+  /// "admin", "admin_highlight", "Mod" etc of the post json or of the poster info html
+  pub fn from_capcode(capcode: &str) -> CapcodeKind {
+    let capcode = capcode.trim().trim_start_matches('#').trim().to_ascii_lowercase();
+
+    return match capcode.trim_end_matches("_highlight") {
+      "admin" | "administrator" => CapcodeKind::Admin,
+      "mod" | "moderator" => CapcodeKind::Mod,
+      "developer" | "dev" => CapcodeKind::Developer,
+      "founder" => CapcodeKind::Founder,
+      _ => CapcodeKind::Other
+    };
+  }
+}
+
+/// Capcodes the staff put into the comment itself:
+///
+/// <span class="capcodeAdmin">Posting as staff</span>
+///
+/// The text of the span is covered by a Capcode spannable.
+pub struct CapcodeHandler {
+  capcode: &'static str,
+  kind: CapcodeKind
+}
+
+impl CapcodeHandler {
+  pub fn new(capcode: &'static str, kind: CapcodeKind) -> CapcodeHandler {
+    return CapcodeHandler { capcode, kind };
+  }
+}
+
+impl RuleHandler for CapcodeHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::Capcode { capcode: String::from(self.capcode), kind: self.kind }
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
+
+#[test]
+fn test_capcode_kind_from_capcode() {
+  assert_eq!(CapcodeKind::Admin, CapcodeKind::from_capcode("admin_highlight"));
+  assert_eq!(CapcodeKind::Mod, CapcodeKind::from_capcode("## Mod"));
+  assert_eq!(CapcodeKind::Developer, CapcodeKind::from_capcode("developer"));
+  assert_eq!(CapcodeKind::Founder, CapcodeKind::from_capcode("Founder"));
+  assert_eq!(CapcodeKind::Other, CapcodeKind::from_capcode("manager"));
+}
@@ -0,0 +1,65 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData};
+
+const TAG: &str = "CodeBlockHandler";
+
+/// Handles both code blocks (<pre>, <pre class="prettyprint"> on 4chan) and inline code (<code>).
+/// Text inside of them is kept as is (including whitespaces and line breaks).
+pub struct CodeBlockHandler {
+  is_inline: bool
+}
+
+impl CodeBlockHandler {
+  pub fn new_block() -> CodeBlockHandler {
+    return CodeBlockHandler { is_inline: false };
+  }
+
+  pub fn new_inline() -> CodeBlockHandler {
+    return CodeBlockHandler { is_inline: true };
+  }
+}
+
+impl RuleHandler for CodeBlockHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+    let len = ctx.new_text_len();
+
+    let spannable_data = if self.is_inline {
+      SpannableData::InlineCode
+    } else {
+      // <pre><code>...</code></pre> is a code block, not a block with inline code inside of it
+      let mut index = ctx.prev_out_spannables_index;
+      while index < ctx.out_spannables.len() {
+        if ctx.out_spannables[index].spannable_data == SpannableData::InlineCode {
+          ctx.out_spannables.remove(index);
+        } else {
+          index += 1;
+        }
+      }
+
+      SpannableData::CodeBlock
+    };
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
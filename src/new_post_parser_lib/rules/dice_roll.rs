@@ -0,0 +1,94 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData, PostRoll};
+use regex::Regex;
+
+lazy_static! {
+  // "Rolled 5, 3 = 8", "Rolled 5, 3 = 8 (2d6)", "Rolled 2d6: 5, 3 = 8"
+  static ref DICE_ROLL_PATTERN: Regex = Regex::new(r"^\s*Rolled\s+(?:(\d+)d(\d+):\s*)?(\d+(?:\s*,\s*\d+)*)\s*=\s*(-?\d+)(?:\s*\((\d+)d(\d+)[^)]*\))?\s*$").unwrap();
+}
+
+/// <b>Rolled 5, 3 = 8</b> dice rolls (/qst/, /tg/). Other <b> elements are ignored.
+pub struct DiceRollHandler {}
+
+impl DiceRollHandler {
+  pub fn new() -> DiceRollHandler {
+    return DiceRollHandler {};
+  }
+}
+
+impl RuleHandler for DiceRollHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let text = ctx.new_text();
+
+    let post_roll = match parse_dice_roll(&text) {
+      None => return,
+      Some(post_roll) => post_roll
+    };
+
+    let start = ctx.new_text_start();
+
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::DiceRoll(post_roll)
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
+
+pub fn parse_dice_roll(text: &str) -> Option<PostRoll> {
+  let captures = DICE_ROLL_PATTERN.captures(text)?;
+
+  let results = captures.get(3)?.as_str()
+    .split(',')
+    .map(|result| result.trim().parse::<u32>())
+    .collect::<Result<Vec<u32>, _>>()
+    .ok()?;
+
+  let total = captures.get(4)?.as_str().parse::<i64>().ok()?;
+
+  let dice_and_sides = captures.get(1).zip(captures.get(2))
+    .or_else(|| captures.get(5).zip(captures.get(6)));
+
+  let (dice, sides) = match dice_and_sides {
+    None => (results.len() as u32, Option::None),
+    Some((dice, sides)) => (dice.as_str().parse::<u32>().ok()?, Option::Some(sides.as_str().parse::<u32>().ok()?))
+  };
+
+  return Option::Some(PostRoll { dice, sides, results, total });
+}
+
+#[test]
+fn test_parse_dice_roll() {
+  assert_eq!(
+    Option::Some(PostRoll { dice: 2, sides: Option::None, results: vec![5, 3], total: 8 }),
+    parse_dice_roll("Rolled 5, 3 = 8")
+  );
+  assert_eq!(
+    Option::Some(PostRoll { dice: 2, sides: Option::Some(6), results: vec![5, 3], total: 8 }),
+    parse_dice_roll("Rolled 5, 3 = 8 (2d6)")
+  );
+  assert_eq!(
+    Option::Some(PostRoll { dice: 1, sides: Option::Some(20), results: vec![17], total: 19 }),
+    parse_dice_roll("Rolled 1d20: 17 = 19")
+  );
+  assert_eq!(Option::None, parse_dice_roll("Rolled a car"));
+  assert_eq!(Option::None, parse_dice_roll("(USER WAS BANNED FOR THIS POST)"));
+}
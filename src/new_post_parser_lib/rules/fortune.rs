@@ -0,0 +1,64 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData, ParserWarning};
+use crate::parser_warnings::report_warning;
+use crate::util::color_decoder::color_name_to_color_hex;
+
+const TAG: &str = "FortuneHandler";
+const DEFAULT_FORTUNE_COLOR: &str = "#000000";
+
+/// <span class="fortune" style="color:#fd4d32"><br><br><b>Your fortune: Bad Luck</b></span> (/s4s/)
+pub struct FortuneHandler {}
+
+impl FortuneHandler {
+  pub fn new() -> FortuneHandler {
+    return FortuneHandler {};
+  }
+}
+
+impl RuleHandler for FortuneHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+
+    let color = ctx.element.parsed_style()
+      .get("color")
+      .and_then(|color| {
+        if color.starts_with('#') {
+          return Option::Some(color.clone());
+        }
+
+        return color_name_to_color_hex(color);
+      })
+      .unwrap_or_else(|| {
+        report_warning(
+          ParserWarning::new(TAG, String::from("fortune without a color"))
+            .with_element(ctx.element)
+            .with_position(start)
+        );
+        return String::from(DEFAULT_FORTUNE_COLOR);
+      });
+
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::Fortune { color }
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
@@ -0,0 +1,55 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx, ends_with_new_line};
+use crate::{TextPart, Spannable, SpannableData};
+
+const TAG: &str = "HeadingHandler";
+
+/// Handles <h1>-<h6>. Headings always occupy their own line.
+pub struct HeadingHandler {
+  level: u8
+}
+
+impl HeadingHandler {
+  pub fn new(level: u8) -> HeadingHandler {
+    return HeadingHandler { level };
+  }
+}
+
+impl RuleHandler for HeadingHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    if !ends_with_new_line(ctx.out_text_parts) {
+      ctx.out_text_parts.push(TextPart::from_static("\n"));
+    }
+
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    // The new line that was added by pre_handle() is not a part of the heading
+    let mut first_text_part_index = ctx.prev_out_text_parts_index;
+    if first_text_part_index < ctx.out_text_parts.len() && ctx.out_text_parts[first_text_part_index].text == "\n" {
+      first_text_part_index += 1;
+    }
+
+    if first_text_part_index == ctx.out_text_parts.len() {
+      // Empty heading
+      return;
+    }
+
+    let start = ctx.out_text_parts.chars_before(first_text_part_index);
+    let len = ctx.out_text_parts.chars_from(first_text_part_index);
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::Heading { level: self.level }
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+
+    ctx.out_text_parts.push(TextPart::from_static("\n"));
+  }
+
+}
@@ -0,0 +1,41 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData};
+
+const TAG: &str = "ItalicHandler";
+
+pub struct ItalicHandler {}
+
+impl ItalicHandler {
+  pub fn new() -> ItalicHandler {
+    return ItalicHandler {};
+  }
+}
+
+impl RuleHandler for ItalicHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::Italic
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
\ No newline at end of file
@@ -1,5 +1,5 @@
-use crate::rules::rule_handler::RuleHandler;
-use crate::{PostRaw, PostParserContext, Element, Spannable, TextPart};
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::TextPart;
 
 pub struct LineBreakRuleHandler {}
 
@@ -11,29 +11,9 @@ impl LineBreakRuleHandler {
 
 impl RuleHandler for LineBreakRuleHandler {
 
-  fn pre_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    out_text_parts: &mut Vec<TextPart>,
-    _: &mut Vec<Spannable>
-  ) -> bool {
-    out_text_parts.push(TextPart::new(String::from('\n')));
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    ctx.out_text_parts.push(TextPart::from_static("\n"));
     return true;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: usize,
-    _: &mut Vec<TextPart>,
-    _: usize,
-    _: &mut Vec<Spannable>
-  ) {
-    // no-op
-  }
-
 }
\ No newline at end of file
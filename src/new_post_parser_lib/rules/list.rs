@@ -0,0 +1,138 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx, ends_with_new_line};
+use crate::{TextPart, Spannable, SpannableData};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const TAG: &str = "ListHandler";
+const START_ATTR: &str = "start";
+const BULLET: &str = "• ";
+
+pub struct ListState {
+  ordered: bool,
+  // Index of the current <li> of this list
+  index: usize
+}
+
+/// Lists that are currently being processed. Shared between ListHandler and ListItemHandler since
+/// <li> needs to know what kind of list it belongs to and how deep that list is.
+pub type ListStack = Rc<RefCell<Vec<ListState>>>;
+
+pub fn new_list_stack() -> ListStack {
+  return Rc::new(RefCell::new(Vec::with_capacity(4)));
+}
+
+/// Handles <ul> and <ol>
+pub struct ListHandler {
+  ordered: bool,
+  list_stack: ListStack
+}
+
+impl ListHandler {
+  pub fn new(ordered: bool, list_stack: ListStack) -> ListHandler {
+    return ListHandler { ordered, list_stack };
+  }
+}
+
+impl RuleHandler for ListHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    if ctx.element.children.is_empty() {
+      // post_handle() is not called for elements without children
+      return true;
+    }
+
+    let start = ctx.element.attr(START_ATTR)
+      .and_then(|start| start.trim().parse::<usize>().ok())
+      .unwrap_or(1);
+
+    self.list_stack.borrow_mut().push(ListState {
+      ordered: self.ordered,
+      index: start.saturating_sub(1)
+    });
+
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    self.list_stack.borrow_mut().pop();
+
+    // Whatever follows the list starts on a new line
+    if !ends_with_new_line(ctx.out_text_parts) {
+      ctx.out_text_parts.push(TextPart::from_static("\n"));
+    }
+  }
+
+}
+
+/// Handles <li>. Every item starts on a new line and is prefixed with either a bullet or its number.
+/// <li> outside of a list is treated as an item of an unordered list.
+pub struct ListItemHandler {
+  list_stack: ListStack
+}
+
+impl ListItemHandler {
+  pub fn new(list_stack: ListStack) -> ListItemHandler {
+    return ListItemHandler { list_stack };
+  }
+}
+
+impl RuleHandler for ListItemHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    if !ends_with_new_line(ctx.out_text_parts) {
+      ctx.out_text_parts.push(TextPart::from_static("\n"));
+    }
+
+    let mut list_stack = self.list_stack.borrow_mut();
+
+    match list_stack.last_mut() {
+      None => ctx.out_text_parts.push(TextPart::from_static(BULLET)),
+      Some(list_state) => {
+        list_state.index += 1;
+
+        if list_state.ordered {
+          ctx.out_text_parts.push(TextPart::new(format!("{}. ", list_state.index)));
+        } else {
+          ctx.out_text_parts.push(TextPart::from_static(BULLET));
+        }
+      }
+    }
+
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    // Neither the new line that was added by pre_handle() nor the one that was added after a nested
+    // list are a part of the item
+    let mut first_text_part_index = ctx.prev_out_text_parts_index;
+    if first_text_part_index < ctx.out_text_parts.len() && ctx.out_text_parts[first_text_part_index].text == "\n" {
+      first_text_part_index += 1;
+    }
+
+    let mut last_text_part_index = ctx.out_text_parts.len();
+    if last_text_part_index > first_text_part_index && ctx.out_text_parts[last_text_part_index - 1].text == "\n" {
+      last_text_part_index -= 1;
+    }
+
+    let start = ctx.out_text_parts.chars_before(first_text_part_index);
+    let len = ctx.out_text_parts.chars_before(last_text_part_index) - start;
+
+    let list_stack = self.list_stack.borrow();
+
+    let (depth, ordered, index) = match list_stack.last() {
+      None => (1, false, 1),
+      Some(list_state) => (list_stack.len(), list_state.ordered, list_state.index)
+    };
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::ListItem { depth, ordered, index }
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
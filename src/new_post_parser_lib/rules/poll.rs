@@ -0,0 +1,231 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::html_parser::node::Node;
+use crate::{Element, Spannable, TextPart, SpannableData, PostPoll, ParserWarning};
+use crate::parser_warnings::report_warning;
+use regex::Regex;
+
+const TAG: &str = "PollHandler";
+// The poll widget is drawn by the client in place of this character
+const POLL_PLACEHOLDER: &str = "\u{FFFC}";
+
+lazy_static! {
+  // "Rei (12 votes)", "Rei: 12", "Rei - 12 votes", "Rei [12]"
+  static ref OPTION_WITH_VOTES_PATTERN: Regex = Regex::new(r"^(.*?)[\s:\-(\[]+(\d+)\s*(?:votes?)?[)\]]?$").unwrap();
+}
+
+/// <div class="poll"> of the altchans that embed polls into the OP:
+///
+/// <div class="poll">
+///   <div class="poll-question">Best girl?</div>
+///   <ul>
+///     <li class="poll-option"><span class="poll-option-text">Rei</span> <span class="poll-votes">12</span></li>
+///     <li>Asuka (7 votes)</li>
+///   </ul>
+/// </div>
+///
+/// The markup is replaced with a single placeholder character covered by a Poll spannable.
+pub struct PollHandler {}
+
+impl PollHandler {
+  pub fn new() -> PollHandler {
+    return PollHandler {};
+  }
+}
+
+impl RuleHandler for PollHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    let post_poll = match parse_poll(ctx.element) {
+      None => {
+        // Let the children be processed as regular text so that nothing is lost
+        report_warning(
+          ParserWarning::new(TAG, String::from("poll without options"))
+            .with_element(ctx.element)
+            .with_position_after(ctx.out_text_parts)
+        );
+        return false;
+      }
+      Some(post_poll) => post_poll
+    };
+
+    let start = ctx.text_len();
+
+    ctx.out_text_parts.push(TextPart::from_static(POLL_PLACEHOLDER));
+
+    ctx.out_spannables.push(Spannable {
+      start,
+      len: 1,
+      spannable_data: SpannableData::Poll(post_poll)
+    });
+
+    return true;
+  }
+
+}
+
+/// None when the element has no poll options
+pub fn parse_poll(element: &Element) -> Option<PostPoll> {
+  let mut question = Option::None;
+  let mut options = Vec::new();
+
+  collect_poll(element, &mut question, &mut options);
+
+  if options.is_empty() {
+    return Option::None;
+  }
+
+  return Option::Some(PostPoll { question: question.unwrap_or_default(), options });
+}
+
+fn collect_poll(element: &Element, question: &mut Option<String>, options: &mut Vec<(String, u64)>) {
+  let mut stack = vec![element.children.iter()];
+
+  while let Option::Some(children) = stack.last_mut() {
+    let child = match children.next() {
+      Option::None => {
+        stack.pop();
+        continue;
+      }
+      Option::Some(Node::Element(child)) => child,
+      Option::Some(_) => continue
+    };
+
+    if is_option(child) {
+      if let Some(option) = parse_option(child) {
+        options.push(option);
+      }
+
+      continue;
+    }
+
+    if question.is_none() && (is_question(child) || (options.is_empty() && is_heading(child))) {
+      let text = normalize_text(&child.collect_text());
+      if !text.is_empty() {
+        *question = Option::Some(text);
+      }
+
+      continue;
+    }
+
+    stack.push(child.children.iter());
+  }
+}
+
+fn parse_option(element: &Element) -> Option<(String, u64)> {
+  let votes_text = find_descendant(element, &is_votes).map(|votes| votes.collect_text());
+  let label_text = find_descendant(element, &is_label).map(|label| label.collect_text());
+
+  if let Some(votes_text) = votes_text {
+    let votes = votes_text.chars()
+      .filter(|ch| ch.is_ascii_digit())
+      .collect::<String>()
+      .parse::<u64>()
+      .unwrap_or(0);
+
+    let label = match label_text {
+      Some(label_text) => normalize_text(&label_text),
+      None => normalize_text(&element.collect_text().replacen(&votes_text, "", 1))
+    };
+
+    if label.is_empty() {
+      return Option::None;
+    }
+
+    return Option::Some((label, votes));
+  }
+
+  let text = normalize_text(&label_text.unwrap_or_else(|| element.collect_text()));
+  if text.is_empty() {
+    return Option::None;
+  }
+
+  return match OPTION_WITH_VOTES_PATTERN.captures(&text) {
+    Some(captures) if !captures[1].is_empty() => {
+      let votes = captures[2].parse::<u64>().unwrap_or(0);
+      Option::Some((captures[1].to_string(), votes))
+    }
+    _ => Option::Some((text, 0))
+  };
+}
+
+fn find_descendant<'a>(element: &'a Element, predicate: &dyn Fn(&Element) -> bool) -> Option<&'a Element> {
+  let mut stack = vec![element.children.iter()];
+
+  while let Option::Some(children) = stack.last_mut() {
+    match children.next() {
+      Option::None => {
+        stack.pop();
+      }
+      Option::Some(Node::Element(child)) => {
+        if predicate(child) {
+          return Option::Some(child);
+        }
+
+        stack.push(child.children.iter());
+      }
+      Option::Some(_) => {}
+    }
+  }
+
+  return Option::None;
+}
+
+fn is_option(element: &Element) -> bool {
+  // "poll-options" is the container of the options, not an option
+  return element.tag_name == "li"
+    || has_class_ending_with(element, &["option", "answer", "choice"]);
+}
+
+fn is_question(element: &Element) -> bool {
+  return has_class_containing(element, &["question", "title"]);
+}
+
+fn is_heading(element: &Element) -> bool {
+  return match element.tag_name.as_ref() {
+    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "b" | "strong" | "legend" => true,
+    _ => false
+  };
+}
+
+fn is_votes(element: &Element) -> bool {
+  return has_class_containing(element, &["vote", "count", "result"]);
+}
+
+fn is_label(element: &Element) -> bool {
+  return has_class_containing(element, &["text", "label", "name"]);
+}
+
+fn has_class_containing(element: &Element, parts: &[&str]) -> bool {
+  return element.classes()
+    .any(|class| {
+      let class = class.to_ascii_lowercase();
+      return parts.iter().any(|part| class.contains(part));
+    });
+}
+
+fn has_class_ending_with(element: &Element, suffixes: &[&str]) -> bool {
+  return element.classes()
+    .any(|class| {
+      let class = class.to_ascii_lowercase();
+      return suffixes.iter().any(|suffix| class.ends_with(suffix));
+    });
+}
+
+fn normalize_text(text: &str) -> String {
+  return text.split_whitespace().collect::<Vec<&str>>().join(" ");
+}
+
+#[test]
+fn test_parse_option_text() {
+  let option = |text: &str| {
+    let mut element = Element::new("li", false);
+    element.children.push(Node::Text(String::from(text)));
+    return element;
+  };
+
+  assert_eq!(Option::Some((String::from("Rei"), 12)), parse_option(&option("Rei (12 votes)")));
+  assert_eq!(Option::Some((String::from("Asuka"), 7)), parse_option(&option("Asuka: 7")));
+  assert_eq!(Option::Some((String::from("Misato"), 1)), parse_option(&option(" Misato - 1 vote ")));
+  assert_eq!(Option::Some((String::from("Nobody"), 0)), parse_option(&option("Nobody")));
+  assert_eq!(Option::None, parse_option(&option("  ")));
+}
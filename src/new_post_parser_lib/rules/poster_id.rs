@@ -0,0 +1,53 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData, PosterId};
+
+const TAG: &str = "PosterIdHandler";
+
+/// Poster ids some sites put into the comment (quoted posts, mod notes):
+///
+/// <span class="posteruid id_XYZ">(ID: <span class="hand">XYZ</span>)</span>
+///
+/// The text is kept and covered by a PosterId spannable so the client can color it like the id of
+/// the post header (PosterId::color()).
+pub struct PosterIdHandler {}
+
+impl PosterIdHandler {
+  pub fn new() -> PosterIdHandler {
+    return PosterIdHandler {};
+  }
+}
+
+impl RuleHandler for PosterIdHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let poster_id = match PosterId::of_element(ctx.element) {
+      None => return,
+      Some(poster_id) => poster_id
+    };
+
+    let start = ctx.new_text_start();
+
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::PosterId(poster_id)
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
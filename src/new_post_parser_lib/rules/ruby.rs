@@ -0,0 +1,97 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::html_parser::node::Node;
+use crate::{Spannable, TextPart, SpannableData, RubyMode};
+
+const RT_TAG: &str = "rt";
+const RP_TAG: &str = "rp";
+
+/// <ruby>漢<rp>(</rp><rt>kan</rt><rp>)</rp>字<rt>ji</rt></ruby> furigana (Futaba, /jp/). Every base
+/// text gets a Ruby spannable with its reading, whether the reading stays in the text is decided by
+/// the RubyMode. <rp> fallback parentheses are dropped.
+pub struct RubyHandler {
+  ruby_mode: RubyMode
+}
+
+impl RubyHandler {
+  pub fn new(ruby_mode: RubyMode) -> RubyHandler {
+    return RubyHandler { ruby_mode };
+  }
+}
+
+impl RuleHandler for RubyHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    let element = ctx.element;
+
+    let has_readings = element.children.iter().any(|child| {
+      return match child {
+        Node::Element(child) => child.tag_name == RT_TAG,
+        _ => false
+      };
+    });
+
+    if !has_readings {
+      return false;
+    }
+
+    let mut base = String::new();
+
+    for child in element.children.iter() {
+      match child {
+        Node::Text(text) => base.push_str(text),
+        Node::Element(child) if child.tag_name == RP_TAG => {}
+        Node::Element(child) if child.tag_name == RT_TAG => {
+          let reading = child.collect_text();
+          self.push_ruby(&base, reading.trim(), ctx);
+          base.clear();
+        }
+        // <rb> and formatting of the base text
+        Node::Element(child) => base.push_str(&child.collect_text()),
+        Node::Comment(_) => {}
+      }
+    }
+
+    // Trailing base text without a reading
+    if !base.is_empty() {
+      ctx.out_text_parts.push(TextPart::new(base));
+    }
+
+    return true;
+  }
+
+}
+
+impl RubyHandler {
+
+  fn push_ruby(&self, base: &str, reading: &str, ctx: &mut HandlerCtx) {
+    if base.is_empty() {
+      // Nothing to annotate, keep the reading as a plain text
+      if self.ruby_mode == RubyMode::Parenthesized && !reading.is_empty() {
+        ctx.out_text_parts.push(TextPart::new(format!("({})", reading)));
+      }
+
+      return;
+    }
+
+    let start = ctx.text_len();
+
+    let base_text_part = TextPart::new(String::from(base));
+    let len = base_text_part.characters_count();
+    ctx.out_text_parts.push(base_text_part);
+
+    if reading.is_empty() {
+      return;
+    }
+
+    if self.ruby_mode == RubyMode::Parenthesized {
+      ctx.out_text_parts.push(TextPart::new(format!("({})", reading)));
+    }
+
+    ctx.out_spannables.push(Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::Ruby { reading: String::from(reading) }
+    });
+  }
+
+}
@@ -1,58 +1,100 @@
-use crate::{PostRaw, PostParserContext, Element, Spannable, TextPart};
-use crate::util::helpers::{SumBy, MapJoin};
+use crate::{PostRaw, PostParserContext, Element, Spannable, TextParts};
+use crate::util::helpers::MapJoin;
 
 pub trait RuleHandler {
 
-  fn pre_handle(
-    &self,
-    post_raw: &PostRaw,
-    post_parser_context: &PostParserContext,
-    element: &Element,
-    out_text_parts: &mut Vec<TextPart>,
-    out_spannables: &mut Vec<Spannable>
-  ) -> bool;
-
-  fn post_handle(
-    &self,
-    post_raw: &PostRaw,
-    post_parser_context: &PostParserContext,
-    element: &Element,
-    prev_out_text_parts_index: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    prev_out_spannables_index: usize,
-    out_spannables: &mut Vec<Spannable>
-  );
+  /// Called before the child nodes of the element are processed. Returns true when the element was
+  /// fully handled and its child nodes must be skipped.
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool;
+
+  /// Called after the child nodes of the element were all processed, the text they produced starts
+  /// at ctx.prev_out_text_parts_index.
+  fn post_handle(&self, _: &mut HandlerCtx) {
+    // no-op
+  }
+
+  /// The spannables added in post_handle() wrap the spannables of the child nodes (a spoiler around
+  /// a quote link). They are moved in front of the children's ones so that the outer spannable goes
+  /// first, spannables covering the same text keep this order when sorted.
+  fn wraps_child_spannables(&self) -> bool {
+    return false;
+  }
 
 }
 
-pub trait RuleHandlerPostHandleMeta {
-  fn get_out_text_parts_diff_text(&self, prev_out_text_parts_index: usize, out_text_parts: &Vec<TextPart>) -> String;
-  fn get_out_text_parts_diff_len(&self, prev_out_text_parts_index: usize, out_text_parts: &Vec<TextPart>) -> i32;
-  fn get_out_text_parts_new_len(&self, prev_out_text_parts_index: usize, out_text_parts: &Vec<TextPart>) -> i32;
+/// Everything a RuleHandler gets to work with: the post, the parser context, the element that is
+/// being handled and the output buffers the text parts and the spannables are added to.
+pub struct HandlerCtx<'a> {
+  pub post_raw: &'a PostRaw,
+  pub post_parser_context: &'a PostParserContext,
+  pub element: &'a Element,
+  pub out_text_parts: &'a mut TextParts,
+  pub out_spannables: &'a mut Vec<Spannable>,
+  /// out_text_parts.len() at the moment the element was entered. In post_handle() everything after
+  /// it was produced by the child nodes, in pre_handle() it's the same as out_text_parts.len().
+  pub prev_out_text_parts_index: usize,
+  /// Same as prev_out_text_parts_index but for out_spannables
+  pub prev_out_spannables_index: usize
 }
 
-impl RuleHandlerPostHandleMeta for dyn RuleHandler {
+impl<'a> HandlerCtx<'a> {
+  pub fn new(
+    post_raw: &'a PostRaw,
+    post_parser_context: &'a PostParserContext,
+    element: &'a Element,
+    prev_out_text_parts_index: usize,
+    out_text_parts: &'a mut TextParts,
+    prev_out_spannables_index: usize,
+    out_spannables: &'a mut Vec<Spannable>
+  ) -> HandlerCtx<'a> {
+    return HandlerCtx {
+      post_raw,
+      post_parser_context,
+      element,
+      out_text_parts,
+      out_spannables,
+      prev_out_text_parts_index,
+      prev_out_spannables_index
+    };
+  }
+
+  /// Length (in chars) of all the text produced so far, where the next text part will start
+  pub fn text_len(&self) -> usize {
+    return self.out_text_parts.chars_count();
+  }
 
-  fn get_out_text_parts_diff_text(&self, prev_out_text_parts_index: usize, out_text_parts: &Vec<TextPart>) -> String {
-    return out_text_parts[prev_out_text_parts_index..]
-      .iter()
-      .map_join(&|text_part| text_part.text.as_str());
+  /// Whether the child nodes of the element produced any text parts
+  pub fn has_new_text(&self) -> bool {
+    return self.prev_out_text_parts_index < self.out_text_parts.len();
   }
 
-  fn get_out_text_parts_diff_len(&self, prev_out_text_parts_index: usize, out_text_parts: &Vec<TextPart>) -> i32 {
-    if prev_out_text_parts_index <= 0 {
-      return 0;
-    }
+  /// Where the text produced by the child nodes of the element starts
+  pub fn new_text_start(&self) -> usize {
+    return self.out_text_parts.chars_before(self.prev_out_text_parts_index);
+  }
 
-    return out_text_parts[0..prev_out_text_parts_index]
-      .iter()
-      .sum_by(&|string| string.characters_count as i32);
+  /// Length of the text produced by the child nodes of the element
+  pub fn new_text_len(&self) -> usize {
+    return self.out_text_parts.chars_from(self.prev_out_text_parts_index);
   }
 
-  fn get_out_text_parts_new_len(&self, prev_out_text_parts_index: usize, out_text_parts: &Vec<TextPart>) -> i32 {
-    return out_text_parts[prev_out_text_parts_index..]
+  /// The text produced by the child nodes of the element
+  pub fn new_text(&self) -> String {
+    return self.out_text_parts[self.prev_out_text_parts_index..]
       .iter()
-      .sum_by(&|string| string.characters_count as i32);
+      .map_join(&|text_part| text_part.text.as_ref());
   }
 
-}
\ No newline at end of file
+}
+
+/// Whether the text that was produced so far ends with a line break (or there is no text at all)
+pub fn ends_with_new_line(out_text_parts: &TextParts) -> bool {
+  let last_text_part = out_text_parts.iter()
+    .rev()
+    .find(|text_part| text_part.characters_count > 0);
+
+  return match last_text_part {
+    None => true,
+    Some(text_part) => text_part.text.ends_with('\n')
+  };
+}
@@ -0,0 +1,46 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData};
+
+const TAG: &str = "SimpleSpanHandler";
+
+/// Marks the whole text of an element with [spannable_data]. Used for markup that differs between
+/// the sites only by tag/class names (<span class="spoiler">, <span class="unkfunc"> etc).
+pub struct SimpleSpanHandler {
+  spannable_data: SpannableData
+}
+
+impl SimpleSpanHandler {
+  pub fn new(spannable_data: SpannableData) -> SimpleSpanHandler {
+    return SimpleSpanHandler { spannable_data };
+  }
+}
+
+impl RuleHandler for SimpleSpanHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: self.spannable_data.clone()
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
@@ -0,0 +1,43 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData};
+
+const TAG: &str = "SjisArtHandler";
+
+/// Handles <span class="sjis">. The text of the art is kept as is, the client is expected to render
+/// it with a font that has the same glyph widths as the MS PGothic.
+pub struct SjisArtHandler {}
+
+impl SjisArtHandler {
+  pub fn new() -> SjisArtHandler {
+    return SjisArtHandler {};
+  }
+}
+
+impl RuleHandler for SjisArtHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::SjisArt
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+}
\ No newline at end of file
@@ -1,156 +1,151 @@
-use crate::rules::rule_handler::{RuleHandler, RuleHandlerPostHandleMeta};
-use crate::{PostRaw, PostParserContext, Element, Spannable, PostLink, SpannableData, TextPart};
-use crate::util::helpers::SumBy;
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, PostLink, SpannableData, QuoteFormat, ParserWarning};
+use crate::parser_warnings::report_warning;
 use crate::html_parser::node::Node;
 use crate::rules::anchor::handle_single_post_quote;
 
-const TAG: &str = "SpanHandler";
+const TAG: &str = "DeadLinkHandler";
 
-pub struct SpanHandler {}
-
-impl RuleHandler for SpanHandler {
-
-  fn pre_handle(
-    &self,
-    post_raw: &PostRaw,
-    post_parser_context: &PostParserContext,
-    element: &Element,
-    out_text_parts: &mut Vec<TextPart>,
-    out_spannables: &mut Vec<Spannable>
-  ) -> bool {
-    if element.has_class("deadlink") {
-      // dead post quote
-      return self.handle_deadlink_class(post_raw, post_parser_context, element, out_text_parts, out_spannables);
-    }
+/// Handles <span class="deadlink"> (quotes of the deleted posts). Goes before GreenTextHandler (see
+/// ParsingRules::add_default_rules()) so that <span class="quote deadlink"> is a dead quote and not
+/// a greentext.
+pub struct DeadLinkHandler {
+  quote_format: QuoteFormat
+}
 
-    return false;
+impl DeadLinkHandler {
+  pub fn new() -> DeadLinkHandler {
+    return DeadLinkHandler::with_quote_format(QuoteFormat::default());
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    element: &Element,
-    prev_out_text_parts_index: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    _: usize,
-    out_spannables: &mut Vec<Spannable>
-  ) {
-    if prev_out_text_parts_index == out_text_parts.len() {
-      // Nothing was added since handle() call. This probably means that the current tag has an empty
-      // body.
-      return;
-    }
-
-    if element.has_class("quote") {
-      // greentext
-      self.handle_quote_class(prev_out_text_parts_index, out_text_parts, out_spannables)
-    }
-
-    if element.has_class("deadlink") {
-      // handled in pre_handled()
-      return;
-    }
+  /// [quote_format] is how the dead quotes are written
+  pub fn with_quote_format(quote_format: QuoteFormat) -> DeadLinkHandler {
+    return DeadLinkHandler { quote_format };
   }
-
 }
 
-impl SpanHandler {
-  pub fn new() -> SpanHandler {
-    return SpanHandler {};
-  }
+impl RuleHandler for DeadLinkHandler {
+
+  fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+    let element = ctx.element;
 
-  fn handle_deadlink_class(
-    &self,
-    post_raw: &PostRaw,
-    post_parser_context: &PostParserContext,
-    element: &Element,
-    out_text_parts: &mut Vec<TextPart>,
-    out_spannables: &mut Vec<Spannable>
-  ) -> bool {
     if element.children.len() > 1 {
-      eprintln!("{} element.children.len() != 1, len={}", TAG, element.children.len() > 1);
+      report_warning(
+        ParserWarning::new(TAG, format!("element.children.len() != 1, len={}", element.children.len()))
+          .with_element(element)
+          .with_position_after(ctx.out_text_parts)
+      );
       return true;
     }
 
-    let quote_text_child_node = element.children.first().unwrap();
+    let quote_text_child_node = match element.children.first() {
+      Option::Some(quote_text_child_node) => quote_text_child_node,
+      // <span class="deadlink"></span>
+      Option::None => return true
+    };
 
     let quote_text_child = match quote_text_child_node {
       Node::Text(link_text_child_node_text) => {
-        String::from(html_escape::decode_html_entities(&link_text_child_node_text))
+        link_text_child_node_text.clone()
+      }
+      Node::Element(child_element) => {
+        report_warning(
+          ParserWarning::new(TAG, format!("unexpected <{}> node, expected Node::Text", child_element.tag_name))
+            .with_element(element)
+            .with_position_after(ctx.out_text_parts)
+        );
+        return true;
       }
-      Node::Element(element) => {
-        eprintln!("{} unexpected node: {}, expected Node::Text", TAG, element);
+      Node::Comment(text) => {
+        report_warning(
+          ParserWarning::new(TAG, format!("unexpected comment node: {}, expected Node::Text", text))
+            .with_element(element)
+            .with_position_after(ctx.out_text_parts)
+        );
         return true;
       }
     };
 
-    if quote_text_child.starts_with(">>") {
-      let quote_text = &quote_text_child[2..];
-      let quote_value_result = quote_text.parse::<u64>();
+    let (board_code, post_no) = match self.quote_format.parse_quote(&quote_text_child) {
+      Option::Some(parsed_quote) => parsed_quote,
+      Option::None => {
+        report_warning(
+          ParserWarning::new(TAG, format!("Failed to parse link_text_child ({})", quote_text_child))
+            .with_element(element)
+            .with_position_after(ctx.out_text_parts)
+        );
+        // Let the text be processed as a regular text
+        return false;
+      }
+    };
+
+    let quote_label = self.quote_format.quote_label(&quote_text_child, board_code.as_deref(), post_no);
 
-      let quote_value = match quote_value_result {
-        Ok(value) => value,
-        Err(_) => {
-          eprintln!("{} failed to convert quote_text: {} into u64", TAG, quote_text);
-          return true;
+    let post_link = match board_code {
+      // Dead cross board quote: ">>>/a/12345"
+      Option::Some(board_code) => PostLink::DeadCrossBoard { board_code, post_no },
+      Option::None => {
+        if ctx.post_parser_context.is_internal_thread_post(post_no) {
+          PostLink::quote(ctx.post_raw, ctx.post_parser_context, post_no)
+        } else {
+          PostLink::Dead { post_no }
         }
-      };
-
-      let post_link = if post_parser_context.is_internal_thread_post(quote_value) {
-        PostLink::Quote { post_no: quote_value }
-      } else {
-        PostLink::Dead { post_no: quote_value }
-      };
-
-      let total_text_length = out_text_parts
-        .iter()
-        .sum_by(&|string| string.characters_count as i32) as usize;
-
-      handle_single_post_quote(
-        post_raw,
-        post_parser_context,
-        out_text_parts,
-        out_spannables,
-        post_link,
-        &quote_text_child,
-        total_text_length
-      );
+      }
+    };
 
-      return true;
-    }
+    let total_text_length = ctx.text_len();
+
+    handle_single_post_quote(
+      ctx.post_raw,
+      ctx.post_parser_context,
+      ctx.out_text_parts,
+      ctx.out_spannables,
+      post_link,
+      quote_label,
+      total_text_length
+    );
 
-    eprintln!("{} Failed to parse link_text_child ({})", TAG, quote_text_child);
     return true;
   }
 
-  fn handle_quote_class(
-    &self,
-    prev_out_text_parts_index: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    out_spannables: &mut Vec<Spannable>
-  ) {
-    let start = (self as &dyn RuleHandler).get_out_text_parts_diff_len(
-      prev_out_text_parts_index,
-      &out_text_parts
-    ) as usize;
-
-    let len = (self as &dyn RuleHandler).get_out_text_parts_new_len(
-      prev_out_text_parts_index,
-      &out_text_parts
-    ) as usize;
+}
+
+/// Handles <span class="quote"> (greentext)
+pub struct GreenTextHandler {}
+
+impl GreenTextHandler {
+  pub fn new() -> GreenTextHandler {
+    return GreenTextHandler {};
+  }
+}
+
+impl RuleHandler for GreenTextHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    let start = ctx.new_text_start();
+    let len = ctx.new_text_len();
 
     let spannable = Spannable {
       start,
       len,
-      // TODO: maybe I could include the children tags' text length here too so in case when
-      //  there is any kind of link inside green text we could mark that link as green too.
-      //  Right now it may not work.
+      // Covers the text of the children too so links inside of the quote are nested in the
+      // greentext, crossing spannables are resolved in normalize_spannables()
       spannable_data: SpannableData::GreenText
     };
 
     if spannable.is_valid() {
-      out_spannables.push(spannable);
+      ctx.out_spannables.push(spannable);
     }
   }
+
 }
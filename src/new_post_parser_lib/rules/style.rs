@@ -1,5 +1,5 @@
-use crate::rules::rule_handler::{RuleHandler, RuleHandlerPostHandleMeta};
-use crate::{PostRaw, PostParserContext, Element, Spannable, TextPart};
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::Spannable;
 use crate::util::style_tag_value_decoder::decode_style_spans;
 
 const TAG: &str = "StyleHandler";
@@ -14,34 +14,18 @@ impl StyleHandler {
 
 impl RuleHandler for StyleHandler {
 
-  fn pre_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: &mut Vec<TextPart>,
-    _: &mut Vec<Spannable>
-  ) -> bool {
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
     return false;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    element: &Element,
-    prev_out_text_parts_index: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    _: usize,
-    out_spannables: &mut Vec<Spannable>
-  ) {
-    if prev_out_text_parts_index == out_text_parts.len() {
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
       // Nothing was added since handle() call. This probably means that the current tag has an empty
       // body.
       return;
     }
 
-    let style_attr_value_maybe = element.get_attr_value("style");
+    let style_attr_value_maybe = ctx.element.get_attr_value("style");
 
     let style_attr_value = if let Option::None = style_attr_value_maybe {
       return;
@@ -49,15 +33,9 @@ impl RuleHandler for StyleHandler {
       style_attr_value_maybe.unwrap()
     };
 
-    let start = (self as &dyn RuleHandler).get_out_text_parts_diff_len(
-      prev_out_text_parts_index,
-      &out_text_parts
-    ) as usize;
+    let start = ctx.new_text_start();
 
-    let len = (self as &dyn RuleHandler).get_out_text_parts_new_len(
-      prev_out_text_parts_index,
-      &out_text_parts
-    ) as usize;
+    let len = ctx.new_text_len();
 
     let spannables = decode_style_spans(style_attr_value)
       .iter()
@@ -71,7 +49,7 @@ impl RuleHandler for StyleHandler {
 
     for spannable in spannables {
       if spannable.is_valid() {
-        out_spannables.push(spannable);
+        ctx.out_spannables.push(spannable);
       }
     }
   }
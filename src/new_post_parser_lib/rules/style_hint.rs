@@ -0,0 +1,58 @@
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData, ParserWarning};
+use crate::parser_warnings::report_warning;
+use crate::util::style_hint_decoder::{decode_style_hint, has_style_hint_source};
+
+const TAG: &str = "StyleHintHandler";
+
+/// Emits a StyleHint spannable for elements with an inline style or a known class (e.g. <span
+/// class="fortune">) that map to client theme colors or font flags, the tag of such element adds to
+/// the hint (<b style="color:red"> is bold). Elements without any known styling produce nothing.
+pub struct StyleHintHandler {}
+
+impl StyleHintHandler {
+  pub fn new() -> StyleHintHandler {
+    return StyleHintHandler {};
+  }
+}
+
+impl RuleHandler for StyleHintHandler {
+
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+    return false;
+  }
+
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    if !ctx.has_new_text() {
+      // Nothing was added since handle() call. This probably means that the current tag has an empty
+      // body.
+      return;
+    }
+
+    if !has_style_hint_source(ctx.element) {
+      return;
+    }
+
+    let style_hint = decode_style_hint(ctx.element);
+    if style_hint.is_empty() {
+      return;
+    }
+
+    let start = ctx.new_text_start();
+
+    let len = ctx.new_text_len();
+
+    let spannable = Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::StyleHint(style_hint)
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    } else {
+      report_warning(ParserWarning::new(TAG, String::from("invalid spannable")).with_element(ctx.element).with_position(start));
+    }
+  }
+
+}
@@ -1,5 +1,5 @@
-use crate::rules::rule_handler::{RuleHandler, RuleHandlerPostHandleMeta};
-use crate::{PostRaw, PostParserContext, Element, TextPart, Spannable, SpannableData};
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{Spannable, SpannableData};
 
 pub struct TableHandler {}
 
@@ -11,36 +11,13 @@ impl TableHandler {
 
 impl RuleHandler for TableHandler {
 
-  fn pre_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: &mut Vec<TextPart>,
-    _: &mut Vec<Spannable>
-  ) -> bool {
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
     return false;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    prev_out_text_parts_index: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    _: usize,
-    out_spannables: &mut Vec<Spannable>
-  ) {
-    let start = (self as &dyn RuleHandler).get_out_text_parts_diff_len(
-      prev_out_text_parts_index,
-      &out_text_parts
-    ) as usize;
-
-    let len = (self as &dyn RuleHandler).get_out_text_parts_new_len(
-      prev_out_text_parts_index,
-      &out_text_parts
-    ) as usize;
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    let start = ctx.new_text_start();
+    let len = ctx.new_text_len();
 
     let spannable = Spannable {
       start,
@@ -49,7 +26,7 @@ impl RuleHandler for TableHandler {
     };
 
     if spannable.is_valid() {
-      out_spannables.push(spannable);
+      ctx.out_spannables.push(spannable);
     }
   }
 
@@ -1,5 +1,8 @@
-use crate::rules::rule_handler::{RuleHandler, RuleHandlerPostHandleMeta};
-use crate::{PostRaw, PostParserContext, Element, TextPart, Spannable};
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::{TextPart, Spannable, SpannableData};
+
+/// Goes after every cell. The one after the last cell of a row is removed by TableRowHandler.
+pub const TABLE_CELL_SEPARATOR: &str = " | ";
 
 pub struct TableDataHandler {}
 
@@ -11,31 +14,12 @@ impl TableDataHandler {
 
 impl RuleHandler for TableDataHandler {
 
-  fn pre_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: &mut Vec<TextPart>,
-    _: &mut Vec<Spannable>
-  ) -> bool {
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
     return false;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    prev_out_text_parts_index: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    _: usize,
-    _: &mut Vec<Spannable>
-  ) {
-    let text = (self as &dyn RuleHandler).get_out_text_parts_diff_text(
-      prev_out_text_parts_index,
-      &out_text_parts
-    );
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    let text = ctx.new_text();
 
     let mut only_contains_whitespaces = true;
 
@@ -50,7 +34,19 @@ impl RuleHandler for TableDataHandler {
       return;
     }
 
-    out_text_parts.push(TextPart::new(String::from(' ')));
+    let start = ctx.new_text_start();
+
+    let spannable = Spannable {
+      start,
+      len: text.chars().count(),
+      spannable_data: SpannableData::TableCell
+    };
+
+    if spannable.is_valid() {
+      ctx.out_spannables.push(spannable);
+    }
+
+    ctx.out_text_parts.push(TextPart::from_static(TABLE_CELL_SEPARATOR));
   }
 
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
-use crate::rules::rule_handler::RuleHandler;
-use crate::{PostRaw, PostParserContext, Element, TextPart, Spannable};
+use crate::rules::rule_handler::{RuleHandler, HandlerCtx};
+use crate::TextPart;
+use crate::rules::table_data::TABLE_CELL_SEPARATOR;
 
 pub struct TableRowHandler {}
 
@@ -11,28 +12,20 @@ impl TableRowHandler {
 
 impl RuleHandler for TableRowHandler {
 
-  fn pre_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: &mut Vec<TextPart>,
-    _: &mut Vec<Spannable>
-  ) -> bool {
+  fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
     return false;
   }
 
-  fn post_handle(
-    &self,
-    _: &PostRaw,
-    _: &PostParserContext,
-    _: &Element,
-    _: usize,
-    out_text_parts: &mut Vec<TextPart>,
-    _: usize,
-    _: &mut Vec<Spannable>
-  ) {
-    out_text_parts.push(TextPart::new(String::from('\n')));
+  fn post_handle(&self, ctx: &mut HandlerCtx) {
+    let ends_with_separator = ctx.out_text_parts.last()
+      .map(|text_part| text_part.text == TABLE_CELL_SEPARATOR)
+      .unwrap_or(false);
+
+    if ends_with_separator {
+      ctx.out_text_parts.pop();
+    }
+
+    ctx.out_text_parts.push(TextPart::from_static("\n"));
   }
 
 }
\ No newline at end of file
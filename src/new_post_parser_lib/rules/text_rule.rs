@@ -0,0 +1,105 @@
+use crate::{TextRule, TextRuleOutput, Spannable, SpannableData};
+use crate::util::text_cleanup::remove_text_ranges;
+use regex::{Regex, Captures};
+
+impl TextRule {
+  /// The handler decides what spannables every match gets (and whether the match is replaced with
+  /// one of its groups)
+  pub fn new(pattern: Regex, handler: Box<dyn Fn(&Captures) -> TextRuleOutput>) -> TextRule {
+    return TextRule { pattern, handler };
+  }
+
+  /// Declarative rule: the match is replaced with the text of the capture group which gets a
+  /// spannable_data spannable. E.g. (r"\[spoiler\](.+?)\[/spoiler\]", 1, Spoiler) turns
+  /// "[spoiler]text[/spoiler]" into a spoilered "text". Group 0 keeps the whole match.
+  pub fn template(pattern: Regex, group: usize, spannable_data: SpannableData) -> TextRule {
+    return TextRule::new(pattern, Box::new(move |_| {
+      return TextRuleOutput {
+        spans: vec![(group, spannable_data.clone())],
+        keep_group: if group == 0 { Option::None } else { Option::Some(group) }
+      };
+    }));
+  }
+}
+
+/// Applies the text rules one after another (every rule sees the text produced by the previous one).
+/// Returns the new text if any of the rules removed something. Spannable offsets are in chars.
+pub fn apply_text_rules(text: &str, text_rules: &Vec<std::rc::Rc<TextRule>>, out_spannables: &mut Vec<Spannable>) -> Option<String> {
+  let mut current_text: Option<String> = Option::None;
+
+  for text_rule in text_rules {
+    let text = current_text.as_deref().unwrap_or(text);
+
+    let mut new_spannables: Vec<Spannable> = Vec::new();
+    let mut removed_ranges: Vec<(usize, usize)> = Vec::new();
+
+    for captures in text_rule.pattern.captures_iter(text) {
+      let whole_match = captures.get(0).unwrap();
+      if whole_match.start() == whole_match.end() {
+        continue;
+      }
+
+      let text_rule_output = (text_rule.handler)(&captures);
+
+      for (group, spannable_data) in text_rule_output.spans {
+        let group_match = match captures.get(group) {
+          Option::Some(group_match) => group_match,
+          Option::None => continue
+        };
+
+        new_spannables.push(Spannable {
+          start: text[..group_match.start()].chars().count(),
+          len: group_match.as_str().chars().count(),
+          spannable_data
+        });
+      }
+
+      if let Option::Some(keep_group) = text_rule_output.keep_group {
+        match captures.get(keep_group) {
+          Option::Some(group_match) => {
+            removed_ranges.push((whole_match.start(), group_match.start()));
+            removed_ranges.push((group_match.end(), whole_match.end()));
+          }
+          Option::None => removed_ranges.push((whole_match.start(), whole_match.end()))
+        }
+      }
+    }
+
+    out_spannables.extend(new_spannables.into_iter().filter(|spannable| spannable.is_valid()));
+    removed_ranges.retain(|(start, end)| start < end);
+
+    if !removed_ranges.is_empty() {
+      current_text = Option::Some(remove_text_ranges(text, out_spannables, &removed_ranges));
+    }
+  }
+
+  return current_text;
+}
+
+#[test]
+fn test_apply_text_rules() {
+  let text_rules = vec![
+    std::rc::Rc::new(TextRule::template(Regex::new(r"\[spoiler\](.+?)\[/spoiler\]").unwrap(), 1, SpannableData::Spoiler)),
+    std::rc::Rc::new(TextRule::new(
+      Regex::new(r"R: (\d+) / I: (\d+)").unwrap(),
+      Box::new(|_| TextRuleOutput { spans: vec![(1, SpannableData::BoldText), (2, SpannableData::Italic)], keep_group: Option::None })
+    )),
+  ];
+
+  let mut spannables = vec![
+    Spannable { start: 0, len: 28, spannable_data: SpannableData::Underline }
+  ];
+
+  let text = apply_text_rules("ü [spoiler]secret[/spoiler] R: 150 / I: 32", &text_rules, &mut spannables);
+
+  assert_eq!(Option::Some(String::from("ü secret R: 150 / I: 32")), text);
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 9, spannable_data: SpannableData::Underline },
+      Spannable { start: 2, len: 6, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 12, len: 3, spannable_data: SpannableData::BoldText },
+      Spannable { start: 21, len: 2, spannable_data: SpannableData::Italic },
+    ],
+    spannables
+  );
+}
@@ -0,0 +1,60 @@
+use crate::{PostLink, PostParserContext, PostRaw};
+use regex::Regex;
+
+lazy_static! {
+  static ref FOUR_CHAN_URL_PATTERN: Regex = Regex::new(r"^(?:https?:)?//(?:boards\.)?4chan(?:nel)?\.org/(\w+)/(?:(?:thread|res)/(\d+)(?:/[^#]*)?(?:#[pq](\d+))?|catalog#s=([^#&]+))?/?$").unwrap();
+}
+
+/// Converts urls of a site into post links (quotes, thread links etc) instead of generic url links.
+/// The embedding app may register its own resolvers via PostParser::add_site_url_resolver().
+pub trait SiteUrlResolver {
+  /// Returns None when the url does not belong to this site or it's not a link to a board, thread
+  /// or post.
+  fn resolve(&self, url: &str, post_raw: &PostRaw, post_parser_context: &PostParserContext) -> Option<PostLink>;
+}
+
+pub struct FourChanUrlResolver {}
+
+impl FourChanUrlResolver {
+  pub fn new() -> FourChanUrlResolver {
+    return FourChanUrlResolver {};
+  }
+}
+
+impl SiteUrlResolver for FourChanUrlResolver {
+  fn resolve(&self, url: &str, post_raw: &PostRaw, post_parser_context: &PostParserContext) -> Option<PostLink> {
+    let captures = FOUR_CHAN_URL_PATTERN.captures(url)?;
+    let board_code = captures.get(1)?.as_str();
+
+    if let Some(search_query) = captures.get(4) {
+      return Option::Some(PostLink::SearchLink {
+        board_code: String::from(board_code),
+        search_query: String::from(search_query.as_str())
+      });
+    }
+
+    let thread_no = match captures.get(2) {
+      None => return Option::Some(PostLink::BoardLink { board_code: String::from(board_code) }),
+      Some(thread_no) => thread_no.as_str().parse::<u64>().ok()?
+    };
+
+    let post_no = match captures.get(3) {
+      None => thread_no,
+      Some(post_no) => post_no.as_str().parse::<u64>().ok()?
+    };
+
+    let is_current_thread = post_raw.site_name() == "4chan"
+      && board_code == post_raw.board_code()
+      && thread_no == post_raw.thread_no();
+
+    if is_current_thread {
+      return if post_parser_context.is_internal_thread_post(post_no) {
+        Option::Some(PostLink::quote(post_raw, post_parser_context, post_no))
+      } else {
+        Option::Some(PostLink::Dead { post_no })
+      };
+    }
+
+    return Option::Some(PostLink::ThreadLink { board_code: String::from(board_code), thread_no, post_no });
+  }
+}
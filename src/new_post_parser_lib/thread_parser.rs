@@ -0,0 +1,281 @@
+pub mod thread_parser {
+  use std::collections::{HashMap, HashSet};
+  use std::collections::hash_map::DefaultHasher;
+  use std::hash::{Hash, Hasher};
+  use linked_hash_map::LinkedHashMap;
+  use crate::{ThreadParser, ParsedPost, PostRaw, PostParserContext, PostParser, ParserProfile, ParserLimits, SpanOffsetMode, ParsedPostHash, ThreadUpdate, FilterEngine};
+  #[cfg(feature = "rayon")]
+  use rayon::prelude::*;
+
+  impl ThreadParser {
+    pub fn new(my_replies: HashSet<u64>, span_offset_mode: SpanOffsetMode) -> ThreadParser {
+      return ThreadParser {
+        my_replies,
+        span_offset_mode,
+        parsed_posts: LinkedHashMap::new(),
+        post_hashes: HashMap::new(),
+        filter_engine: FilterEngine::new(),
+        filters_version: 0
+      }
+    }
+
+    /// All the posts are re-parsed (and re-filtered) by the next update()
+    pub fn set_filter_engine(&mut self, filter_engine: FilterEngine) {
+      self.filter_engine = filter_engine;
+      self.filters_version += 1;
+    }
+
+    pub fn parsed_post(&self, post_no: u64) -> Option<&ParsedPost> {
+      return self.parsed_posts.get(&post_no);
+    }
+
+    /// Parsed posts in thread order
+    pub fn parsed_posts(&self) -> Vec<&ParsedPost> {
+      return self.parsed_posts.values().collect();
+    }
+
+    /// The posts parsed after this change will see the new set (quotes to the user's posts are marked
+    /// with is_you), the posts affected by the change are re-parsed by the next update()
+    pub fn set_my_replies(&mut self, my_replies: HashSet<u64>) {
+      self.my_replies = my_replies;
+    }
+
+    /// Takes the whole current state of the thread (every post that is still alive, e.g. the thread
+    /// json after an auto-refresh) and only parses the posts that weren't parsed before or whose
+    /// comment/metadata changed or whose context changed (a quoted post was deleted, or became one
+    /// of the user's posts). The posts that are not in new_posts anymore are dropped.
+    pub fn update(&mut self, new_posts: &[PostRaw]) -> ThreadUpdate {
+      let thread_posts = new_posts.iter()
+        .map(|post_raw| post_raw.post_no())
+        .collect::<HashSet<u64>>();
+
+      let mut thread_update = ThreadUpdate {
+        added: Vec::new(),
+        invalidated: Vec::new(),
+        removed: Vec::new()
+      };
+
+      let mut posts_to_parse: Vec<&PostRaw> = Vec::new();
+
+      for post_raw in new_posts {
+        let post_no = post_raw.post_no();
+
+        let prev_post_hash = match self.post_hashes.get(&post_no) {
+          Option::None => {
+            thread_update.added.push(post_no);
+            posts_to_parse.push(post_raw);
+            continue;
+          }
+          Option::Some(prev_post_hash) => prev_post_hash
+        };
+
+        let prev_parsed_post = self.parsed_posts.get(&post_no).unwrap();
+        let context_hash = self.context_hash(post_no, &prev_parsed_post.replies_to, &thread_posts);
+
+        if prev_post_hash.post_hash != post_hash(post_raw) || prev_post_hash.context_hash != context_hash {
+          thread_update.invalidated.push(post_no);
+          posts_to_parse.push(post_raw);
+        }
+      }
+
+      for post_no in self.parsed_posts.keys() {
+        if !thread_posts.contains(post_no) {
+          thread_update.removed.push(*post_no);
+        }
+      }
+
+      let thread_context = PostParserContext::new_with_span_offset_mode(
+        self.my_replies.clone(),
+        thread_posts.clone(),
+        self.span_offset_mode
+      );
+
+      let filter_engine = &self.filter_engine;
+
+      let mut newly_parsed_posts = ThreadParser::parse_posts(&posts_to_parse, &thread_context, &|thread_context| {
+        let mut post_parser = ThreadParser::create_post_parser(new_posts, thread_context);
+        if !filter_engine.is_empty() {
+          post_parser.set_filter_engine(filter_engine.clone());
+        }
+
+        return post_parser;
+      }).into_iter()
+        .map(|parsed_post| (parsed_post.post_descriptor.post_no(), parsed_post))
+        .collect::<HashMap<u64, ParsedPost>>();
+
+      // Rebuild the map so that it's in the order of new_posts
+      let mut parsed_posts = LinkedHashMap::with_capacity(new_posts.len());
+
+      for post_raw in new_posts {
+        let post_no = post_raw.post_no();
+
+        let parsed_post = match newly_parsed_posts.remove(&post_no) {
+          Option::Some(parsed_post) => {
+            let context_hash = self.context_hash(post_no, &parsed_post.replies_to, &thread_posts);
+            self.post_hashes.insert(post_no, ParsedPostHash { post_hash: post_hash(post_raw), context_hash });
+
+            parsed_post
+          }
+          Option::None => self.parsed_posts.remove(&post_no).unwrap()
+        };
+
+        parsed_posts.insert(post_no, parsed_post);
+      }
+
+      for post_no in &thread_update.removed {
+        self.post_hashes.remove(post_no);
+      }
+
+      self.parsed_posts = parsed_posts;
+      return thread_update;
+    }
+
+    fn context_hash(&self, post_no: u64, replies_to: &HashSet<u64>, thread_posts: &HashSet<u64>) -> u64 {
+      let mut replies_to = replies_to.iter().collect::<Vec<&u64>>();
+      replies_to.sort();
+
+      let mut hasher = DefaultHasher::new();
+      self.filters_version.hash(&mut hasher);
+      self.my_replies.contains(&post_no).hash(&mut hasher);
+
+      for reply_to in replies_to {
+        reply_to.hash(&mut hasher);
+        thread_posts.contains(reply_to).hash(&mut hasher);
+        self.my_replies.contains(reply_to).hash(&mut hasher);
+      }
+
+      return hasher.finish();
+    }
+
+    /// Parses all the posts of a thread (or a batch of new posts of a thread) in order with a single
+    /// PostParser (so the rules, the handlers and the site url resolvers are only created once) using
+    /// the parser profile of the posts' site. All the posts of the batch are added to the internal
+    /// posts of the context so quotes to any of them are recognized as internal on the first pass.
+    /// With the "rayon" feature enabled the posts are parsed in parallel (one PostParser per worker
+    /// thread), the returned posts are still in the same order as the input posts.
+    pub fn parse_thread(posts: &[PostRaw], post_parser_context: &PostParserContext) -> Vec<ParsedPost> {
+      return ThreadParser::parse_thread_with(posts, post_parser_context, &|thread_context| {
+        return ThreadParser::create_post_parser(posts, thread_context);
+      });
+    }
+
+    /// Same as parse_thread() but every post is also run through the filters
+    /// (ParsedPost::filter_matches, FilterHighlight spannables)
+    pub fn parse_thread_filtered(
+      posts: &[PostRaw],
+      post_parser_context: &PostParserContext,
+      filter_engine: &FilterEngine
+    ) -> Vec<ParsedPost> {
+      return ThreadParser::parse_thread_with(posts, post_parser_context, &|thread_context| {
+        let mut post_parser = ThreadParser::create_post_parser(posts, thread_context);
+        post_parser.set_filter_engine(filter_engine.clone());
+
+        return post_parser;
+      });
+    }
+
+    fn create_post_parser<'a>(posts: &[PostRaw], thread_context: &'a PostParserContext) -> PostParser<'a> {
+      let parser_profile = posts.first()
+        .and_then(|post_raw| ParserProfile::for_site(&post_raw.post_descriptor.thread_descriptor.board_descriptor.site_descriptor))
+        .unwrap_or_default();
+
+      return PostParser::new_with_profile(thread_context, parser_profile, ParserLimits::default());
+    }
+
+    /// Same as parse_thread() but the PostParser is created (and configured) by the caller
+    #[cfg(not(feature = "rayon"))]
+    pub fn parse_thread_with(
+      posts: &[PostRaw],
+      post_parser_context: &PostParserContext,
+      create_post_parser: &dyn for<'a> Fn(&'a PostParserContext) -> PostParser<'a>
+    ) -> Vec<ParsedPost> {
+      let thread_context = ThreadParser::create_thread_context(posts, post_parser_context);
+
+      let posts = posts.iter().collect::<Vec<&PostRaw>>();
+      return ThreadParser::parse_posts(&posts, &thread_context, create_post_parser);
+    }
+
+    /// Same as parse_thread() but the PostParser is created (and configured) by the caller. Every
+    /// worker thread creates its own PostParser so [create_post_parser] has to be Sync.
+    #[cfg(feature = "rayon")]
+    pub fn parse_thread_with(
+      posts: &[PostRaw],
+      post_parser_context: &PostParserContext,
+      create_post_parser: &(dyn for<'a> Fn(&'a PostParserContext) -> PostParser<'a> + Sync)
+    ) -> Vec<ParsedPost> {
+      let thread_context = ThreadParser::create_thread_context(posts, post_parser_context);
+
+      let posts = posts.iter().collect::<Vec<&PostRaw>>();
+      return ThreadParser::parse_posts(&posts, &thread_context, create_post_parser);
+    }
+
+    /// The context of parse_thread_with(), all the posts of the batch are internal
+    fn create_thread_context(posts: &[PostRaw], post_parser_context: &PostParserContext) -> PostParserContext {
+      let mut thread_posts = post_parser_context.thread_posts.clone();
+      thread_posts.extend(posts.iter().map(|post_raw| post_raw.post_no()));
+
+      return PostParserContext::new_with_span_offset_mode(
+        post_parser_context.my_replies.clone(),
+        thread_posts,
+        *post_parser_context.span_offset_mode()
+      ).with_search_highlight(post_parser_context.search_highlight_options().clone());
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn parse_posts(
+      posts: &[&PostRaw],
+      thread_context: &PostParserContext,
+      create_post_parser: &dyn for<'a> Fn(&'a PostParserContext) -> PostParser<'a>
+    ) -> Vec<ParsedPost> {
+      let post_parser = create_post_parser(thread_context);
+      let mut parsed_posts = Vec::with_capacity(posts.len());
+
+      for post_raw in posts {
+        parsed_posts.push(post_parser.parse_post(post_raw));
+      }
+
+      return parsed_posts;
+    }
+
+    #[cfg(feature = "rayon")]
+    fn parse_posts(
+      posts: &[&PostRaw],
+      thread_context: &PostParserContext,
+      create_post_parser: &(dyn for<'a> Fn(&'a PostParserContext) -> PostParser<'a> + Sync)
+    ) -> Vec<ParsedPost> {
+      // PostParser is not Send (the rules are Rc'd) so every worker creates its own one. An indexed
+      // parallel iterator collects the results in the input order.
+      return posts.par_iter()
+        .map_init(
+          || create_post_parser(thread_context),
+          |post_parser, post_raw| post_parser.parse_post(*post_raw)
+        )
+        .collect();
+    }
+
+    /// Inverse of ParsedPost::replies_to: for every quoted post, the posts that quote it
+    pub fn build_replies_from(parsed_posts: &[ParsedPost]) -> HashMap<u64, HashSet<u64>> {
+      let mut replies_from: HashMap<u64, HashSet<u64>> = HashMap::new();
+
+      for parsed_post in parsed_posts {
+        let post_no = parsed_post.post_descriptor.post_no();
+
+        for reply_to in &parsed_post.replies_to {
+          replies_from.entry(*reply_to)
+            .or_insert_with(HashSet::new)
+            .insert(post_no);
+        }
+      }
+
+      return replies_from;
+    }
+  }
+
+  fn post_hash(post_raw: &PostRaw) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    post_raw.com.hash(&mut hasher);
+    post_raw.metadata.hash(&mut hasher);
+
+    return hasher.finish();
+  }
+}
\ No newline at end of file
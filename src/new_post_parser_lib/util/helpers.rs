@@ -1,4 +1,5 @@
 use std::slice::Iter;
+use std::time::Instant;
 
 pub trait SumBy<T> {
   fn sum_by(&self, func: &dyn Fn(&T) -> i32) -> i32;
@@ -57,4 +58,12 @@ impl<T> MapJoin<T> for Iter<'_, T> {
 
     return result_string;
   }
-}
\ No newline at end of file
+}
+/// None on wasm32-unknown-unknown where there is no clock (Instant::now() panics)
+pub(crate) fn now() -> Option<Instant> {
+  if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+    return Option::None;
+  }
+
+  return Option::Some(Instant::now());
+}
@@ -0,0 +1,104 @@
+use regex::Regex;
+
+lazy_static! {
+  // \x{200B} is the zero width space of WordBreakMode::ZeroWidthSpace
+  static ref URL_PATTERN: Regex = Regex::new(r"(?:https?://|www\.)[-a-zA-Z0-9@:%._\+~#=\x{200B}]{1,256}\.[a-zA-Z0-9()]{1,6}\b(?:[-\w0-9()@:%_\+.~#?&//=!,;'\x{200B}]*)").unwrap();
+  static ref MAGNET_PATTERN: Regex = Regex::new(r"magnet:\?[^\s<>]+").unwrap();
+}
+
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+// Most likely belong to the sentence and not to the link ("see https://example.com.")
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ':', ';', '!', '?', '\'', '"'];
+
+pub struct FoundLink<'a> {
+  /// Byte offsets in the text
+  pub start: usize,
+  pub end: usize,
+  /// What the link should open, "www." links get the "https://" scheme
+  pub link: String,
+  pub text: &'a str
+}
+
+/// Finds http(s)://, www. and magnet: links in plain text
+pub fn find_links(text: &str) -> Vec<FoundLink> {
+  let mut found_links = Vec::new();
+
+  if !text.contains("://") && !text.contains("www.") && !text.contains("magnet:") {
+    return found_links;
+  }
+
+  for pattern in [&*URL_PATTERN, &*MAGNET_PATTERN].iter() {
+    for link_match in pattern.find_iter(text) {
+      let start = link_match.start();
+      let end = start + trim_link_end(link_match.as_str()).len();
+
+      if start >= end {
+        continue;
+      }
+
+      let link_text = &text[start..end];
+      let link = if link_text.starts_with("www.") {
+        format!("https://{}", link_text)
+      } else {
+        String::from(link_text)
+      };
+
+      let link = if link.contains(ZERO_WIDTH_SPACE) {
+        link.replace(ZERO_WIDTH_SPACE, "")
+      } else {
+        link
+      };
+
+      found_links.push(FoundLink { start, end, link, text: link_text });
+    }
+  }
+
+  found_links.sort_by_key(|found_link| found_link.start);
+  return found_links;
+}
+
+fn trim_link_end(link: &str) -> &str {
+  let mut link = link;
+
+  loop {
+    let last_char = match link.chars().last() {
+      None => return link,
+      Some(last_char) => last_char
+    };
+
+    let should_trim = if TRAILING_PUNCTUATION.contains(&last_char) {
+      true
+    } else if last_char == ')' {
+      // "(see https://en.wikipedia.org/wiki/Rust_(programming_language))"
+      link.matches(')').count() > link.matches('(').count()
+    } else {
+      false
+    };
+
+    if !should_trim {
+      return link;
+    }
+
+    link = &link[..link.len() - last_char.len_utf8()];
+  }
+}
+
+#[test]
+fn test_find_links() {
+  let links = find_links("see https://example.com/test.");
+  assert_eq!(1, links.len());
+  assert_eq!("https://example.com/test", links[0].text);
+
+  let links = find_links("(see https://en.wikipedia.org/wiki/Rust_(programming_language)), or www.rust-lang.org!");
+  assert_eq!(2, links.len());
+  assert_eq!("https://en.wikipedia.org/wiki/Rust_(programming_language)", links[0].link);
+  assert_eq!("www.rust-lang.org", links[1].text);
+  assert_eq!("https://www.rust-lang.org", links[1].link);
+
+  let links = find_links("magnet:?xt=urn:btih:c12fe1c06bba254a9dc9f519b335aa7c1367a88a&dn=test test");
+  assert_eq!(1, links.len());
+  assert_eq!("magnet:?xt=urn:btih:c12fe1c06bba254a9dc9f519b335aa7c1367a88a&dn=test", links[0].link);
+
+  assert!(find_links("no links here.").is_empty());
+}
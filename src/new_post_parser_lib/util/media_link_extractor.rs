@@ -0,0 +1,90 @@
+use crate::{Spannable, SpannableData, PostLink, MediaLinkKind, MediaLink};
+use regex::Regex;
+
+lazy_static! {
+  static ref YOUTUBE_PATTERN: Regex = Regex::new(r"^(?:https?://)?(?:www\.|m\.|music\.)?(?:youtube\.com/(?:watch\?(?:[^#]*&)?v=|shorts/|embed/|live/)|youtu\.be/)([\w-]{11})").unwrap();
+  static ref STREAMABLE_PATTERN: Regex = Regex::new(r"^(?:https?://)?(?:www\.)?streamable\.com/(?:e/)?(\w+)/?(?:[?#].*)?$").unwrap();
+  static ref VIMEO_PATTERN: Regex = Regex::new(r"^(?:https?://)?(?:www\.|player\.)?vimeo\.com/(?:video/)?(\d+)").unwrap();
+  static ref SOUNDCLOUD_PATTERN: Regex = Regex::new(r"^(?:https?://)?(?:www\.|m\.)?soundcloud\.com/([\w-]+/[\w-]+)/?(?:[?#].*)?$").unwrap();
+}
+
+/// Adds a MediaLink spannable over every url link that points to a supported media site.
+/// Must be called after all the links were added.
+pub fn extract_media_links(out_spannables: &mut Vec<Spannable>) {
+  let mut media_link_spannables = Vec::new();
+
+  for spannable in out_spannables.iter() {
+    let link = match &spannable.spannable_data {
+      SpannableData::Link(PostLink::UrlLink { link }) => link,
+      _ => continue
+    };
+
+    if let Option::Some(media_link) = find_media_link(link) {
+      media_link_spannables.push(Spannable {
+        start: spannable.start,
+        len: spannable.len,
+        spannable_data: SpannableData::MediaLink { kind: media_link.kind, id: media_link.id }
+      });
+    }
+  }
+
+  out_spannables.extend(media_link_spannables);
+}
+
+/// Unique media links of the MediaLink spannables in the order of appearance
+pub fn collect_media_links(spannables: &Vec<Spannable>) -> Vec<MediaLink> {
+  let mut media_links: Vec<MediaLink> = Vec::new();
+
+  for spannable in spannables {
+    if let SpannableData::MediaLink { kind, id } = &spannable.spannable_data {
+      let media_link = MediaLink { kind: kind.clone(), id: id.clone() };
+
+      if !media_links.contains(&media_link) {
+        media_links.push(media_link);
+      }
+    }
+  }
+
+  return media_links;
+}
+
+pub fn find_media_link(url: &str) -> Option<MediaLink> {
+  let patterns: [(&Regex, MediaLinkKind); 4] = [
+    (&YOUTUBE_PATTERN, MediaLinkKind::YouTube),
+    (&STREAMABLE_PATTERN, MediaLinkKind::Streamable),
+    (&VIMEO_PATTERN, MediaLinkKind::Vimeo),
+    (&SOUNDCLOUD_PATTERN, MediaLinkKind::SoundCloud),
+  ];
+
+  for (pattern, kind) in patterns.iter() {
+    if let Option::Some(captures) = pattern.captures(url) {
+      return Option::Some(MediaLink { kind: kind.clone(), id: String::from(captures.get(1)?.as_str()) });
+    }
+  }
+
+  return Option::None;
+}
+
+#[test]
+fn test_find_media_link() {
+  let youtube = |id: &str| Option::Some(MediaLink { kind: MediaLinkKind::YouTube, id: String::from(id) });
+
+  assert_eq!(youtube("dQw4w9WgXcQ"), find_media_link("https://www.youtube.com/watch?v=dQw4w9WgXcQ"));
+  assert_eq!(youtube("dQw4w9WgXcQ"), find_media_link("https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ&t=10"));
+  assert_eq!(youtube("dQw4w9WgXcQ"), find_media_link("https://youtu.be/dQw4w9WgXcQ?t=1"));
+  assert_eq!(youtube("abcdefghijk"), find_media_link("https://youtube.com/shorts/abcdefghijk"));
+  assert_eq!(
+    Option::Some(MediaLink { kind: MediaLinkKind::Streamable, id: String::from("abc12") }),
+    find_media_link("https://streamable.com/abc12")
+  );
+  assert_eq!(
+    Option::Some(MediaLink { kind: MediaLinkKind::Vimeo, id: String::from("76979871") }),
+    find_media_link("https://vimeo.com/76979871")
+  );
+  assert_eq!(
+    Option::Some(MediaLink { kind: MediaLinkKind::SoundCloud, id: String::from("artist/some-track") }),
+    find_media_link("https://soundcloud.com/artist/some-track")
+  );
+  assert_eq!(Option::None, find_media_link("https://www.youtube.com/channel/abc"));
+  assert_eq!(Option::None, find_media_link("https://example.com/watch?v=dQw4w9WgXcQ"));
+}
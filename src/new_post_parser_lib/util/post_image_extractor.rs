@@ -0,0 +1,234 @@
+use crate::{HtmlParser, Element, PostImage, PostImageKind, ParserWarning};
+use crate::html_parser::node::Node;
+use crate::parser_warnings::report_warning;
+use regex::Regex;
+
+const TAG: &str = "PostImageExtractor";
+// Containers of a single file (4chan/vichan, dvach)
+const FILE_CLASSES: &[&str] = &["file", "post__image"];
+// Classes of the element with the link and "(1.2 MB, 1920x1080)" (4chan, vichan, lynxchan, dvach)
+const FILE_INFO_CLASSES: &[&str] = &["fileText", "fileinfo", "uploadDetails", "post__file-attr"];
+const FILE_THUMB_CLASSES: &[&str] = &["fileThumb", "imgLink", "post__image-link"];
+const NSFW_CLASS_PART: &str = "nsfw";
+const POST_FILE_NAME_CLASS: &str = "postfilename";
+const SPOILER_CLASSES: &[&str] = &["imgspoiler", "spoiler"];
+
+lazy_static! {
+  // "12 KB", "1.5 MiB", "300 B", "1234Кб" (dvach)
+  static ref FILE_SIZE_PATTERN: Regex = Regex::new(r"(?i)(\d+(?:\.\d+)?)\s*([KMGКМГ]?)(?:i?B|б)\b").unwrap();
+  // "01:23", "1:02:03"
+  static ref DURATION_PATTERN: Regex = Regex::new(r"\b(?:(\d{1,2}):)?(\d{1,2}):(\d{2})\b").unwrap();
+  static ref FILE_DIMENSIONS_PATTERN: Regex = Regex::new(r"(\d+)x(\d+)").unwrap();
+}
+
+/// The files of the file block html of a post:
+///
+/// <div class="file" id="f100">
+///   <div class="fileText" id="fT100">File: <a title="long name.png" href="//i.4cdn.org/g/1600000000000.png">long n(...).png</a> (1.2 MB, 1920x1080)</div>
+///   <a class="fileThumb" href="//i.4cdn.org/g/1600000000000.png"><img src="//i.4cdn.org/g/1600000000000s.jpg"></a>
+/// </div>
+///
+/// Every <div class="file"> (<figure class="post__image"> on dvach) is a file so posts with multiple
+/// files (vichan, dvach) get all of them, html without them is treated as a single file. Deleted
+/// files (no link to the file) are skipped.
+pub fn extract_post_images(files_html: &str) -> Vec<PostImage> {
+  let nodes = match HtmlParser::new().decode_entities(true).parse(files_html) {
+    Ok(nodes) => nodes,
+    Err(error) => {
+      report_warning(ParserWarning::new(TAG, format!("Failed to parse files html ({}), error={}", files_html, error)));
+      return Vec::new();
+    }
+  };
+
+  let mut root = Element::new("div", false);
+  root.children = nodes;
+
+  let file_elements = select_files(&root);
+  if file_elements.is_empty() {
+    return extract_post_image(&root).into_iter().collect();
+  }
+
+  return file_elements.into_iter()
+    .filter_map(|file_element| extract_post_image(file_element))
+    .collect();
+}
+
+/// None when there is no link to the file
+pub fn extract_post_image(file_element: &Element) -> Option<PostImage> {
+  let file_info = find_descendant(file_element, &|element| element.has_any_class(FILE_INFO_CLASSES));
+  let file_thumb = find_descendant(file_element, &|element| {
+    return element.tag_name == "a" && (element.has_any_class(FILE_THUMB_CLASSES) || element.select_first("img").is_some());
+  });
+
+  let file_link = file_info.and_then(|file_info| file_info.select_first("a[href]"));
+
+  let full_url = file_link.or(file_thumb)
+    .and_then(|link| link.attr("href"))
+    .map(|href| String::from(href))?;
+
+  let server_file_name = file_name_of_url(&full_url);
+  let (server_name, extension) = split_extension(server_file_name);
+
+  // 4chan puts the full name into the title when it's shortened in the text and into the title of
+  // the file info when the file is spoilered (the text is "Spoiler Image" then), vichan has a
+  // separate element for it (the link text is the server file name there)
+  let name_element = find_descendant(file_element, &|element| element.has_class(POST_FILE_NAME_CLASS))
+    .or(file_link);
+
+  let original_name = file_info.and_then(|file_info| file_info.attr("title"))
+    .or_else(|| name_element.and_then(|name_element| name_element.attr("title")))
+    // Attribute values are never decoded by the parser
+    .map(|title| String::from(html_escape::decode_html_entities(title)))
+    .or_else(|| name_element.map(|name_element| name_element.collect_text()))
+    .map(|original_name| String::from(original_name.trim()))
+    .filter(|original_name| !original_name.is_empty())
+    .unwrap_or_else(|| String::from(server_file_name));
+
+  let info_text = file_info.map(|file_info| file_info.collect_text()).unwrap_or_default();
+  let (width, height) = parse_dimensions(&info_text).unwrap_or((0, 0));
+
+  let thumbnail_url = file_thumb
+    .and_then(|file_thumb| file_thumb.select_first("img"))
+    .and_then(|img| img.attr("src"))
+    .map(|src| String::from(src));
+
+  let is_spoiler = file_thumb.map_or(false, |file_thumb| file_thumb.has_any_class(SPOILER_CLASSES))
+    || file_element.select_first(".imgspoiler").is_some();
+
+  let is_nsfw = file_element.classes().any(|class| class.to_ascii_lowercase().contains(NSFW_CLASS_PART))
+    || find_descendant(file_element, &|element| element.classes().any(|class| class.to_ascii_lowercase().contains(NSFW_CLASS_PART))).is_some();
+
+  let extension = extension.trim_start_matches('.');
+  let kind = image_kind(extension);
+
+  return Option::Some(PostImage {
+    original_name,
+    server_name: String::from(server_name),
+    extension: String::from(extension),
+    size: parse_file_size(&info_text).unwrap_or(0),
+    width,
+    height,
+    full_url: Option::Some(full_url),
+    thumbnail_url,
+    is_spoiler,
+    kind,
+    duration: if kind == PostImageKind::Image { Option::None } else { parse_duration(&info_text) },
+    is_nsfw
+  });
+}
+
+/// Bytes of a "1.2 MB" like size
+pub fn parse_file_size(text: &str) -> Option<u64> {
+  let captures = FILE_SIZE_PATTERN.captures(text)?;
+  let size = captures[1].parse::<f64>().ok()?;
+
+  let multiplier = match captures[2].to_uppercase().as_str() {
+    "K" | "К" => 1024f64,
+    "M" | "М" => 1024f64 * 1024f64,
+    "G" | "Г" => 1024f64 * 1024f64 * 1024f64,
+    _ => 1f64
+  };
+
+  return Option::Some((size * multiplier).round() as u64);
+}
+
+/// Seconds of a "01:23" or "1:02:03" like duration
+pub fn parse_duration(text: &str) -> Option<u32> {
+  let captures = DURATION_PATTERN.captures(text)?;
+  let part = |index: usize| captures.get(index).map_or(0, |part| part.as_str().parse::<u32>().unwrap_or(0));
+
+  return Option::Some(part(1) * 3600 + part(2) * 60 + part(3));
+}
+
+pub fn image_kind(extension: &str) -> PostImageKind {
+  return match extension.trim_start_matches('.').to_ascii_lowercase().as_str() {
+    "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "avif" | "jxl" => PostImageKind::Image,
+    "webm" | "mp4" | "mov" | "mkv" | "m4v" => PostImageKind::Video,
+    "mp3" | "ogg" | "opus" | "flac" | "wav" | "m4a" => PostImageKind::Audio,
+    _ => PostImageKind::Other
+  };
+}
+
+/// "1920x1080" -> (1920, 1080)
+pub fn parse_dimensions(text: &str) -> Option<(u32, u32)> {
+  let captures = FILE_DIMENSIONS_PATTERN.captures(text)?;
+  return Option::Some((captures[1].parse::<u32>().ok()?, captures[2].parse::<u32>().ok()?));
+}
+
+/// "//i.4cdn.org/g/1600000000000.png?v=1" -> "1600000000000.png"
+pub fn file_name_of_url(url: &str) -> &str {
+  let path = url.split(|ch| ch == '?' || ch == '#').next().unwrap_or(url);
+  return path.rsplit('/').next().unwrap_or(path);
+}
+
+/// "name.png" -> ("name", ".png")
+pub fn split_extension(file_name: &str) -> (&str, &str) {
+  return match file_name.rfind('.') {
+    Option::Some(index) if index > 0 => (&file_name[..index], &file_name[index..]),
+    _ => (file_name, "")
+  };
+}
+
+/// The file containers that are not inside of another file container
+fn select_files(root: &Element) -> Vec<&Element> {
+  let mut files = Vec::new();
+  let mut stack = vec![root.children.iter()];
+
+  while let Option::Some(children) = stack.last_mut() {
+    match children.next() {
+      Option::None => {
+        stack.pop();
+      }
+      Option::Some(Node::Element(child)) => {
+        if child.has_any_class(FILE_CLASSES) {
+          files.push(child);
+        } else {
+          stack.push(child.children.iter());
+        }
+      }
+      Option::Some(_) => {}
+    }
+  }
+
+  return files;
+}
+
+fn find_descendant<'a>(element: &'a Element, predicate: &dyn Fn(&Element) -> bool) -> Option<&'a Element> {
+  let mut stack = vec![element.children.iter()];
+
+  while let Option::Some(children) = stack.last_mut() {
+    match children.next() {
+      Option::None => {
+        stack.pop();
+      }
+      Option::Some(Node::Element(child)) => {
+        if predicate(child) {
+          return Option::Some(child);
+        }
+
+        stack.push(child.children.iter());
+      }
+      Option::Some(_) => {}
+    }
+  }
+
+  return Option::None;
+}
+
+#[test]
+fn test_parse_file_size() {
+  assert_eq!(Option::Some(1258291), parse_file_size("(1.2 MB, 1920x1080)"));
+  assert_eq!(Option::Some(12288), parse_file_size("12 KiB"));
+  assert_eq!(Option::Some(300), parse_file_size("300 B, 10x20"));
+  assert_eq!(Option::Some(1073741824), parse_file_size("1gb"));
+  assert_eq!(Option::Some(1263616), parse_file_size("(1234Кб, 1280x720, 00:01:23)"));
+  assert_eq!(Option::None, parse_file_size("1920x1080"));
+}
+
+#[test]
+fn test_parse_duration() {
+  assert_eq!(Option::Some(83), parse_duration("(1234Кб, 1280x720, 00:01:23)"));
+  assert_eq!(Option::Some(3723), parse_duration("1:02:03"));
+  assert_eq!(Option::Some(35), parse_duration("(1.2 MB, 1280x720, 00:35)"));
+  assert_eq!(Option::None, parse_duration("(1.2 MB, 1280x720)"));
+}
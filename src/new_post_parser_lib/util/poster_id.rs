@@ -0,0 +1,60 @@
+use crate::{PosterId, Element};
+
+const ID_CLASS_PREFIX: &str = "id_";
+pub const POSTER_ID_CLASSES: &[&str] = &["posteruid", "poster_id"];
+
+impl PosterId {
+  pub fn new(id: &str) -> PosterId {
+    return PosterId { id: String::from(id) };
+  }
+
+  /// <span class="posteruid id_XYZ">(ID: <span class="hand">XYZ</span>)</span>, the id is taken
+  /// from the class and from the "(ID: XYZ)" text when there is no id class
+  pub fn of_element(element: &Element) -> Option<PosterId> {
+    let class_id = element.classes()
+      .find_map(|class| class.strip_prefix(ID_CLASS_PREFIX))
+      .filter(|id| !id.is_empty());
+
+    if let Option::Some(class_id) = class_id {
+      return Option::Some(PosterId::new(class_id));
+    }
+
+    let text = element.collect_text();
+    let text = text.trim().trim_start_matches('(').trim_end_matches(')').trim();
+    let text = text.strip_prefix("ID:").unwrap_or(text).trim();
+
+    if text.is_empty() {
+      return Option::None;
+    }
+
+    return Option::Some(PosterId::new(text));
+  }
+
+  /// ARGB color of the id, the same hash 4chan uses for the id backgrounds so the colors match
+  /// the site
+  pub fn color(&self) -> u32 {
+    let mut hash: i32 = 0;
+
+    for code_unit in self.id.encode_utf16() {
+      hash = hash.wrapping_shl(5).wrapping_sub(hash).wrapping_add(code_unit as i32);
+    }
+
+    let red = ((hash >> 24) & 0xFF) as u32;
+    let green = ((hash >> 16) & 0xFF) as u32;
+    let blue = ((hash >> 8) & 0xFF) as u32;
+
+    return 0xFF000000 | (red << 16) | (green << 8) | blue;
+  }
+}
+
+#[test]
+fn test_poster_id_color() {
+  // The same id always gets the same color
+  assert_eq!(PosterId::new("AbCd1234").color(), PosterId::new("AbCd1234").color());
+  assert_ne!(PosterId::new("AbCd1234").color(), PosterId::new("XyZw1234").color());
+
+  // hash("a") = 97
+  assert_eq!(0xFF000000, PosterId::new("a").color());
+  // hash("abcd") = 2987074
+  assert_eq!(0xFF002D94, PosterId::new("abcd").color());
+}
@@ -0,0 +1,100 @@
+use crate::{Spannable, SpannableData, SearchHighlightOptions, ParserWarning};
+use crate::parser_warnings::report_warning;
+use regex::{Regex, RegexBuilder};
+
+const TAG: &str = "SearchHighlighter";
+
+/// One alternation of all the (escaped) terms, longer terms first so that "cats" wins over "cat".
+/// None when there are no non-empty terms or the pattern could not be compiled (reported as a
+/// warning).
+pub fn create_search_highlight_pattern(search_highlight_options: &SearchHighlightOptions) -> Option<Regex> {
+  let mut terms = search_highlight_options.terms
+    .iter()
+    .filter(|term| !term.is_empty())
+    .collect::<Vec<&String>>();
+
+  if terms.is_empty() {
+    return Option::None;
+  }
+
+  terms.sort_by(|term1, term2| term2.chars().count().cmp(&term1.chars().count()));
+
+  let pattern = terms
+    .iter()
+    .map(|term| regex::escape(term))
+    .collect::<Vec<String>>()
+    .join("|");
+
+  return match RegexBuilder::new(&pattern).case_insensitive(search_highlight_options.case_insensitive).build() {
+    Ok(regex) => Option::Some(regex),
+    Err(error) => {
+      report_warning(ParserWarning::new(TAG, format!("failed to compile \"{}\": {}", pattern, error)));
+      Option::None
+    }
+  };
+}
+
+/// Adds a SearchHighlight spannable over every occurrence of the terms in the final text.
+/// Spannable offsets are in chars.
+pub fn highlight_search_terms(
+  text: &str,
+  search_highlight_pattern: &Regex,
+  search_highlight_options: &SearchHighlightOptions,
+  out_spannables: &mut Vec<Spannable>
+) {
+  let mut chars_count_before_match = 0;
+  let mut prev_match_end = 0;
+
+  for found in search_highlight_pattern.find_iter(text) {
+    if search_highlight_options.whole_word && !is_whole_word(text, found.start(), found.end()) {
+      continue;
+    }
+
+    chars_count_before_match += text[prev_match_end..found.start()].chars().count();
+    let match_chars_count = found.as_str().chars().count();
+
+    out_spannables.push(Spannable {
+      start: chars_count_before_match,
+      len: match_chars_count,
+      spannable_data: SpannableData::SearchHighlight
+    });
+
+    chars_count_before_match += match_chars_count;
+    prev_match_end = found.end();
+  }
+}
+
+fn is_whole_word(text: &str, start: usize, end: usize) -> bool {
+  let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+
+  let prev_char = text[..start].chars().next_back();
+  let next_char = text[end..].chars().next();
+
+  return !prev_char.map(is_word_char).unwrap_or(false) && !next_char.map(is_word_char).unwrap_or(false);
+}
+
+#[test]
+fn test_highlight_search_terms() {
+  let highlight = |text: &str, terms: Vec<&str>, case_insensitive: bool, whole_word: bool| {
+    let search_highlight_options = SearchHighlightOptions {
+      terms: terms.iter().map(|term| String::from(*term)).collect(),
+      case_insensitive,
+      whole_word
+    };
+
+    let mut spannables = Vec::new();
+
+    if let Option::Some(pattern) = create_search_highlight_pattern(&search_highlight_options) {
+      highlight_search_terms(text, &pattern, &search_highlight_options, &mut spannables);
+    }
+
+    return spannables.iter().map(|spannable| (spannable.start, spannable.len)).collect::<Vec<(usize, usize)>>();
+  };
+
+  assert_eq!(vec![(4, 3), (12, 4)], highlight("the cat and cats", vec!["cat", "cats"], false, false));
+  assert_eq!(vec![(4, 3)], highlight("the cat and cats", vec!["cat"], false, true));
+  assert_eq!(vec![(0, 3), (6, 3)], highlight("Cat и cat", vec!["cat"], true, false));
+  assert_eq!(vec![(6, 3)], highlight("Cat и cat", vec!["cat"], false, false));
+  assert_eq!(vec![(2, 3)], highlight("ф C++ c++x", vec!["C++"], false, true));
+  assert_eq!(Vec::<(usize, usize)>::new(), highlight("anything", vec![""], false, false));
+}
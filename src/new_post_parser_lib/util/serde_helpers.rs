@@ -0,0 +1,24 @@
+/// Element attributes are (de)serialized as a list of [name, value] pairs so that their order is
+/// preserved (a json object would not guarantee it)
+pub mod linked_hash_map_as_pairs {
+  use linked_hash_map::LinkedHashMap;
+  use std::borrow::Cow;
+  use crate::html_parser::name_interner::intern_name;
+  use serde::{Serializer, Deserializer, Deserialize};
+  use serde::ser::SerializeSeq;
+
+  pub fn serialize<S: Serializer>(map: &LinkedHashMap<Cow<'static, str>, Option<String>>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(Option::Some(map.len()))?;
+
+    for pair in map.iter() {
+      seq.serialize_element(&pair)?;
+    }
+
+    return seq.end();
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<LinkedHashMap<Cow<'static, str>, Option<String>>, D::Error> {
+    let pairs = Vec::<(String, Option<String>)>::deserialize(deserializer)?;
+    return Ok(pairs.into_iter().map(|(name, value)| (intern_name(&name), value)).collect());
+  }
+}
@@ -0,0 +1,157 @@
+use crate::{Spannable, SpanOffsetMode};
+
+/// Spannables are built with offsets in unicode scalars (chars), this converts them into the units
+/// of the selected mode
+pub fn convert_span_offsets(text: &str, spannables: &mut Vec<Spannable>, span_offset_mode: &SpanOffsetMode) {
+  if *span_offset_mode == SpanOffsetMode::UnicodeScalars || spannables.is_empty() {
+    return;
+  }
+
+  // Text without multi byte characters has the same offsets in every mode
+  if text.is_ascii() && *span_offset_mode != SpanOffsetMode::Graphemes {
+    return;
+  }
+
+  let offsets = match span_offset_mode {
+    SpanOffsetMode::Utf8Bytes => return convert_to_units(text, spannables, char::len_utf8),
+    SpanOffsetMode::Utf16CodeUnits => return convert_to_units(text, spannables, char::len_utf16),
+    SpanOffsetMode::Graphemes => grapheme_offsets(text),
+    SpanOffsetMode::UnicodeScalars => return
+  };
+
+  let max_offset = offsets.len() - 1;
+
+  for spannable in spannables.iter_mut() {
+    let start = spannable.start.min(max_offset);
+    let end = (spannable.start + spannable.len).min(max_offset);
+
+    spannable.start = offsets[start].0;
+    spannable.len = offsets[end].1 - spannable.start;
+  }
+}
+
+/// Converts the char offsets into the units of the unit_len function in a single pass over the
+/// text. Only the offsets of the span boundaries are stored (not of every char of the text).
+/// Offsets past the end of the text are clamped to it.
+fn convert_to_units(text: &str, spannables: &mut Vec<Spannable>, unit_len: fn(char) -> usize) {
+  let mut boundaries = spannables.iter()
+    .flat_map(|spannable| vec![spannable.start, spannable.start + spannable.len])
+    .collect::<Vec<usize>>();
+
+  boundaries.sort_unstable();
+  boundaries.dedup();
+
+  let mut unit_offsets: Vec<usize> = Vec::with_capacity(boundaries.len());
+  let mut unit_offset = 0;
+
+  for (char_index, ch) in text.chars().enumerate() {
+    while unit_offsets.len() < boundaries.len() && boundaries[unit_offsets.len()] == char_index {
+      unit_offsets.push(unit_offset);
+    }
+
+    if unit_offsets.len() == boundaries.len() {
+      break;
+    }
+
+    unit_offset += unit_len(ch);
+  }
+
+  // The loop went through the whole text, the rest of the boundaries are at (or past) its end
+  unit_offsets.resize(boundaries.len(), unit_offset);
+
+  let to_units = |char_offset: usize| unit_offsets[boundaries.binary_search(&char_offset).unwrap()];
+
+  for spannable in spannables.iter_mut() {
+    let start = to_units(spannable.start);
+    let end = to_units(spannable.start + spannable.len);
+
+    spannable.start = start;
+    spannable.len = end - start;
+  }
+}
+
+/// For every char index (plus the end of the text) returns the index of the grapheme cluster the
+/// char belongs to when it's a span start and the index of the first cluster after it when it's a
+/// span end, so that a span never cuts a cluster in half.
+pub(crate) fn grapheme_offsets(text: &str) -> Vec<(usize, usize)> {
+  let mut offsets = Vec::with_capacity(text.len() + 1);
+  let mut clusters_count = 0;
+  let mut prev_char: Option<char> = Option::None;
+  let mut regional_indicators_in_row = 0;
+
+  for ch in text.chars() {
+    let is_regional_indicator = ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch);
+    let extends_cluster = match prev_char {
+      None => false,
+      Some(prev_char) => {
+        is_grapheme_extender(ch)
+          || prev_char == '\u{200D}'
+          || (prev_char == '\r' && ch == '\n')
+          || (is_regional_indicator && regional_indicators_in_row % 2 == 1)
+      }
+    };
+
+    if is_regional_indicator {
+      regional_indicators_in_row += 1;
+    } else {
+      regional_indicators_in_row = 0;
+    }
+
+    if extends_cluster {
+      offsets.push((clusters_count - 1, clusters_count));
+    } else {
+      offsets.push((clusters_count, clusters_count));
+      clusters_count += 1;
+    }
+
+    prev_char = Option::Some(ch);
+  }
+
+  offsets.push((clusters_count, clusters_count));
+  return offsets;
+}
+
+/// Approximation of the Grapheme_Extend property that covers what is commonly found in posts
+/// (combining diacritics, emoji variation selectors, skin tones, ZWJ sequences and tag sequences)
+fn is_grapheme_extender(ch: char) -> bool {
+  return match ch {
+    '\u{0300}'..='\u{036F}' |
+    '\u{0483}'..='\u{0489}' |
+    '\u{1AB0}'..='\u{1AFF}' |
+    '\u{1DC0}'..='\u{1DFF}' |
+    '\u{200C}'..='\u{200D}' |
+    '\u{20D0}'..='\u{20FF}' |
+    '\u{3099}'..='\u{309A}' |
+    '\u{FE00}'..='\u{FE0F}' |
+    '\u{FE20}'..='\u{FE2F}' |
+    '\u{1F3FB}'..='\u{1F3FF}' |
+    '\u{E0020}'..='\u{E007F}' |
+    '\u{E0100}'..='\u{E01EF}' => true,
+    _ => false
+  };
+}
+
+#[test]
+fn test_convert_span_offsets() {
+  use crate::SpannableData;
+
+  let text = "ab 😀 ñc";
+
+  let convert = |span_offset_mode: SpanOffsetMode| {
+    let mut spannables = vec![
+      Spannable { start: 0, len: 2, spannable_data: SpannableData::BoldText },
+      Spannable { start: 3, len: 4, spannable_data: SpannableData::Italic },
+      Spannable { start: 5, len: 10, spannable_data: SpannableData::Underline },
+    ];
+
+    convert_span_offsets(text, &mut spannables, &span_offset_mode);
+
+    return spannables.iter()
+      .map(|spannable| (spannable.start, spannable.len))
+      .collect::<Vec<(usize, usize)>>();
+  };
+
+  assert_eq!(vec![(0, 2), (3, 8), (8, 3)], convert(SpanOffsetMode::Utf8Bytes));
+  assert_eq!(vec![(0, 2), (3, 5), (6, 2)], convert(SpanOffsetMode::Utf16CodeUnits));
+  assert_eq!(vec![(0, 2), (3, 4), (5, 10)], convert(SpanOffsetMode::UnicodeScalars));
+}
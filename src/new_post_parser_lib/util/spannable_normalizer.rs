@@ -0,0 +1,245 @@
+use crate::{Spannable, SpannableData, ParserWarning};
+use crate::parser_warnings::report_warning;
+
+const TAG: &str = "SpannableNormalizer";
+
+impl SpannableData {
+  /// Whether two spannables of this kind that overlap or touch each other may be merged into one.
+  /// Links are never merged since every one of them points to a different place.
+  pub fn is_mergeable(&self) -> bool {
+    return match self {
+      SpannableData::Spoiler
+      | SpannableData::BoldText
+      | SpannableData::Italic
+      | SpannableData::Underline
+      | SpannableData::GreenText
+      | SpannableData::Strikethrough => true,
+      _ => false
+    }
+  }
+}
+
+/// Cleans up the spannables of a parsed comment:
+/// 1. Zero-length spannables are removed and spannables are clamped to the text length.
+/// 2. (optional) Spannables of the same mergeable kind are merged, see merge_spannables().
+/// 3. Spannables are sorted by start, outer spannables go before the inner ones with the same start.
+/// 4. Spannables that cross each other (<b>a<i>b</b>c</i>) are split so that any two spannables are
+///    either nested or disjoint. Links are never split unless they cross another link, so a link
+///    inside a greentext quote stays in one piece and the quote is split around it if needed.
+pub fn normalize_spannables(text_length: usize, spannables: &mut Vec<Spannable>, merge_adjacent: bool) {
+  clamp_spannables(text_length, spannables);
+
+  if merge_adjacent {
+    merge_spannables(spannables);
+  }
+
+  sort_spannables(spannables);
+  split_crossing_spannables(spannables);
+}
+
+fn clamp_spannables(text_length: usize, spannables: &mut Vec<Spannable>) {
+  spannables.retain(|spannable| spannable.is_valid() && spannable.start < text_length);
+
+  for spannable in spannables.iter_mut() {
+    if spannable.start + spannable.len > text_length {
+      report_warning(
+        ParserWarning::new(TAG, format!("Spannable ({}) goes past the end of the text ({}), clamping it", spannable.spannable_data, text_length))
+          .with_position(spannable.start)
+      );
+      spannable.len = text_length - spannable.start;
+    }
+  }
+}
+
+fn sort_spannables(spannables: &mut Vec<Spannable>) {
+  // Stable sort so that spannables covering the same text keep the order they were added in
+  spannables.sort_by(|first, second| {
+    return first.start.cmp(&second.start).then(second.len.cmp(&first.len));
+  });
+}
+
+fn split_crossing_spannables(spannables: &mut Vec<Spannable>) {
+  let mut index = 0;
+
+  while index < spannables.len() {
+    let mut split_happened = false;
+
+    for other_index in (index + 1)..spannables.len() {
+      let current = &spannables[index];
+      let other = &spannables[other_index];
+
+      let current_end = current.start + current.len;
+      if other.start >= current_end {
+        // Sorted by start so the rest of the spannables can't cross the current one
+        break;
+      }
+
+      if other.start + other.len <= current_end {
+        // Nested
+        continue;
+      }
+
+      // other starts inside of current and ends after it
+      let is_current_link = matches!(current.spannable_data, SpannableData::Link(_));
+      let is_other_link = matches!(other.spannable_data, SpannableData::Link(_));
+
+      let (split_index, split_at) = if is_other_link && !is_current_link {
+        (index, other.start)
+      } else {
+        (other_index, current_end)
+      };
+
+      let spannable = &mut spannables[split_index];
+      let tail = Spannable {
+        start: split_at,
+        len: spannable.start + spannable.len - split_at,
+        spannable_data: spannable.spannable_data.clone()
+      };
+
+      spannable.len = split_at - spannable.start;
+      spannables.push(tail);
+
+      split_happened = true;
+      break;
+    }
+
+    if split_happened {
+      sort_spannables(spannables);
+      continue;
+    }
+
+    index += 1;
+  }
+}
+
+/// Nested spannables of the same mergeable kind (<s>a<s>b</s>c</s>) are merged into the outermost
+/// one and adjacent spannables of the same mergeable kind (<s>a</s><s>b</s>) are coalesced into one
+/// run. Spannables separated by at least one character stay separate. The merged spannable takes
+/// the place of the first spannable of the run.
+pub fn merge_spannables(spannables: &mut Vec<Spannable>) {
+  let mut index = 0;
+
+  while index < spannables.len() {
+    if !spannables[index].spannable_data.is_mergeable() {
+      index += 1;
+      continue;
+    }
+
+    let mut other_index = index + 1;
+
+    while other_index < spannables.len() {
+      let current = &spannables[index];
+      let other = &spannables[other_index];
+
+      let touches = other.start <= current.start + current.len
+        && current.start <= other.start + other.len;
+
+      if current.spannable_data != other.spannable_data || !touches {
+        other_index += 1;
+        continue;
+      }
+
+      let other = spannables.remove(other_index);
+      let current = &mut spannables[index];
+
+      let start = current.start.min(other.start);
+      let end = (current.start + current.len).max(other.start + other.len);
+
+      current.start = start;
+      current.len = end - start;
+
+      // The merged spannable became bigger so it may now touch spannables we have already skipped
+      other_index = index + 1;
+    }
+
+    index += 1;
+  }
+}
+
+#[test]
+fn test_nested_spoilers_are_merged() {
+  let mut spannables = vec![
+    Spannable { start: 1, len: 1, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 0, len: 3, spannable_data: SpannableData::Spoiler },
+  ];
+
+  merge_spannables(&mut spannables);
+
+  assert_eq!(vec![Spannable { start: 0, len: 3, spannable_data: SpannableData::Spoiler }], spannables);
+}
+
+#[test]
+fn test_adjacent_spoilers_are_coalesced() {
+  let mut spannables = vec![
+    Spannable { start: 0, len: 1, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 2, len: 1, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 1, len: 1, spannable_data: SpannableData::Spoiler },
+  ];
+
+  merge_spannables(&mut spannables);
+
+  assert_eq!(vec![Spannable { start: 0, len: 3, spannable_data: SpannableData::Spoiler }], spannables);
+}
+
+#[test]
+fn test_separated_spoilers_stay_distinct() {
+  let mut spannables = vec![
+    Spannable { start: 0, len: 1, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 2, len: 1, spannable_data: SpannableData::Spoiler },
+  ];
+
+  merge_spannables(&mut spannables);
+
+  assert_eq!(2, spannables.len());
+}
+
+#[test]
+fn test_different_kinds_and_links_are_not_merged() {
+  let mut spannables = vec![
+    Spannable { start: 0, len: 1, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 1, len: 1, spannable_data: SpannableData::BoldText },
+    Spannable { start: 2, len: 1, spannable_data: SpannableData::Link(crate::PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: Option::None }) },
+    Spannable { start: 3, len: 1, spannable_data: SpannableData::Link(crate::PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: Option::None }) },
+  ];
+
+  merge_spannables(&mut spannables);
+
+  assert_eq!(4, spannables.len());
+}
+
+#[test]
+fn test_invalid_spannables_are_removed_and_clamped() {
+  let mut spannables = vec![
+    Spannable { start: 0, len: 0, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 5, len: 1, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 2, len: 10, spannable_data: SpannableData::BoldText },
+  ];
+
+  normalize_spannables(4, &mut spannables, true);
+
+  assert_eq!(vec![Spannable { start: 2, len: 2, spannable_data: SpannableData::BoldText }], spannables);
+}
+
+#[test]
+fn test_spannables_are_sorted_and_crossing_ones_are_split() {
+  let mut spannables = vec![
+    Spannable { start: 3, len: 4, spannable_data: SpannableData::Italic },
+    Spannable { start: 0, len: 5, spannable_data: SpannableData::BoldText },
+    Spannable { start: 4, len: 4, spannable_data: SpannableData::Link(crate::PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: Option::None }) },
+  ];
+
+  normalize_spannables(10, &mut spannables, true);
+
+  // The link stays in one piece, bold and italic are split around its start and the end of the bold
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 4, spannable_data: SpannableData::BoldText },
+      Spannable { start: 3, len: 1, spannable_data: SpannableData::Italic },
+      Spannable { start: 4, len: 4, spannable_data: SpannableData::Link(crate::PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: Option::None }) },
+      Spannable { start: 4, len: 1, spannable_data: SpannableData::BoldText },
+      Spannable { start: 4, len: 1, spannable_data: SpannableData::Italic },
+      Spannable { start: 5, len: 2, spannable_data: SpannableData::Italic },
+    ],
+    spannables
+  );
+}
@@ -0,0 +1,108 @@
+use crate::{Spannable, SpannableData, ParsedSpannableText};
+
+/// A spannable and the spannables nested inside of it. Children are drawn on top of their parent.
+#[derive(Debug, PartialEq)]
+pub struct SpannableNode<'a> {
+  pub spannable: &'a Spannable,
+  pub children: Vec<SpannableNode<'a>>
+}
+
+impl SpannableData {
+  /// Spannables covering the same text are layered by this priority, the one with the higher
+  /// priority is drawn on top (spoiler > link > greentext > background styling).
+  pub fn layer_priority(&self) -> u8 {
+    return match self {
+      SpannableData::ThemeJson { .. }
+      | SpannableData::ListItem { .. }
+      | SpannableData::Heading { .. }
+      | SpannableData::TableCell
+      | SpannableData::SjisArt
+      | SpannableData::CodeBlock
+      | SpannableData::TextBackgroundColorRaw { .. }
+      | SpannableData::TextBackgroundColorId { .. } => 0,
+      SpannableData::GreenText => 1,
+      SpannableData::Link(_) => 3,
+      SpannableData::MediaLink { .. } => 4,
+      SpannableData::Spoiler => 5,
+      _ => 2
+    }
+  }
+}
+
+impl ParsedSpannableText {
+  pub fn spannable_tree(&self) -> Vec<SpannableNode> {
+    return build_spannable_tree(&self.spannables);
+  }
+}
+
+/// Builds a tree out of normalized (see normalize_spannables()) spannables. Spannables that cover
+/// the same text are nested by their layer_priority().
+pub fn build_spannable_tree(spannables: &Vec<Spannable>) -> Vec<SpannableNode> {
+  let mut sorted_spannables = spannables.iter().collect::<Vec<&Spannable>>();
+  sorted_spannables.sort_by(|first, second| {
+    return first.start.cmp(&second.start)
+      .then(second.len.cmp(&first.len))
+      .then(first.spannable_data.layer_priority().cmp(&second.spannable_data.layer_priority()));
+  });
+
+  let mut roots: Vec<SpannableNode> = Vec::new();
+  let mut stack: Vec<SpannableNode> = Vec::new();
+
+  for spannable in sorted_spannables {
+    while let Option::Some(top) = stack.last() {
+      let contains = top.spannable.start <= spannable.start
+        && spannable.start + spannable.len <= top.spannable.start + top.spannable.len;
+
+      if contains {
+        break;
+      }
+
+      pop_node(&mut stack, &mut roots);
+    }
+
+    stack.push(SpannableNode { spannable, children: Vec::new() });
+  }
+
+  while !stack.is_empty() {
+    pop_node(&mut stack, &mut roots);
+  }
+
+  return roots;
+}
+
+fn pop_node<'a>(stack: &mut Vec<SpannableNode<'a>>, roots: &mut Vec<SpannableNode<'a>>) {
+  let node = stack.pop().unwrap();
+
+  match stack.last_mut() {
+    None => roots.push(node),
+    Some(parent) => parent.children.push(node)
+  }
+}
+
+#[test]
+fn test_spoilered_quote_inside_greentext() {
+  let spannables = vec![
+    Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+    Spannable { start: 0, len: 5, spannable_data: SpannableData::Spoiler },
+    Spannable { start: 0, len: 5, spannable_data: SpannableData::Link(crate::PostLink::Quote { post_no: 123, is_you: false, is_op: false, preview: Option::None }) },
+    Spannable { start: 7, len: 1, spannable_data: SpannableData::BoldText },
+  ];
+
+  let tree = build_spannable_tree(&spannables);
+
+  assert_eq!(
+    vec![
+      SpannableNode {
+        spannable: &spannables[0],
+        children: vec![
+          SpannableNode {
+            spannable: &spannables[2],
+            children: vec![SpannableNode { spannable: &spannables[1], children: vec![] }]
+          }
+        ]
+      },
+      SpannableNode { spannable: &spannables[3], children: vec![] },
+    ],
+    tree
+  );
+}
@@ -0,0 +1,134 @@
+use crate::{Element, SpannableStyleHint, ChanThemeColorId};
+use crate::util::color_decoder::color_name_to_color_hex;
+
+// Colors sites use in inline styles for things that have a theme color in the client
+const COLOR_ROLES: &[(&str, ChanThemeColorId)] = &[
+  ("#789922", ChanThemeColorId::PostInlineQuoteColor),
+  ("#117743", ChanThemeColorId::PostNameColor),
+  ("#0f0c5d", ChanThemeColorId::PostSubjectColor),
+  ("#cc1105", ChanThemeColorId::PostSubjectColor),
+  ("#ff0000", ChanThemeColorId::AccentColor),
+  ("#dd0000", ChanThemeColorId::AccentColor),
+];
+
+// Classes whose site css makes the text bold
+const BOLD_CLASSES: &[&str] = &["fortune"];
+
+impl SpannableStyleHint {
+  pub fn is_empty(&self) -> bool {
+    return self.color_role.is_none() && self.background_role.is_none() && !self.is_bold && !self.is_italic;
+  }
+}
+
+/// Whether the element carries anything the site css styles it by: an inline style or one of the
+/// known classes. Plain <b>/<i> tags are already covered by the Bold/Italic spannables so the tag
+/// alone doesn't count, it only adds to the hint of an element that has one of the others.
+pub fn has_style_hint_source(element: &Element) -> bool {
+  return element.get_attribute("style").is_some()
+    || BOLD_CLASSES.iter().any(|class_name| element.has_class(class_name));
+}
+
+/// What the site css (inline style, tag and classes) of the element does to its text, in terms of
+/// client theme colors and font flags
+pub fn decode_style_hint(element: &Element) -> SpannableStyleHint {
+  let mut style_hint = SpannableStyleHint::default();
+
+  match element.tag_name.as_ref() {
+    "b" | "strong" => style_hint.is_bold = true,
+    "i" | "em" => style_hint.is_italic = true,
+    _ => {}
+  }
+
+  if BOLD_CLASSES.iter().any(|class_name| element.has_class(class_name)) {
+    style_hint.is_bold = true;
+  }
+
+  for (name, value) in element.parsed_style() {
+    match name.as_str() {
+      "color" => {
+        if let Option::Some(color_role) = color_to_color_role(&value) {
+          style_hint.color_role = Option::Some(color_role);
+        }
+      }
+      "background" | "background-color" => {
+        if let Option::Some(color_role) = color_to_color_role(&value) {
+          style_hint.background_role = Option::Some(color_role);
+        }
+      }
+      "font-weight" => {
+        style_hint.is_bold = match value.to_ascii_lowercase().as_str() {
+          "bold" | "bolder" => true,
+          "normal" | "lighter" => false,
+          weight => weight.parse::<u32>().map(|weight| weight >= 600).unwrap_or(style_hint.is_bold)
+        };
+      }
+      "font-style" => {
+        style_hint.is_italic = value.eq_ignore_ascii_case("italic") || value.eq_ignore_ascii_case("oblique");
+      }
+      _ => {}
+    }
+  }
+
+  return style_hint;
+}
+
+fn color_to_color_role(color: &str) -> Option<ChanThemeColorId> {
+  let color_hex = if color.starts_with('#') {
+    expand_short_color_hex(color)
+  } else {
+    color_name_to_color_hex(color)?
+  };
+
+  return COLOR_ROLES.iter()
+    .find(|(role_color_hex, _)| role_color_hex.eq_ignore_ascii_case(&color_hex))
+    .map(|(_, color_role)| color_role.clone());
+}
+
+// "#d00" -> "#dd0000"
+fn expand_short_color_hex(color_hex: &str) -> String {
+  if color_hex.len() != 4 {
+    return String::from(color_hex);
+  }
+
+  let mut expanded = String::with_capacity(7);
+  expanded.push('#');
+
+  for ch in color_hex.chars().skip(1) {
+    expanded.push(ch);
+    expanded.push(ch);
+  }
+
+  return expanded;
+}
+
+#[cfg(test)]
+fn test_element(tag_name: &str, style: &str) -> Element {
+  return Element::new(tag_name, false).with_attr("style", style);
+}
+
+#[test]
+fn test_decode_style_hint() {
+  let element = test_element("b", "color: #789922; font-style: italic");
+
+  assert_eq!(
+    SpannableStyleHint {
+      color_role: Option::Some(ChanThemeColorId::PostInlineQuoteColor),
+      background_role: Option::None,
+      is_bold: true,
+      is_italic: true
+    },
+    decode_style_hint(&element)
+  );
+
+  let element = test_element("span", "background-color:#d00;font-weight:400");
+
+  assert_eq!(
+    SpannableStyleHint {
+      color_role: Option::None,
+      background_role: Option::Some(ChanThemeColorId::AccentColor),
+      is_bold: false,
+      is_italic: false
+    },
+    decode_style_hint(&element)
+  );
+}
@@ -1,6 +1,7 @@
 use crate::SpannableData;
 use std::collections::HashMap;
 use crate::util::color_decoder::color_name_to_color_hex;
+use crate::html_parser::style::parse_inline_style;
 
 lazy_static::lazy_static! {
   static ref STYLE_DECODERS: HashMap<&'static str, fn(&str) -> Option<SpannableData>> = {
@@ -41,26 +42,15 @@ pub fn decode_style_spans(style_attr_value: &str) -> Vec<SpannableData> {
     return Vec::new();
   }
 
-  let style_params: Vec<&str> = style_attr_value.split(';').collect();
+  let style_params = parse_inline_style(style_attr_value);
   if style_params.is_empty() {
     return Vec::new();
   }
 
   let mut output_spannables: Vec<SpannableData> = Vec::with_capacity(style_params.len());
 
-  for style_param in style_params {
-    if style_param.is_empty() {
-      continue;
-    }
-
-    let style_param_parts: Vec<&str> = style_param.split(':').collect();
-    if style_param_parts.len() != 2 {
-      continue;
-    }
-
-    let style_param_name = style_param_parts.get(0).unwrap().trim();
-    let style_param_value = style_param_parts.get(1).unwrap().trim();
-    let decoder_maybe = STYLE_DECODERS.get(style_param_name);
+  for (style_param_name, style_param_value) in style_params {
+    let decoder_maybe = STYLE_DECODERS.get(style_param_name.as_str());
 
     let decoder = if let Option::None = decoder_maybe {
       continue
@@ -68,7 +58,7 @@ pub fn decode_style_spans(style_attr_value: &str) -> Vec<SpannableData> {
       decoder_maybe.unwrap()
     };
 
-    let decoded_spannable_data = decoder(style_param_value);
+    let decoded_spannable_data = decoder(&style_param_value);
 
     if let Option::Some { .. } = decoded_spannable_data {
       output_spannables.push(decoded_spannable_data.unwrap());
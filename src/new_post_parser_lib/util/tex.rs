@@ -0,0 +1,120 @@
+use crate::{Spannable, SpannableData, TeXMode};
+use crate::util::text_cleanup::{remove_text_ranges, replace_text_ranges};
+use regex::Regex;
+
+// The client draws the rendered formula in place of this character
+const TEX_PLACEHOLDER: &str = "\u{FFFC}";
+
+lazy_static! {
+  // [math] is inline, [eqn] is a display mode (centered, on its own line) formula
+  static ref TEX_PATTERN: Regex = Regex::new(r"(?s)\[math\](.+?)\[/math\]|\[eqn\](.+?)\[/eqn\]").unwrap();
+}
+
+/// Finds the [math]...[/math] and [eqn]...[/eqn] regions (/sci/) and covers them with TeX spannables.
+/// The tags are removed, the TeX itself is either kept in the text or replaced with a placeholder
+/// depending on the [tex_mode]. Returns the new text if anything was found. Spannable offsets are
+/// expected to be in chars.
+pub fn extract_tex(text: &str, spannables: &mut Vec<Spannable>, tex_mode: &TeXMode) -> Option<String> {
+  if *tex_mode == TeXMode::Disabled || !text.contains('[') {
+    return Option::None;
+  }
+
+  // (byte range of the whole match, byte range of the TeX, display_mode)
+  let mut regions: Vec<((usize, usize), (usize, usize), bool)> = Vec::new();
+
+  for captures in TEX_PATTERN.captures_iter(text) {
+    let whole_match = captures.get(0).unwrap();
+    let (tex, display_mode) = match captures.get(1) {
+      Option::Some(tex) => (tex, false),
+      Option::None => (captures.get(2).unwrap(), true)
+    };
+
+    if tex.as_str().trim().is_empty() {
+      continue;
+    }
+
+    regions.push(((whole_match.start(), whole_match.end()), (tex.start(), tex.end()), display_mode));
+  }
+
+  if regions.is_empty() {
+    return Option::None;
+  }
+
+  return match tex_mode {
+    TeXMode::Disabled => Option::None,
+    TeXMode::Source => {
+      let mut removed_ranges: Vec<(usize, usize)> = Vec::with_capacity(regions.len() * 2);
+
+      for ((start, end), (tex_start, tex_end), display_mode) in regions {
+        spannables.push(Spannable {
+          start: text[..tex_start].chars().count(),
+          len: text[tex_start..tex_end].chars().count(),
+          spannable_data: SpannableData::TeX { display_mode, source: Option::None }
+        });
+
+        removed_ranges.push((start, tex_start));
+        removed_ranges.push((tex_end, end));
+      }
+
+      Option::Some(remove_text_ranges(text, spannables, &removed_ranges))
+    }
+    TeXMode::Placeholder => {
+      let replaced_ranges = regions.iter()
+        .map(|((start, end), _, _)| (*start, *end, TEX_PLACEHOLDER))
+        .collect::<Vec<(usize, usize, &str)>>();
+
+      let new_text = replace_text_ranges(text, spannables, &replaced_ranges);
+
+      // Every region is one placeholder char now
+      let mut removed_chars = 0;
+
+      for ((start, end), (tex_start, tex_end), display_mode) in regions {
+        spannables.push(Spannable {
+          start: text[..start].chars().count() - removed_chars,
+          len: 1,
+          spannable_data: SpannableData::TeX { display_mode, source: Option::Some(String::from(&text[tex_start..tex_end])) }
+        });
+
+        removed_chars += text[start..end].chars().count() - 1;
+      }
+
+      Option::Some(new_text)
+    }
+  };
+}
+
+#[test]
+fn test_extract_tex() {
+  let text = "ü [math]x^2[/math] and [eqn]\\int_0^1 f[/eqn] [math][/math]";
+
+  let mut spannables = vec![
+    Spannable { start: 0, len: 1, spannable_data: SpannableData::BoldText }
+  ];
+  assert_eq!(
+    Option::Some(String::from("ü x^2 and \\int_0^1 f [math][/math]")),
+    extract_tex(text, &mut spannables, &TeXMode::Source)
+  );
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 1, spannable_data: SpannableData::BoldText },
+      Spannable { start: 2, len: 3, spannable_data: SpannableData::TeX { display_mode: false, source: Option::None } },
+      Spannable { start: 10, len: 10, spannable_data: SpannableData::TeX { display_mode: true, source: Option::None } },
+    ],
+    spannables
+  );
+
+  let mut spannables = Vec::new();
+  assert_eq!(
+    Option::Some(String::from("ü \u{FFFC} and \u{FFFC} [math][/math]")),
+    extract_tex(text, &mut spannables, &TeXMode::Placeholder)
+  );
+  assert_eq!(
+    vec![
+      Spannable { start: 2, len: 1, spannable_data: SpannableData::TeX { display_mode: false, source: Option::Some(String::from("x^2")) } },
+      Spannable { start: 8, len: 1, spannable_data: SpannableData::TeX { display_mode: true, source: Option::Some(String::from("\\int_0^1 f")) } },
+    ],
+    spannables
+  );
+
+  assert_eq!(Option::None, extract_tex(text, &mut Vec::new(), &TeXMode::Disabled));
+}
@@ -0,0 +1,210 @@
+use crate::{Spannable, TextCleanupOptions};
+use regex::Regex;
+
+lazy_static! {
+  static ref EMBED_MARKER_PATTERN: Regex = Regex::new(r"(?i)[ \t]*(?:\[embed\]|\(embed\))").unwrap();
+  static ref DUPLICATE_YOU_MARKERS_PATTERN: Regex = Regex::new(r" \(You\)((?: \(You\))+)").unwrap();
+  static ref OP_MARKER_PATTERN: Regex = Regex::new(r" \(OP\)").unwrap();
+}
+
+/// Removes the junk selected by the options from the text and moves the spannables so that they
+/// still cover the same (remaining) text. Spannables that only covered removed text are dropped.
+/// Spannable offsets are expected to be in chars.
+pub fn cleanup_text(text: &str, spannables: &mut Vec<Spannable>, text_cleanup_options: &TextCleanupOptions) -> Option<String> {
+  let removed_ranges = collect_removed_ranges(text, text_cleanup_options);
+  if removed_ranges.is_empty() {
+    return Option::None;
+  }
+
+  return Option::Some(remove_text_ranges(text, spannables, &removed_ranges));
+}
+
+/// Shortens the runs of more than [max_blank_lines] blank lines (empty lines between two line
+/// breaks) to [max_blank_lines]. Spannable offsets are expected to be in chars.
+pub fn collapse_blank_lines(text: &str, spannables: &mut Vec<Spannable>, max_blank_lines: usize) -> Option<String> {
+  let max_line_breaks = max_blank_lines + 1;
+  let mut removed_ranges: Vec<(usize, usize)> = Vec::new();
+  let mut line_breaks_start = 0;
+  let mut line_breaks_count = 0;
+
+  // Line breaks are single byte chars so the char count of a run is the same as its byte count
+  for (index, ch) in text.char_indices().chain(std::iter::once((text.len(), '\0'))) {
+    if ch == '\n' {
+      if line_breaks_count == 0 {
+        line_breaks_start = index;
+      }
+
+      line_breaks_count += 1;
+      continue;
+    }
+
+    if line_breaks_count > max_line_breaks {
+      removed_ranges.push((line_breaks_start + max_line_breaks, index));
+    }
+
+    line_breaks_count = 0;
+  }
+
+  if removed_ranges.is_empty() {
+    return Option::None;
+  }
+
+  return Option::Some(remove_text_ranges(text, spannables, &removed_ranges));
+}
+
+/// Removes the (sorted, non overlapping) byte ranges from the text and moves the spannables (chars)
+/// so that they still cover the same remaining text. Spannables that only covered removed text are
+/// dropped.
+pub fn remove_text_ranges(text: &str, spannables: &mut Vec<Spannable>, removed_ranges: &Vec<(usize, usize)>) -> String {
+  let replaced_ranges = removed_ranges.iter()
+    .map(|(start, end)| (*start, *end, ""))
+    .collect::<Vec<(usize, usize, &str)>>();
+
+  return replace_text_ranges(text, spannables, &replaced_ranges);
+}
+
+/// Same as [remove_text_ranges] but every range is replaced with its replacement text. Spannables
+/// that end after a replaced range cover its replacement, spannables that started or ended inside of
+/// it are cut at its start.
+pub fn replace_text_ranges(text: &str, spannables: &mut Vec<Spannable>, replaced_ranges: &Vec<(usize, usize, &str)>) -> String {
+  let mut new_text = String::with_capacity(text.len());
+  let mut prev_end = 0;
+
+  for (start, end, replacement) in replaced_ranges {
+    new_text.push_str(&text[prev_end..*start]);
+    new_text.push_str(replacement);
+    prev_end = *end;
+  }
+
+  new_text.push_str(&text[prev_end..]);
+
+  let replaced_char_ranges = replaced_ranges.iter()
+    .map(|(start, end, replacement)| {
+      let char_start = text[..*start].chars().count();
+      return (char_start, char_start + text[*start..*end].chars().count(), replacement.chars().count());
+    })
+    .collect::<Vec<(usize, usize, usize)>>();
+
+  for spannable in spannables.iter_mut() {
+    let start = shift_offset(spannable.start, &replaced_char_ranges);
+    let end = shift_offset(spannable.start + spannable.len, &replaced_char_ranges);
+
+    spannable.start = start;
+    spannable.len = end - start;
+  }
+
+  spannables.retain(|spannable| spannable.is_valid());
+  return new_text;
+}
+
+/// Sorted non overlapping byte ranges
+fn collect_removed_ranges(text: &str, text_cleanup_options: &TextCleanupOptions) -> Vec<(usize, usize)> {
+  let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+  if text_cleanup_options.strip_embed_markers {
+    for found in EMBED_MARKER_PATTERN.find_iter(text) {
+      ranges.push((found.start(), found.end()));
+    }
+  }
+
+  if text_cleanup_options.strip_duplicate_you_markers {
+    for captures in DUPLICATE_YOU_MARKERS_PATTERN.captures_iter(text) {
+      // The first (You) stays
+      let duplicates = captures.get(1).unwrap();
+      ranges.push((duplicates.start(), duplicates.end()));
+    }
+  }
+
+  if text_cleanup_options.strip_op_markers {
+    for found in OP_MARKER_PATTERN.find_iter(text) {
+      ranges.push((found.start(), found.end()));
+    }
+  }
+
+  for pattern in &text_cleanup_options.strip_patterns {
+    for found in pattern.find_iter(text) {
+      if found.start() < found.end() {
+        ranges.push((found.start(), found.end()));
+      }
+    }
+  }
+
+  ranges.sort();
+
+  let mut merged_ranges: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+
+  for (start, end) in ranges {
+    match merged_ranges.last_mut() {
+      Some(last) if start <= last.1 => last.1 = last.1.max(end),
+      _ => merged_ranges.push((start, end))
+    }
+  }
+
+  return merged_ranges;
+}
+
+fn shift_offset(offset: usize, replaced_char_ranges: &Vec<(usize, usize, usize)>) -> usize {
+  let mut removed_before = 0;
+  let mut inserted_before = 0;
+
+  for (start, end, replacement_len) in replaced_char_ranges {
+    if *start >= offset {
+      break;
+    }
+
+    removed_before += (*end).min(offset) - start;
+
+    if offset >= *end {
+      inserted_before += replacement_len;
+    }
+  }
+
+  return offset - removed_before + inserted_before;
+}
+
+#[test]
+fn test_cleanup_text() {
+  let text = ">>1 (OP) (You) (You) link [Embed] end";
+  let mut spannables = vec![
+    Spannable { start: 0, len: 20, spannable_data: crate::SpannableData::Link(crate::PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: Option::None }) },
+    Spannable { start: 21, len: 4, spannable_data: crate::SpannableData::BoldText },
+    Spannable { start: 26, len: 7, spannable_data: crate::SpannableData::Italic },
+  ];
+
+  let text_cleanup_options = TextCleanupOptions {
+    strip_embed_markers: true,
+    strip_duplicate_you_markers: true,
+    strip_op_markers: true,
+    strip_patterns: Vec::new()
+  };
+
+  let cleaned_text = cleanup_text(text, &mut spannables, &text_cleanup_options);
+
+  assert_eq!(Option::Some(String::from(">>1 (You) link end")), cleaned_text);
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 9, spannable_data: crate::SpannableData::Link(crate::PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: Option::None }) },
+      Spannable { start: 10, len: 4, spannable_data: crate::SpannableData::BoldText },
+    ],
+    spannables
+  );
+}
+
+#[test]
+fn test_collapse_blank_lines() {
+  let text = "a\n\n\n\nb\n\nc\n\n\n";
+  let mut spannables = vec![
+    Spannable { start: 0, len: 6, spannable_data: crate::SpannableData::BoldText },
+    Spannable { start: 5, len: 4, spannable_data: crate::SpannableData::Italic },
+  ];
+
+  assert_eq!(Option::None, collapse_blank_lines(text, &mut Vec::new(), 3));
+  assert_eq!(Option::Some(String::from("a\n\nb\n\nc\n\n")), collapse_blank_lines(text, &mut spannables, 1));
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 4, spannable_data: crate::SpannableData::BoldText },
+      Spannable { start: 3, len: 4, spannable_data: crate::SpannableData::Italic },
+    ],
+    spannables
+  );
+}
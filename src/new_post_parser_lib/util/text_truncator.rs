@@ -0,0 +1,65 @@
+use crate::Spannable;
+use crate::util::span_offset_converter::grapheme_offsets;
+
+/// Cuts the text after [max_length] chars (moving the cut back to the start of the grapheme cluster
+/// it falls into so that emojis and combining characters are never split), drops the trailing
+/// whitespace of the remaining text and appends the ellipsis. Spannables past the cut are dropped,
+/// the ones crossing it are clamped, none of them cover the ellipsis. None when the text is not
+/// longer than [max_length]. Spannable offsets are expected to be in chars.
+pub fn truncate_text(text: &str, spannables: &mut Vec<Spannable>, max_length: usize, ellipsis: &str) -> Option<String> {
+  let chars_count = text.chars().count();
+  if chars_count <= max_length {
+    return Option::None;
+  }
+
+  // (cluster, cluster) for the chars that start a cluster, (cluster - 1, cluster) for the rest
+  let offsets = grapheme_offsets(text);
+
+  let mut cut_index = max_length;
+  while cut_index > 0 && offsets[cut_index].0 != offsets[cut_index].1 {
+    cut_index -= 1;
+  }
+
+  let cut_byte_index = text.char_indices().nth(cut_index).map(|(index, _)| index).unwrap_or(text.len());
+  let kept_text = text[..cut_byte_index].trim_end();
+  let kept_length = kept_text.chars().count();
+
+  spannables.retain(|spannable| spannable.start < kept_length);
+
+  for spannable in spannables.iter_mut() {
+    spannable.len = spannable.len.min(kept_length - spannable.start);
+  }
+
+  let mut truncated_text = String::with_capacity(kept_text.len() + ellipsis.len());
+  truncated_text.push_str(kept_text);
+  truncated_text.push_str(ellipsis);
+
+  return Option::Some(truncated_text);
+}
+
+#[test]
+fn test_truncate_text() {
+  use crate::SpannableData;
+
+  let text = "Hello world, 👍🏻 text";
+
+  let mut spannables = vec![
+    Spannable { start: 0, len: 5, spannable_data: SpannableData::BoldText },
+    Spannable { start: 6, len: 11, spannable_data: SpannableData::Italic },
+    Spannable { start: 18, len: 4, spannable_data: SpannableData::Underline },
+  ];
+
+  // The cut falls between the thumbs up and its skin tone modifier
+  let truncated_text = truncate_text(text, &mut spannables, 14, "…");
+
+  assert_eq!(Option::Some(String::from("Hello world,…")), truncated_text);
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 5, spannable_data: SpannableData::BoldText },
+      Spannable { start: 6, len: 6, spannable_data: SpannableData::Italic },
+    ],
+    spannables
+  );
+
+  assert_eq!(Option::None, truncate_text(text, &mut Vec::new(), 100, "…"));
+}
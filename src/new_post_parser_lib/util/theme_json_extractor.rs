@@ -30,9 +30,13 @@ pub fn detect_and_extract_theme_json(total_text: &str, out_spannables: &mut Vec<
     let theme_name = try_extract_theme_name(&total_text[json_open_bracket_index..json_end_bracket_index]);
     let is_light_theme = is_light_theme_maybe.unwrap();
 
+    // json_parts are byte offsets while spannables are measured in chars
+    let start = total_text[..json_open_bracket_index].chars().count();
+    let len = total_text[json_open_bracket_index..json_end_bracket_index].chars().count();
+
     let spannable = Spannable {
-      start: json_open_bracket_index,
-      len: json_end_bracket_index - json_open_bracket_index,
+      start,
+      len,
       spannable_data: SpannableData::ThemeJson { theme_name, is_light_theme }
     };
 
@@ -0,0 +1,156 @@
+use crate::{Spannable, SpannableData, WhitespacePolicy};
+use crate::util::text_cleanup::remove_text_ranges;
+
+/// Removes the whitespace selected by the policy and moves the spannables so that they still cover
+/// the same (remaining) text. The text of code blocks, inline code and sjis art is kept as is.
+/// Spannable offsets are expected to be in chars.
+pub fn normalize_whitespace(text: &str, spannables: &mut Vec<Spannable>, whitespace_policy: &WhitespacePolicy) -> Option<String> {
+  if !whitespace_policy.collapse_runs && !whitespace_policy.trim_line_ends && !whitespace_policy.trim_post_ends {
+    return Option::None;
+  }
+
+  let preserved_ranges = collect_preserved_ranges(text, spannables);
+  let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+  if whitespace_policy.trim_post_ends {
+    let leading_len = text.len() - text.trim_start().len();
+    if leading_len > 0 {
+      ranges.push((0, leading_len));
+    }
+
+    let trimmed_end = text.trim_end().len();
+    if trimmed_end < text.len() {
+      ranges.push((trimmed_end, text.len()));
+    }
+  }
+
+  // (start, end) byte ranges of the runs of spaces and tabs
+  let mut runs: Vec<(usize, usize)> = Vec::new();
+  let mut run_start: Option<usize> = Option::None;
+
+  for (index, ch) in text.char_indices() {
+    let is_space = ch == ' ' || ch == '\t';
+
+    match (is_space, run_start) {
+      (true, Option::None) => run_start = Option::Some(index),
+      (false, Option::Some(start)) => {
+        runs.push((start, index));
+        run_start = Option::None;
+      }
+      _ => {}
+    }
+  }
+
+  if let Option::Some(start) = run_start {
+    runs.push((start, text.len()));
+  }
+
+  for (start, end) in runs {
+    let is_line_end = end == text.len() || text[end..].starts_with('\n') || text[end..].starts_with("\r\n");
+
+    if whitespace_policy.trim_line_ends && is_line_end {
+      ranges.push((start, end));
+    } else if whitespace_policy.collapse_runs && end - start > 1 {
+      // Spaces and tabs are single byte chars, the first one of the run stays
+      ranges.push((start + 1, end));
+    }
+  }
+
+  // The preserved text is cut out of the ranges ("  " of "<pre>a  </pre>  " is kept even though
+  // the trailing whitespace of the post starts there)
+  for (preserved_start, preserved_end) in preserved_ranges {
+    ranges = ranges.into_iter()
+      .flat_map(|(start, end)| {
+        if preserved_end <= start || preserved_start >= end {
+          return vec![(start, end)];
+        }
+
+        let mut pieces = Vec::with_capacity(2);
+
+        if start < preserved_start {
+          pieces.push((start, preserved_start));
+        }
+
+        if preserved_end < end {
+          pieces.push((preserved_end, end));
+        }
+
+        return pieces;
+      })
+      .collect();
+  }
+
+  if ranges.is_empty() {
+    return Option::None;
+  }
+
+  ranges.sort();
+
+  let mut merged_ranges: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+
+  for (start, end) in ranges {
+    match merged_ranges.last_mut() {
+      Some(last) if start <= last.1 => last.1 = last.1.max(end),
+      _ => merged_ranges.push((start, end))
+    }
+  }
+
+  return Option::Some(remove_text_ranges(text, spannables, &merged_ranges));
+}
+
+/// Byte ranges of the text that must be kept as is
+fn collect_preserved_ranges(text: &str, spannables: &Vec<Spannable>) -> Vec<(usize, usize)> {
+  let preserved_spannables = spannables.iter()
+    .filter(|spannable| {
+      return match spannable.spannable_data {
+        SpannableData::CodeBlock | SpannableData::InlineCode | SpannableData::SjisArt => true,
+        _ => false
+      };
+    })
+    .collect::<Vec<&Spannable>>();
+
+  if preserved_spannables.is_empty() {
+    return Vec::new();
+  }
+
+  // char offset -> byte offset, the last one is the end of the text
+  let byte_offsets = text.char_indices()
+    .map(|(index, _)| index)
+    .chain(std::iter::once(text.len()))
+    .collect::<Vec<usize>>();
+
+  let to_byte_offset = |char_offset: usize| byte_offsets[char_offset.min(byte_offsets.len() - 1)];
+
+  return preserved_spannables.iter()
+    .map(|spannable| (to_byte_offset(spannable.start), to_byte_offset(spannable.start + spannable.len)))
+    .collect();
+}
+
+#[test]
+fn test_normalize_whitespace() {
+  let text = "  a  \tb   \nc  d\t\n  e  ";
+
+  let whitespace_policy = WhitespacePolicy {
+    collapse_runs: true,
+    trim_line_ends: true,
+    trim_post_ends: true
+  };
+
+  let mut spannables = vec![
+    Spannable { start: 2, len: 5, spannable_data: SpannableData::BoldText },
+    Spannable { start: 11, len: 4, spannable_data: SpannableData::InlineCode },
+  ];
+
+  let normalized_text = normalize_whitespace(text, &mut spannables, &whitespace_policy);
+
+  assert_eq!(Option::Some(String::from("a b\nc  d\n e")), normalized_text);
+  assert_eq!(
+    vec![
+      Spannable { start: 0, len: 3, spannable_data: SpannableData::BoldText },
+      Spannable { start: 4, len: 4, spannable_data: SpannableData::InlineCode },
+    ],
+    spannables
+  );
+
+  assert_eq!(Option::None, normalize_whitespace(text, &mut Vec::new(), &WhitespacePolicy::default()));
+}
@@ -0,0 +1,88 @@
+use crate::WordBreakMode;
+use crate::html_parser::node::Node;
+
+const WBR_TAG: &str = "wbr";
+const SOFT_HYPHEN: char = '\u{AD}';
+const ZERO_WIDTH_SPACE: &str = "\u{200B}";
+
+impl WordBreakMode {
+  pub fn text(&self) -> &'static str {
+    return match self {
+      WordBreakMode::Strip => "",
+      WordBreakMode::ZeroWidthSpace => ZERO_WIDTH_SPACE
+    };
+  }
+}
+
+/// <wbr> elements are replaced with the text of the word_break_mode and joined with the text nodes
+/// around them, soft hyphens are removed from the text. Sites put both into long words and links
+/// and since links are detected per text node "https://exam<wbr>ple.com" must become a single text
+/// node before the nodes are converted into text.
+pub fn join_word_breaks(nodes: &mut Vec<Node>, word_break_mode: &WordBreakMode) {
+  let mut stack = vec![nodes];
+
+  while let Option::Some(nodes) = stack.pop() {
+    join_word_breaks_shallow(nodes, word_break_mode);
+
+    for node in nodes.iter_mut() {
+      if let Node::Element(element) = node {
+        stack.push(&mut element.children);
+      }
+    }
+  }
+}
+
+/// Only the [nodes] themselves, not their descendants
+fn join_word_breaks_shallow(nodes: &mut Vec<Node>, word_break_mode: &WordBreakMode) {
+  let has_word_breaks = nodes.iter().any(|node| {
+    return match node {
+      Node::Element(element) => element.tag_name == WBR_TAG,
+      Node::Text(text) => text.contains(SOFT_HYPHEN),
+      Node::Comment(_) => false
+    };
+  });
+
+  if has_word_breaks {
+    let mut joined_nodes: Vec<Node> = Vec::with_capacity(nodes.len());
+
+    for node in nodes.drain(..) {
+      let text = match node {
+        Node::Element(ref element) if element.tag_name == WBR_TAG => String::from(word_break_mode.text()),
+        Node::Text(text) => text.replace(SOFT_HYPHEN, ""),
+        node => {
+          joined_nodes.push(node);
+          continue;
+        }
+      };
+
+      match joined_nodes.last_mut() {
+        Option::Some(Node::Text(prev_text)) => prev_text.push_str(&text),
+        _ => {
+          if !text.is_empty() {
+            joined_nodes.push(Node::Text(text));
+          }
+        }
+      }
+    }
+
+    *nodes = joined_nodes;
+  }
+}
+
+#[test]
+fn test_join_word_breaks() {
+  use crate::HtmlParser;
+
+  let html = "https://exam<wbr>ple.com/lo\u{AD}ng <b>wo<wbr>rd</b><wbr><i>x</i>";
+
+  let mut nodes = HtmlParser::new().parse(html).unwrap();
+  join_word_breaks(&mut nodes, &WordBreakMode::Strip);
+  assert_eq!("https://example.com/long <b>word</b><i>x</i>", crate::html_parser::renderer::render_nodes(&nodes));
+
+  let mut nodes = HtmlParser::new().parse(html).unwrap();
+  join_word_breaks(&mut nodes, &WordBreakMode::ZeroWidthSpace);
+  assert_eq!(
+    "https://exam\u{200B}ple.com/long <b>wo\u{200B}rd</b>\u{200B}<i>x</i>",
+    crate::html_parser::renderer::render_nodes(&nodes)
+  );
+}
@@ -0,0 +1,110 @@
+//! JS api (the "wasm" feature, build with `wasm-pack build -- --features wasm`):
+//!
+//! ```js
+//! const parsed = parseComment(html, {
+//!   siteName: "4chan", boardCode: "g", threadNo: 1, postNo: 2, postSubNo: 0,
+//!   threadPosts: [1, 2], myReplies: [2]
+//! });
+//! // { text: ">>1 (OP)", spannables: [{ start: 0, len: 8, kind: "Quote", flags: 2, number1: 1,
+//! //   number2: 0, text1: null, text2: null }] }
+//! ```
+//!
+//! The spannables are the flattened ones of the C api (see ffi::FfiSpannable for what the payload
+//! fields mean), the kind is the name of the FfiSpannableKind. Post numbers are js numbers so they
+//! must fit into 2^53.
+
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+use js_sys::{Array, Object, Reflect};
+use crate::{PostParser, PostParserContext, PostRaw};
+use crate::ffi::flatten_spannable;
+
+#[wasm_bindgen(js_name = parseComment)]
+pub fn parse_comment(html: &str, context: JsValue) -> Result<JsValue, JsValue> {
+  let post_raw = PostRaw::new(
+    &get_string(&context, "siteName")?,
+    &get_string(&context, "boardCode")?,
+    get_number(&context, "threadNo")?,
+    get_number(&context, "postNo")?,
+    get_number(&context, "postSubNo").unwrap_or(0),
+    html
+  );
+
+  let post_parser_context = PostParserContext::new(
+    get_post_no_set(&context, "myReplies")?,
+    get_post_no_set(&context, "threadPosts")?
+  );
+
+  let parsed_comment = PostParser::new(&post_parser_context).parse_comment(&post_raw);
+  let spannables = Array::new();
+
+  for spannable in parsed_comment.spannables.iter() {
+    let flat_spannable = flatten_spannable(spannable);
+    let js_spannable = Object::new();
+
+    set(&js_spannable, "start", JsValue::from_f64(flat_spannable.start as f64))?;
+    set(&js_spannable, "len", JsValue::from_f64(flat_spannable.len as f64))?;
+    set(&js_spannable, "kind", JsValue::from_str(&format!("{:?}", flat_spannable.kind)))?;
+    set(&js_spannable, "flags", JsValue::from_f64(flat_spannable.flags as f64))?;
+    set(&js_spannable, "number1", JsValue::from_f64(flat_spannable.number1 as f64))?;
+    set(&js_spannable, "number2", JsValue::from_f64(flat_spannable.number2 as f64))?;
+    set(&js_spannable, "text1", to_js_string(flat_spannable.text1))?;
+    set(&js_spannable, "text2", to_js_string(flat_spannable.text2))?;
+
+    spannables.push(&js_spannable);
+  }
+
+  let result = Object::new();
+  set(&result, "text", JsValue::from_str(parsed_comment.parsed_text.as_str()))?;
+  set(&result, "spannables", spannables.into())?;
+
+  return Ok(result.into());
+}
+
+fn set(object: &Object, key: &str, value: JsValue) -> Result<(), JsValue> {
+  Reflect::set(object, &JsValue::from_str(key), &value)?;
+  return Ok(());
+}
+
+fn to_js_string(string: Option<&str>) -> JsValue {
+  return match string {
+    Option::None => JsValue::NULL,
+    Option::Some(string) => JsValue::from_str(string)
+  };
+}
+
+fn get_string(context: &JsValue, key: &str) -> Result<String, JsValue> {
+  return Reflect::get(context, &JsValue::from_str(key))?
+    .as_string()
+    .ok_or_else(|| JsValue::from_str(&format!("context.{} must be a string", key)));
+}
+
+fn get_number(context: &JsValue, key: &str) -> Result<u64, JsValue> {
+  return Reflect::get(context, &JsValue::from_str(key))?
+    .as_f64()
+    .map(|number| number as u64)
+    .ok_or_else(|| JsValue::from_str(&format!("context.{} must be a number", key)));
+}
+
+// Missing arrays are treated as empty
+fn get_post_no_set(context: &JsValue, key: &str) -> Result<HashSet<u64>, JsValue> {
+  let value = Reflect::get(context, &JsValue::from_str(key))?;
+  if value.is_undefined() || value.is_null() {
+    return Ok(HashSet::new());
+  }
+
+  if !Array::is_array(&value) {
+    return Err(JsValue::from_str(&format!("context.{} must be an array", key)));
+  }
+
+  let mut post_nos = HashSet::new();
+
+  for post_no in Array::from(&value).iter() {
+    match post_no.as_f64() {
+      Option::Some(post_no) => post_nos.insert(post_no as u64),
+      Option::None => return Err(JsValue::from_str(&format!("context.{} must only contain numbers", key)))
+    };
+  }
+
+  return Ok(post_nos);
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod test_main {
+  use new_post_parser_lib::{PostParserContext, PostRaw, PostParser, QuoteSuffixes};
+  use std::collections::HashSet;
+  use std::alloc::{GlobalAlloc, System, Layout};
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  struct CountingAllocator {}
+
+  static ALLOCATIONS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+  unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+      ALLOCATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
+      return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+      System.dealloc(ptr, layout);
+    }
+  }
+
+  #[global_allocator]
+  static GLOBAL: CountingAllocator = CountingAllocator {};
+
+  const QUOTES_COUNT: u64 = 500;
+
+  fn create_quote_everyone_post() -> (PostRaw, PostParserContext) {
+    let mut comment = String::new();
+    let mut thread_posts = HashSet::new();
+    let mut my_replies = HashSet::new();
+
+    for post_no in 1..=QUOTES_COUNT {
+      comment.push_str(format!("<a href=\"#p{}\" class=\"quotelink\">&gt;&gt;{}</a><br>", post_no, post_no).as_str());
+
+      if post_no % 2 == 0 {
+        thread_posts.insert(post_no);
+      }
+
+      if post_no % 3 == 0 {
+        my_replies.insert(post_no);
+      }
+    }
+
+    let post_raw = PostRaw::new("4chan", "g", 1, QUOTES_COUNT + 1, 0, comment.as_str());
+    let post_parser_context = PostParserContext::new(my_replies, thread_posts);
+
+    return (post_raw, post_parser_context);
+  }
+
+  fn count_allocations<T>(block: impl FnOnce() -> T) -> (T, usize) {
+    let allocations_before = ALLOCATIONS_COUNT.load(Ordering::SeqCst);
+    let result = block();
+    let allocations = ALLOCATIONS_COUNT.load(Ordering::SeqCst) - allocations_before;
+
+    return (result, allocations);
+  }
+
+  #[test]
+  fn allocations_test_quote_everyone_post() {
+    let (post_raw, post_parser_context) = create_quote_everyone_post();
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let (_, no_suffixes_post_parser_context) = create_quote_everyone_post();
+    let no_suffixes_post_parser_context = no_suffixes_post_parser_context.with_quote_suffixes(QuoteSuffixes::none());
+    let no_suffixes_post_parser = PostParser::new(&no_suffixes_post_parser_context);
+
+    // The first parse initializes the lazy statics (regexes etc.)
+    no_suffixes_post_parser.parse_post(&post_raw);
+
+    let (post_comment_parsed, allocations) = count_allocations(|| post_parser.parse_post(&post_raw).post_comment_parsed);
+    let (_, no_suffixes_allocations) = count_allocations(|| no_suffixes_post_parser.parse_post(&post_raw));
+
+    // More than 400 of the quotes get " (OP)", " (You)" or " (DEAD)", those are static text parts
+    // so only the growth of the text parts vectors may cost a few allocations
+    assert!(
+      allocations < no_suffixes_allocations + (QUOTES_COUNT as usize / 10),
+      "allocations={}, no_suffixes_allocations={}",
+      allocations,
+      no_suffixes_allocations
+    );
+
+    assert_eq!(QUOTES_COUNT as usize, post_comment_parsed.spannables.len());
+    assert!(post_comment_parsed.parsed_text.starts_with(">>1 (OP) (DEAD)\n>>2\n>>3 (You) (DEAD)\n"));
+  }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod test_main {
+  use std::ffi::{CString, CStr};
+  use std::ptr;
+  use std::collections::HashSet;
+  use new_post_parser_lib::ffi::{post_parser_create, post_parser_parse_comment, post_parser_parsed_comment_destroy, post_parser_destroy, post_parser_last_error, FfiSpannableKind, FFI_FLAG_IS_YOU, encode_parsed_comment};
+  use new_post_parser_lib::{PostParserContext, PostParser, PostRaw, set_of};
+
+  #[test]
+  fn ffi_test_parse_comment() {
+    let my_replies = vec![2u64];
+    let thread_posts = vec![1u64, 2u64];
+
+    let site_name = CString::new("4chan").unwrap();
+    let board_code = CString::new("g").unwrap();
+    let comment = CString::new("<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a><br>https://github.com").unwrap();
+
+    unsafe {
+      let post_parser = post_parser_create(my_replies.as_ptr(), my_replies.len(), thread_posts.as_ptr(), thread_posts.len());
+
+      let parsed_comment = post_parser_parse_comment(
+        post_parser,
+        site_name.as_ptr(),
+        board_code.as_ptr(),
+        1,
+        3,
+        0,
+        comment.as_ptr()
+      );
+
+      assert!(!parsed_comment.is_null());
+      assert!(post_parser_last_error().is_null());
+      assert_eq!(">>2 (You)\nhttps://github.com", CStr::from_ptr((*parsed_comment).text).to_str().unwrap());
+
+      let spannables = std::slice::from_raw_parts((*parsed_comment).spannables, (*parsed_comment).spannables_len);
+      assert_eq!(2, spannables.len());
+
+      assert_eq!(FfiSpannableKind::Quote, spannables[0].kind);
+      assert_eq!(0, spannables[0].start);
+      assert_eq!(9, spannables[0].len);
+      assert_eq!(2, spannables[0].number1);
+      assert_eq!(FFI_FLAG_IS_YOU, spannables[0].flags);
+      assert!(spannables[0].text1.is_null());
+
+      assert_eq!(FfiSpannableKind::UrlLink, spannables[1].kind);
+      assert_eq!(10, spannables[1].start);
+      assert_eq!("https://github.com", CStr::from_ptr(spannables[1].text1).to_str().unwrap());
+
+      post_parser_parsed_comment_destroy(parsed_comment);
+
+      let parsed_comment = post_parser_parse_comment(post_parser, ptr::null(), board_code.as_ptr(), 1, 3, 0, comment.as_ptr());
+      assert!(parsed_comment.is_null());
+      assert_eq!(
+        "post_parser_parse_comment() bad string argument",
+        CStr::from_ptr(post_parser_last_error()).to_str().unwrap()
+      );
+
+      post_parser_destroy(post_parser);
+    }
+  }
+
+  #[test]
+  fn ffi_test_encode_parsed_comment() {
+    let post_parser_context = PostParserContext::new(set_of!(), set_of!(1u64));
+    let post_parser = PostParser::new(&post_parser_context);
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>");
+
+    let buffer = encode_parsed_comment(&post_parser.parse_comment(&post_raw));
+
+    let mut expected: Vec<u8> = Vec::new();
+    expected.extend_from_slice(&1u32.to_be_bytes());
+    expected.extend_from_slice(&8i32.to_be_bytes());
+    expected.extend_from_slice(">>1 (OP)".as_bytes());
+    expected.extend_from_slice(&1u32.to_be_bytes());
+    expected.extend_from_slice(&0u32.to_be_bytes());
+    expected.extend_from_slice(&8u32.to_be_bytes());
+    expected.extend_from_slice(&(FfiSpannableKind::Quote as u32).to_be_bytes());
+    expected.extend_from_slice(&2u32.to_be_bytes());
+    expected.extend_from_slice(&1u64.to_be_bytes());
+    expected.extend_from_slice(&0u64.to_be_bytes());
+    expected.extend_from_slice(&(-1i32).to_be_bytes());
+    expected.extend_from_slice(&(-1i32).to_be_bytes());
+
+    assert_eq!(expected, buffer);
+  }
+}
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod test_main {
+  use std::collections::HashSet;
+  use new_post_parser_lib::{PostParserContext, PostParser, PostRaw, ParserProfile, ParserLimits, HtmlParser};
+
+  // Same seeds as fuzz/corpus/parse_comment (without the profile byte)
+  const SEED_COMMENTS: &[&str] = &[
+    "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>Based<br><span class=\"quote\">&gt;implying</span><br><s>spoiler</s> https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+    "<a href=\"/g/thread/5#p6\" class=\"quotelink\">&gt;&gt;&gt;/g/6</a><br><pre class=\"prettyprint\">fn main() {\n  println!(\"&lt;hi&gt;\");\n}</pre><span class=\"deadlink\">&gt;&gt;3</span>",
+    "<a href=\"https://desuarchive.org/g/thread/1/#2\" class=\"backlink\" data-board=\"g\" data-post=\"2\">&gt;&gt;2</a><br><span class=\"greentext\">&gt;archived</span>",
+    ">>1\n[b]bold[/b] [spoiler]spoiler[/spoiler]\n[code]a < b[/code]\n[quote]quoted[/quote]",
+    ">>1\n**bold** *italic* ~~strike~~ `code`\n```\nfenced\n```\n[link](https://example.com)",
+    "<b>Ünïcödé 😀👍🏽</b><ul><li>item<li>item 2</ul><table><tr><td>a<td>b</table>",
+  ];
+
+  // Fragments that are inserted into the seeds
+  const FRAGMENTS: &[&str] = &[
+    "<", ">", "</", "/>", "<!--", "-->", "<![CDATA[", "]]>", "&", "&gt;", "&#", ";", "\"", "'", "=",
+    "<br>", "<s>", "</s>", "<span class=\"quote\">", "</span>", "<a href=\"#p", "\n", "[b]", "[/",
+    "**", "`", ">>", ">>>/", "https://", "😀", "\u{0301}", "\u{200D}", " ", "\t",
+  ];
+
+  /// xorshift64*, the sequence only depends on the seed so failures are reproducible
+  struct Random {
+    state: u64
+  }
+
+  impl Random {
+    fn next(&mut self) -> u64 {
+      self.state ^= self.state >> 12;
+      self.state ^= self.state << 25;
+      self.state ^= self.state >> 27;
+      return self.state.wrapping_mul(0x2545F4914F6CDD1D);
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+      return (self.next() % bound as u64) as usize;
+    }
+  }
+
+  fn char_boundary_at_or_before(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+
+    while !text.is_char_boundary(index) {
+      index -= 1;
+    }
+
+    return index;
+  }
+
+  /// Applies a few random truncations/insertions/deletions/swaps to the seed
+  fn mutate(random: &mut Random, seed: &str) -> String {
+    let mut text = String::from(seed);
+
+    for _ in 0..(1 + random.next_below(4)) {
+      let position = char_boundary_at_or_before(&text, random.next_below(text.len() + 1));
+
+      match random.next_below(4) {
+        0 => text.truncate(position),
+        1 => text.insert_str(position, FRAGMENTS[random.next_below(FRAGMENTS.len())]),
+        2 => {
+          let end = char_boundary_at_or_before(&text, position + random.next_below(8));
+          text.replace_range(position..end, "");
+        }
+        _ => {
+          let other_position = char_boundary_at_or_before(&text, random.next_below(text.len() + 1));
+          let (start, end) = (position.min(other_position), position.max(other_position));
+          let moved = String::from(&text[start..end]);
+
+          text.replace_range(start..end, "");
+          text.push_str(&moved);
+        }
+      }
+    }
+
+    return text;
+  }
+
+  fn parser_profiles() -> Vec<ParserProfile> {
+    return vec![
+      ParserProfile::four_chan(),
+      ParserProfile::dvach(),
+      ParserProfile::desuarchive(),
+      ParserProfile::bbcode(),
+      ParserProfile::markdown(),
+    ];
+  }
+
+  #[test]
+  fn fuzz_test_mutated_comments() {
+    let thread_posts = (1..=10u64).collect::<HashSet<u64>>();
+    let post_parser_context = PostParserContext::new(HashSet::new(), thread_posts)
+      .with_max_parsed_length(120);
+
+    let mut random = Random { state: 0x9E3779B97F4A7C15 };
+
+    for parser_profile in parser_profiles() {
+      let post_parser = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default());
+
+      for _ in 0..300 {
+        let seed = SEED_COMMENTS[random.next_below(SEED_COMMENTS.len())];
+        let comment = mutate(&mut random, seed);
+
+        let _ = HtmlParser::new().decode_entities(true).parse(&comment);
+
+        let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, &comment);
+        let parsed_comment = post_parser.parse_comment(&post_raw);
+        let text_length = parsed_comment.parsed_text.chars().count();
+
+        for spannable in parsed_comment.spannables.iter() {
+          assert!(
+            spannable.start + spannable.len <= text_length,
+            "{} is out of bounds of {:?} (comment: {:?})",
+            spannable,
+            parsed_comment.parsed_text,
+            comment
+          );
+        }
+
+        let _ = post_parser.parse_for_catalog(&post_raw, 50);
+      }
+    }
+  }
+
+  #[test]
+  fn fuzz_test_random_bytes() {
+    let post_parser_context = PostParserContext::new(HashSet::new(), HashSet::new());
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let mut random = Random { state: 0xD1B54A32D192ED03 };
+
+    // Includes the bytes of "é" and "😀" so that multi byte chars (and invalid sequences) appear
+    let alphabet = b"<>/=\"'&;#! abc\n\xC3\xA9\xF0\x9F\x98\x80";
+
+    for _ in 0..500 {
+      let bytes = (0..random.next_below(64))
+        .map(|_| alphabet[random.next_below(alphabet.len())])
+        .collect::<Vec<u8>>();
+
+      let comment = String::from_utf8_lossy(&bytes);
+      let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, &comment);
+      let parsed_comment = post_parser.parse_comment(&post_raw);
+
+      assert!(parsed_comment.spannables.iter().all(|spannable| spannable.start + spannable.len <= parsed_comment.parsed_text.chars().count()));
+    }
+  }
+}
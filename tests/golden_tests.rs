@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod test_main {
+  use std::fs;
+  use std::path::{Path, PathBuf};
+  use std::collections::HashSet;
+  use new_post_parser_lib::{PostParserContext, PostParser, PostRaw, ParserProfile, ParserLimits, ParsedSpannableText, set_of};
+
+  // Every tests/fixtures/<site name>/<name>.html comment is parsed with the profile of the site and
+  // the result is compared with <name>.json. Run with UPDATE_SNAPSHOTS=1 to (re)create the snapshots
+  // after an intended change and review them in the diff.
+  const FIXTURES_DIR: &str = "tests/fixtures";
+  const UPDATE_SNAPSHOTS_ENV: &str = "UPDATE_SNAPSHOTS";
+
+  fn fixture_paths() -> Vec<(String, PathBuf)> {
+    let fixtures_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR);
+    let mut fixture_paths = Vec::new();
+
+    for site_dir in fs::read_dir(&fixtures_dir).unwrap() {
+      let site_dir = site_dir.unwrap().path();
+      let site_name = site_dir.file_name().unwrap().to_string_lossy().to_string();
+
+      for fixture in fs::read_dir(&site_dir).unwrap() {
+        let fixture = fixture.unwrap().path();
+
+        if fixture.extension().map(|extension| extension == "html").unwrap_or(false) {
+          fixture_paths.push((site_name.clone(), fixture));
+        }
+      }
+    }
+
+    fixture_paths.sort();
+    return fixture_paths;
+  }
+
+  fn parse_fixture(site_name: &str, comment: &str) -> ParsedSpannableText {
+    let parser_profile = ParserProfile::for_site_name(site_name)
+      .unwrap_or_else(|| panic!("No parser profile for fixtures dir {}", site_name));
+
+    // >>1 is the OP, >>2 is a post of the thread, >>3 is a post of the user
+    let post_parser_context = PostParserContext::new(set_of!(3u64), set_of!(1u64, 2u64, 3u64));
+    let post_parser = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default());
+
+    let post_raw = PostRaw::new(site_name, "g", 1, 4, 0, comment);
+    return post_parser.parse_comment(&post_raw);
+  }
+
+  fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+
+    for ch in text.chars() {
+      match ch {
+        '"' => escaped.push_str("\\\""),
+        '\\' => escaped.push_str("\\\\"),
+        '\n' => escaped.push_str("\\n"),
+        '\r' => escaped.push_str("\\r"),
+        '\t' => escaped.push_str("\\t"),
+        ch if (ch as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", ch as u32)),
+        ch => escaped.push(ch)
+      }
+    }
+
+    escaped.push('"');
+    return escaped;
+  }
+
+  /// One spannable per line so that the diffs point at the spannable that changed. The covered
+  /// text is included to make offset regressions visible.
+  fn to_snapshot(parsed_comment: &ParsedSpannableText) -> String {
+    let chars = parsed_comment.parsed_text.chars().collect::<Vec<char>>();
+    let mut snapshot = String::new();
+
+    snapshot.push_str("{\n");
+    snapshot.push_str(&format!("  \"text\": {},\n", escape_json(&parsed_comment.parsed_text)));
+    snapshot.push_str("  \"spannables\": [\n");
+
+    for (index, spannable) in parsed_comment.spannables.iter().enumerate() {
+      let end = (spannable.start + spannable.len).min(chars.len());
+      let covered_text = chars[spannable.start.min(end)..end].iter().collect::<String>();
+      let separator = if index + 1 < parsed_comment.spannables.len() { "," } else { "" };
+
+      snapshot.push_str(&format!(
+        "    {{ \"start\": {}, \"len\": {}, \"text\": {}, \"data\": {} }}{}\n",
+        spannable.start,
+        spannable.len,
+        escape_json(&covered_text),
+        escape_json(&spannable.spannable_data.to_string()),
+        separator
+      ));
+    }
+
+    snapshot.push_str("  ]\n");
+    snapshot.push_str("}\n");
+    return snapshot;
+  }
+
+  /// Line diff of the snapshots (lines only in expected are "-", lines only in actual are "+")
+  fn diff(expected: &str, actual: &str) -> String {
+    let expected_lines = expected.lines().collect::<Vec<&str>>();
+    let actual_lines = actual.lines().collect::<Vec<&str>>();
+
+    // Longest common subsequence table
+    let mut lcs = vec![vec![0usize; actual_lines.len() + 1]; expected_lines.len() + 1];
+    for i in (0..expected_lines.len()).rev() {
+      for j in (0..actual_lines.len()).rev() {
+        lcs[i][j] = if expected_lines[i] == actual_lines[j] {
+          lcs[i + 1][j + 1] + 1
+        } else {
+          lcs[i + 1][j].max(lcs[i][j + 1])
+        };
+      }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected_lines.len() || j < actual_lines.len() {
+      if i < expected_lines.len() && j < actual_lines.len() && expected_lines[i] == actual_lines[j] {
+        output.push_str(&format!("  {}\n", expected_lines[i]));
+        i += 1;
+        j += 1;
+      } else if i < expected_lines.len() && (j == actual_lines.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+        output.push_str(&format!("- {}\n", expected_lines[i]));
+        i += 1;
+      } else {
+        output.push_str(&format!("+ {}\n", actual_lines[j]));
+        j += 1;
+      }
+    }
+
+    return output;
+  }
+
+  #[test]
+  fn golden_test_fixtures() {
+    let update_snapshots = std::env::var(UPDATE_SNAPSHOTS_ENV).map(|value| value == "1").unwrap_or(false);
+    let fixture_paths = fixture_paths();
+    let mut failures: Vec<String> = Vec::new();
+
+    assert!(!fixture_paths.is_empty());
+
+    for (site_name, fixture_path) in fixture_paths.iter() {
+      let comment = fs::read_to_string(fixture_path).unwrap();
+      let actual = to_snapshot(&parse_fixture(site_name, &comment));
+
+      let snapshot_path = fixture_path.with_extension("json");
+
+      if update_snapshots {
+        fs::write(&snapshot_path, &actual).unwrap();
+        continue;
+      }
+
+      match fs::read_to_string(&snapshot_path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) => {
+          failures.push(format!("{} does not match the output:\n{}", snapshot_path.display(), diff(&expected, &actual)));
+        }
+        Err(_) => {
+          failures.push(format!("{} is missing, run the tests with {}=1 to create it", snapshot_path.display(), UPDATE_SNAPSHOTS_ENV));
+        }
+      }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+  }
+
+  #[test]
+  fn golden_test_diff() {
+    let expected = "{\n  \"a\": 1,\n  \"b\": 2\n}\n";
+    let actual = "{\n  \"a\": 1,\n  \"b\": 3\n}\n";
+
+    assert_eq!("  {\n    \"a\": 1,\n-   \"b\": 2\n+   \"b\": 3\n  }\n", diff(expected, actual));
+  }
+}
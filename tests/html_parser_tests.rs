@@ -1,6 +1,12 @@
 #[cfg(test)]
 mod test_main {
-  use new_post_parser_lib::{HtmlParser};
+  use new_post_parser_lib::{HtmlParser, Element, BbCodeParser, MarkdownParser, PlainTextParser, ParserLimits};
+  use new_post_parser_lib::html_parser::html_parser_error::{HtmlParserError, HtmlParserErrorKind};
+  use new_post_parser_lib::html_parser::tokenizer::Token;
+  use new_post_parser_lib::html_parser::node::{Node, BorrowedNode};
+  use new_post_parser_lib::html_parser::visitor::{NodeVisitor, VisitAction, walk};
+  use new_post_parser_lib::html_parser::renderer::render_nodes;
+  use std::borrow::Cow;
 
   #[test]
   fn html_parser_test_1() {
@@ -76,4 +82,623 @@ embed)
     assert_eq!(expected, actual);
   }
 
-}
\ No newline at end of file
+  #[test]
+  fn html_parser_test_closing_tag_without_end() {
+    let html = "<b>Test</b";
+
+    let html_parser = HtmlParser::new();
+    let error = html_parser.parse(html).err().unwrap();
+
+    assert_eq!(HtmlParserError::new(HtmlParserErrorKind::TagEndNotFound, 8), error);
+  }
+
+  #[test]
+  fn html_parser_test_empty_tag() {
+    let html = "Test<>Test";
+
+    let html_parser = HtmlParser::new();
+    let error = html_parser.parse(html).err().unwrap();
+
+    assert_eq!(HtmlParserError::new(HtmlParserErrorKind::EmptyTag, 5), error);
+  }
+
+  #[test]
+  fn html_parser_test_tag_without_name() {
+    let html = "Test<a=b>Test";
+
+    let html_parser = HtmlParser::new();
+    let error = html_parser.parse(html).err().unwrap();
+
+    assert_eq!(HtmlParserError::new(HtmlParserErrorKind::TagHasNoName, 5), error);
+  }
+
+  #[test]
+  fn html_parser_test_comment() {
+    let html = "Test1<!-- <b class=\"a>b\">commented out</b> --><b>Test2</b>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "Test1\n<!-- <b class=\"a>b\">commented out</b> -->\n<b>\nTest2\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_unterminated_comment() {
+    let html = "Test1<!-- <b>Test2</b>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "Test1\n<!-- <b>Test2</b>-->\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_self_closing_tags() {
+    let html = "Test1<br/>Test2<wbr />Test3<span class=\"x\"/>Test4<a href=//boards.4chan.org/g/>Test5</a>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "Test1\n<br>\nTest2\n<wbr>\nTest3\n<span, class=x>\nTest4\n<a, href=//boards.4chan.org/g/>\nTest5\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+    assert_eq!(8, nodes.len());
+  }
+
+  #[test]
+  fn html_parser_test_decode_entities() {
+    let html = "<span class=\"quote\">&gt;what&#039;s &#x27;the&#39; &amp;gt; &quot;best&quot;</span>";
+
+    let html_parser = HtmlParser::new()
+      .decode_entities(true);
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "<span, class=quote>\n>what's 'the' &gt; \"best\"\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_tokenizer() {
+    let html = "Test1<b class=\"x\">Test2<br></b><!--c-->";
+
+    let html_parser = HtmlParser::new();
+    let tokens = html_parser.tokenize(html)
+      .map(|token_result| token_result.unwrap())
+      .collect::<Vec<Token>>();
+
+    assert_eq!(6, tokens.len());
+    assert_eq!(Token::Text(Cow::from("Test1")), tokens[0]);
+
+    match &tokens[1] {
+      Token::StartTag(element) => {
+        assert_eq!("b", element.tag_name);
+        assert_eq!(Option::Some("x"), element.attr("class"));
+        assert!(!element.is_void_element);
+      }
+      token => panic!("Unexpected token: {:?}", token)
+    }
+
+    assert_eq!(Token::Text(Cow::from("Test2")), tokens[2]);
+
+    match &tokens[3] {
+      Token::StartTag(element) => {
+        assert_eq!("br", element.tag_name);
+        assert!(element.is_void_element);
+      }
+      token => panic!("Unexpected token: {:?}", token)
+    }
+
+    assert_eq!(Token::EndTag(Cow::from("b")), tokens[4]);
+    assert_eq!(Token::Comment(Cow::from("c")), tokens[5]);
+  }
+
+  #[test]
+  fn html_parser_test_parse_borrowed() {
+    let html = "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>Text";
+
+    let html_parser = HtmlParser::new().decode_entities(true);
+    let nodes = html_parser.parse_borrowed(html).unwrap();
+
+    match &nodes[0] {
+      BorrowedNode::Element(element) => {
+        assert!(matches!(element.attributes.get("href"), Option::Some(Option::Some(Cow::Borrowed("#p1")))));
+        // Decoded entities can't be borrowed
+        assert!(matches!(&element.children[0], BorrowedNode::Text(Cow::Owned(text)) if text == ">>1"));
+      }
+      node => panic!("Unexpected node: {:?}", node)
+    }
+
+    assert!(matches!(&nodes[1], BorrowedNode::Text(Cow::Borrowed("Text"))));
+
+    let owned_nodes = nodes.into_iter().map(|node| node.into_owned()).collect::<Vec<Node>>();
+    assert_eq!(html_parser.parse(html).unwrap(), owned_nodes);
+  }
+
+  #[test]
+  fn html_parser_test_less_than_sign_in_text() {
+    let html_parser = HtmlParser::new().decode_entities(true);
+
+    let nodes = html_parser.parse("1 < 2 <3 <b>bold</b><").unwrap();
+    assert_eq!("1 < 2 <3 \n<b>\nbold\n<\n", html_parser.debug_concat_into_string(&nodes));
+
+    let nodes = html_parser.parse("<").unwrap();
+    assert_eq!(vec![Node::Text(String::from("<"))], nodes);
+  }
+
+  #[test]
+  fn html_parser_test_truncated_tags_do_not_panic() {
+    let htmls = [
+      "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>Text",
+      "<span class='quote'>&gt;implying</span><!-- comment --><![CDATA[<b>]]>",
+      "<script>var a = 1 < 2;</script><textarea>&lt;ü</textarea>",
+      "<img src=x alt=\"😀\"/><b>Ünïcödé < text</b>",
+      "<pre>code</pre><p>1 <= 2</p><ul><li>item</ul>",
+    ];
+
+    let html_parser = HtmlParser::new().decode_entities(true);
+
+    for html in htmls.iter() {
+      for (index, _) in html.char_indices() {
+        let _ = html_parser.parse(&html[..index]);
+        let _ = html_parser.parse(&html[index..]);
+      }
+    }
+  }
+
+  #[test]
+  fn html_parser_test_tokenizer_stops_after_error() {
+    let html = "Test1</b";
+
+    let html_parser = HtmlParser::new();
+    let tokens = html_parser.tokenize(html).collect::<Vec<_>>();
+
+    assert_eq!(2, tokens.len());
+    assert!(tokens[0].is_ok());
+    assert!(tokens[1].is_err());
+  }
+
+  #[test]
+  fn html_parser_test_unclosed_and_stray_closing_tags() {
+    let html = "Test1</s>Test2<b>Test3";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "Test1\nTest2\n<b>\nTest3\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_bbcode() {
+    let bbcode = "[B]Test1[/b] [spoiler][i]Test2[/spoiler][/i]\r\n[quote=Anon]Test3[/quote][unknown]Test4[/s]\n[code]a[b]\n[/code]Test5";
+
+    let html_parser = HtmlParser::new();
+    let nodes = BbCodeParser::new().parse(bbcode).unwrap();
+
+    let expected = "<b>\nTest1\n \n<s>\n<i>\nTest2\n[/i]\n<br>\n<span, class=quote>\nTest3\n[unknown]Test4[/s]\n<br>\n<pre>\na[b]\n\nTest5\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+    assert_eq!(10, nodes.len());
+  }
+
+  #[test]
+  fn html_parser_test_markdown() {
+    let markdown = "**Test1** *Test2 __Test3__* ~~a~~ `*b*` 2 * 3 * 4 snake_case_name \\*c\\*\r\n>Test4 [link](https://example.com)\n>>123\n```\n**d**\n\n```\n**unclosed";
+
+    let html_parser = HtmlParser::new();
+    let nodes = MarkdownParser::new().parse(markdown).unwrap();
+
+    let expected = "<b>\nTest1\n \n<i>\nTest2 \n<b>\nTest3\n \n<del>\na\n \n<code>\n*b*\n 2 * 3 * 4 snake_case_name *c*\n<br>\n<span, class=quote>\n>Test4 \n<a, href=https://example.com>\nlink\n<br>\n>>123\n<br>\n<pre>\n**d**\n\n<br>\n**unclosed\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_plain_text() {
+    let text = ">Test1 >>123\r\n>>456 Test2 >>>789 >>>/g/\n<b>Test3</b> >>99999999999999999999999";
+
+    let html_parser = HtmlParser::new();
+    let nodes = PlainTextParser::new().parse(text).unwrap();
+
+    let expected = "<span, class=quote>\n>Test1 \n<a, class=quotelink, href=#p123>\n>>123\n<br>\n<a, class=quotelink, href=#p456>\n>>456\n Test2 >>>789 >>>/g/\n<br>\n<b>Test3</b> >>99999999999999999999999\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_raw_text_elements() {
+    let html = "<script>if (a < b && c > d) { x = \"<b>\"; }</script><style>a > b { color: red; }</STYLE><textarea>&lt;b&gt;</textarea><script></script>Test";
+
+    let html_parser = HtmlParser::new()
+      .decode_entities(true);
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "<script>\nif (a < b && c > d) { x = \"<b>\"; }\n<style>\na > b { color: red; }\n<textarea>\n<b>\n<script>\nTest\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+    assert_eq!(5, nodes.len());
+  }
+
+  #[test]
+  fn html_parser_test_cdata() {
+    let html = "Test1<![CDATA[<b>&gt;</b>]]>Test2";
+
+    let html_parser = HtmlParser::new()
+      .decode_entities(true);
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "Test1\n<b>&gt;</b>\nTest2\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_attribute_values() {
+    let html = "<input disabled data-foo=bar title='a \"quoted\" = value' value=\"\" class = \"x y\">";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let element = match &nodes[0] {
+      Node::Element(element) => element,
+      node => panic!("Unexpected node: {}", node)
+    };
+
+    assert_eq!("input", element.tag_name);
+    assert_eq!(Some(None), element.get_attribute("disabled"));
+    assert_eq!(None, element.get_attr_value("disabled"));
+    assert_eq!("bar", element.get_attr_value("data-foo").unwrap());
+    assert_eq!("a \"quoted\" = value", element.get_attr_value("title").unwrap());
+    assert_eq!("", element.get_attr_value("value").unwrap());
+    assert_eq!("x y", element.get_attr_value("class").unwrap());
+    assert_eq!(None, element.get_attribute("href"));
+  }
+
+  #[test]
+  fn html_parser_test_multi_byte_text() {
+    let html = "<span title=\"Ünïcödé 😀\">Привет 👍🏽</span> 日本語";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let element = match &nodes[0] {
+      Node::Element(element) => element,
+      node => panic!("Unexpected node: {}", node)
+    };
+
+    assert_eq!("Ünïcödé 😀", element.get_attr_value("title").unwrap());
+    assert_eq!(vec![Node::Text(String::from("Привет 👍🏽"))], element.children);
+    assert_eq!(Node::Text(String::from(" 日本語")), nodes[1]);
+
+    // Error positions are in utf-16 code units
+    let error = html_parser.parse("😀 Привет<>").err().unwrap();
+    assert_eq!(HtmlParserError::new(HtmlParserErrorKind::EmptyTag, 10), error);
+  }
+
+  #[test]
+  fn html_parser_test_names_are_lowercased() {
+    let html = "<SPAN CLASS=\"Quote\">Test1<BR></SPAN>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "<span, class=Quote>\nTest1\n<br>\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_names_are_not_lowercased_when_disabled() {
+    let html = "<SPAN CLASS=\"Quote\">Test1<BR></SPAN>";
+
+    let html_parser = HtmlParser::new()
+      .lowercase_names(false);
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "<SPAN, CLASS=Quote>\nTest1\n<BR>\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn html_parser_test_unclosed_inline_tag_is_closed_by_parent_closing_tag() {
+    let html = "<span class=\"quote\"><b>Test1</span>Test2</b>Test3";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "<span, class=quote>\n<b>\nTest1\nTest2\nTest3\n";
+
+    let actual = html_parser.debug_concat_into_string(&nodes);
+    assert_eq!(expected, actual);
+    assert_eq!(3, nodes.len());
+  }
+
+  #[test]
+  fn html_parser_test_implicitly_closed_tags() {
+    let html = "<a href=\"#p1\">Test1<a href=\"#p2\">Test2</a><ul><li>Test3<ul><li>Test4</ul><li>Test5</ul>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    assert_eq!(3, nodes.len());
+
+    let list = match &nodes[2] {
+      Node::Element(element) => element,
+      node => panic!("Unexpected node: {}", node)
+    };
+
+    // Nested list's <li> must not close the outer <li>
+    assert_eq!("ul", list.tag_name);
+    assert_eq!(2, list.children.len());
+  }
+
+  #[test]
+  fn html_parser_test_element_query_helpers() {
+    let html = "<a id=\"p1\" class=\"quotelink  Deadlink\" href=\"#p123\" hidden>Test</a>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let element = match &nodes[0] {
+      Node::Element(element) => element,
+      node => panic!("Unexpected node: {}", node)
+    };
+
+    assert_eq!(vec!["quotelink", "Deadlink"], element.classes().collect::<Vec<&str>>());
+    assert!(element.has_class("deadlink"));
+    assert!(!element.has_class("quote"));
+    assert!(element.has_any_class(&["quote", "quotelink"]));
+    assert!(!element.has_any_class(&["quote", "spoiler"]));
+    assert_eq!(Option::Some("p1"), element.id());
+    assert_eq!(Option::Some("#p123"), element.attr("href"));
+    assert_eq!(Option::None, element.attr("hidden"));
+    assert_eq!("default", element.attr_or("title", "default"));
+    assert_eq!("#p123", element.attr_or("href", "default"));
+  }
+
+  #[test]
+  fn html_parser_test_element_matches_selector() {
+    let html = "<a id=\"p1\" class=\"quotelink deadlink\" href=\"#p123\" hidden>Test</a>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let element = match &nodes[0] {
+      Node::Element(element) => element,
+      node => panic!("Unexpected node: {}", node)
+    };
+
+    assert!(element.matches("a"));
+    assert!(element.matches("A"));
+    assert!(element.matches("*"));
+    assert!(element.matches(".deadlink"));
+    assert!(element.matches("a.quotelink.deadlink"));
+    assert!(element.matches("#p1"));
+    assert!(element.matches("a[href]"));
+    assert!(element.matches("a[hidden]"));
+    assert!(element.matches("a.quotelink[href=\"#p123\"]"));
+    assert!(element.matches("a[href='#p123']"));
+    assert!(element.matches("a[href=#p123]"));
+
+    assert!(!element.matches(""));
+    assert!(!element.matches("span"));
+    assert!(!element.matches("span.quote"));
+    assert!(!element.matches("a.quote"));
+    assert!(!element.matches("#p2"));
+    assert!(!element.matches("a[title]"));
+    assert!(!element.matches("a[href=\"#p1\"]"));
+    assert!(!element.matches("span a"));
+  }
+
+  struct TestVisitor {
+    skip_tag: &'static str,
+    stop_text: &'static str,
+    events: Vec<String>
+  }
+
+  impl NodeVisitor for TestVisitor {
+    fn visit_element(&mut self, element: &Element, depth: usize) -> VisitAction {
+      self.events.push(format!("{}<{}>", depth, element.tag_name));
+
+      if element.tag_name == self.skip_tag {
+        return VisitAction::SkipChildren;
+      }
+
+      return VisitAction::Continue;
+    }
+
+    fn leave_element(&mut self, element: &Element, depth: usize) {
+      self.events.push(format!("{}</{}>", depth, element.tag_name));
+    }
+
+    fn visit_text(&mut self, text: &String, depth: usize) -> VisitAction {
+      self.events.push(format!("{}{}", depth, text));
+
+      if text == self.stop_text {
+        return VisitAction::Stop;
+      }
+
+      return VisitAction::Continue;
+    }
+  }
+
+  #[test]
+  fn html_parser_test_walk_nodes_with_visitor() {
+    let html = "<span><s>Test1</s><b>Test2</b></span>Test3<i>Test4</i>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let mut visitor = TestVisitor { skip_tag: "s", stop_text: "", events: Vec::new() };
+    assert!(walk(&nodes, &mut visitor));
+    assert_eq!(
+      "0<span> 1<s> 1<b> 2Test2 1</b> 0</span> 0Test3 0<i> 1Test4 0</i>",
+      visitor.events.join(" ")
+    );
+
+    // Elements that were entered must be left even when the walking was stopped
+    let mut visitor = TestVisitor { skip_tag: "", stop_text: "Test2", events: Vec::new() };
+    assert!(!walk(&nodes, &mut visitor));
+    assert_eq!(
+      "0<span> 1<s> 2Test1 1</s> 1<b> 2Test2 1</b> 0</span>",
+      visitor.events.join(" ")
+    );
+  }
+
+  #[test]
+  fn html_parser_test_deeply_nested_tree() {
+    // Way deeper than what the stack of a test thread can handle with recursion
+    let depth = 100_000;
+    let html = format!("{}Test{}", "<b>".repeat(depth), "</b>".repeat(depth));
+
+    let html_parser = HtmlParser::new()
+      .limits(ParserLimits { max_depth: Option::None, ..ParserLimits::default() });
+    let nodes = html_parser.parse(&html).unwrap();
+
+    let mut visitor = TestVisitor { skip_tag: "", stop_text: "Test", events: Vec::new() };
+    assert!(!walk(&nodes, &mut visitor));
+    assert_eq!(depth * 2 + 1, visitor.events.len());
+    assert_eq!(format!("{}Test", depth), visitor.events[depth]);
+    assert_eq!(format!("{}</b>", depth - 1), visitor.events[depth + 1]);
+    assert_eq!("0</b>", visitor.events.last().unwrap());
+
+    match &nodes[0] {
+      Node::Element(element) => assert_eq!("Test", element.collect_text()),
+      node => panic!("Unexpected node: {}", node)
+    }
+
+    assert!(html == render_nodes(&nodes));
+    assert_eq!(depth + 1, html_parser.debug_concat_into_string(&nodes).lines().count());
+  }
+
+  #[test]
+  fn html_parser_test_render_nodes() {
+    let html = "Test<a href=\"#p333650561\" class=\"quotelink\">&gt;&gt;33365<wbr>0561</a><br><span class=\"quote\">&gt;what&#039;s the<wbr>best alternative</span><br><!-- comment --><input title='quoted' disabled>1 &amp; 2 &lt; 3<script>if (a < b) {}</script>";
+
+    let html_parser = HtmlParser::new()
+      .decode_entities(true);
+    let nodes = html_parser.parse(html).unwrap();
+
+    let expected = "Test<a href=\"#p333650561\" class=\"quotelink\">&gt;&gt;33365<wbr>0561</a><br><span class=\"quote\">&gt;what's the<wbr>best alternative</span><br><!-- comment --><input title=\"quoted\" disabled>1 &amp; 2 &lt; 3<script>if (a < b) {}</script>";
+    let actual = render_nodes(&nodes);
+    assert_eq!(expected, actual);
+
+    assert_eq!(nodes, html_parser.parse(&actual).unwrap());
+    assert_eq!("<a href=\"#p333650561\" class=\"quotelink\">&gt;&gt;33365<wbr>0561</a>", nodes[1].to_html());
+  }
+
+  #[test]
+  fn html_parser_test_render_self_closing_elements() {
+    let html = "<span class=\"x\"/>after<br/><b/><i>Test</i>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let actual = render_nodes(&nodes);
+    assert_eq!("<span class=\"x\"/>after<br><b/><i>Test</i>", actual);
+    assert_eq!(nodes, html_parser.parse(&actual).unwrap());
+  }
+
+  #[test]
+  fn html_parser_test_render_attribute_quotes() {
+    let html = "<span title='a \"b\" it&#039;s' data-x=\"it's\">Test</span>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let actual = render_nodes(&nodes);
+    assert_eq!("<span title=\"a &quot;b&quot; it&#039;s\" data-x=\"it's\">Test</span>", actual);
+
+    let reparsed_nodes = html_parser.parse(&actual).unwrap();
+    assert_eq!(actual, render_nodes(&reparsed_nodes));
+
+    match &reparsed_nodes[0] {
+      Node::Element(element) => {
+        assert_eq!(Some("a &quot;b&quot; it&#039;s"), element.get_attribute("title").unwrap().map(|value| value.as_str()));
+        assert_eq!(Some("it's"), element.get_attribute("data-x").unwrap().map(|value| value.as_str()));
+      }
+      node => panic!("Unexpected node: {}", node)
+    }
+  }
+
+  #[test]
+  fn html_parser_test_element_parsed_style() {
+    let html = "<span style=\"COLOR: #789922 ; font-family: 'a;b:c', serif;;background:url(http://a.b/c.png);invalid;color:red\">Test</span><b>Test</b>";
+
+    let html_parser = HtmlParser::new();
+    let nodes = html_parser.parse(html).unwrap();
+
+    let elements = nodes.iter()
+      .map(|node| match node {
+        Node::Element(element) => element,
+        node => panic!("Unexpected node: {}", node)
+      })
+      .collect::<Vec<&Element>>();
+
+    let parsed_style = elements[0].parsed_style()
+      .into_iter()
+      .collect::<Vec<(String, String)>>();
+
+    assert_eq!(
+      vec![
+        (String::from("font-family"), String::from("'a;b:c', serif")),
+        (String::from("background"), String::from("url(http://a.b/c.png)")),
+        (String::from("color"), String::from("red")),
+      ],
+      parsed_style
+    );
+
+    assert!(elements[1].parsed_style().is_empty());
+  }
+
+  #[test]
+  fn html_parser_test_limits() {
+    let limits = |max_nodes: Option<usize>, max_depth: Option<usize>, max_parse_millis: Option<u64>| {
+      return ParserLimits { max_parse_millis, max_nodes, max_depth };
+    };
+
+    let html = "<b><i><u>x</u></i></b>";
+    assert_eq!(
+      HtmlParserError::new(HtmlParserErrorKind::TooDeep, 9),
+      HtmlParser::new().limits(limits(None, Some(2), None)).parse(html).unwrap_err()
+    );
+    assert!(HtmlParser::new().limits(limits(None, Some(3), None)).parse(html).is_ok());
+
+    let html = "a<b>b</b>c";
+    assert_eq!(
+      HtmlParserError::new(HtmlParserErrorKind::TooManyNodes, 10),
+      HtmlParser::new().limits(limits(Some(3), None, None)).parse(html).unwrap_err()
+    );
+    assert!(HtmlParser::new().limits(limits(Some(4), None, None)).parse(html).is_ok());
+
+    let html = "<b>x</b>".repeat(1000);
+    assert_eq!(
+      HtmlParserErrorKind::Timeout,
+      HtmlParser::new().limits(limits(None, None, Some(0))).parse(&html).unwrap_err().kind
+    );
+
+    // The default limits protect against the crafted deeply nested posts
+    let html = format!("{}x", "<b>".repeat(100_000));
+    assert_eq!(HtmlParserErrorKind::TooDeep, HtmlParser::new().parse(&html).unwrap_err().kind);
+  }
+
+}
@@ -0,0 +1,256 @@
+#[cfg(test)]
+mod test_main {
+  use new_post_parser_lib::{PostImageKind, PageScraper, SiteSelectors, CatalogThreadRaw, BoardPage, PostParser, PostParserContext, set_of};
+  use std::collections::HashSet;
+
+  const FOUR_CHAN_THREAD_PAGE: &str = r##"<!DOCTYPE html>
+<html><head><title>/g/ - Test thread</title><script>var a = "<div class=\"post\">";</script></head>
+<body>
+<div class="board">
+<div class="thread" id="t100">
+  <div class="postContainer opContainer" id="pc100">
+    <div id="p100" class="post op">
+      <div class="file" id="f100">
+        <div class="fileText" id="fT100">File: <a title="very long original name.png" href="//i.4cdn.org/g/1600000000000.png" target="_blank">very long or(...).png</a> (1.5 MB, 1920x1080)</div>
+        <a class="fileThumb" href="//i.4cdn.org/g/1600000000000.png" target="_blank"><img src="//i.4cdn.org/g/1600000000000s.jpg" alt="1.5 MB" data-md5="bWQ1" style="height: 140px; width: 250px;"></a>
+      </div>
+      <div class="postInfo desktop" id="pi100">
+        <span class="subject">Tom &amp; Jerry</span>
+        <span class="nameBlock"><span class="name">Anonymous</span> <span class="postertrip">!!abc</span> <span class="posteruid id_xyz">(ID: <span class="hand" title="Highlight posts by this ID">xyz</span>)</span> <span title="United States" class="flag flag-us"></span></span>
+        <span class="dateTime" data-utc="1600000000">09/13/20(Sun)08:26:40</span>
+        <span class="postNum desktop"><a href="#p100" title="Link to this post">No.</a><a href="javascript:quote('100');" title="Reply to this post">100</a></span>
+      </div>
+      <blockquote class="postMessage" id="m100">First<br><span class="quote">&gt;implying</span></blockquote>
+    </div>
+  </div>
+  <div class="postContainer replyContainer" id="pc101">
+    <div id="p101" class="post reply">
+      <div class="postInfo desktop" id="pi101">
+        <span class="nameBlock"><span class="name">Anonymous</span></span>
+        <span class="dateTime" data-utc="1600000060">09/13/20(Sun)08:27:40</span>
+      </div>
+      <div class="file" id="f101">
+        <div class="fileText" id="fT101">File: <a href="//i.4cdn.org/g/1600000000001.webm" target="_blank">cat.webm</a> (300 KB, 640x480)</div>
+        <a class="fileThumb imgspoiler" href="//i.4cdn.org/g/1600000000001.webm" target="_blank"><img src="//s.4cdn.org/image/spoiler-g1.png" alt="Spoiler Image"></a>
+      </div>
+      <blockquote class="postMessage" id="m101"><a href="#p100" class="quotelink">&gt;&gt;100</a><br>Reply</blockquote>
+    </div>
+  </div>
+  <div class="postContainer replyContainer">
+    <div class="post reply"><blockquote class="postMessage">Post without a number</blockquote></div>
+  </div>
+</div>
+</div>
+</body></html>"##;
+
+  #[test]
+  fn page_scraper_test_scrape_four_chan_thread() {
+    let page_scraper = PageScraper::new("4chan", "g", SiteSelectors::for_site_name("4chan").unwrap());
+    let posts = page_scraper.scrape_thread(FOUR_CHAN_THREAD_PAGE).unwrap();
+
+    assert_eq!(2, posts.len());
+
+    let op = &posts[0];
+    assert_eq!(100, op.thread_no());
+    assert_eq!(100, op.post_no());
+    assert_eq!("4chan", op.site_name());
+    assert_eq!("g", op.board_code());
+    assert_eq!("First<br><span class=\"quote\">&gt;implying</span>", op.com);
+    assert_eq!(Option::Some(String::from("Tom &amp; Jerry")), op.metadata.sub);
+    assert!(op.metadata.name_html.as_ref().unwrap().starts_with("<span class=\"nameBlock\"><span class=\"name\">Anonymous</span>"));
+    assert_eq!(Option::Some(String::from("xyz")), op.metadata.id);
+    assert_eq!(Option::Some(String::from("<span title=\"United States\" class=\"flag flag-us\"></span>")), op.metadata.flag_html);
+    assert_eq!(Option::Some(1600000000), op.metadata.time);
+
+    assert_eq!(1, op.metadata.files.len());
+    let op_file = &op.metadata.files[0];
+    assert_eq!("very long original name", op_file.filename);
+    assert_eq!(".png", op_file.ext);
+    assert_eq!("1600000000000", op_file.tim);
+    assert_eq!(1572864, op_file.fsize);
+    assert_eq!((1920, 1080), (op_file.w, op_file.h));
+    assert_eq!(Option::Some(String::from("bWQ1")), op_file.md5);
+    assert!(!op_file.spoiler);
+
+    let reply = &posts[1];
+    assert_eq!(100, reply.thread_no());
+    assert_eq!(101, reply.post_no());
+    assert_eq!(Option::None, reply.metadata.sub);
+    assert_eq!(Option::None, reply.metadata.id);
+    assert_eq!(Option::Some(1600000060), reply.metadata.time);
+
+    let reply_file = &reply.metadata.files[0];
+    assert_eq!("cat", reply_file.filename);
+    assert_eq!(".webm", reply_file.ext);
+    assert_eq!(307200, reply_file.fsize);
+    assert_eq!(Option::None, reply_file.md5);
+    assert!(reply_file.spoiler);
+
+    // The scraped posts go through PostParser like the posts from the json api
+    let post_parser_context = PostParserContext::new(HashSet::new(), set_of!(100u64, 101u64));
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let parsed_op = post_parser.parse_post(op);
+    assert_eq!("First\n>implying", parsed_op.post_comment_parsed.parsed_text.as_str());
+    assert_eq!(Option::Some(String::from("Anonymous")), parsed_op.metadata.poster_info.name);
+    assert_eq!("very long original name.png", parsed_op.metadata.files[0].original_name);
+    assert_eq!(Option::Some(String::from("//i.4cdn.org/g/1600000000000s.jpg")), parsed_op.metadata.images[0].thumbnail_url);
+
+    let parsed_reply = post_parser.parse_post(reply);
+    assert_eq!(">>100 (OP)\nReply", parsed_reply.post_comment_parsed.parsed_text.as_str());
+  }
+
+  #[test]
+  fn page_scraper_test_scrape_vichan_thread() {
+    let html = r##"<div class="thread" id="thread_1" data-board="b">
+<div class="files"><div class="file"><p class="fileinfo">File: <a href="/b/src/1600000000000.jpg">1600000000000.jpg</a> <span class="unimportant">(12.5 KB, 200x300, <a class="postfilename" title="op.jpg">op.jpg</a>)</span></p></div>
+<div class="file"><p class="fileinfo">File: <a href="/b/src/1600000000002.webm">1600000000002.webm</a> <span class="unimportant">(1.5 MB, 640x360, 00:42, <a class="postfilename" title="op2.webm">op2.webm</a>)</span></p></div></div>
+<div class="post op" id="op_1"><p class="intro"><span class="subject">Subject</span> <span class="name">Anon</span> <time datetime="2020-09-13T12:26:40Z">09/13/20 (Sun) 12:26:40</time></p><div class="body">OP text</div></div>
+<div class="post reply" id="reply_2"><p class="intro"><span class="name">Anon2</span> <span class="trip">!tr1p</span> <time datetime="2020-09-13T15:27:40+03:00">09/13/20 (Sun) 12:27:40</time></p>
+<div class="files"><div class="file"><p class="fileinfo">File: <a href="/b/src/1600000000001.png">1600000000001.png</a> <span class="unimportant">(300 B, 10x20, <a class="postfilename" title="reply image.png">reply image.png</a>)</span></p></div></div>
+<div class="body">Reply text</div></div>
+</div>"##;
+
+    let page_scraper = PageScraper::new("lainchan", "b", SiteSelectors::vichan());
+    let posts = page_scraper.scrape_thread(html).unwrap();
+
+    assert_eq!(2, posts.len());
+    assert_eq!((1, 1), (posts[0].thread_no(), posts[0].post_no()));
+    assert_eq!("OP text", posts[0].com);
+    assert_eq!(Option::Some(String::from("Subject")), posts[0].metadata.sub);
+    assert_eq!(Option::Some(1600000000), posts[0].metadata.time);
+    // The files of the OP are not inside of the OP element
+    assert_eq!(
+      vec!["op", "op2"],
+      posts[0].metadata.files.iter().map(|file| file.filename.as_str()).collect::<Vec<&str>>()
+    );
+
+    assert_eq!((1, 2), (posts[1].thread_no(), posts[1].post_no()));
+    assert_eq!("Reply text", posts[1].com);
+    assert_eq!(Option::Some(1600000060), posts[1].metadata.time);
+
+    let reply_file = &posts[1].metadata.files[0];
+    assert_eq!("reply image", reply_file.filename);
+    assert_eq!(("1600000000001", ".png"), (reply_file.tim.as_str(), reply_file.ext.as_str()));
+    assert_eq!(300, reply_file.fsize);
+    assert_eq!((10, 20), (reply_file.w, reply_file.h));
+
+    let post_parser_context = PostParserContext::new(HashSet::new(), set_of!(1u64, 2u64));
+
+    let op_images = PostParser::new(&post_parser_context).parse_post(&posts[0]).metadata.images;
+    assert_eq!(
+      vec![("op.jpg", PostImageKind::Image, Option::None), ("op2.webm", PostImageKind::Video, Option::Some(42))],
+      op_images.iter().map(|image| (image.original_name.as_str(), image.kind, image.duration)).collect::<Vec<(&str, PostImageKind, Option<u32>)>>()
+    );
+
+    let parsed_reply = PostParser::new(&post_parser_context).parse_post(&posts[1]);
+    assert_eq!(Option::Some(String::from("Anon2")), parsed_reply.metadata.poster_info.name);
+    assert_eq!(Option::Some(String::from("!tr1p")), parsed_reply.metadata.poster_info.tripcode);
+  }
+  #[test]
+  fn page_scraper_test_scrape_vichan_catalog() {
+    let html = r##"<div class="threads"><div id="Grid">
+<div class="mix" data-reply="12" data-bump="1600000500" data-time="1600000000" data-id="1" data-sticky="false" data-locked="false">
+  <div class="thread grid-li grid-size-small">
+    <a href="/b/res/1.html"><img src="/b/thumb/1600000000000.png" id="img-1" data-subject="Tom &amp; Jerry" class="thread-image"></a>
+    <div class="replies">
+      <strong>R: 12 / I: 3</strong>
+      <p class="intro"><span class="subject">Tom &amp; Jerry</span></p>
+      OP <em>comment</em><br>second line
+    </div>
+  </div>
+</div>
+<div class="mix" data-reply="0" data-bump="2020-09-13T12:26:40Z" data-id="2">
+  <div class="thread grid-li grid-size-small">
+    <div class="replies"><strong>R: 0 / I: 0</strong><p class="intro"></p>No subject</div>
+  </div>
+</div>
+<div class="mix"><div class="replies">Thread without a number</div></div>
+</div></div>"##;
+
+    let page_scraper = PageScraper::new("lainchan", "b", SiteSelectors::vichan());
+    let threads = page_scraper.scrape_catalog(html).unwrap();
+
+    assert_eq!(
+      vec![
+        CatalogThreadRaw {
+          thread_no: 1,
+          subject_html: Option::Some(String::from("<span class=\"subject\">Tom &amp; Jerry</span>")),
+          comment_html: String::from("OP <em>comment</em><br>second line"),
+          reply_count: 12,
+          image_count: 3,
+          last_modified: Option::Some(1600000500)
+        },
+        CatalogThreadRaw {
+          thread_no: 2,
+          subject_html: Option::None,
+          comment_html: String::from("No subject"),
+          reply_count: 0,
+          image_count: 0,
+          last_modified: Option::Some(1600000000)
+        }
+      ],
+      threads
+    );
+
+    // 4chan's catalog is not html
+    let page_scraper = PageScraper::new("4chan", "g", SiteSelectors::four_chan());
+    assert!(page_scraper.scrape_catalog(html).unwrap().is_empty());
+  }
+  #[test]
+  fn page_scraper_test_scrape_board_page() {
+    let post = |post_no: u64, comment: &str| {
+      return format!(
+        "<div class=\"postContainer\"><div id=\"p{}\" class=\"post\"><blockquote class=\"postMessage\">{}</blockquote></div></div>",
+        post_no,
+        comment
+      );
+    };
+
+    let four_chan_page = format!(
+      "<div class=\"board\"><div class=\"thread\" id=\"t1\">{}{}</div><hr><div class=\"thread\" id=\"t5\">{}</div><hr></div>\
+      <div class=\"pagelist\"><div class=\"prev\"><form action=\"./\"><input type=\"submit\" value=\"Previous\"></form></div>\
+      <div class=\"pages\">[<a href=\"./\">1</a>] [<strong>2</strong>] [<a href=\"3\">3</a>] [<a href=\"10\">10</a>]</div>\
+      <div class=\"next\"><form action=\"3\"><input type=\"submit\" value=\"Next\"></form></div>\
+      <div class=\"pages cataloglink\"><a href=\"./catalog\">Catalog</a></div></div>",
+      post(1, "OP 1"),
+      post(4, "Reply 4"),
+      post(5, "OP 5")
+    );
+
+    let page_scraper = PageScraper::new("4chan", "g", SiteSelectors::four_chan());
+    let board_page: BoardPage = page_scraper.scrape_board_page(&four_chan_page).unwrap();
+
+    assert_eq!(2, board_page.threads.len());
+    assert_eq!(
+      vec![(1, 1, "OP 1"), (1, 4, "Reply 4")],
+      board_page.threads[0].iter().map(|post| (post.thread_no(), post.post_no(), post.com.as_str())).collect::<Vec<(u64, u64, &str)>>()
+    );
+    assert_eq!(
+      vec![(5, 5, "OP 5")],
+      board_page.threads[1].iter().map(|post| (post.thread_no(), post.post_no(), post.com.as_str())).collect::<Vec<(u64, u64, &str)>>()
+    );
+    assert_eq!(Option::Some(2), board_page.current_page);
+    assert_eq!(Option::Some(10), board_page.total_pages);
+    assert_eq!(Option::Some(String::from("3")), board_page.next_page_url);
+
+    // The last page of a vichan board
+    let vichan_page = "<div class=\"thread\" id=\"thread_7\"><div class=\"post op\" id=\"op_7\"><div class=\"body\">OP</div></div></div>\
+      <div class=\"pages\"><form action=\"/b/1.html\"><input type=\"submit\" value=\"Previous\"></form> \
+      [<a href=\"/b/index.html\">1</a>] [<a class=\"selected\">2</a>] | <a href=\"/b/catalog.html\">Catalog</a></div>";
+
+    let page_scraper = PageScraper::new("lainchan", "b", SiteSelectors::vichan());
+    let board_page = page_scraper.scrape_board_page(vichan_page).unwrap();
+
+    assert_eq!(1, board_page.threads.len());
+    assert_eq!(7, board_page.threads[0][0].post_no());
+    assert_eq!(Option::Some(2), board_page.current_page);
+    assert_eq!(Option::Some(2), board_page.total_pages);
+    assert_eq!(Option::None, board_page.next_page_url);
+
+    // No pagination
+    let board_page = page_scraper.scrape_board_page("<div class=\"thread\"></div>").unwrap();
+    assert!(board_page.threads.is_empty());
+    assert_eq!((Option::None, Option::None, Option::None), (board_page.current_page, board_page.total_pages, board_page.next_page_url));
+  }
+}
@@ -1,7 +1,14 @@
 #[cfg(test)]
 mod test_main {
-  use new_post_parser_lib::{PostParserContext, Spannable, PostRaw, PostParser, SpannableData, PostLink, set_of, Element, ThreadDescriptor, BoardDescriptor, SiteDescriptor, PostDescriptor};
-  use std::collections::HashSet;
+  use new_post_parser_lib::{PostParserContext, Spannable, PostRaw, PostParser, SpannableData, PostLink, set_of, ThreadDescriptor, BoardDescriptor, SiteDescriptor, PostDescriptor, ParserLimits, STagMeaning, ParsingRules, ParsingRule, Attribute, TextPart, TextParts, ParserProfile, MediaLinkKind, MediaLink, SpanOffsetMode, SpannableStyleHint, PostRoll, PostPoll, TextCleanupOptions, PostMetadataRaw, PostFileRaw, PostFlag, PostFlagKind, PostFile, PostImage, PostImageKind, CapcodeKind, ColoredQuoteRole, PosterId, PosterInfo, ParsedPost, ThreadParser, ThreadUpdate, SearchHighlightOptions, FilterEngine, FilterPattern, FilterScope, FilterAction, FilterMatch, TextRule, TextRuleOutput, CommentFormat, QuoteFormat, QuoteLabelStyle, LineBreakMode, WhitespacePolicy, CatalogSnippet, ParsedSpannableText, QuoteSuffixes, CrossThreadSuffix, QuotePreview, WordBreakMode, TeXMode, RubyMode, UnknownTagPolicy, ParserWarning};
+  #[cfg(feature = "stats")]
+  use new_post_parser_lib::PostParserStats;
+  use new_post_parser_lib::html_parser::html_parser_error::HtmlParserErrorKind;
+  use new_post_parser_lib::rules::rule_handler::{RuleHandler, HandlerCtx};
+  use new_post_parser_lib::site_url_resolver::SiteUrlResolver;
+  use std::rc::Rc;
+  use std::collections::{HashSet, HashMap};
+  use regex::Regex;
 
   fn create_post_parser_context(
     my_replies: HashSet<u64>,
@@ -35,7 +42,8 @@ mod test_main {
         post_no: post_id,
         post_sub_no: 0u64
       },
-      com: String::from(raw_comment)
+      com: String::from(raw_comment),
+      metadata: PostMetadataRaw::default()
     };
 
     let post_parser = PostParser::new(&post_parser_context);
@@ -124,7 +132,7 @@ Feel free to tell me specifically what I&#039;m wrong about. I&#039;ll take one
 Feel free to tell me specifically what I'm wrong about. I'll take one thing he says: that Tomoya is behaving negatively by \"dragging her down.\"";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145 }) },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145, is_you: false, is_op: false, preview: None }) },
       Spannable { start: 12, len: 18, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 333520391 }) }
     ];
 
@@ -145,7 +153,7 @@ Feel free to tell me specifically what I&#039;m wrong about. I&#039;ll take one
 Feel free to tell me specifically what I'm wrong about. I'll take one thing he says: that Tomoya is behaving negatively by \"dragging her down.\"";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 16, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145 }) },
+      Spannable { start: 0, len: 16, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145, is_you: false, is_op: true, preview: None }) },
       Spannable { start: 17, len: 18, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 333520391 }) }
     ];
 
@@ -166,7 +174,7 @@ Feel free to tell me specifically what I&#039;m wrong about. I&#039;ll take one
 Feel free to tell me specifically what I'm wrong about. I'll take one thing he says: that Tomoya is behaving negatively by \"dragging her down.\"";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 22, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145 }) },
+      Spannable { start: 0, len: 22, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145, is_you: true, is_op: true, preview: None }) },
       Spannable { start: 23, len: 18, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 333520391 }) }
     ];
 
@@ -187,7 +195,7 @@ Feel free to tell me specifically what I&#039;m wrong about. I&#039;ll take one
 Feel free to tell me specifically what I'm wrong about. I'll take one thing he says: that Tomoya is behaving negatively by \"dragging her down.\"";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 21, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145 }) },
+      Spannable { start: 0, len: 21, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145, is_you: true, is_op: true, preview: None }) },
       Spannable { start: 22, len: 18, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 333520391 }) }
     ];
 
@@ -208,8 +216,8 @@ Feel free to tell me specifically what I&#039;m wrong about. I&#039;ll take one
 Feel free to tell me specifically what I'm wrong about. I'll take one thing he says: that Tomoya is behaving negatively by \"dragging her down.\"";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 22, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145 }) },
-      Spannable { start: 23, len: 17, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520391 }) }
+      Spannable { start: 0, len: 22, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520145, is_you: true, is_op: true, preview: None }) },
+      Spannable { start: 23, len: 17, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333520391, is_you: true, is_op: false, preview: None }) }
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -227,9 +235,9 @@ stop you</span><br><s>Should I use a female version of my name for maximal self-
     let expected_parsed_comment = ">>333890765\n>letting \"realism\" stop you\nShould I use a female version of my name for maximal self-insertion?";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333890765 }) },
-      Spannable { start: 11, len: 28, spannable_data: SpannableData::GreenText },
-      Spannable { start: 39, len: 68, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333890765, is_you: false, is_op: false, preview: None }) },
+      Spannable { start: 12, len: 27, spannable_data: SpannableData::GreenText },
+      Spannable { start: 40, len: 67, spannable_data: SpannableData::Spoiler },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -246,8 +254,8 @@ stop you</span><br><s>Should I use a female version of my name for maximal self-
     let expected_parsed_comment = ">>333863078";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333863078 }) },
       Spannable { start: 0, len: 11, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333863078, is_you: false, is_op: false, preview: None }) },
       Spannable { start: 0, len: 11, spannable_data: SpannableData::GreenText },
     ];
 
@@ -266,7 +274,7 @@ stop you</span><br><s>Should I use a female version of my name for maximal self-
     let expected_parsed_comment = ">>333918351\nBecause JOPs can just go to their dedicated thread on /jp/. >>>34511118 (DEAD)";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333918351 }) },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333918351, is_you: false, is_op: false, preview: None }) },
       Spannable { start: 73, len: 17, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 34511118 }) },
     ];
 
@@ -285,8 +293,8 @@ stop you</span><br><s>Should I use a female version of my name for maximal self-
     let expected_parsed_comment = ">>333918351\nBecause JOPs can just go to their dedicated thread on /jp/. >>>34511118";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333918351 }) },
-      Spannable { start: 73, len: 10, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 34511118 }) },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 333918351, is_you: false, is_op: false, preview: None }) },
+      Spannable { start: 73, len: 10, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 34511118, is_you: false, is_op: false, preview: None }) },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -331,8 +339,9 @@ https://www.youtube.com/watch?v=57tu8AtKf9E";
 
     let expected_spannables = vec![
       Spannable { start: 0, len: 43, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://www.youtube.com/watch?v=57tu8AtKf9E") }) },
-      Spannable { start: 44, len: 47, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://boards.4channel.org/vg/thread/333979978") }) },
-      Spannable { start: 97, len: 30, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://boards.4channel.org/v/") }) },
+      Spannable { start: 0, len: 43, spannable_data: SpannableData::MediaLink { kind: MediaLinkKind::YouTube, id: String::from("57tu8AtKf9E") } },
+      Spannable { start: 44, len: 47, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("vg"), thread_no: 333979978, post_no: 333979978 }) },
+      Spannable { start: 97, len: 30, spannable_data: SpannableData::Link(PostLink::BoardLink { board_code: String::from("v") }) },
       Spannable { start: 129, len: 44, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("http://visual-novels-general.wikia.com/wiki/") }) },
       Spannable { start: 174, len: 39, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://sites.google.com/view/moechart/") }) },
       Spannable { start: 214, len: 35, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://files.catbox.moe/143by7.png") }) },
@@ -345,6 +354,7 @@ https://www.youtube.com/watch?v=57tu8AtKf9E";
       Spannable { start: 420, len: 26, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://w4534gerhnrh.com/2") }) },
       Spannable { start: 447, len: 22, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://pastebin.com/3") }) },
       Spannable { start: 471, len: 43, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://www.youtube.com/watch?v=57tu8AtKf9E") }) },
+      Spannable { start: 471, len: 43, spannable_data: SpannableData::MediaLink { kind: MediaLinkKind::YouTube, id: String::from("57tu8AtKf9E") } },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -363,7 +373,7 @@ https://www.youtube.com/watch?v=57tu8AtKf9E";
     let expected_parsed_comment = ">>81423695\nWe have one here with sound.\n>>>/wsg/3849481 →";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 10, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 81423695 }) },
+      Spannable { start: 0, len: 10, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 81423695, is_you: false, is_op: false, preview: None }) },
       Spannable { start: 40, len: 17, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("wsg"), thread_no: 3849481, post_no: 3849481 }) },
     ];
 
@@ -404,23 +414,23 @@ https://www.youtube.com/watch?v=57tu8AtKf9E";
     Previous Thread >>81404563 →";
 
     let expected_spannables = vec![
-      Spannable { start: 18, len: 12, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 76759434, post_no: 76759434 }) },
       Spannable { start: 0, len: 30, spannable_data: SpannableData::GreenText },
-      Spannable { start: 31, len: 22, spannable_data: SpannableData::GreenText },
+      Spannable { start: 18, len: 12, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 76759434, post_no: 76759434 }) },
+      Spannable { start: 32, len: 21, spannable_data: SpannableData::GreenText },
       Spannable { start: 53, len: 10, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("fglt") }) },
-      Spannable { start: 64, len: 20, spannable_data: SpannableData::GreenText },
+      Spannable { start: 65, len: 19, spannable_data: SpannableData::GreenText },
       Spannable { start: 84, len: 9, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("fwt") }) },
-      Spannable { start: 94, len: 15, spannable_data: SpannableData::GreenText },
+      Spannable { start: 95, len: 14, spannable_data: SpannableData::GreenText },
       Spannable { start: 109, len: 10, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("pcbg") }) },
-      Spannable { start: 120, len: 24, spannable_data: SpannableData::GreenText },
+      Spannable { start: 121, len: 23, spannable_data: SpannableData::GreenText },
       Spannable { start: 144, len: 9, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("dpt") }) },
-      Spannable { start: 154, len: 23, spannable_data: SpannableData::GreenText },
+      Spannable { start: 155, len: 22, spannable_data: SpannableData::GreenText },
       Spannable { start: 177, len: 9, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("tpg") }) },
-      Spannable { start: 187, len: 20, spannable_data: SpannableData::GreenText },
+      Spannable { start: 188, len: 19, spannable_data: SpannableData::GreenText },
       Spannable { start: 207, len: 9, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("csg") }) },
-      Spannable { start: 217, len: 19, spannable_data: SpannableData::GreenText },
+      Spannable { start: 218, len: 18, spannable_data: SpannableData::GreenText },
       Spannable { start: 236, len: 9, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("hsg") }) },
-      Spannable { start: 246, len: 20, spannable_data: SpannableData::GreenText },
+      Spannable { start: 247, len: 19, spannable_data: SpannableData::GreenText },
       Spannable { start: 266, len: 9, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("hpg") }) },
       Spannable { start: 322, len: 33, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://rentry.org/installwindows") }) },
       Spannable { start: 373, len: 12, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 81404563, post_no: 81404563 }) },
@@ -470,7 +480,7 @@ what?";
     let expected_parsed_comment = ">>221656514\nBe seeing you in the next rotation anons!\nhttps://s1.desu-usergeneratedcontent.xyz/a/image/1614/51/1614513969521.png";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 221656514 }) },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 221656514, is_you: false, is_op: false, preview: None }) },
       Spannable { start: 54, len: 74, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://s1.desu-usergeneratedcontent.xyz/a/image/1614/51/1614513969521.png") }) },
     ];
 
@@ -498,9 +508,9 @@ what?";
     let expected_spannables = vec![
       Spannable { start: 0, len: 13, spannable_data: SpannableData::GreenText },
       Spannable { start: 14, len: 42, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://desuarchive.org/aco/thread/5189244") }) },
-      Spannable { start: 57, len: 20, spannable_data: SpannableData::GreenText },
+      Spannable { start: 58, len: 19, spannable_data: SpannableData::GreenText },
       Spannable { start: 78, len: 38, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://boards.4chan.org/search#/cyoag") }) },
-      Spannable { start: 117, len: 48, spannable_data: SpannableData::GreenText },
+      Spannable { start: 118, len: 47, spannable_data: SpannableData::GreenText },
       Spannable { start: 166, len: 29, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://pastebin.com/vrqYhnpu") }) },
     ];
 
@@ -518,7 +528,7 @@ what?";
     let expected_parsed_comment = ">>221655599\nAaaaaaa the day can\'t be over yet\n\nだれか !!!時よ止まれ,お願いします！！！";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 221655599 }) },
+      Spannable { start: 0, len: 11, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 221655599, is_you: false, is_op: false, preview: None }) },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -543,18 +553,28 @@ what?";
     let expected_parsed_comment = "looks like autism and seasonal sales/releases are related, check the autism lows vs dslr highs. \
     seems that /p/haggots are confirmed retards and gearfagging is the seasonal temporary cure. LEL\n\n\
     >>3878363\nah well, I was kinda drunk passing Vietnam to Cambodia river border, traveled for around 8 hours via a wooden boat. good times\n\n\n\
-    Camera-Specific Properties: \n\n\
-    Camera Software Android RP1A.200720.012.P615XXU4CUC3 \n\n\
-    Image-Specific Properties: \n\n\
-    Image Width 2000 \n\
-    Image Height 1200 \n\
-    Image Orientation Top, Left-Hand \n\n";
+    Camera-Specific Properties:\n\n\
+    Camera Software | Android RP1A.200720.012.P615XXU4CUC3\n\n\
+    Image-Specific Properties:\n\n\
+    Image Width | 2000\n\
+    Image Height | 1200\n\
+    Image Orientation | Top, Left-Hand\n\n";
 
     let expected_spannables = vec![
-      Spannable { start: 193, len: 9, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 3878363 }) },
-      Spannable { start: 331, len: 28, spannable_data: SpannableData::BoldText },
-      Spannable { start: 416, len: 27, spannable_data: SpannableData::BoldText },
-      Spannable { start: 331, len: 187, spannable_data: SpannableData::Monospace },
+      Spannable { start: 193, len: 9, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 3878363, is_you: false, is_op: false, preview: None }) },
+      Spannable { start: 332, len: 188, spannable_data: SpannableData::Monospace },
+      Spannable { start: 332, len: 27, spannable_data: SpannableData::BoldText },
+      Spannable { start: 332, len: 27, spannable_data: SpannableData::TableCell },
+      Spannable { start: 361, len: 15, spannable_data: SpannableData::TableCell },
+      Spannable { start: 379, len: 36, spannable_data: SpannableData::TableCell },
+      Spannable { start: 417, len: 26, spannable_data: SpannableData::BoldText },
+      Spannable { start: 417, len: 26, spannable_data: SpannableData::TableCell },
+      Spannable { start: 445, len: 11, spannable_data: SpannableData::TableCell },
+      Spannable { start: 459, len: 4, spannable_data: SpannableData::TableCell },
+      Spannable { start: 464, len: 12, spannable_data: SpannableData::TableCell },
+      Spannable { start: 479, len: 4, spannable_data: SpannableData::TableCell },
+      Spannable { start: 484, len: 17, spannable_data: SpannableData::TableCell },
+      Spannable { start: 504, len: 14, spannable_data: SpannableData::TableCell },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -571,8 +591,8 @@ what?";
     let expected_parsed_comment = "More of her? \n\n(USER WAS BANNED FOR THIS POST)";
 
     let expected_spannables = vec![
-      Spannable { start: 14, len: 32, spannable_data: SpannableData::TextForegroundColorRaw { color_hex: "#FF0000".to_string() } },
-      Spannable { start: 14, len: 32, spannable_data: SpannableData::BoldText },
+      // The markup (color, bold) is replaced with the BanMessage spannable
+      Spannable { start: 15, len: 31, spannable_data: SpannableData::BanMessage },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -589,8 +609,8 @@ what?";
     let expected_parsed_comment = "More of her? \n\n(USER WAS BANNED FOR THIS POST)";
 
     let expected_spannables = vec![
-      Spannable { start: 14, len: 32, spannable_data: SpannableData::TextForegroundColorRaw { color_hex: "#FF0000".to_string() } },
-      Spannable { start: 14, len: 32, spannable_data: SpannableData::BoldText },
+      // The markup (color, bold) is replaced with the BanMessage spannable
+      Spannable { start: 15, len: 31, spannable_data: SpannableData::BanMessage },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -608,8 +628,31 @@ what?";
     let expected_parsed_comment = "1. You must check your #fortune in order to post in this thread.\n\nYour fortune: Excellent Luck";
 
     let expected_spannables = vec![
-      Spannable { start: 65, len: 29, spannable_data: SpannableData::BoldText },
-      Spannable { start: 0, len: 94, spannable_data: SpannableData::TextForegroundColorRaw { color_hex: "#fd4d32".to_string() } },
+      Spannable { start: 64, len: 30, spannable_data: SpannableData::TextForegroundColorRaw { color_hex: "#fd4d32".to_string() } },
+      Spannable { start: 64, len: 30, spannable_data: SpannableData::StyleHint(SpannableStyleHint { color_role: None, background_role: None, is_bold: true, is_italic: false }) },
+      Spannable { start: 64, len: 30, spannable_data: SpannableData::Fortune { color: "#fd4d32".to_string() } },
+      Spannable { start: 66, len: 28, spannable_data: SpannableData::BoldText },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1235, 1235, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_style_hint_for_unstyled_fortune() {
+    // The fortune class alone makes the text bold, there is no inline style to decode
+    let post_comment_raw = "1. You must check your #fortune in order to post in this thread.<span class=\"fortune\">\
+    <br><br><b>Your fortune: Excellent Luck</b></span>";
+    let expected_parsed_comment = "1. You must check your #fortune in order to post in this thread.\n\nYour fortune: Excellent Luck";
+
+    let expected_spannables = vec![
+      Spannable { start: 64, len: 30, spannable_data: SpannableData::StyleHint(SpannableStyleHint { color_role: None, background_role: None, is_bold: true, is_italic: false }) },
+      Spannable { start: 64, len: 30, spannable_data: SpannableData::Fortune { color: "#000000".to_string() } },
+      Spannable { start: 66, len: 28, spannable_data: SpannableData::BoldText },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -629,7 +672,7 @@ what?";
 
     let expected_spannables = vec![
       Spannable { start: 0, len: 12, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: "g".to_string(), thread_no: 81446291, post_no: 81478722 }) },
-      Spannable { start: 40, len: 29, spannable_data: SpannableData::Monospace },
+      Spannable { start: 41, len: 28, spannable_data: SpannableData::CodeBlock },
     ];
 
     let post_parser_context = create_post_parser_context(
@@ -721,23 +764,23 @@ what?";
 
     let expected_spannables = vec![
       Spannable { start: 34, len: 13, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: "vg".to_string(), thread_no: 334945645, post_no: 334945645 }) },
-      Spannable { start: 48, len: 24, spannable_data: SpannableData::GreenText },
+      Spannable { start: 49, len: 23, spannable_data: SpannableData::GreenText },
       Spannable { start: 116, len: 44, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://projectignis.github.io/download.html".to_string() }) },
       Spannable { start: 199, len: 27, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://www.duelingbook.com".to_string() }) },
-      Spannable { start: 311, len: 14, spannable_data: SpannableData::GreenText },
+      Spannable { start: 312, len: 13, spannable_data: SpannableData::GreenText },
       Spannable { start: 336, len: 61, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "http://www.yugioh-card.com/en/rulebook/SD_RuleBook_EN_V10.pdf".to_string() }) },
       Spannable { start: 404, len: 36, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://yugipedia.com/wiki/Yugipedia".to_string() }) },
       Spannable { start: 465, len: 19, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "http://yugioh.party".to_string() }) },
       Spannable { start: 499, len: 23, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "http://yugiohprices.com".to_string() }) },
       Spannable { start: 533, len: 30, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://www.db.yugioh-card.com".to_string() }) },
-      Spannable { start: 564, len: 11, spannable_data: SpannableData::GreenText },
+      Spannable { start: 565, len: 10, spannable_data: SpannableData::GreenText },
       Spannable { start: 581, len: 45, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://www.izazin.com/taikai/results?tag=遊戯王".to_string() }) },
       Spannable { start: 632, len: 35, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "http://yugiohtopdecks.com/decklists".to_string() }) },
-      Spannable { start: 668, len: 6, spannable_data: SpannableData::GreenText },
+      Spannable { start: 669, len: 5, spannable_data: SpannableData::GreenText },
       Spannable { start: 679, len: 29, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "http://blog.livedoor.jp/maxut".to_string() }) },
       Spannable { start: 713, len: 26, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://ygorganization.com".to_string() }) },
-      Spannable { start: 740, len: 19, spannable_data: SpannableData::GreenText },
-      Spannable { start: 1306, len: 23, spannable_data: SpannableData::GreenText },
+      Spannable { start: 741, len: 18, spannable_data: SpannableData::GreenText },
+      Spannable { start: 1307, len: 22, spannable_data: SpannableData::GreenText },
       Spannable { start: 1361, len: 29, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://challonge.com/dngcup8".to_string() }) },
       Spannable { start: 1428, len: 34, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://challonge.com/dngfisherman".to_string() }) },
       Spannable { start: 1496, len: 28, spannable_data: SpannableData::Link(PostLink::UrlLink { link: "https://challonge.com/dngHAT".to_string() }) },
@@ -832,7 +875,7 @@ what?";
     let expected_parsed_comment = "{\n\"name\": \"Kuromakaba Light\",\n\"is_light_theme\": true,\n\"light_nav_bar\": true,\n\"light_status_bar\": true,\n\"accent_color\": \"#f3630e\",\n\"primary_color\": \"#000000\",\n\"back_color\": \"#e2e2e2\",\n\"post_details_color\": \"#6a6a6a\",\n\"post_highlight_quote_color\": \"#f3630e\",\n\"post_highlighted_color\": \"#f1f1f1\",\n\"post_inline_quote_color\": \"#899918\",\n\"post_link_color\": \"#f3630e\",\n\"post_name_color\": \"#6a6a6a\",\n\"post_quote_color\": \"#f3630e\",\n\"post_saved_reply_color\": \"#f1f1f1\",\n\"post_spoiler_color\": \"#c6c6c6\",\n\"post_spoiler_reveal_text_color\": \"#303030\",\n\"post_subject_color\": \"#134b6b\",\n\"post_unseen_label_color\": \"#ea8162\",\n\"text_color_hint\": \"#6a6a6a\",\n\"text_color_primary\": \"#474747\",\n\"text_color_secondary\": \"#6a6a6a\",\n\"bookmark_counter_has_replies_color\": \"#f3630e\",\n\"bookmark_counter_normal_color\": \"#474747\",\n\"bookmark_counter_not_watching_color\": \"#6a6a6a\",\n\"divider_color\": \"#c6c6c6\",\n\"error_color\": \"#ff0000\"\n}";
 
     let expected_spannables = vec![
-      Spannable { start: 0, len: 904, spannable_data: SpannableData::Monospace },
+      Spannable { start: 0, len: 904, spannable_data: SpannableData::CodeBlock },
       Spannable { start: 0, len: 904, spannable_data: SpannableData::ThemeJson { theme_name: "Kuromakaba Light".to_string(), is_light_theme: true } },
     ];
 
@@ -844,6 +887,2436 @@ what?";
     run_test(1235, 1235, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
   }
 
+  #[test]
+  fn post_parser_test_nested_spoilers_are_merged() {
+    let post_comment_raw = "<s>Test1<s>Test2</s>Test3</s>";
+    let expected_parsed_comment = "Test1Test2Test3";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 15, spannable_data: SpannableData::Spoiler },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_adjacent_spoilers_are_coalesced() {
+    let post_comment_raw = "<s>Test1</s><s>Test2</s><b>Test3</b><b>Test4</b>";
+    let expected_parsed_comment = "Test1Test2Test3Test4";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 10, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 10, len: 10, spannable_data: SpannableData::BoldText },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_separated_spoilers_stay_distinct() {
+    let post_comment_raw = "<s>Test1</s> <s>Test2</s><br><a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>";
+    let expected_parsed_comment = "Test1 Test2\n>>1>>1";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 5, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 6, len: 5, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 12, len: 3, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: None }) },
+      Spannable { start: 15, len: 3, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: false, preview: None }) },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(1)
+    );
+
+    run_test(2, 3, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_parse_budget_exceeded() {
+    let mut post_comment_raw = String::new();
+
+    for index in 0..2000 {
+      post_comment_raw.push_str(
+        format!("<span class=\"quote\">&gt;line {} <s>spoiler <b>bold</b></s></span><br>", index).as_str()
+      );
+    }
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw.as_str());
+
+    let full_post_comment_parsed = PostParser::new(&post_parser_context)
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert!(!full_post_comment_parsed.timed_out);
+
+    let parser_limits = ParserLimits { max_parse_millis: Some(0), ..ParserLimits::default() };
+    let partial_post_comment_parsed = PostParser::new_with_limits(&post_parser_context, parser_limits)
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert!(partial_post_comment_parsed.timed_out);
+
+    let partial_text = partial_post_comment_parsed.parsed_text.as_str();
+    let partial_text_chars_count = partial_text.chars().count();
+
+    assert!(!partial_text.is_empty());
+    assert!(partial_text.len() < full_post_comment_parsed.parsed_text.len());
+    assert!(full_post_comment_parsed.parsed_text.starts_with(partial_text));
+    assert!(!partial_post_comment_parsed.spannables.is_empty());
+
+    for spannable in partial_post_comment_parsed.spannables.iter() {
+      assert!(spannable.len > 0, "spannable={}", spannable);
+      assert!(
+        spannable.start + spannable.len <= partial_text_chars_count,
+        "spannable={}, chars_count={}",
+        spannable,
+        partial_text_chars_count
+      );
+    }
+  }
+
+  #[test]
+  fn post_parser_test_malformed_html_falls_back_to_unparsed_comment() {
+    let post_comment_raw = "Test1<b>Test2</b";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+    let post_comment_parsed = PostParser::new(&post_parser_context)
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(post_comment_raw, post_comment_parsed.parsed_text.as_str());
+    assert!(post_comment_parsed.spannables.is_empty());
+    assert!(post_comment_parsed.html_parser_error.is_some());
+  }
+
+  #[test]
+  fn post_parser_test_comments_are_skipped() {
+    let post_comment_raw = "Test1<!-- <s>\"Test2\"></s> --><b>Test3</b>";
+    let expected_parsed_comment = "Test1Test3";
+
+    let expected_spannables = vec![
+      Spannable { start: 5, len: 5, spannable_data: SpannableData::BoldText },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_self_closing_tags() {
+    let post_comment_raw = "Test1<br/>Test2<br /><span class=\"quote\"/>Test3";
+    let expected_parsed_comment = "Test1\nTest2\nTest3";
+
+    let expected_spannables = vec![];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_uppercase_tags() {
+    let post_comment_raw = "<SPAN CLASS=\"quote\">&gt;Test1</SPAN><BR><S>Test2</S><B>Test3</B>";
+    let expected_parsed_comment = ">Test1\nTest2Test3";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+      Spannable { start: 7, len: 5, spannable_data: SpannableData::Spoiler },
+      Spannable { start: 12, len: 5, spannable_data: SpannableData::BoldText },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_unclosed_bold_inside_greentext() {
+    let post_comment_raw = "<span class=\"quote\">&gt;<b>Test1</span><br>Test2";
+    let expected_parsed_comment = ">Test1\nTest2";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+      Spannable { start: 1, len: 5, spannable_data: SpannableData::BoldText },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_strikethrough() {
+    let post_comment_raw = "<s>Test1</s> <del>Test2</del> <strike>Test3</strike>";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+    let post_comment_parsed = PostParser::new(&post_parser_context)
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("Test1 Test2 Test3", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 5, spannable_data: SpannableData::Spoiler },
+        Spannable { start: 6, len: 5, spannable_data: SpannableData::Strikethrough },
+        Spannable { start: 12, len: 5, spannable_data: SpannableData::Strikethrough },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_s_tag_meaning(STagMeaning::Strikethrough);
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 5, spannable_data: SpannableData::Strikethrough },
+        Spannable { start: 6, len: 5, spannable_data: SpannableData::Strikethrough },
+        Spannable { start: 12, len: 5, spannable_data: SpannableData::Strikethrough },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_bold_italic_underline() {
+    let post_comment_raw = "<b>Test1</b> <strong>Test2</strong> <i>Test3</i> <em>Test4</em> <u><i>Test5</i></u>";
+    let expected_parsed_comment = "Test1 Test2 Test3 Test4 Test5";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 5, spannable_data: SpannableData::BoldText },
+      Spannable { start: 6, len: 5, spannable_data: SpannableData::BoldText },
+      Spannable { start: 12, len: 5, spannable_data: SpannableData::Italic },
+      Spannable { start: 18, len: 5, spannable_data: SpannableData::Italic },
+      Spannable { start: 24, len: 5, spannable_data: SpannableData::Italic },
+      Spannable { start: 24, len: 5, spannable_data: SpannableData::Underline },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_inline_code_and_code_block() {
+    let post_comment_raw = "Run <code>ls -l</code><br><pre><code>fn main() {\n    println!(\"{}\", 1);\n}</code></pre>";
+    let expected_parsed_comment = "Run ls -l\nfn main() {\n    println!(\"{}\", 1);\n}";
+
+    let expected_spannables = vec![
+      Spannable { start: 4, len: 5, spannable_data: SpannableData::InlineCode },
+      Spannable { start: 10, len: 36, spannable_data: SpannableData::CodeBlock },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_superscript_and_subscript() {
+    let post_comment_raw = "E=mc<sup>2</sup>, H<sub>2</sub>O";
+    let expected_parsed_comment = "E=mc2, H2O";
+
+    let expected_spannables = vec![
+      Spannable { start: 4, len: 1, spannable_data: SpannableData::Superscript },
+      Spannable { start: 8, len: 1, spannable_data: SpannableData::Subscript },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_lists() {
+    let post_comment_raw = "Steps:<ol start=\"2\"><li>Test1</li><li>Test2<ul><li>Test3</li><li>Test4</li></ul></li></ol>Test5";
+    let expected_parsed_comment = "Steps:\n2. Test1\n3. Test2\n• Test3\n• Test4\nTest5";
+
+    let expected_spannables = vec![
+      Spannable { start: 7, len: 8, spannable_data: SpannableData::ListItem { depth: 1, ordered: true, index: 2 } },
+      Spannable { start: 16, len: 24, spannable_data: SpannableData::ListItem { depth: 1, ordered: true, index: 3 } },
+      Spannable { start: 25, len: 7, spannable_data: SpannableData::ListItem { depth: 2, ordered: false, index: 1 } },
+      Spannable { start: 33, len: 7, spannable_data: SpannableData::ListItem { depth: 2, ordered: false, index: 2 } },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_headings() {
+    let post_comment_raw = "Intro<h1>Test1</h1>Test2<h3></h3><h6>Test3</h6>";
+    let expected_parsed_comment = "Intro\nTest1\nTest2\nTest3\n";
+
+    let expected_spannables = vec![
+      Spannable { start: 6, len: 5, spannable_data: SpannableData::Heading { level: 1 } },
+      Spannable { start: 18, len: 5, spannable_data: SpannableData::Heading { level: 6 } },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_sjis_art() {
+    let post_comment_raw = "<span class=\"sjis\">　　∧＿∧<br>　（　´∀｀）<br>　（　　　　）</span>Test";
+    let expected_parsed_comment = "　　∧＿∧\n　（　´∀｀）\n　（　　　　）Test";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 21, spannable_data: SpannableData::SjisArt },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  struct UppercaseHandler {}
+
+  impl RuleHandler for UppercaseHandler {
+    fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+      ctx.out_text_parts.push(TextPart::new(ctx.element.collect_text().to_uppercase()));
+      return true;
+    }
+  }
+
+  #[test]
+  fn post_parser_test_custom_parsing_rules() {
+    let post_comment_raw = "<span class=\"shout red\">Test1</span> <i class=\"shout\">Test2</i> <b>Test3</b> <span class=\"quote\">&gt;Test4</span>";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .add_tag_class_handler("span", "shout", Rc::new(UppercaseHandler {}))
+      .remove_tag_handlers("b");
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("TEST1 Test2 Test3 >Test4", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 6, len: 5, spannable_data: SpannableData::Italic },
+        Spannable { start: 18, len: 6, spannable_data: SpannableData::GreenText },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    post_parser.comment_parser
+      .parsing_rules()
+      .add_class_handler("shout", Rc::new(UppercaseHandler {}));
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("TEST1 TEST2 Test3 >Test4", post_comment_parsed.parsed_text.as_str());
+  }
+
+  #[test]
+  fn post_parser_test_dvach_profile() {
+    let post_comment_raw = "<span class=\"unkfunc\">&gt;Test1</span><br><span class=\"spoiler\">Test2</span> <s>Test3</s> <span class=\"u\">Test4</span>";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("2ch.hk", "b", 1, 2, 0, post_comment_raw);
+    let parser_profile = ParserProfile::for_site(&post_raw.post_descriptor.thread_descriptor.board_descriptor.site_descriptor).unwrap();
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">Test1\nTest2 Test3 Test4", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 7, len: 5, spannable_data: SpannableData::Spoiler },
+        Spannable { start: 13, len: 5, spannable_data: SpannableData::Strikethrough },
+        Spannable { start: 19, len: 5, spannable_data: SpannableData::Underline },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    assert!(ParserProfile::for_site_name("4chan").is_some());
+    assert!(ParserProfile::for_site_name("unknown").is_none());
+  }
+
+  #[test]
+  fn post_parser_test_vichan_profile() {
+    let post_comment_raw = "<a onclick=\"highlightReply('123', event);\" href=\"/tech/res/100.html#123\">&gt;&gt;123</a><br>\
+    <a href=\"/g/res/200.html#201\">&gt;&gt;&gt;/g/201</a><br><span class=\"heading\">Test1</span> <span class=\"detected\">Test2</span> <em>Test3</em>";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(123u64)
+    );
+
+    let post_raw = PostRaw::new("8kun", "tech", 100, 124, 0, post_comment_raw);
+    let parser_profile = ParserProfile::for_site_name("8kun").unwrap();
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">>123\n>>>/g/201 →\nTest1 Test2 Test3", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 5, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 123, is_you: false, is_op: false, preview: None }) },
+        Spannable { start: 6, len: 11, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 200, post_no: 201 }) },
+        Spannable { start: 18, len: 5, spannable_data: SpannableData::BoldText },
+        Spannable { start: 18, len: 5, spannable_data: SpannableData::TextForegroundColorRaw { color_hex: String::from("#AF0A0F") } },
+        Spannable { start: 24, len: 5, spannable_data: SpannableData::BoldText },
+        Spannable { start: 30, len: 5, spannable_data: SpannableData::Italic },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_board_thread_and_search_links() {
+    let post_comment_raw = "Go to &gt;&gt;&gt;/g/ or &gt;&gt;&gt;/vg/ <a href=\"/g/thread/123/some-subject\">Test1</a> \
+    <a href=\"//boards.4channel.org/g/catalog#s=linux+desktop\">Test2</a>";
+    let expected_parsed_comment = "Go to >>>/g/ or >>>/vg/ Test1 → Test2";
+
+    let expected_spannables = vec![
+      Spannable { start: 6, len: 6, spannable_data: SpannableData::Link(PostLink::BoardLink { board_code: String::from("g") }) },
+      Spannable { start: 16, len: 7, spannable_data: SpannableData::Link(PostLink::BoardLink { board_code: String::from("vg") }) },
+      Spannable { start: 24, len: 7, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 123, post_no: 123 }) },
+      Spannable { start: 32, len: 5, spannable_data: SpannableData::Link(PostLink::SearchLink { board_code: String::from("g"), search_query: String::from("linux+desktop") }) },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_dead_cross_board_quote() {
+    let post_comment_raw = "<span class=\"deadlink\">&gt;&gt;&gt;/a/12345</span><br><span class=\"deadlink\">&gt;&gt;&gt;/a/</span>Test";
+    let expected_parsed_comment = ">>>/a/12345 (DEAD)\n>>>/a/Test";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 18, spannable_data: SpannableData::Link(PostLink::DeadCrossBoard { board_code: String::from("a"), post_no: 12345 }) },
+      Spannable { start: 19, len: 6, spannable_data: SpannableData::Link(PostLink::BoardLink { board_code: String::from("a") }) },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(12345u64),
+      set_of!()
+    );
+
+    run_test(12345, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_autolink_plain_text_urls() {
+    let post_comment_raw = "Ссылка (https://example.com/a_(b)), www.example.org. и magnet:?xt=urn:btih:c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+    let expected_parsed_comment = post_comment_raw;
+
+    let expected_spannables = vec![
+      Spannable { start: 8, len: 25, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com/a_(b)") }) },
+      Spannable { start: 36, len: 15, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://www.example.org") }) },
+      Spannable { start: 55, len: 60, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("magnet:?xt=urn:btih:c12fe1c06bba254a9dc9f519b335aa7c1367a88a") }) },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1234567890, 123456780, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  struct TestSiteUrlResolver {}
+
+  impl SiteUrlResolver for TestSiteUrlResolver {
+    fn resolve(&self, url: &str, _: &PostRaw, _: &PostParserContext) -> Option<PostLink> {
+      let board_code = url.strip_prefix("https://test.chan/")?.strip_suffix("/")?;
+      return Option::Some(PostLink::BoardLink { board_code: String::from(board_code) });
+    }
+  }
+
+  #[test]
+  fn post_parser_test_site_url_resolvers() {
+    let post_comment_raw = "https://boards.4chan.org/g/thread/1#p3 https://boards.4channel.org/g/thread/2#p4 \
+    https://boards.4chan.org/g/ https://test.chan/b/ https://example.com/";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(3u64)
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 5, 0, post_comment_raw);
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.add_site_url_resolver(Box::new(TestSiteUrlResolver {}));
+
+    let post_links = post_parser.parse_post(&post_raw)
+      .post_comment_parsed
+      .spannables
+      .iter()
+      .map(|spannable| spannable.spannable_data.clone())
+      .collect::<Vec<SpannableData>>();
+
+    assert_eq!(
+      vec![
+        SpannableData::Link(PostLink::Quote { post_no: 3, is_you: false, is_op: false, preview: None }),
+        SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 2, post_no: 4 }),
+        SpannableData::Link(PostLink::BoardLink { board_code: String::from("g") }),
+        SpannableData::Link(PostLink::BoardLink { board_code: String::from("b") }),
+        SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com/") }),
+      ],
+      post_links
+    );
+  }
+
+  #[test]
+  fn post_parser_test_mailto_and_tel_links() {
+    let post_comment_raw = "<a href=\"mailto:sage\">Anonymous</a> \
+    <a href=\"mailto:anon@example.com?subject=hi\">mail me</a> \
+    <a href=\"tel:+123456\">call me</a> \
+    <a href=\"mailto:sage\"><span class=\"name\">Name</span></a>";
+    let expected_parsed_comment = "Anonymous mail me call me Name";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 9, spannable_data: SpannableData::Link(PostLink::Email { email: String::from("sage") }) },
+      Spannable { start: 10, len: 7, spannable_data: SpannableData::Link(PostLink::Email { email: String::from("anon@example.com") }) },
+      Spannable { start: 18, len: 7, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("tel:+123456") }) },
+    ];
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+
+    run_test(
+      1234567890,
+      1234567891,
+      &post_parser_context,
+      post_comment_raw,
+      expected_parsed_comment,
+      &expected_spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_media_links() {
+    let post_comment_raw = "https://youtu.be/dQw4w9WgXcQ <a href=\"https://vimeo.com/76979871\">vimeo</a> \
+    https://www.youtube.com/watch?v=dQw4w9WgXcQ https://example.com/";
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+
+    let media_link_spannables = parsed_post.post_comment_parsed.spannables
+      .iter()
+      .filter(|spannable| match spannable.spannable_data { SpannableData::MediaLink { .. } => true, _ => false })
+      .map(|spannable| Spannable { start: spannable.start, len: spannable.len, spannable_data: spannable.spannable_data.clone() })
+      .collect::<Vec<Spannable>>();
+
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 28, spannable_data: SpannableData::MediaLink { kind: MediaLinkKind::YouTube, id: String::from("dQw4w9WgXcQ") } },
+        Spannable { start: 29, len: 5, spannable_data: SpannableData::MediaLink { kind: MediaLinkKind::Vimeo, id: String::from("76979871") } },
+        Spannable { start: 35, len: 43, spannable_data: SpannableData::MediaLink { kind: MediaLinkKind::YouTube, id: String::from("dQw4w9WgXcQ") } },
+      ],
+      media_link_spannables
+    );
+
+    assert_eq!(
+      vec![
+        MediaLink { kind: MediaLinkKind::YouTube, id: String::from("dQw4w9WgXcQ") },
+        MediaLink { kind: MediaLinkKind::Vimeo, id: String::from("76979871") },
+      ],
+      parsed_post.media_links
+    );
+  }
+
+  #[test]
+  fn post_parser_test_span_offset_modes() {
+    let post_comment_raw = "日本👍🏽 <b>bold</b> <s>e\u{301}</s>";
+
+    let parse_spans = |span_offset_mode: SpanOffsetMode| {
+      let post_parser_context = PostParserContext::new_with_span_offset_mode(set_of!(), set_of!(), span_offset_mode);
+      let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+      return PostParser::new(&post_parser_context)
+        .parse_post(&post_raw)
+        .post_comment_parsed
+        .spannables
+        .iter()
+        .map(|spannable| (spannable.start, spannable.len))
+        .collect::<Vec<(usize, usize)>>();
+    };
+
+    assert_eq!(vec![(5, 4), (10, 2)], parse_spans(SpanOffsetMode::UnicodeScalars));
+    assert_eq!(vec![(15, 4), (20, 3)], parse_spans(SpanOffsetMode::Utf8Bytes));
+    assert_eq!(vec![(7, 4), (12, 2)], parse_spans(SpanOffsetMode::Utf16CodeUnits));
+    assert_eq!(vec![(4, 4), (9, 1)], parse_spans(SpanOffsetMode::Graphemes));
+  }
+
+  #[test]
+  fn post_parser_test_spoilered_quote_inside_greentext_tree() {
+    let post_comment_raw = "<span class=\"quote\"><s><a href=\"#p123\" class=\"quotelink\">&gt;&gt;123</a></s></span>";
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(123u64));
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+    let post_comment_parsed = PostParser::new(&post_parser_context).parse_post(&post_raw).post_comment_parsed;
+    let tree = post_comment_parsed.spannable_tree();
+
+    assert_eq!(1, tree.len());
+    assert_eq!(SpannableData::GreenText, tree[0].spannable.spannable_data);
+    assert_eq!(1, tree[0].children.len());
+    assert_eq!(SpannableData::Link(PostLink::Quote { post_no: 123, is_you: false, is_op: false, preview: None }), tree[0].children[0].spannable.spannable_data);
+    assert_eq!(1, tree[0].children[0].children.len());
+    assert_eq!(SpannableData::Spoiler, tree[0].children[0].children[0].spannable.spannable_data);
+  }
+
+  #[test]
+  fn post_parser_test_dice_rolls() {
+    let post_comment_raw = "<b>Rolled 5, 3 = 8 (2d6)</b><br>Action<br><b>Not a roll</b>";
+    let expected_parsed_comment = "Rolled 5, 3 = 8 (2d6)\nAction\nNot a roll";
+
+    let post_roll = PostRoll { dice: 2, sides: Some(6), results: vec![5, 3], total: 8 };
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 21, spannable_data: SpannableData::BoldText },
+      Spannable { start: 0, len: 21, spannable_data: SpannableData::DiceRoll(post_roll.clone()) },
+      Spannable { start: 29, len: 10, spannable_data: SpannableData::BoldText },
+    ];
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+
+    run_test(1234567890, 1234567891, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+
+    let post_raw = PostRaw::new("4chan", "g", 1234567890, 1234567891, 0, post_comment_raw);
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+    assert_eq!(vec![post_roll], parsed_post.rolls);
+  }
+
+  #[test]
+  fn post_parser_test_text_cleanup() {
+    let post_comment_raw = "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> see <b>this</b> https://youtu.be/dQw4w9WgXcQ [Embed]";
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64));
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.set_text_cleanup_options(TextCleanupOptions {
+      strip_embed_markers: true,
+      strip_duplicate_you_markers: true,
+      strip_op_markers: true,
+      strip_patterns: Vec::new()
+    });
+
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!(">>1 see this https://youtu.be/dQw4w9WgXcQ", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![(0, 3), (8, 4), (13, 28), (13, 28)],
+      post_comment_parsed.spannables.iter().map(|spannable| (spannable.start, spannable.len)).collect::<Vec<(usize, usize)>>()
+    );
+  }
+
+  #[test]
+  fn post_parser_test_post_metadata() {
+    let metadata = PostMetadataRaw {
+      sub: Some(String::from("<b>Fate</b> &amp; friends")),
+      name_html: None,
+      name: Some(String::from("Anon &quot;the&quot; Anon")),
+      trip: Some(String::from("!!abc")),
+      capcode: Some(String::from("mod")),
+      id: Some(String::from("AbCd1234")),
+      country: None,
+      country_name: None,
+      board_flag: Some(String::from("AC")),
+      flag_name: Some(String::from("Anarcho-Capitalist")),
+      flag_html: None,
+      time: Some(1600000000),
+      files: vec![
+        PostFileRaw {
+          filename: String::from("cat &amp; dog"),
+          ext: String::from(".png"),
+          tim: String::from("1600000000123"),
+          fsize: 1024,
+          w: 640,
+          h: 480,
+          md5: Some(String::from("abc==")),
+          spoiler: true
+        }
+      ],
+      files_html: None
+    };
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "a", 1, 1, 0, "Comment").with_metadata(metadata);
+
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+    let parsed_metadata = parsed_post.metadata;
+
+    let subject_parsed = parsed_metadata.subject_parsed.unwrap();
+    assert_eq!("Fate & friends", subject_parsed.parsed_text.as_str());
+    assert_eq!(
+      &vec![Spannable { start: 0, len: 4, spannable_data: SpannableData::BoldText }],
+      subject_parsed.spannables.as_ref()
+    );
+
+    assert_eq!(
+      PosterInfo {
+        name: Some(String::from("Anon \"the\" Anon")),
+        tripcode: None,
+        secure_tripcode: Some(String::from("!!abc")),
+        capcode: Some(String::from("Mod"))
+      },
+      parsed_metadata.poster_info
+    );
+    assert_eq!(Some(PosterId::new("AbCd1234")), parsed_metadata.poster_id);
+    assert_eq!(
+      Some(PostFlag { code: String::from("AC"), name: Some(String::from("Anarcho-Capitalist")), kind: PostFlagKind::Board }),
+      parsed_metadata.flag
+    );
+    assert_eq!(Some(1600000000), parsed_metadata.timestamp);
+    assert_eq!(
+      vec![
+        PostFile {
+          original_name: String::from("cat & dog.png"),
+          server_name: String::from("1600000000123"),
+          extension: String::from("png"),
+          size: 1024,
+          width: 640,
+          height: 480,
+          md5: Some(String::from("abc==")),
+          is_spoiler: true
+        }
+      ],
+      parsed_metadata.files
+    );
+
+    assert_eq!("Comment", parsed_post.post_comment_parsed.parsed_text.as_str());
+  }
+
+  #[test]
+  fn post_parser_test_poster_info_html() {
+    let mut metadata = PostMetadataRaw::default();
+    metadata.name_html = Some(String::from("<span class=\"name\">Anon &amp; co</span> <span class=\"postertrip\">!Ep8pui8Vw2</span> \
+    <strong class=\"capcode hand id_admin\" title=\"Highlight posts by Administrators\">## Admin</strong>"));
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "a", 1, 1, 0, "").with_metadata(metadata);
+
+    let parsed_metadata = PostParser::new(&post_parser_context).parse_post(&post_raw).metadata;
+
+    assert_eq!(
+      PosterInfo {
+        name: Some(String::from("Anon & co")),
+        tripcode: Some(String::from("!Ep8pui8Vw2")),
+        secure_tripcode: None,
+        capcode: Some(String::from("Admin"))
+      },
+      parsed_metadata.poster_info
+    );
+
+    assert_eq!("Anon & co !Ep8pui8Vw2 ## Admin", parsed_metadata.poster_info_parsed.parsed_text.as_str());
+    assert_eq!(
+      &vec![Spannable { start: 22, len: 8, spannable_data: SpannableData::Capcode { capcode: String::from("Admin"), kind: CapcodeKind::Admin } }],
+      parsed_metadata.poster_info_parsed.spannables.as_ref()
+    );
+  }
+
+  #[test]
+  fn post_parser_test_replies_to_and_replies_from() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64, 2u64, 3u64));
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let posts = vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP"),
+      PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>\
+      <a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a><br><a href=\"#p100\" class=\"quotelink\">&gt;&gt;100</a>"),
+    ];
+
+    let parsed_posts = posts.iter()
+      .map(|post_raw| post_parser.parse_post(post_raw))
+      .collect::<Vec<ParsedPost>>();
+
+    assert_eq!(set_of!(), parsed_posts[0].replies_to);
+    assert_eq!(set_of!(1u64), parsed_posts[1].replies_to);
+    assert_eq!(set_of!(1u64, 2u64, 100u64), parsed_posts[2].replies_to);
+
+    let replies_from = ThreadParser::build_replies_from(&parsed_posts);
+    assert_eq!(3, replies_from.len());
+    assert_eq!(&set_of!(2u64, 3u64), replies_from.get(&1).unwrap());
+    assert_eq!(&set_of!(3u64), replies_from.get(&2).unwrap());
+    assert_eq!(&set_of!(3u64), replies_from.get(&100).unwrap());
+  }
+
+  #[test]
+  fn post_parser_test_parse_thread() {
+    // Post 3 is not known to the context but it's a part of the thread
+    let post_parser_context = create_post_parser_context(set_of!(2u64), set_of!());
+
+    let posts = vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP <a href=\"#p3\" class=\"quotelink\">&gt;&gt;3</a>"),
+      PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a><br><a href=\"#p4\" class=\"quotelink\">&gt;&gt;4</a>"),
+    ];
+
+    let parsed_posts = ThreadParser::parse_thread(&posts, &post_parser_context);
+
+    let texts = parsed_posts.iter()
+      .map(|parsed_post| parsed_post.post_comment_parsed.parsed_text.as_str())
+      .collect::<Vec<&str>>();
+
+    assert_eq!(vec!["OP >>3", ">>1 (OP)", ">>2 (You)\n>>4 (DEAD)"], texts);
+    assert_eq!(
+      &SpannableData::Link(PostLink::Quote { post_no: 3, is_you: false, is_op: false, preview: None }),
+      &parsed_posts[0].post_comment_parsed.spannables[0].spannable_data
+    );
+  }
+
+  #[cfg(feature = "rayon")]
+  #[test]
+  fn post_parser_test_parse_thread_in_parallel_keeps_order() {
+    let posts = (1..=500u64)
+      .map(|post_no| {
+        let comment = format!(
+          "Post {}<br><a href=\"#p{}\" class=\"quotelink\">&gt;&gt;{}</a> <s>{}</s> https://example.com/{}",
+          post_no,
+          post_no / 2,
+          post_no / 2,
+          post_no,
+          post_no
+        );
+
+        return PostRaw::new("4chan", "g", 1, post_no, 0, comment.as_str());
+      })
+      .collect::<Vec<PostRaw>>();
+
+    let my_replies = set_of!(3u64, 10u64, 250u64);
+    let post_parser_context = create_post_parser_context(my_replies.clone(), set_of!());
+
+    let parsed_posts = ThreadParser::parse_thread_with(&posts, &post_parser_context, &|thread_context| {
+      return PostParser::new(thread_context);
+    });
+
+    // Sequential parse of the same posts with the same context
+    let thread_context = create_post_parser_context(my_replies, posts.iter().map(|post_raw| post_raw.post_no()).collect());
+    let post_parser = PostParser::new(&thread_context);
+
+    assert_eq!(posts.len(), parsed_posts.len());
+
+    for (post_raw, parsed_post) in posts.iter().zip(parsed_posts.iter()) {
+      assert_eq!(post_raw.post_no(), parsed_post.post_descriptor.post_no());
+      assert_eq!(post_parser.parse_post(post_raw).to_bytes(), parsed_post.to_bytes(), "post_no={}", post_raw.post_no());
+    }
+  }
+
+  #[test]
+  fn post_parser_test_thread_parser_update() {
+    let mut thread_parser = ThreadParser::new(set_of!(), SpanOffsetMode::UnicodeScalars);
+
+    let thread_update = thread_parser.update(&vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP"),
+      PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a>"),
+    ]);
+
+    assert_eq!(ThreadUpdate { added: vec![1, 2, 3], invalidated: vec![], removed: vec![] }, thread_update);
+
+    // Nothing changed
+    let thread_update = thread_parser.update(&vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP"),
+      PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a>"),
+    ]);
+
+    assert_eq!(ThreadUpdate { added: vec![], invalidated: vec![], removed: vec![] }, thread_update);
+
+    // Post 2 got deleted (the quote in post 3 becomes dead), post 1 got edited and post 4 is new
+    thread_parser.set_my_replies(set_of!(4u64));
+
+    let thread_update = thread_parser.update(&vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP edited"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a>"),
+      PostRaw::new("4chan", "g", 1, 4, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>"),
+    ]);
+
+    assert_eq!(ThreadUpdate { added: vec![4], invalidated: vec![1, 3], removed: vec![2] }, thread_update);
+
+    let texts = thread_parser.parsed_posts().iter()
+      .map(|parsed_post| parsed_post.post_comment_parsed.parsed_text.as_str())
+      .collect::<Vec<&str>>();
+
+    assert_eq!(vec!["OP edited", ">>2 (DEAD)", ">>1 (OP)"], texts);
+    assert!(thread_parser.parsed_post(2).is_none());
+
+    // Post 3 now quotes one of the user's posts
+    let thread_update = thread_parser.update(&vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP edited"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p4\" class=\"quotelink\">&gt;&gt;4</a>"),
+      PostRaw::new("4chan", "g", 1, 4, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a>"),
+    ]);
+
+    assert_eq!(ThreadUpdate { added: vec![], invalidated: vec![3], removed: vec![] }, thread_update);
+    assert_eq!(">>4 (You)", thread_parser.parsed_post(3).unwrap().post_comment_parsed.parsed_text.as_str());
+  }
+
+  #[test]
+  fn post_parser_test_parsed_post_to_bytes_from_bytes() {
+    let metadata = PostMetadataRaw {
+      sub: Some(String::from("<b>Subject</b>")),
+      name: Some(String::from("Anonymous")),
+      capcode: Some(String::from("admin")),
+      country: Some(String::from("US")),
+      country_name: Some(String::from("United States")),
+      time: Some(-5),
+      files: vec![
+        PostFileRaw {
+          filename: String::from("file"),
+          ext: String::from(".webm"),
+          tim: String::from("1600000000123"),
+          fsize: 3000000,
+          w: 1920,
+          h: 1080,
+          md5: None,
+          spoiler: false
+        }
+      ],
+      files_html: Some(String::from("<div class=\"file\"><div class=\"fileText\">File: <a href=\"//i.4cdn.org/g/1600000000123.webm\">file.webm</a> \
+      (2.86 MB, 1920x1080)</div><a class=\"fileThumb\" href=\"//i.4cdn.org/g/1600000000123.webm\"><img src=\"//i.4cdn.org/g/1600000000123s.jpg\"></a></div>")),
+      ..PostMetadataRaw::default()
+    };
+
+    let quote_previews = vec![(2u64, QuotePreview { poster_name: String::from("Anonymous"), comment_preview: String::from("Hi") })]
+      .into_iter()
+      .collect::<HashMap<u64, QuotePreview>>();
+
+    let post_parser_context = create_post_parser_context(set_of!(2u64), set_of!(1u64, 2u64))
+      .with_quote_previews(quote_previews);
+    let post_raw = PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a><br>\
+    <a href=\"#p100\" class=\"quotelink\">&gt;&gt;100</a><br><s>spoiler</s> ünïcödé <b>Rolled 3, 4 = 7</b><br>\
+    https://www.youtube.com/watch?v=dQw4w9WgXcQ<div class=\"poll\"><b>Yes?</b><ul><li>Yes (3 votes)</li></ul></div>").with_metadata(metadata);
+
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+    let bytes = parsed_post.to_bytes();
+    let decoded = ParsedPost::from_bytes(&bytes).unwrap();
+
+    assert_eq!(parsed_post.post_descriptor, decoded.post_descriptor);
+    assert_eq!(parsed_post.post_comment_parsed.original_text, decoded.post_comment_parsed.original_text);
+    assert_eq!(parsed_post.post_comment_parsed.parsed_text, decoded.post_comment_parsed.parsed_text);
+    assert_eq!(parsed_post.post_comment_parsed.spannables, decoded.post_comment_parsed.spannables);
+    assert_eq!(
+      parsed_post.metadata.subject_parsed.as_ref().unwrap().spannables,
+      decoded.metadata.subject_parsed.as_ref().unwrap().spannables
+    );
+    assert_eq!(parsed_post.metadata.poster_info, decoded.metadata.poster_info);
+    assert_eq!(parsed_post.metadata.poster_info_parsed.spannables, decoded.metadata.poster_info_parsed.spannables);
+    assert_eq!(parsed_post.metadata.flag, decoded.metadata.flag);
+    assert_eq!(Some(-5), decoded.metadata.timestamp);
+    assert_eq!(parsed_post.metadata.files, decoded.metadata.files);
+    assert_eq!(1, decoded.metadata.images.len());
+    assert_eq!(parsed_post.metadata.images, decoded.metadata.images);
+    assert_eq!(1, decoded.media_links.len());
+    assert_eq!(parsed_post.media_links, decoded.media_links);
+    assert_eq!(1, decoded.rolls.len());
+    assert_eq!(parsed_post.rolls, decoded.rolls);
+    assert_eq!(1, decoded.polls.len());
+    assert_eq!(parsed_post.polls, decoded.polls);
+    assert_eq!(set_of!(2u64, 100u64), decoded.replies_to);
+
+    // Encoding is deterministic
+    assert_eq!(bytes, decoded.to_bytes());
+
+    // Truncated/corrupted data and other versions are rejected
+    assert!(ParsedPost::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(ParsedPost::from_bytes(&[]).is_err());
+
+    let mut other_version = bytes.clone();
+    other_version[5] += 1;
+    assert!(ParsedPost::from_bytes(&other_version).is_err());
+  }
+
+  #[test]
+  fn post_parser_test_search_highlight() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64))
+      .with_search_highlight(SearchHighlightOptions {
+        terms: vec![String::from("op"), String::from("rust")],
+        case_insensitive: true,
+        whole_word: true
+      });
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>Rust &gt; <s>rustc</s> OP");
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+    let post_comment_parsed = parsed_post.post_comment_parsed;
+
+    assert_eq!(">>1 (OP)\nRust > rustc OP", post_comment_parsed.parsed_text.as_str());
+
+    let highlights = post_comment_parsed.spannables
+      .iter()
+      .filter(|spannable| spannable.spannable_data == SpannableData::SearchHighlight)
+      .map(|spannable| (spannable.start, spannable.len))
+      .collect::<Vec<(usize, usize)>>();
+
+    // The "(OP)" suffix is a part of the final text so it's highlighted too
+    assert_eq!(vec![(5, 2), (9, 4), (22, 2)], highlights);
+  }
+
+  #[test]
+  fn post_parser_test_filter_engine() {
+    let mut filter_engine = FilterEngine::new();
+    filter_engine.add_filter(
+      1,
+      FilterPattern::Plain { text: String::from("rust"), case_insensitive: true },
+      vec![FilterScope::Comment, FilterScope::Subject],
+      FilterAction::Highlight,
+      Some(String::from("#ff0000"))
+    ).unwrap();
+    filter_engine.add_filter(
+      2,
+      FilterPattern::Regex { pattern: String::from(r"^Mr\.") },
+      vec![FilterScope::Name],
+      FilterAction::Hide,
+      None
+    ).unwrap();
+    filter_engine.add_filter(
+      3,
+      FilterPattern::Regex { pattern: String::from(r"\.webm$") },
+      vec![FilterScope::Filename],
+      FilterAction::Remove,
+      None
+    ).unwrap();
+
+    assert!(filter_engine.add_filter(4, FilterPattern::Regex { pattern: String::from("(") }, vec![], FilterAction::Hide, None).is_err());
+
+    let metadata = PostMetadataRaw {
+      sub: Some(String::from("Rust general")),
+      name: Some(String::from("Mr. Anon")),
+      ..PostMetadataRaw::default()
+    };
+
+    let posts = vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP").with_metadata(metadata),
+      PostRaw::new("4chan", "g", 1, 2, 0, "I like <b>rust</b> and RUST"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "Nothing to see here"),
+    ];
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let parsed_posts = ThreadParser::parse_thread_filtered(&posts, &post_parser_context, &filter_engine);
+
+    assert_eq!(
+      vec![FilterMatch { filter_id: 1, action: FilterAction::Highlight }, FilterMatch { filter_id: 2, action: FilterAction::Hide }],
+      parsed_posts[0].filter_matches
+    );
+    assert_eq!(vec![FilterMatch { filter_id: 1, action: FilterAction::Highlight }], parsed_posts[1].filter_matches);
+    assert_eq!(Vec::<FilterMatch>::new(), parsed_posts[2].filter_matches);
+
+    let filter_highlight = |start: usize, len: usize| Spannable {
+      start,
+      len,
+      spannable_data: SpannableData::FilterHighlight { color: String::from("#ff0000") }
+    };
+
+    let subject_highlights = parsed_posts[0].metadata.subject_parsed.as_ref().unwrap().spannables
+      .iter()
+      .filter(|spannable| matches!(spannable.spannable_data, SpannableData::FilterHighlight { .. }))
+      .collect::<Vec<&Spannable>>();
+    assert_eq!(vec![&filter_highlight(0, 4)], subject_highlights);
+
+    let comment_highlights = parsed_posts[1].post_comment_parsed.spannables
+      .iter()
+      .filter(|spannable| matches!(spannable.spannable_data, SpannableData::FilterHighlight { .. }))
+      .collect::<Vec<&Spannable>>();
+    assert_eq!(vec![&filter_highlight(7, 4), &filter_highlight(16, 4)], comment_highlights);
+  }
+
+  #[test]
+  fn post_parser_test_text_rules() {
+    let post_comment_raw = "<span class=\"quote\">&gt;[spoiler]Test1[/spoiler]</span><br>R: 150 / I: 32";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .add_text_rule(TextRule::template(Regex::new(r"\[spoiler\](.+?)\[/spoiler\]").unwrap(), 1, SpannableData::Spoiler))
+      .add_text_rule(TextRule::new(
+        Regex::new(r"R: (\d+) / I: (\d+)").unwrap(),
+        Box::new(|_| TextRuleOutput { spans: vec![(0, SpannableData::BoldText)], keep_group: Option::None })
+      ));
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">Test1\nR: 150 / I: 32", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 1, len: 5, spannable_data: SpannableData::Spoiler },
+        Spannable { start: 7, len: 14, spannable_data: SpannableData::BoldText },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_bbcode_profile() {
+    let post_comment_raw = "[quote]Test1[/quote]\n[spoiler]Test2[/spoiler] [b]Test3[/b] [s]<b>Test4</b>[/s]";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("bbcode", "b", 1, 2, 0, post_comment_raw);
+    let parser_profile = ParserProfile::bbcode();
+    assert_eq!(CommentFormat::BbCode, parser_profile.comment_format);
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("Test1\nTest2 Test3 <b>Test4</b>", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 5, spannable_data: SpannableData::GreenText },
+        Spannable { start: 6, len: 5, spannable_data: SpannableData::Spoiler },
+        Spannable { start: 12, len: 5, spannable_data: SpannableData::BoldText },
+        Spannable { start: 18, len: 12, spannable_data: SpannableData::Strikethrough },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_markdown_profile() {
+    let post_comment_raw = ">Test1\n**Test2** *Test3* [Test4](https://example.com) ~~Test5~~";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("markdown", "b", 1, 2, 0, post_comment_raw);
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, ParserProfile::markdown(), ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">Test1\nTest2 Test3 Test4 Test5", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 7, len: 5, spannable_data: SpannableData::BoldText },
+        Spannable { start: 13, len: 5, spannable_data: SpannableData::Italic },
+        Spannable { start: 19, len: 5, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com") }) },
+        Spannable { start: 25, len: 5, spannable_data: SpannableData::Strikethrough },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_plain_text_profile() {
+    let post_comment_raw = ">Test1\n>>1 >>3 Test2 https://example.com";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(1, 2)
+    );
+
+    let post_raw = PostRaw::new("warosu", "g", 1, 2, 0, post_comment_raw);
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, ParserProfile::plain_text(), ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">Test1\n>>1 (OP) >>3 (DEAD) Test2 https://example.com", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 7, len: 8, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: true, preview: None }) },
+        Spannable { start: 16, len: 10, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 3 }) },
+        Spannable { start: 33, len: 19, spannable_data: SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com") }) },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_malformed_html_falls_back_to_plain_text() {
+    let post_comment_raw = ">Test1\n>>1 <b>Test2</b";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(1, 2)
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+    let post_comment_parsed = PostParser::new(&post_parser_context)
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert!(post_comment_parsed.html_parser_error.is_some());
+    assert_eq!(">Test1\n>>1 (OP) <b>Test2</b", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 7, len: 8, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: true, preview: None }) },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_foolfuuka_profile() {
+    let post_comment_raw = concat!(
+      "<span class=\"greentext\">&gt;Test1</span><br>",
+      "<a href=\"https://desuarchive.org/g/thread/1/#1\" class=\"backlink\" data-function=\"highlight\" data-backlink=\"true\" data-board=\"g\" data-post=\"1\">&gt;&gt;1</a><br>",
+      "<a href=\"https://desuarchive.org/g/thread/1/#3\" class=\"backlink\" data-board=\"g\" data-post=\"3\">&gt;&gt;3</a><br>",
+      "<a href=\"https://desuarchive.org/g/thread/10/#11\" class=\"backlink\" data-board=\"g\" data-post=\"11\">&gt;&gt;11</a><br>",
+      "<a href=\"https://desuarchive.org/a/thread/20/#21\" class=\"backlink\" data-board=\"a\" data-post=\"21\">&gt;&gt;&gt;/a/21</a><br>",
+      "<span class=\"spoiler\">Test2</span>"
+    );
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(1, 2)
+    );
+
+    let post_raw = PostRaw::new("desuarchive", "g", 1, 2, 0, post_comment_raw);
+    let parser_profile = ParserProfile::for_site(&post_raw.post_descriptor.thread_descriptor.board_descriptor.site_descriptor).unwrap();
+    assert_eq!("desuarchive", parser_profile.site_name);
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">Test1\n>>1 (OP)\n>>3 (DEAD)\n>>11 →\n>>>/a/21 →\nTest2", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 7, len: 8, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: true, preview: None }) },
+        Spannable { start: 16, len: 10, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 3 }) },
+        Spannable { start: 27, len: 6, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("g"), thread_no: 10, post_no: 11 }) },
+        Spannable { start: 34, len: 10, spannable_data: SpannableData::Link(PostLink::ThreadLink { board_code: String::from("a"), thread_no: 20, post_no: 21 }) },
+        Spannable { start: 45, len: 5, spannable_data: SpannableData::Spoiler },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_quote_formats() {
+    let post_comment_raw = "<span class=\"deadlink\">#3</span> <a href=\"#p1\" class=\"quotelink\">#1</a> <span class=\"deadlink\">&gt;&gt;5</span>";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!(1, 2)
+    );
+
+    let post_raw = PostRaw::new("futaba", "b", 1, 2, 0, post_comment_raw);
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_quote_format(QuoteFormat::futaba().with_label_style(QuoteLabelStyle::FourChan));
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">>3 (DEAD) >>1 (OP) >>5", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 10, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 3 }) },
+        Spannable { start: 11, len: 8, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: true, preview: None }) },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    let post_raw = PostRaw::new("2ch.hk", "b", 1, 2, 0, "<span class=\"deadlink\">№3</span>");
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_quote_format(QuoteFormat::numero());
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("№3 (DEAD)", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 9, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 3 }) },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_line_break_mode() {
+    let post_comment_raw = "<p>Test1\nTest2</p><p><b>Test3</b></p><div>Test4</div>Test5<br><br><br><br><i>Test6</i><br><pre>a\nb</pre>";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+    let mut post_parser = PostParser::new(&post_parser_context);
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("Test1\nTest2Test3Test4Test5\n\n\n\nTest6\na\nb", post_comment_parsed.parsed_text.as_str());
+
+    post_parser.set_line_break_mode(LineBreakMode { text_newlines: false, block_elements: true, max_blank_lines: Option::Some(1) });
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("Test1 Test2\nTest3\nTest4\nTest5\n\nTest6\na\nb", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 12, len: 5, spannable_data: SpannableData::BoldText },
+        Spannable { start: 31, len: 5, spannable_data: SpannableData::Italic },
+        Spannable { start: 37, len: 3, spannable_data: SpannableData::CodeBlock },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_whitespace_policy() {
+    let post_comment_raw = "<br>  <b>Test1</b>   Test2  <br><pre>a    b  </pre>Test3 \t<br><span class=\"sjis\">  (  ﾟ∀ﾟ)  </span><br> ";
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, post_comment_raw);
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.set_whitespace_policy(WhitespacePolicy { collapse_runs: true, trim_line_ends: true, trim_post_ends: true });
+
+    let post_comment_parsed = post_parser
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!("Test1 Test2\na    b  Test3\n  (  ﾟ∀ﾟ)  ", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 5, spannable_data: SpannableData::BoldText },
+        Spannable { start: 12, len: 8, spannable_data: SpannableData::CodeBlock },
+        Spannable { start: 26, len: 11, spannable_data: SpannableData::SjisArt },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_max_parsed_length() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64))
+      .with_max_parsed_length(12)
+      .with_truncation_ellipsis(" [...]");
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br><b>Bold text</b> and the rest of the comment");
+    let post_comment_parsed = PostParser::new(&post_parser_context).parse_post(&post_raw).post_comment_parsed;
+
+    assert!(post_comment_parsed.was_truncated);
+    assert_eq!(">>1 (OP)\nBol [...]", post_comment_parsed.parsed_text.as_str());
+
+    let spans = post_comment_parsed.spannables
+      .iter()
+      .map(|spannable| (spannable.start, spannable.len))
+      .collect::<Vec<(usize, usize)>>();
+
+    assert_eq!(vec![(0, 8), (9, 3)], spans);
+
+    let short_post_raw = PostRaw::new("4chan", "g", 1, 3, 0, "Short");
+    let short_post_comment_parsed = PostParser::new(&post_parser_context).parse_post(&short_post_raw).post_comment_parsed;
+
+    assert!(!short_post_comment_parsed.was_truncated);
+    assert_eq!("Short", short_post_comment_parsed.parsed_text.as_str());
+  }
+
+  #[test]
+  fn post_parser_test_parse_for_catalog() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64));
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let post_raw = PostRaw::new(
+      "4chan",
+      "g",
+      1,
+      2,
+      0,
+      "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>First   line<br><br><span class=\"quote\">&gt;implying</span><br>\
+      <a href=\"/g/thread/5#p6\" class=\"quotelink\">&gt;&gt;&gt;/g/6</a> https://example.com"
+    );
+
+    let catalog_snippet = post_parser.parse_for_catalog(&post_raw, 100);
+    assert_eq!(CatalogSnippet { text: String::from("First line >implying https://example.com"), was_truncated: false }, catalog_snippet);
+
+    let catalog_snippet = post_parser.parse_for_catalog(&post_raw, 11);
+    assert_eq!(CatalogSnippet { text: String::from("First line…"), was_truncated: true }, catalog_snippet);
+  }
+
+  #[test]
+  #[cfg(feature = "stats")]
+  fn post_parser_test_stats() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_parser = PostParser::new(&post_parser_context);
+
+    post_parser.parse_comment(&PostRaw::new("4chan", "g", 1, 1, 0, "<b>Bold</b> text<!-- comment -->"));
+    post_parser.parse_comment(&PostRaw::new("4chan", "g", 1, 2, 0, "Text"));
+
+    let stats = post_parser.stats();
+    assert_eq!(2, stats.posts_parsed);
+    assert_eq!(5, stats.nodes_parsed);
+
+    post_parser.reset_stats();
+    assert_eq!(PostParserStats::default(), post_parser.stats());
+  }
+
+  #[test]
+  fn post_parser_test_parsed_spannable_text_builder() {
+    let parsed_spannable_text = ParsedSpannableText::builder()
+      .with_span_offset_mode(SpanOffsetMode::Utf16CodeUnits)
+      .append("😀 ")
+      .append_span("5 posts", SpannableData::BoldText)
+      .append(" omitted, ")
+      .append_span("show", SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com") }))
+      .add_span(0, 100, SpannableData::Italic)
+      .add_span(3, 0, SpannableData::Underline)
+      .build();
+
+    assert_eq!("😀 5 posts omitted, show", parsed_spannable_text.parsed_text.as_str());
+    assert_eq!("😀 5 posts omitted, show", parsed_spannable_text.original_text.as_str());
+
+    let spans = parsed_spannable_text.spannables
+      .iter()
+      .map(|spannable| (spannable.start, spannable.len, spannable.spannable_data.to_string()))
+      .collect::<Vec<(usize, usize, String)>>();
+
+    // The italic span is clamped to the text, the empty underline span is dropped and the offsets
+    // are in utf-16 code units ("😀" is two of them)
+    assert_eq!(
+      vec![
+        (0, 24, SpannableData::Italic.to_string()),
+        (3, 7, SpannableData::BoldText.to_string()),
+        (20, 4, SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com") }).to_string())
+      ],
+      spans
+    );
+  }
+
+  #[test]
+  fn post_parser_test_spoiler_with_links() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(123u64));
+    let post_parser = PostParser::new(&post_parser_context);
+    let quote = SpannableData::Link(PostLink::Quote { post_no: 123, is_you: false, is_op: false, preview: None });
+
+    let comments = vec![
+      (
+        "<s><a href=\"#p123\" class=\"quotelink\">&gt;&gt;123</a></s>",
+        vec![(0, 5, SpannableData::Spoiler.to_string()), (0, 5, quote.to_string())]
+      ),
+      (
+        "<s>see <a href=\"#p123\" class=\"quotelink\">&gt;&gt;123</a> and https://example.com</s>",
+        vec![
+          (0, 33, SpannableData::Spoiler.to_string()),
+          (4, 5, quote.to_string()),
+          (14, 19, SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com") }).to_string())
+        ]
+      ),
+      (
+        "<span class=\"quote\">&gt;implying <s><a href=\"#p123\" class=\"quotelink\">&gt;&gt;123</a></s></span>",
+        vec![
+          (0, 15, SpannableData::GreenText.to_string()),
+          (10, 5, SpannableData::Spoiler.to_string()),
+          (10, 5, quote.to_string())
+        ]
+      )
+    ];
+
+    for (comment, expected_spans) in comments {
+      let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, comment);
+      let parsed_comment = post_parser.parse_comment(&post_raw);
+
+      let spans = parsed_comment.spannables
+        .iter()
+        .map(|spannable| (spannable.start, spannable.len, spannable.spannable_data.to_string()))
+        .collect::<Vec<(usize, usize, String)>>();
+
+      assert_eq!(expected_spans, spans, "comment: {}", comment);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_quote_suffixes() {
+    let post_comment_raw = "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> \
+      <a href=\"#p3\" class=\"quotelink\">&gt;&gt;3</a> \
+      <a href=\"/g/thread/5#p6\" class=\"quotelink\">&gt;&gt;&gt;/g/6</a> \
+      <a href=\"#p999\" class=\"quotelink\">&gt;&gt;999</a> end";
+
+    let all_suffixes = QuoteSuffixes { cross_thread: CrossThreadSuffix::Label, ..QuoteSuffixes::default() };
+
+    let expected = vec![
+      (
+        all_suffixes,
+        ">>1 (OP) >>3 (You) >>>/g/6 (Cross-thread) >>999 (DEAD) end",
+        vec![(0, 8), (9, 9), (19, 22), (42, 12)]
+      ),
+      (
+        QuoteSuffixes { op: false, dead: false, ..QuoteSuffixes::default() },
+        ">>1 >>3 (You) >>>/g/6 → >>999 end",
+        vec![(0, 3), (4, 9), (14, 9), (24, 5)]
+      ),
+      (
+        QuoteSuffixes::none(),
+        ">>1 >>3 >>>/g/6 >>999 end",
+        vec![(0, 3), (4, 3), (8, 7), (16, 5)]
+      )
+    ];
+
+    for (quote_suffixes, expected_text, expected_spans) in expected {
+      let post_parser_context = create_post_parser_context(set_of!(3u64), set_of!(1u64, 2u64, 3u64))
+        .with_quote_suffixes(quote_suffixes);
+
+      let post_raw = PostRaw::new("4chan", "g", 1, 4, 0, post_comment_raw);
+      let parsed_comment = PostParser::new(&post_parser_context).parse_comment(&post_raw);
+
+      let spans = parsed_comment.spannables
+        .iter()
+        .map(|spannable| (spannable.start, spannable.len))
+        .collect::<Vec<(usize, usize)>>();
+
+      assert_eq!(expected_text, parsed_comment.parsed_text.as_str());
+      assert_eq!(expected_spans, spans);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_quote_previews() {
+    let quote_preview = QuotePreview {
+      poster_name: String::from("Anonymous"),
+      comment_preview: String::from("First post of the thread")
+    };
+
+    let mut quote_previews = HashMap::new();
+    quote_previews.insert(1u64, quote_preview.clone());
+
+    let post_comment_raw = "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br><a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a>";
+    let expected_parsed_comment = ">>1 (OP)\n>>2";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 8, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 1, is_you: false, is_op: true, preview: Some(quote_preview) }) },
+      Spannable { start: 9, len: 3, spannable_data: SpannableData::Link(PostLink::Quote { post_no: 2, is_you: false, is_op: false, preview: None }) },
+    ];
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64, 2u64))
+      .with_quote_previews(quote_previews);
+
+    run_test(1, 3, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_word_break_modes() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new(
+      "4chan",
+      "g",
+      1,
+      2,
+      0,
+      "<b>Supercali\u{AD}fragilistic</b> https://example.com/very<wbr>/long<wbr>/link end<WBR/>less"
+    );
+
+    let expected = vec![
+      (WordBreakMode::Strip, "Supercalifragilistic https://example.com/very/long/link endless", vec![(0, 20), (21, 34)]),
+      (
+        WordBreakMode::ZeroWidthSpace,
+        "Supercalifragilistic https://example.com/very\u{200B}/long\u{200B}/link end\u{200B}less",
+        vec![(0, 20), (21, 36)]
+      )
+    ];
+
+    for (word_break_mode, expected_text, expected_spans) in expected {
+      let mut post_parser = PostParser::new(&post_parser_context);
+      post_parser.set_word_break_mode(word_break_mode);
+
+      let parsed_comment = post_parser.parse_comment(&post_raw);
+
+      let spans = parsed_comment.spannables
+        .iter()
+        .map(|spannable| (spannable.start, spannable.len))
+        .collect::<Vec<(usize, usize)>>();
+
+      assert_eq!(expected_text, parsed_comment.parsed_text.as_str());
+      assert_eq!(expected_spans, spans);
+
+      // The url of the link never has the zero width spaces
+      let link = SpannableData::Link(PostLink::UrlLink { link: String::from("https://example.com/very/long/link") });
+      assert_eq!(link, parsed_comment.spannables[1].spannable_data);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_flag_html() {
+    let flags = vec![
+      (
+        "<span class=\"flag flag-us\" title=\"United States\"></span>",
+        Some(PostFlag { code: String::from("US"), name: Some(String::from("United States")), kind: PostFlagKind::Country })
+      ),
+      (
+        "<span class=\"bfl bfl-ac\" title=\"Anarcho-Capitalist\"></span>",
+        Some(PostFlag { code: String::from("AC"), name: Some(String::from("Anarcho-Capitalist")), kind: PostFlagKind::Board })
+      ),
+      (
+        "<img src=\"//s.4cdn.org/image/country/troll/an.gif\" alt=\"AN\" title=\"Anarchist\" class=\"countryFlag\">",
+        Some(PostFlag { code: String::from("AN"), name: Some(String::from("Anarchist")), kind: PostFlagKind::Troll })
+      ),
+      (
+        "<img class=\"flag\" src=\"/static/flags/fi.png\" title=\"Finland &amp; friends\">",
+        Some(PostFlag { code: String::from("FI"), name: Some(String::from("Finland & friends")), kind: PostFlagKind::Country })
+      ),
+      (
+        "<span class=\"flag flag-jp\"></span>",
+        Some(PostFlag { code: String::from("JP"), name: None, kind: PostFlagKind::Country })
+      ),
+      ("<span class=\"name\">Anonymous</span>", None)
+    ];
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_parser = PostParser::new(&post_parser_context);
+
+    for (flag_html, expected_flag) in flags {
+      let metadata = PostMetadataRaw { flag_html: Some(String::from(flag_html)), ..PostMetadataRaw::default() };
+      let post_raw = PostRaw::new("4chan", "pol", 1, 2, 0, "").with_metadata(metadata);
+
+      assert_eq!(expected_flag, post_parser.parse_metadata(&post_raw).flag, "flag_html: {}", flag_html);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_polls() {
+    let post_comment_raw = "Vote<div class=\"poll\"><div class=\"poll-question\">Best &amp; worst?</div><ul class=\"poll-options\">\
+    <li class=\"poll-option\"><span class=\"poll-option-text\">Rei</span> <span class=\"poll-votes\">12 votes</span></li>\
+    <li class=\"poll-option\">Asuka (7 votes)</li></ul></div>now";
+    let expected_parsed_comment = "Vote\u{FFFC}now";
+
+    let post_poll = PostPoll {
+      question: String::from("Best & worst?"),
+      options: vec![(String::from("Rei"), 12), (String::from("Asuka"), 7)]
+    };
+
+    let expected_spannables = vec![
+      Spannable { start: 4, len: 1, spannable_data: SpannableData::Poll(post_poll.clone()) },
+    ];
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+
+    run_test(1234567890, 1234567891, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+
+    let post_raw = PostRaw::new("4chan", "g", 1234567890, 1234567891, 0, post_comment_raw);
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+    assert_eq!(vec![post_poll], parsed_post.polls);
+
+    // Not a poll without options, the text is kept
+    run_test(1234567890, 1234567891, &post_parser_context, "<div class=\"poll\">Closed</div>", "Closed", &vec![]);
+  }
+
+  #[test]
+  fn post_parser_test_tex_modes() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new(
+      "4chan",
+      "sci",
+      1,
+      2,
+      0,
+      "Solve [math]x^2 &lt; 1[/math]<br>[eqn]\\sum_{n=1}^{\\infty} \\frac{1}{n^2}[/eqn]<br><b>pls</b>"
+    );
+
+    let expected = vec![
+      (
+        TeXMode::Disabled,
+        "Solve [math]x^2 < 1[/math]\n[eqn]\\sum_{n=1}^{\\infty} \\frac{1}{n^2}[/eqn]\npls",
+        vec![Spannable { start: 72, len: 3, spannable_data: SpannableData::BoldText }]
+      ),
+      (
+        TeXMode::Source,
+        "Solve x^2 < 1\n\\sum_{n=1}^{\\infty} \\frac{1}{n^2}\npls",
+        vec![
+          Spannable { start: 6, len: 7, spannable_data: SpannableData::TeX { display_mode: false, source: None } },
+          Spannable { start: 14, len: 33, spannable_data: SpannableData::TeX { display_mode: true, source: None } },
+          Spannable { start: 48, len: 3, spannable_data: SpannableData::BoldText },
+        ]
+      ),
+      (
+        TeXMode::Placeholder,
+        "Solve \u{FFFC}\n\u{FFFC}\npls",
+        vec![
+          Spannable { start: 6, len: 1, spannable_data: SpannableData::TeX { display_mode: false, source: Some(String::from("x^2 < 1")) } },
+          Spannable {
+            start: 8,
+            len: 1,
+            spannable_data: SpannableData::TeX { display_mode: true, source: Some(String::from("\\sum_{n=1}^{\\infty} \\frac{1}{n^2}")) }
+          },
+          Spannable { start: 10, len: 3, spannable_data: SpannableData::BoldText },
+        ]
+      )
+    ];
+
+    for (tex_mode, expected_text, expected_spannables) in expected {
+      let mut post_parser = PostParser::new(&post_parser_context);
+      post_parser.set_tex_mode(tex_mode);
+
+      let parsed_comment = post_parser.parse_comment(&post_raw);
+
+      assert_eq!(expected_text, parsed_comment.parsed_text.as_str(), "tex_mode={:?}", tex_mode);
+      assert_eq!(&expected_spannables, parsed_comment.spannables.as_ref(), "tex_mode={:?}", tex_mode);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_ruby() {
+    let post_comment_raw = "<ruby>漢<rp>(</rp><rt>かん</rt><rp>)</rp>字<rt>じ</rt></ruby>です <ruby><rb>東京</rb><rt> とうきょう </rt></ruby>";
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+
+    run_test(
+      1234567890,
+      1234567891,
+      &post_parser_context,
+      post_comment_raw,
+      "漢(かん)字(じ)です 東京(とうきょう)",
+      &vec![
+        Spannable { start: 0, len: 1, spannable_data: SpannableData::Ruby { reading: String::from("かん") } },
+        Spannable { start: 5, len: 1, spannable_data: SpannableData::Ruby { reading: String::from("じ") } },
+        Spannable { start: 12, len: 2, spannable_data: SpannableData::Ruby { reading: String::from("とうきょう") } },
+      ]
+    );
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.set_ruby_mode(RubyMode::BaseOnly);
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_raw = PostRaw::new("4chan", "jp", 1234567890, 1234567891, 0, post_comment_raw);
+    let parsed_comment = post_parser.parse_comment(&post_raw);
+
+    assert_eq!("漢字です 東京", parsed_comment.parsed_text.as_str());
+    assert_eq!(
+      &vec![
+        Spannable { start: 0, len: 1, spannable_data: SpannableData::Ruby { reading: String::from("かん") } },
+        Spannable { start: 1, len: 1, spannable_data: SpannableData::Ruby { reading: String::from("じ") } },
+        Spannable { start: 5, len: 2, spannable_data: SpannableData::Ruby { reading: String::from("とうきょう") } },
+      ],
+      parsed_comment.spannables.as_ref()
+    );
+  }
+
+  #[test]
+  fn post_parser_test_unknown_tag_policy() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<p>one <marquee>two <b>three</b></marquee> <blink></blink>four</p>");
+
+    let expected = vec![
+      (UnknownTagPolicy::KeepChildren, "one two three four", vec![Spannable { start: 8, len: 5, spannable_data: SpannableData::BoldText }]),
+      (UnknownTagPolicy::DropSubtree, "one  four", vec![]),
+      (
+        UnknownTagPolicy::Marker,
+        "one two three four",
+        vec![
+          Spannable { start: 4, len: 9, spannable_data: SpannableData::Unknown { tag: String::from("marquee") } },
+          Spannable { start: 8, len: 5, spannable_data: SpannableData::BoldText },
+        ]
+      ),
+    ];
+
+    for (unknown_tag_policy, expected_text, expected_spannables) in expected {
+      let unknown_tags = Rc::new(std::cell::RefCell::new(Vec::<String>::new()));
+      let unknown_tags_clone = unknown_tags.clone();
+
+      let mut post_parser = PostParser::new(&post_parser_context);
+      post_parser.set_unknown_tag_policy(unknown_tag_policy);
+      post_parser.set_unknown_tag_callback(Box::new(move |post_raw, element| {
+        unknown_tags_clone.borrow_mut().push(format!("{}:{}", post_raw.site_name(), element.tag_name));
+      }));
+
+      let parsed_comment = post_parser.parse_comment(&post_raw);
+
+      assert_eq!(expected_text, parsed_comment.parsed_text.as_str(), "policy={:?}", unknown_tag_policy);
+      assert_eq!(&expected_spannables, parsed_comment.spannables.as_ref(), "policy={:?}", unknown_tag_policy);
+      assert_eq!(vec!["4chan:marquee", "4chan:blink"], *unknown_tags.borrow(), "policy={:?}", unknown_tag_policy);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_warnings() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "Test <span class=\"deadlink\">&gt;&gt;abc</span> <a>link</a>");
+    let parsed_comment = post_parser.parse_comment(&post_raw);
+
+    assert_eq!("Test >>abc ", parsed_comment.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        ParserWarning {
+          source: String::from("DeadLinkHandler"),
+          message: String::from("Failed to parse link_text_child (>>abc)"),
+          html: Some(String::from("<span class=\"deadlink\">&gt;&gt;abc</span>")),
+          position: Some(5)
+        },
+        ParserWarning {
+          source: String::from("AnchorRuleHandler"),
+          message: String::from("<a> tag has no \"href\" attribute"),
+          html: Some(String::from("<a>link</a>")),
+          position: Some(11)
+        },
+      ],
+      parsed_comment.warnings
+    );
+
+    // Every parse gets its own warnings
+    let post_raw = PostRaw::new("4chan", "g", 1, 3, 0, "Test");
+    assert!(post_parser.parse_comment(&post_raw).warnings.is_empty());
+  }
+
+  #[test]
+  fn post_parser_test_deeply_nested_comment() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, &format!("{}Test{}", "<span>".repeat(100_000), "</span>".repeat(100_000)));
+
+    let parsed_comment = PostParser::new(&post_parser_context).parse_comment(&post_raw);
+
+    assert_eq!(HtmlParserErrorKind::TooDeep, parsed_comment.html_parser_error.unwrap().kind);
+    assert!(parsed_comment.parsed_text.contains("Test"));
+  }
+
+  #[test]
+  fn post_parser_test_files_html() {
+    let files_html = "<div class=\"file\" id=\"f100\"><div class=\"fileText\" id=\"fT100\">File: <a title=\"very long original name &amp; co.png\" \
+    href=\"//i.4cdn.org/g/1600000000000.png\" target=\"_blank\">very long or(...).png</a> (1.2 MB, 1920x1080)</div>\
+    <a class=\"fileThumb\" href=\"//i.4cdn.org/g/1600000000000.png\" target=\"_blank\"><img src=\"//i.4cdn.org/g/1600000000000s.jpg\" alt=\"1.2 MB\"></a></div>\
+    <div class=\"file\" id=\"f101\"><div class=\"fileText\" id=\"fT101\" title=\"secret.webm\">File: <a href=\"//i.4cdn.org/g/1600000000001.webm\">Spoiler Image</a> (300 KB, 640x480)</div>\
+    <a class=\"fileThumb imgspoiler\" href=\"//i.4cdn.org/g/1600000000001.webm\"><img src=\"//s.4cdn.org/image/spoiler-g1.png\"></a></div>\
+    <div class=\"file\"><span class=\"fileThumb\"><img src=\"//s.4cdn.org/image/filedeleted-res.gif\" alt=\"File deleted.\"></span></div>";
+
+    let metadata = PostMetadataRaw { files_html: Some(String::from(files_html)), ..PostMetadataRaw::default() };
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 1, 0, "Comment").with_metadata(metadata);
+
+    let parsed_metadata = PostParser::new(&post_parser_context).parse_post(&post_raw).metadata;
+
+    assert_eq!(
+      vec![
+        PostImage {
+          original_name: String::from("very long original name & co.png"),
+          server_name: String::from("1600000000000"),
+          extension: String::from("png"),
+          size: 1258291,
+          width: 1920,
+          height: 1080,
+          full_url: Some(String::from("//i.4cdn.org/g/1600000000000.png")),
+          thumbnail_url: Some(String::from("//i.4cdn.org/g/1600000000000s.jpg")),
+          is_spoiler: false,
+          kind: PostImageKind::Image,
+          duration: None,
+          is_nsfw: false
+        },
+        PostImage {
+          original_name: String::from("secret.webm"),
+          server_name: String::from("1600000000001"),
+          extension: String::from("webm"),
+          size: 307200,
+          width: 640,
+          height: 480,
+          full_url: Some(String::from("//i.4cdn.org/g/1600000000001.webm")),
+          thumbnail_url: Some(String::from("//s.4cdn.org/image/spoiler-g1.png")),
+          is_spoiler: true,
+          kind: PostImageKind::Video,
+          duration: None,
+          is_nsfw: false
+        }
+      ],
+      parsed_metadata.images
+    );
+
+    // The files of the html are the files of the post when there are no json files
+    assert_eq!(
+      vec![("very long original name & co.png", 1258291, false), ("secret.webm", 307200, true)],
+      parsed_metadata.files.iter().map(|file| (file.original_name.as_str(), file.size, file.is_spoiler)).collect::<Vec<(&str, u64, bool)>>()
+    );
+
+    // vichan: the link text is the server file name, the original name is in a.postfilename
+    let files_html = "<div class=\"file\"><p class=\"fileinfo\">File: <a href=\"/b/src/1600000000001.png\">1600000000001.png</a> \
+    <span class=\"unimportant\">(300 B, 10x20, <a class=\"postfilename\" title=\"reply image.png\">reply image.png</a>)</span></p>\
+    <a href=\"/b/src/1600000000001.png\" target=\"_blank\"><img class=\"post-image\" src=\"/b/thumb/1600000000001.png\"></a></div>";
+
+    let metadata = PostMetadataRaw { files_html: Some(String::from(files_html)), ..PostMetadataRaw::default() };
+    let post_raw = PostRaw::new("lainchan", "b", 1, 1, 0, "Comment").with_metadata(metadata);
+    let images = PostParser::new(&post_parser_context).parse_post(&post_raw).metadata.images;
+
+    assert_eq!(1, images.len());
+    assert_eq!("reply image.png", images[0].original_name);
+    assert_eq!((300, 10, 20), (images[0].size, images[0].width, images[0].height));
+    assert_eq!(Some(String::from("/b/thumb/1600000000001.png")), images[0].thumbnail_url);
+  }
+
+  #[test]
+  fn post_parser_test_multiple_files_html() {
+    // dvach
+    let files_html = "<div class=\"post__images post__images_type_multi\">\
+    <figure class=\"post__image\"><figcaption class=\"post__file-attr\"><a class=\"desktop\" target=\"_blank\" href=\"/b/src/1/16000000000010.webm\" title=\"clip.webm\">clip.webm</a>\
+    <span class=\"post__filezise\">(1234Кб, 1280x720, 00:01:23)</span></figcaption>\
+    <a href=\"/b/src/1/16000000000010.webm\" class=\"post__image-link\"><img src=\"/b/thumb/1/16000000000010s.jpg\" class=\"post__file-preview\"></a></figure>\
+    <figure class=\"post__image post__image_nsfw\"><figcaption class=\"post__file-attr\"><a class=\"desktop\" target=\"_blank\" href=\"/b/src/1/16000000000011.png\" title=\"pic.png\">pic.png</a>\
+    <span class=\"post__filezise\">(2Мб, 800x600)</span></figcaption>\
+    <a href=\"/b/src/1/16000000000011.png\" class=\"post__image-link\"><img src=\"/b/thumb/1/16000000000011s.png\" class=\"post__file-preview\"></a></figure>\
+    <figure class=\"post__image\"><figcaption class=\"post__file-attr\"><a class=\"desktop\" target=\"_blank\" href=\"/b/src/1/16000000000012.mp3\" title=\"song.mp3\">song.mp3</a>\
+    <span class=\"post__filezise\">(3000Кб, 0x0, 03:05)</span></figcaption></figure>\
+    </div>";
+
+    let metadata = PostMetadataRaw { files_html: Some(String::from(files_html)), ..PostMetadataRaw::default() };
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("2ch.hk", "b", 1, 1, 0, "Comment").with_metadata(metadata);
+
+    let parsed_metadata = PostParser::new(&post_parser_context).parse_post(&post_raw).metadata;
+
+    assert_eq!(
+      vec![
+        ("clip.webm", "16000000000010", 1263616, PostImageKind::Video, Some(83), false),
+        ("pic.png", "16000000000011", 2097152, PostImageKind::Image, None, true),
+        ("song.mp3", "16000000000012", 3072000, PostImageKind::Audio, Some(185), false)
+      ],
+      parsed_metadata.images.iter()
+        .map(|image| (image.original_name.as_str(), image.server_name.as_str(), image.size, image.kind, image.duration, image.is_nsfw))
+        .collect::<Vec<(&str, &str, u64, PostImageKind, Option<u32>, bool)>>()
+    );
+    assert_eq!(Some(String::from("/b/thumb/1/16000000000011s.png")), parsed_metadata.images[1].thumbnail_url);
+    assert_eq!(None, parsed_metadata.images[2].thumbnail_url);
+    assert_eq!(3, parsed_metadata.files.len());
+  }
+
+  #[test]
+  fn post_parser_test_abbreviated_and_deleted_comments() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let abbreviated = PostRaw::new("4chan", "g", 1, 1, 0, "Long text<br><br><span class=\"abbr\">Comment too long. \
+    <a href=\"/g/thread/1#p1\">Click here</a> to view the full text.</span>");
+    let parsed_post = post_parser.parse_post(&abbreviated);
+
+    assert_eq!("Long text", parsed_post.post_comment_parsed.parsed_text.trim_end());
+    assert!(parsed_post.is_comment_abbreviated);
+    assert!(!parsed_post.is_deleted);
+    assert!(parsed_post.post_comment_parsed.spannables.is_empty());
+
+    let deleted = PostRaw::new("4chan", "g", 1, 2, 0, "<strong class=\"warning\">[Deleted]</strong><br>Some text");
+    let parsed_post = post_parser.parse_post(&deleted);
+
+    assert_eq!("Some text", parsed_post.post_comment_parsed.parsed_text.trim());
+    assert!(!parsed_post.is_comment_abbreviated);
+    assert!(parsed_post.is_deleted);
+
+    let restored = ParsedPost::from_bytes(&parsed_post.to_bytes()).unwrap();
+    assert!(restored.is_deleted);
+    assert!(restored.post_comment_parsed.is_deleted);
+
+    let regular = post_parser.parse_post(&PostRaw::new("4chan", "g", 1, 3, 0, "<strong>Bold</strong>"));
+    assert!(!regular.is_comment_abbreviated);
+    assert!(!regular.is_deleted);
+  }
+
+  #[test]
+  fn post_parser_test_ban_message() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 1, 0, "Text<br><br><strong style=\"color: red;\">(USER WAS BANNED FOR THIS POST)</strong>");
+    let parsed_post = post_parser.parse_post(&post_raw);
+
+    assert_eq!("Text\n\n(USER WAS BANNED FOR THIS POST)", parsed_post.post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![Spannable { start: 6, len: 31, spannable_data: SpannableData::BanMessage }],
+      *parsed_post.post_comment_parsed.spannables
+    );
+    assert!(parsed_post.has_ban_message);
+    assert!(!parsed_post.is_deleted);
+
+    let restored = ParsedPost::from_bytes(&parsed_post.to_bytes()).unwrap();
+    assert!(restored.has_ban_message);
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<strong class=\"warning\">(User was warned for this post)</strong>");
+    let parsed_post = post_parser.parse_post(&post_raw);
+
+    assert_eq!("(User was warned for this post)", parsed_post.post_comment_parsed.parsed_text.as_str());
+    assert!(parsed_post.has_ban_message);
+    assert!(!parsed_post.is_deleted);
+
+    let post_raw = PostRaw::new("vichan", "b", 1, 3, 0, "Text<br><span class=\"public_ban\">(USER WAS BANNED FOR THIS POST)</span>");
+    assert!(post_parser.parse_post(&post_raw).has_ban_message);
+
+    let post_raw = PostRaw::new("4chan", "g", 1, 4, 0, "<strong>Not banned</strong>");
+    let parsed_post = post_parser.parse_post(&post_raw);
+
+    assert!(!parsed_post.has_ban_message);
+    assert_eq!(SpannableData::BoldText, parsed_post.post_comment_parsed.spannables[0].spannable_data);
+  }
+
+  #[test]
+  fn post_parser_test_inline_capcode() {
+    let post_comment_raw = "<span class=\"capcodeAdmin\">Posting as staff</span><br>Rules apply. \
+    <span class=\"capcodeMod\">Mod</span> <span class=\"capcodeFounder\">moot</span>";
+    let expected_parsed_comment = "Posting as staff\nRules apply. Mod moot";
+
+    let expected_spannables = vec![
+      Spannable { start: 0, len: 16, spannable_data: SpannableData::Capcode { capcode: String::from("Admin"), kind: CapcodeKind::Admin } },
+      Spannable { start: 30, len: 3, spannable_data: SpannableData::Capcode { capcode: String::from("Mod"), kind: CapcodeKind::Mod } },
+      Spannable { start: 34, len: 4, spannable_data: SpannableData::Capcode { capcode: String::from("Founder"), kind: CapcodeKind::Founder } },
+    ];
+
+    let post_parser_context = create_post_parser_context(
+      set_of!(),
+      set_of!()
+    );
+
+    run_test(1235, 1235, &post_parser_context, post_comment_raw, expected_parsed_comment, &expected_spannables);
+  }
+
+  #[test]
+  fn post_parser_test_colored_quotes() {
+    let post_comment_raw = "<span class=\"quote\">&gt;green</span><br><span class=\"orangeQuote\">&lt;orange</span><br>\
+    <span class=\"rquote\">&lt;pink</span><br><span class=\"purpleQuote\">^purple</span>";
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("vichan", "b", 1, 2, 0, post_comment_raw);
+
+    let mut parser_profile = ParserProfile::vichan();
+    parser_profile.colored_quote_classes.push((String::from("purpleQuote"), ColoredQuoteRole::Purple));
+
+    let post_comment_parsed = PostParser::new_with_profile(&post_parser_context, parser_profile, ParserLimits::default())
+      .parse_post(&post_raw)
+      .post_comment_parsed;
+
+    assert_eq!(">green\n<orange\n<pink\n^purple", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 6, spannable_data: SpannableData::GreenText },
+        Spannable { start: 7, len: 7, spannable_data: SpannableData::ColoredQuote { role: ColoredQuoteRole::Orange } },
+        Spannable { start: 15, len: 5, spannable_data: SpannableData::ColoredQuote { role: ColoredQuoteRole::Pink } },
+        Spannable { start: 21, len: 7, spannable_data: SpannableData::ColoredQuote { role: ColoredQuoteRole::Purple } },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    // Not configured for 4chan
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<span class=\"orangeQuote\">&lt;orange</span>");
+    let post_comment_parsed = PostParser::new(&post_parser_context).parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!("<orange", post_comment_parsed.parsed_text.as_str());
+    assert!(post_comment_parsed.spannables.is_empty());
+  }
+
+  #[test]
+  fn post_parser_test_poster_ids() {
+    let name_html = "<span class=\"name\">Anonymous</span> <span class=\"posteruid id_Xy1Z\">(ID: <span class=\"hand\" \
+    title=\"Highlight posts by this ID\">Xy1Z</span>)</span>";
+    let post_comment_raw = "Replying to <span class=\"posteruid id_AbCd\">(ID: AbCd)</span>";
+
+    let metadata = PostMetadataRaw { name_html: Some(String::from(name_html)), ..PostMetadataRaw::default() };
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "pol", 1, 2, 0, post_comment_raw).with_metadata(metadata);
+
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+
+    assert_eq!(Some(PosterId::new("Xy1Z")), parsed_post.metadata.poster_id);
+    assert_eq!(Some(String::from("Anonymous")), parsed_post.metadata.poster_info.name);
+
+    assert_eq!("Replying to (ID: AbCd)", parsed_post.post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![Spannable { start: 12, len: 10, spannable_data: SpannableData::PosterId(PosterId::new("AbCd")) }],
+      *parsed_post.post_comment_parsed.spannables
+    );
+
+    let restored = ParsedPost::from_bytes(&parsed_post.to_bytes()).unwrap();
+    assert_eq!(Some(PosterId::new("Xy1Z")), restored.metadata.poster_id);
+    assert_eq!(parsed_post.post_comment_parsed.spannables, restored.post_comment_parsed.spannables);
+
+    // Only the text has the id
+    let post_raw = PostRaw::new("vichan", "b", 1, 3, 0, "<span class=\"poster_id\">QwEr</span>");
+    let parsed_post = PostParser::new(&post_parser_context).parse_post(&post_raw);
+    assert_eq!(
+      vec![Spannable { start: 0, len: 4, spannable_data: SpannableData::PosterId(PosterId::new("QwEr")) }],
+      *parsed_post.post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_quote_graph() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!(1u64, 2u64, 3u64, 4u64, 5u64));
+    let post_parser = PostParser::new(&post_parser_context);
+
+    let posts = vec![
+      PostRaw::new("4chan", "g", 1, 1, 0, "OP"),
+      PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br><a href=\"#p3\" class=\"quotelink\">&gt;&gt;3</a>"),
+      PostRaw::new("4chan", "g", 1, 3, 0, "<a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a>"),
+      PostRaw::new("4chan", "g", 1, 4, 0, "<a href=\"#p4\" class=\"quotelink\">&gt;&gt;4</a>"),
+      PostRaw::new("4chan", "g", 1, 5, 0, "<a href=\"#p3\" class=\"quotelink\">&gt;&gt;3</a><br><a href=\"#p100\" class=\"quotelink\">&gt;&gt;100</a>"),
+    ];
+
+    let parsed_posts = posts.iter()
+      .map(|post_raw| post_parser.parse_post(post_raw))
+      .collect::<Vec<ParsedPost>>();
+
+    let quote_graph = ThreadParser::build_quote_graph(&parsed_posts, 10);
+
+    assert_eq!(vec![vec![2u64, 3u64], vec![4u64]], quote_graph.cycles);
+    assert!(quote_graph.is_in_cycle(3));
+    assert!(!quote_graph.is_in_cycle(5));
+    assert_eq!(&set_of!(2u64, 5u64), quote_graph.replies_from.get(&3).unwrap());
+
+    assert_eq!(vec![(2u64, 1usize), (3, 2), (5, 3)], quote_graph.reply_tree(1));
+    assert_eq!(vec![(3u64, 1usize), (100, 1), (2, 2), (1, 3)], quote_graph.quote_tree(5));
+    assert_eq!(Vec::<(u64, usize)>::new(), quote_graph.reply_tree(5));
+
+    let quote_graph = ThreadParser::build_quote_graph(&parsed_posts, 2);
+    assert_eq!(2, quote_graph.max_depth());
+    assert_eq!(vec![(2u64, 1usize), (3, 2)], quote_graph.reply_tree(1));
+  }
+
+  // Marks the text of the element with an app specific span
+  struct ThirdEyeHandler {}
+
+  impl RuleHandler for ThirdEyeHandler {
+    fn pre_handle(&self, ctx: &mut HandlerCtx) -> bool {
+      let start = ctx.text_len();
+      let text = ctx.element.collect_text();
+
+      ctx.out_spannables.push(Spannable {
+        start,
+        len: text.chars().count(),
+        spannable_data: SpannableData::Custom { kind: 7, payload: text.as_bytes().to_vec() }
+      });
+      ctx.out_text_parts.push(TextPart::new(text));
+
+      return true;
+    }
+  }
+
+  #[test]
+  fn post_parser_test_spannable_data_kinds_and_custom_spans() {
+    assert_eq!(0, SpannableData::Link(PostLink::Dead { post_no: 1 }).kind());
+    assert_eq!(3, SpannableData::BoldText.kind());
+    assert_eq!(11, SpannableData::Heading { level: 1 }.kind());
+    assert_eq!(34, SpannableData::BanMessage.kind());
+    assert_eq!(36, SpannableData::PosterId(PosterId::new("abc")).kind());
+    assert_eq!(255, SpannableData::Custom { kind: 7, payload: vec![] }.kind());
+
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "See <span class=\"thirdeye\">abc.png</span> <b>ok</b>");
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.add_tag_class_handler("span", "thirdeye", Rc::new(ThirdEyeHandler {}));
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let parsed_post = post_parser.parse_post(&post_raw);
+
+    assert_eq!("See abc.png ok", parsed_post.post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 4, len: 7, spannable_data: SpannableData::Custom { kind: 7, payload: b"abc.png".to_vec() } },
+        Spannable { start: 12, len: 2, spannable_data: SpannableData::BoldText },
+      ],
+      *parsed_post.post_comment_parsed.spannables
+    );
+
+    let restored = ParsedPost::from_bytes(&parsed_post.to_bytes()).unwrap();
+    assert_eq!(parsed_post.post_comment_parsed.spannables, restored.post_comment_parsed.spannables);
+  }
+
+  struct MarkHandler {}
+
+  impl RuleHandler for MarkHandler {
+    fn pre_handle(&self, _: &mut HandlerCtx) -> bool {
+      return false;
+    }
+
+    fn post_handle(&self, ctx: &mut HandlerCtx) {
+      if !ctx.has_new_text() {
+        return;
+      }
+
+      let spannable = Spannable {
+        start: ctx.new_text_start(),
+        len: ctx.new_text_len(),
+        spannable_data: SpannableData::Custom { kind: 8, payload: ctx.new_text().into_bytes() }
+      };
+
+      ctx.out_spannables.push(spannable);
+    }
+  }
+
+  #[test]
+  fn post_parser_test_handler_ctx_new_text() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<b>A</b><mark>x<i>yz</i></mark><mark></mark>");
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules.add_tag_handler("mark", Rc::new(MarkHandler {}));
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!("Axyz", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 1, spannable_data: SpannableData::BoldText },
+        Spannable { start: 1, len: 3, spannable_data: SpannableData::Custom { kind: 8, payload: b"xyz".to_vec() } },
+        Spannable { start: 2, len: 2, spannable_data: SpannableData::Italic },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_rule_priorities() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<span class=\"quote deadlink\">&gt;&gt;123</span> <span class=\"quote\">&gt;Test</span>");
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!(">>123 (DEAD) >Test", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 12, spannable_data: SpannableData::Link(PostLink::Dead { post_no: 123 }) },
+        Spannable { start: 13, len: 5, spannable_data: SpannableData::GreenText },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    // Same priority, the rule that was added first handles the element
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<span class=\"shout thirdeye\">abc</span>");
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .add_tag_class_handler("span", "shout", Rc::new(UppercaseHandler {}))
+      .add_tag_class_handler("span", "thirdeye", Rc::new(ThirdEyeHandler {}));
+
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!("ABC", post_comment_parsed.parsed_text.as_str());
+    assert!(post_comment_parsed.spannables.is_empty());
+
+    // Higher priority goes first no matter when the rule was added
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .add_tag_class_handler("span", "shout", Rc::new(UppercaseHandler {}))
+      .add_rule(
+        ParsingRule::new("span", set_of!(Attribute::with_class("thirdeye")), Rc::new(ThirdEyeHandler {}))
+          .with_priority(ParsingRule::ATTRIBUTE_PRIORITY + 1)
+      );
+
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!("abc", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 3, spannable_data: SpannableData::Custom { kind: 7, payload: b"abc".to_vec() } },
+      ],
+      *post_comment_parsed.spannables
+    );
+
+    // Selector rules only apply to the elements that have all of the classes
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<span class=\"shout\">abc</span> <span class=\"thirdeye shout\">def</span>");
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .add_tag_class_handler("span", "shout", Rc::new(UppercaseHandler {}))
+      .add_rule(
+        ParsingRule::with_selector("span.shout.thirdeye", Rc::new(ThirdEyeHandler {}))
+          .with_priority(ParsingRule::ATTRIBUTE_PRIORITY + 1)
+      );
+
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!("ABC def", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 4, len: 3, spannable_data: SpannableData::Custom { kind: 7, payload: b"def".to_vec() } },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_selector_rules_survive_tag_handler_replacement() {
+    let post_parser_context = create_post_parser_context(set_of!(), set_of!());
+    let post_raw = PostRaw::new("4chan", "g", 1, 2, 0, "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> <a href=\"#top\">link</a>");
+
+    let mut parsing_rules = ParsingRules::with_default_rules();
+    parsing_rules
+      .add_rule(ParsingRule::with_selector("a.quotelink[href]", Rc::new(ThirdEyeHandler {})))
+      .set_quote_format(QuoteFormat::futaba())
+      .replace_tag_handler("a", Rc::new(UppercaseHandler {}));
+
+    let mut post_parser = PostParser::new(&post_parser_context);
+    post_parser.comment_parser.set_parsing_rules(parsing_rules);
+
+    let post_comment_parsed = post_parser.parse_post(&post_raw).post_comment_parsed;
+
+    assert_eq!(">>1 LINK", post_comment_parsed.parsed_text.as_str());
+    assert_eq!(
+      vec![
+        Spannable { start: 0, len: 3, spannable_data: SpannableData::Custom { kind: 7, payload: b">>1".to_vec() } },
+      ],
+      *post_comment_parsed.spannables
+    );
+  }
+
+  #[test]
+  fn post_parser_test_text_part_lengths() {
+    let text_part = TextPart::new(String::from("a👍é"));
+
+    assert_eq!("a👍é", text_part.text());
+    assert_eq!(3, text_part.characters_count());
+    assert_eq!(7, text_part.bytes_count());
+    assert_eq!(4, text_part.utf16_count());
+
+    assert_eq!(Some(7), text_part.len_in(&SpanOffsetMode::Utf8Bytes));
+    assert_eq!(Some(4), text_part.len_in(&SpanOffsetMode::Utf16CodeUnits));
+    assert_eq!(Some(3), text_part.len_in(&SpanOffsetMode::UnicodeScalars));
+    assert_eq!(None, text_part.len_in(&SpanOffsetMode::Graphemes));
+
+    let text_part = TextPart::new(String::from("\n"));
+    assert_eq!((1, 1, 1), (text_part.characters_count(), text_part.bytes_count(), text_part.utf16_count()));
+  }
+
+  #[test]
+  fn post_parser_test_text_parts_offsets() {
+    let mut text_parts = TextParts::new();
+    assert_eq!(0, text_parts.chars_count());
+
+    text_parts.push(TextPart::new(String::from("Test")));
+    text_parts.push(TextPart::new(String::from("a👍é")));
+    text_parts.push(TextPart::new(String::from("\n")));
+
+    assert_eq!(3, text_parts.len());
+    assert_eq!(8, text_parts.chars_count());
+    assert_eq!(0, text_parts.chars_before(0));
+    assert_eq!(4, text_parts.chars_before(1));
+    assert_eq!(7, text_parts.chars_before(2));
+    assert_eq!(8, text_parts.chars_before(3));
+    assert_eq!(4, text_parts.chars_from(1));
+    assert_eq!(0, text_parts.chars_from(3));
+
+    assert_eq!("\n", text_parts.pop().unwrap().text());
+    assert_eq!(7, text_parts.chars_count());
+    assert_eq!(0, text_parts.chars_from(2));
+  }
+
   // A general for the discussion of all non-x86 ISAs (RISC-V, SPARC, m68k, PA-RISC, MIPS, Itanium, PowerPC, etc.) retro and modern.<br><br>M68K:<br>http://m68k.info/<br>http://www.apollo-core.com/index.ht<wbr>m<br>https://thebrewingacademy.com/colle<wbr>ctions/atari-st-ste-mega<br>http://www.easy68k.com/paulrsm/<br>https://github.com/grovdata/Amiga_S<wbr>ources<br><br>MIPS:<br>https://www.embeddedplanet.com/prod<wbr>ucts_list/cavium-octeon-iii-develop<wbr>ment-board/<br>https://elinux.org/MIPS_Creator_CI2<wbr>0<br>http://www.sgistuff.net/software/ir<wbr>ixintro/index.html<br>https://sgi.neocities.org/<br><br>SuperH:<br>https://www.apnet.co.jp/product/ms1<wbr>04/ms104-sh4.html<br><br>Z80:<br>http://www.pc1500.com/<br>https://www.kickstarter.com/project<wbr>s/spectrumnext/zx-spectrum-next-iss<wbr>ue-2<br><br>6502:<br>http://6502.org/<br>https://gist.github.com/jblang/a397<wbr>48b3b0d3ceba05cbb92d0c56b3b2<br>https://www.commodorecomputerclub.c<wbr>om/resources/<br>http://home-2002.code-cop.org/c64/<br><br>RISC-V:<br>https://beagleboard.org/beaglev<br>https://bellard.org/tinyemu/<br>https://www.sifive.com/boards/hifiv<wbr>e-unmatched<br><br>SPARC:<br>https://sparc.org/<br><br>POWER/PowerPC:<br>https://www.nxp.com/design/qoriq-de<wbr>veloper-resources/qoriq-t2080-devel<wbr>opment-board:T2080RDB<br>https://www.powerpc-notebook.org<br>https://raptorcs.com/content/BK1SD1<wbr>/intro.html<br><br>VAX:<br>https://github.com/simh/simh<br>http://oboguev.net/vax_mp/<br><br>Alpha:<br>https://github.com/lenticularis39/a<wbr>xpbox<br><br>Multi-system FPGA:<br>https://github.com/mist-devel/mist-<wbr>board/wiki<br>https://github.com/MiSTer-devel/Mai<wbr>n_MiSTer/wiki<br><br>Misc.:<br>http://anycpu.org<br>https://opencores.org/<br><br>More:<br>https://wiki.installgentoo.com/wiki<wbr>//aig/_Alternative_ISA_General<br>https://mega.nz/file/0PplHSyL#eK_f2<wbr>ZSc2f0E8_RLUGz9nVn40myXhyiRDMU_FhgO<wbr>2wk<br><br>Previous thread: <a href=\"/g/thread/81521490#p81521490\" class=\"quotelink\">&gt;&gt;81521490</a>
 
   // TODO: some MEGA links that we are not parsing correctly + another kind of search links